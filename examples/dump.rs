@@ -16,8 +16,10 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
             }
             wasynth::parse::Section::Type(tysec) => {
                 println!("types:");
-                for ty in tysec.types()? {
-                    println!("{}", ty?);
+                for group in tysec.rec_groups()? {
+                    for ty in group?.types() {
+                        println!("{ty}");
+                    }
                 }
             }
             wasynth::parse::Section::Import(imsec) => {
@@ -44,6 +46,12 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
                     println!("{:?}", mem?);
                 }
             }
+            wasynth::parse::Section::Tag(tagsec) => {
+                println!("tags:");
+                for tag in tagsec.tags()? {
+                    println!("{:?}", tag?);
+                }
+            }
             wasynth::parse::Section::Global(_) => (),
             wasynth::parse::Section::Export(_) => (),
             wasynth::parse::Section::Start(_) => (),
@@ -5,14 +5,12 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
 
     let s = include_bytes!("../tests/cases/wasynth_release.wasm");
     let wasm = wat::parse_bytes(s)?;
-    let before = wasmprinter::print_bytes(wasm.clone()).unwrap();
-    let mut module = wasynth::parse::Module::from_binary(&wasm)?.into_synth()?;
+    let module = wasynth::parse::Module::from_binary(&wasm)?;
+    let before = module.to_string();
+    let mut module = module.into_synth()?;
     wasynth::instrument::install_all(&mut module)?;
 
-    let mut buf = Vec::new();
-    module.write_into(&mut buf)?;
-
-    let after = wasmprinter::print_bytes(&buf)?;
+    let after = module.to_string();
 
     let diff = Changeset::new(&before, &after, "\n");
     eprintln!("{diff}");
@@ -0,0 +1,26 @@
+#![no_main]
+
+use std::sync::Once;
+
+use libfuzzer_sys::fuzz_target;
+use wasynth::parse::{Module, ParseLimits};
+
+static INIT_ONCE: Once = Once::new();
+
+fuzz_target!(|data: &[u8]| {
+    INIT_ONCE.call_once(env_logger::init);
+
+    // malformed modules must be rejected with errors, not panics
+    let limits = ParseLimits::new().max_section_size(1 << 20);
+    let Ok(module) = Module::from_binary_with_limits(data, limits) else {
+        return;
+    };
+    if let Ok(synth) = module.clone().into_synth() {
+        // validation and encoding must not panic on malformed modules either
+        let _ = synth.validate();
+        synth
+            .write_into(&mut Vec::new())
+            .expect("write_into failed");
+    }
+    let _ = module.into_synth_preserving();
+});
@@ -0,0 +1,301 @@
+//! Evaluation of constant expressions.
+//!
+//! Constant expressions initialize globals and compute offsets of active element and data
+//! segments. Their values depend on imported globals, which are only known when the module is
+//! instantiated, so the values of imported globals are given by the caller.
+//!
+//! <https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions>
+
+use crate::{
+    instructions::{Expression, Instruction},
+    synth::{
+        sections::{SynthData, SynthElemMode, SynthImportDescription},
+        SynthModule,
+    },
+    wasm_types::HeapType,
+    Error,
+};
+
+/// A value produced by a constant expression.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    /// A null reference of the heap type.
+    Null(HeapType),
+    /// A reference to the function of the index.
+    Func(u32),
+}
+
+impl Value {
+    /// Returns the value as an offset into a memory or a table, if it is an integer.
+    ///
+    /// `i32` values are unsigned offsets of 32-bit memories and tables.
+    pub fn as_offset(&self) -> Option<u64> {
+        match *self {
+            Value::I32(x) => Some(u64::from(x as u32)),
+            Value::I64(x) => Some(x as u64),
+            _ => None,
+        }
+    }
+}
+
+/// A reason why a constant expression could not be evaluated.
+#[derive(Debug, thiserror::Error)]
+pub enum EvalError {
+    #[error("{0} cannot be evaluated in a constant expression")]
+    NonConstant(&'static str),
+    #[error("value of global {0} is not given")]
+    UnknownGlobal(u32),
+    #[error("operands of {0} have mismatching types")]
+    TypeMismatch(&'static str),
+    #[error("operand stack underflow")]
+    StackUnderflow,
+    #[error("constant expression must produce exactly one value, got {0}")]
+    ResultArity(usize),
+    #[error("offset must be an integer, got {0:?}")]
+    Offset(Value),
+    #[error("global {index} initializer cannot be evaluated")]
+    GlobalInit {
+        index: u32,
+        #[source]
+        source: Box<EvalError>,
+    },
+    #[error("element segment {index} offset cannot be evaluated")]
+    ElementSegment {
+        index: u32,
+        #[source]
+        source: Box<EvalError>,
+    },
+    #[error("data segment {index} offset cannot be evaluated")]
+    DataSegment {
+        index: u32,
+        #[source]
+        source: Box<EvalError>,
+    },
+}
+
+impl Expression {
+    /// Evaluates a constant expression, where `globals[x]` is the value of global `x`.
+    ///
+    /// Arithmetic of the extended constant expressions proposal wraps around on overflow.
+    pub fn evaluate(&self, globals: &[Value]) -> Result<Value, EvalError> {
+        let mut stack = Vec::new();
+        for instr in self.instructions() {
+            let value = match instr {
+                Instruction::I32Const(x) => Value::I32(*x),
+                Instruction::I64Const(x) => Value::I64(*x),
+                Instruction::F32Const(x) => Value::F32(*x),
+                Instruction::F64Const(x) => Value::F64(*x),
+                Instruction::V128Const(x) => Value::V128(*x),
+                Instruction::RefNull(rt) => Value::Null(rt.heap_type()),
+                Instruction::RefFunc(x) => Value::Func(*x),
+                Instruction::GlobalGet(x) => *globals
+                    .get(*x as usize)
+                    .ok_or(EvalError::UnknownGlobal(*x))?,
+                Instruction::I32Add
+                | Instruction::I32Sub
+                | Instruction::I32Mul
+                | Instruction::I64Add
+                | Instruction::I64Sub
+                | Instruction::I64Mul => {
+                    let rhs = stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let lhs = stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    binary(instr, lhs, rhs)?
+                }
+                instr => return Err(EvalError::NonConstant(instr.mnemonic())),
+            };
+            stack.push(value);
+        }
+
+        match stack[..] {
+            [value] => Ok(value),
+            _ => Err(EvalError::ResultArity(stack.len())),
+        }
+    }
+}
+
+fn binary(instr: &Instruction, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+    let value = match (instr, lhs, rhs) {
+        (Instruction::I32Add, Value::I32(a), Value::I32(b)) => Value::I32(a.wrapping_add(b)),
+        (Instruction::I32Sub, Value::I32(a), Value::I32(b)) => Value::I32(a.wrapping_sub(b)),
+        (Instruction::I32Mul, Value::I32(a), Value::I32(b)) => Value::I32(a.wrapping_mul(b)),
+        (Instruction::I64Add, Value::I64(a), Value::I64(b)) => Value::I64(a.wrapping_add(b)),
+        (Instruction::I64Sub, Value::I64(a), Value::I64(b)) => Value::I64(a.wrapping_sub(b)),
+        (Instruction::I64Mul, Value::I64(a), Value::I64(b)) => Value::I64(a.wrapping_mul(b)),
+        _ => return Err(EvalError::TypeMismatch(instr.mnemonic())),
+    };
+    Ok(value)
+}
+
+/// Evaluates an offset expression of an active segment.
+fn offset(expr: &Expression, globals: &[Value]) -> Result<u64, EvalError> {
+    let value = expr.evaluate(globals)?;
+    value.as_offset().ok_or(EvalError::Offset(value))
+}
+
+impl SynthModule {
+    /// Computes values of all globals, given the values of imported globals in order.
+    ///
+    /// Initializers may refer to imported globals and to globals defined before them.
+    pub fn global_values(&self, imports: &[Value]) -> Result<Vec<Value>, Error> {
+        let imported = self
+            .import_section
+            .as_ref()
+            .map(|x| &x.imports[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| matches!(x.description, SynthImportDescription::Global(_)))
+            .count();
+        if imports.len() < imported {
+            return Err(Error::ConstEval(EvalError::UnknownGlobal(
+                imports.len() as u32
+            )));
+        }
+        let mut values = imports[..imported].to_vec();
+
+        let globals = self
+            .global_section
+            .as_ref()
+            .map(|x| &x.globals[..])
+            .unwrap_or_default();
+        for (i, global) in globals.iter().enumerate() {
+            let index = (imported + i) as u32;
+            let value = global.init.evaluate(&values).map_err(|e| {
+                Error::ConstEval(EvalError::GlobalInit {
+                    index,
+                    source: Box::new(e),
+                })
+            })?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Computes where active data segments are placed, as pairs of memory indices and offsets.
+    /// Passive data segments are `None`.
+    ///
+    /// `globals` are the values of all globals, e.g. computed by [`Self::global_values`].
+    pub fn data_offsets(&self, globals: &[Value]) -> Result<Vec<Option<(u32, u64)>>, Error> {
+        let all_data = self
+            .data_section
+            .as_ref()
+            .map(|x| &x.all_data[..])
+            .unwrap_or_default();
+        all_data
+            .iter()
+            .enumerate()
+            .map(|(i, data)| match data {
+                SynthData::Active {
+                    memory_index,
+                    offset: expr,
+                    ..
+                } => offset(expr, globals)
+                    .map(|x| Some((*memory_index, x)))
+                    .map_err(|e| {
+                        Error::ConstEval(EvalError::DataSegment {
+                            index: i as u32,
+                            source: Box::new(e),
+                        })
+                    }),
+                SynthData::Passive(_) => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Computes where active element segments are placed, as pairs of table indices and offsets.
+    /// Passive and declarative element segments are `None`.
+    ///
+    /// `globals` are the values of all globals, e.g. computed by [`Self::global_values`].
+    pub fn element_offsets(&self, globals: &[Value]) -> Result<Vec<Option<(u32, u64)>>, Error> {
+        let elements = self
+            .element_section
+            .as_ref()
+            .map(|x| &x.elements[..])
+            .unwrap_or_default();
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, elem)| match &elem.mode {
+                SynthElemMode::Active {
+                    table,
+                    offset: expr,
+                } => offset(expr, globals)
+                    .map(|x| Some((*table, x)))
+                    .map_err(|e| {
+                        Error::ConstEval(EvalError::ElementSegment {
+                            index: i as u32,
+                            source: Box::new(e),
+                        })
+                    }),
+                SynthElemMode::Passive | SynthElemMode::Declarative => Ok(None),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvalError, Value};
+    use crate::{parse::Module, synth::SynthModule, Error};
+
+    fn synth(wat_s: &str) -> SynthModule {
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+        Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth")
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let module = synth(
+            r#"(module
+                (import "env" "base" (global $base i32))
+                (import "env" "table_base" (global $table_base i64))
+                (memory 1)
+                (table 4 funcref)
+                (memory $m64 i64 1)
+                (global $end i32 (i32.add (global.get $base) (i32.const 16)))
+                (global $f funcref (ref.func $f))
+                (elem (offset (i32.sub (i32.const 3) (i32.const 1))) $f)
+                (elem declare func $f)
+                (data (offset (i32.mul (global.get $base) (i32.const 2))) "a")
+                (data "passive")
+                (data (memory $m64)
+                    (offset (i64.add (global.get $table_base) (i64.const -1))) "b")
+                (func $f)
+            )"#,
+        );
+        module.validate().expect("module is invalid");
+
+        let globals = module
+            .global_values(&[Value::I32(-8), Value::I64(10)])
+            .unwrap();
+        assert_eq!(
+            globals,
+            [
+                Value::I32(-8),
+                Value::I64(10),
+                Value::I32(8),
+                Value::Func(0)
+            ]
+        );
+        assert_eq!(
+            module.data_offsets(&globals).unwrap(),
+            [Some((0, 0xFFFF_FFF0)), None, Some((1, 9))]
+        );
+        assert_eq!(
+            module.element_offsets(&globals).unwrap(),
+            [Some((0, 2)), None]
+        );
+
+        let err = module.global_values(&[Value::I32(0)]).unwrap_err();
+        let Error::ConstEval(EvalError::UnknownGlobal(1)) = err else {
+            panic!("expected an unknown global, got {err:?}");
+        };
+    }
+}
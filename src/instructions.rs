@@ -1,10 +1,18 @@
 use std::io::{self, Write};
 
+use self::visit::IndexVisitor;
+
 use crate::{
-    wasm_types::{ReferenceType, ValueType},
-    Bytes, Error, WriteExt,
+    offset_in,
+    synth::IndexSpace,
+    wasm_types::{HeapType, ReferenceType, ValueType},
+    Bytes, Error, ErrorContext, WriteExt,
 };
 
+mod visit;
+
+pub use visit::VisitMut;
+
 #[derive(Clone, Copy, Debug)]
 pub enum BlockType {
     Empty,
@@ -23,10 +31,13 @@ impl BlockType {
             return Ok((Self::Empty, &bytes[1..]));
         }
 
-        match ValueType::from_byte(bytes[0]) {
-            Ok(x) => Ok((Self::Value(x), &bytes[1..])),
+        match ValueType::from_bytes(bytes) {
+            Ok((x, bytes)) => Ok((Self::Value(x), bytes)),
             Err(Error::ValueTypeId(..)) => {
                 let (tyidx, bytes) = bytes.advance_s64()?;
+                if !(-(1i64 << 32)..(1i64 << 32)).contains(&tyidx) {
+                    return Err(Error::ReadLeb128(leb128::read::Error::Overflow));
+                }
                 Ok((Self::TypeIndex(tyidx), bytes))
             }
             Err(err) => Err(err),
@@ -45,28 +56,150 @@ impl BlockType {
             }
         }
     }
+
+    pub(crate) fn visit_indices(&self, func: &mut impl FnMut(IndexSpace, u32)) {
+        match *self {
+            BlockType::TypeIndex(x) => {
+                // an out-of-range s33 index is reported as u32::MAX, which cannot be a valid index
+                func(IndexSpace::Type, u32::try_from(x).unwrap_or(u32::MAX));
+            }
+            BlockType::Value(t) => {
+                if let Some(x) = t.type_index() {
+                    func(IndexSpace::Type, x);
+                }
+            }
+            BlockType::Empty => (),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct MemArg {
     pub align: u32,
-    pub offset: u32,
+    /// Index of the accessed memory. Indices other than 0 are encoded after the alignment, flagged
+    /// with its bit 6, as in the multi-memory proposal.
+    pub memory: u32,
+    /// Offset added to the address, which fits in `u32` unless the memory is a 64-bit memory.
+    pub offset: u64,
 }
 
 impl MemArg {
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
         let (align, bytes) = bytes.advance_u32()?;
-        let (offset, bytes) = bytes.advance_u32()?;
-        Ok((Self { align, offset }, bytes))
+        let (align, memory, bytes) = if align & 0x40 != 0 {
+            let (memory, bytes) = bytes.advance_u32()?;
+            (align & !0x40, memory, bytes)
+        } else {
+            (align, 0, bytes)
+        };
+        let (offset, bytes) = bytes.advance_u64()?;
+        Ok((
+            Self {
+                align,
+                memory,
+                offset,
+            },
+            bytes,
+        ))
     }
 
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
-        wr.write_u32(self.align)?;
-        wr.write_u32(self.offset)?;
+        if self.memory == 0 {
+            wr.write_u32(self.align)?;
+        } else {
+            wr.write_u32(self.align | 0x40)?;
+            wr.write_u32(self.memory)?;
+        }
+        wr.write_u64(self.offset)?;
         Ok(())
     }
 }
 
+/// A catch clause of [`Instruction::TryTable`].
+#[derive(Clone, Copy, Debug)]
+pub enum Catch {
+    /// Catches exceptions of `tag`, branching to `label` with the tag's parameters.
+    Tag { tag: u32, label: u32 },
+    /// Like [`Catch::Tag`], additionally pushing the caught `exnref`.
+    TagRef { tag: u32, label: u32 },
+    /// Catches every exception, branching to `label` without values.
+    All { label: u32 },
+    /// Like [`Catch::All`], pushing the caught `exnref`.
+    AllRef { label: u32 },
+}
+
+impl Catch {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (&[kind], bytes) = bytes.advance()?;
+        match kind {
+            0x00 | 0x01 => {
+                let (tag, bytes) = bytes.advance_u32()?;
+                let (label, bytes) = bytes.advance_u32()?;
+                if kind == 0x00 {
+                    Ok((Self::Tag { tag, label }, bytes))
+                } else {
+                    Ok((Self::TagRef { tag, label }, bytes))
+                }
+            }
+            0x02 | 0x03 => {
+                let (label, bytes) = bytes.advance_u32()?;
+                if kind == 0x02 {
+                    Ok((Self::All { label }, bytes))
+                } else {
+                    Ok((Self::AllRef { label }, bytes))
+                }
+            }
+            _ => Err(Error::CatchKind(kind)),
+        }
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        match *self {
+            Self::Tag { tag, label } => {
+                wr.write_all(&[0x00])?;
+                wr.write_u32(tag)?;
+                wr.write_u32(label)
+            }
+            Self::TagRef { tag, label } => {
+                wr.write_all(&[0x01])?;
+                wr.write_u32(tag)?;
+                wr.write_u32(label)
+            }
+            Self::All { label } => {
+                wr.write_all(&[0x02])?;
+                wr.write_u32(label)
+            }
+            Self::AllRef { label } => {
+                wr.write_all(&[0x03])?;
+                wr.write_u32(label)
+            }
+        }
+    }
+
+    /// Returns the tag index this clause catches, if any.
+    pub fn tag(&self) -> Option<u32> {
+        match *self {
+            Self::Tag { tag, .. } | Self::TagRef { tag, .. } => Some(tag),
+            Self::All { .. } | Self::AllRef { .. } => None,
+        }
+    }
+
+    /// Returns the label index this clause branches to.
+    pub fn label(&self) -> u32 {
+        match *self {
+            Self::Tag { label, .. }
+            | Self::TagRef { label, .. }
+            | Self::All { label }
+            | Self::AllRef { label } => label,
+        }
+    }
+
+    /// Returns whether this clause pushes the caught `exnref`.
+    pub fn is_ref(&self) -> bool {
+        matches!(self, Self::TagRef { .. } | Self::AllRef { .. })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Instruction {
     // Control instructions
@@ -75,17 +208,85 @@ pub enum Instruction {
     Block(BlockType, Vec<Instruction>),
     Loop(BlockType, Vec<Instruction>),
     If(BlockType, Vec<Instruction>, Option<Vec<Instruction>>),
+    /// A legacy `try` block with its body, `catch` clauses of tag indices and optional
+    /// `catch_all` clause.
+    Try(
+        BlockType,
+        Vec<Instruction>,
+        Vec<(u32, Vec<Instruction>)>,
+        Option<Vec<Instruction>>,
+    ),
+    /// A legacy `try` block ending with `delegate` to the label index.
+    TryDelegate(BlockType, Vec<Instruction>, u32),
+    Throw(u32),
+    Rethrow(u32),
+    ThrowRef,
+    TryTable(BlockType, Vec<Catch>, Vec<Instruction>),
     Br(u32),
     BrIf(u32),
     BrTable(Vec<u32>, u32),
     Return,
     Call(u32),
-    CallIndirect { ty: u32, table: u32 },
+    CallIndirect {
+        ty: u32,
+        table: u32,
+    },
+    ReturnCall(u32),
+    ReturnCallIndirect {
+        ty: u32,
+        table: u32,
+    },
+
+    /// `call_ref` of the typed function references proposal, with the type of the callee.
+    CallRef(u32),
+    ReturnCallRef(u32),
 
     // Reference instructions
     RefNull(ReferenceType),
     RefIsNull,
     RefFunc(u32),
+    RefEq,
+    RefAsNonNull,
+    BrOnNull(u32),
+    BrOnNonNull(u32),
+
+    // Aggregate and cast instructions of the GC proposal
+    StructNew(u32),
+    StructNewDefault(u32),
+    /// `struct.get` of a type index and a field index.
+    StructGet(u32, u32),
+    StructGetS(u32, u32),
+    StructGetU(u32, u32),
+    StructSet(u32, u32),
+    ArrayNew(u32),
+    ArrayNewDefault(u32),
+    /// `array.new_fixed` of a type index and the number of elements.
+    ArrayNewFixed(u32, u32),
+    /// `array.new_data` of a type index and a data segment index.
+    ArrayNewData(u32, u32),
+    /// `array.new_elem` of a type index and an element segment index.
+    ArrayNewElem(u32, u32),
+    ArrayGet(u32),
+    ArrayGetS(u32),
+    ArrayGetU(u32),
+    ArraySet(u32),
+    ArrayLen,
+    ArrayFill(u32),
+    /// `array.copy` of the destination and source type indices.
+    ArrayCopy(u32, u32),
+    ArrayInitData(u32, u32),
+    ArrayInitElem(u32, u32),
+    /// `ref.test` of the target type, whose nullability is encoded in the opcode.
+    RefTest(ReferenceType),
+    RefCast(ReferenceType),
+    /// `br_on_cast` of a label index, the source type and the target type.
+    BrOnCast(u32, ReferenceType, ReferenceType),
+    BrOnCastFail(u32, ReferenceType, ReferenceType),
+    AnyConvertExtern,
+    ExternConvertAny,
+    RefI31,
+    I31GetS,
+    I31GetU,
 
     // Parametric instructions
     Drop,
@@ -133,12 +334,14 @@ pub enum Instruction {
     I64Store8(MemArg),
     I64Store16(MemArg),
     I64Store32(MemArg),
-    MemorySize,
-    MemoryGrow,
-    MemoryInit(u32),
+    MemorySize(u32),
+    MemoryGrow(u32),
+    /// `memory.init` of a data segment into a memory.
+    MemoryInit(u32, u32),
     DataDrop(u32),
-    MemoryCopy,
-    MemoryFill,
+    /// `memory.copy` from the second memory to the first one.
+    MemoryCopy(u32, u32),
+    MemoryFill(u32),
 
     // Numeric instructions
     I32Const(i32),
@@ -548,13 +751,126 @@ pub enum Instruction {
     F64x2ConvertLowI32x4U,
     F32x4DemoteF64x2Zero,
     F64x2PromoteLowF32x4,
+
+    // Relaxed SIMD instructions, whose results may depend on the implementation
+    I8x16RelaxedSwizzle,
+    I32x4RelaxedTruncF32x4S,
+    I32x4RelaxedTruncF32x4U,
+    I32x4RelaxedTruncF64x2SZero,
+    I32x4RelaxedTruncF64x2UZero,
+    F32x4RelaxedMadd,
+    F32x4RelaxedNmadd,
+    F64x2RelaxedMadd,
+    F64x2RelaxedNmadd,
+    I8x16RelaxedLaneselect,
+    I16x8RelaxedLaneselect,
+    I32x4RelaxedLaneselect,
+    I64x2RelaxedLaneselect,
+    F32x4RelaxedMin,
+    F32x4RelaxedMax,
+    F64x2RelaxedMin,
+    F64x2RelaxedMax,
+    I16x8RelaxedQ15mulrS,
+    I16x8RelaxedDotI8x16I7x16S,
+    I32x4RelaxedDotI8x16I7x16AddS,
+
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
 }
 
 impl Instruction {
+    /// Reads instructions until one of `endset` at the outermost level, returning the terminating
+    /// byte.
+    ///
+    /// Offsets of instructions relative to `bytes` are pushed into `offsets` in the order of a
+    /// pre-order walk, if given. Blocks may be nested up to `max_depth`. Errors are located at the
+    /// faulty instruction, relative to `bytes`.
     pub(crate) fn from_bytes_vec<'bytes>(
+        bytes: &'bytes [u8],
+        endset: &[u8],
+        offsets: Option<&mut Vec<usize>>,
+        max_depth: usize,
+    ) -> Result<(Vec<Self>, u8, &'bytes [u8]), Error> {
+        let mut start = bytes;
+        Self::read_instructions(bytes, endset, offsets, max_depth, &mut start)
+            .map_err(|e| e.within(ErrorContext::Instruction, offset_in(bytes, start)))
+    }
+
+    /// Reads instructions for [`Instruction::from_bytes_vec`], keeping `start` at the instruction
+    /// being read.
+    fn read_instructions<'bytes>(
         mut bytes: &'bytes [u8],
         endset: &[u8],
+        mut offsets: Option<&mut Vec<usize>>,
+        max_depth: usize,
+        start: &mut &'bytes [u8],
     ) -> Result<(Vec<Self>, u8, &'bytes [u8]), Error> {
+        let base = bytes;
         #[derive(Debug)]
         enum Action<'endset> {
             Parse {
@@ -581,6 +897,28 @@ impl Instruction {
                 outer: Vec<Instruction>,
                 outer_endset: &'endset [u8],
             },
+            Try {
+                block_type: BlockType,
+                clause: TryClause,
+                body: Vec<Instruction>,
+                catches: Vec<(u32, Vec<Instruction>)>,
+                outer: Vec<Instruction>,
+                outer_endset: &'endset [u8],
+            },
+            TryTable {
+                block_type: BlockType,
+                catches: Vec<Catch>,
+                outer: Vec<Instruction>,
+                outer_endset: &'endset [u8],
+            },
+        }
+
+        /// The clause of a legacy `try` block being read.
+        #[derive(Debug)]
+        enum TryClause {
+            Body,
+            Catch(u32),
+            CatchAll,
         }
 
         let mut ret = Vec::new();
@@ -590,6 +928,7 @@ impl Instruction {
         while let Some(top) = stack.pop() {
             match top {
                 Action::Parse { endset } => loop {
+                    *start = bytes;
                     if bytes.is_empty() {
                         return Err(Error::UnexpectedEof(1, 0));
                     }
@@ -603,9 +942,16 @@ impl Instruction {
                             break;
                         }
                     }
+                    if let Some(offsets) = &mut offsets {
+                        offsets.push(offset_in(base, bytes));
+                    }
                     let (instr, bytes_) = {
                         let (&[opcode], bytes_) = bytes.advance()?;
                         log::trace!("opcode: 0x{opcode:02x}");
+                        // the stack holds an action for each enclosing block
+                        if matches!(opcode, 0x02..=0x04 | 0x06 | 0x1F) && stack.len() >= max_depth {
+                            return Err(Error::LimitExceeded("nesting depth", max_depth));
+                        }
                         match opcode {
                             0x00 => (Self::Unreachable, bytes_),
                             0x01 => (Self::Nop, bytes_),
@@ -644,6 +990,48 @@ impl Instruction {
                                 bytes = left;
                                 break;
                             }
+                            0x06 => {
+                                let (bt, left) = BlockType::from_bytes(bytes_)?;
+                                stack.push(Action::Try {
+                                    block_type: bt,
+                                    clause: TryClause::Body,
+                                    body: Vec::new(),
+                                    catches: Vec::new(),
+                                    outer: std::mem::take(&mut ret),
+                                    outer_endset: endset,
+                                });
+                                stack.push(Action::Parse {
+                                    endset: &[0x07, 0x18, 0x19, 0x0B],
+                                });
+                                bytes = left;
+                                break;
+                            }
+                            0x08 => {
+                                let (tag, bytes) = bytes_.advance_u32()?;
+                                (Self::Throw(tag), bytes)
+                            }
+                            0x09 => {
+                                let (li, bytes) = bytes_.advance_u32()?;
+                                (Self::Rethrow(li), bytes)
+                            }
+                            0x0A => (Self::ThrowRef, bytes_),
+                            0x1F => {
+                                let (bt, left) = BlockType::from_bytes(bytes_)?;
+                                let mut catchit = left.advance_vector(Catch::from_bytes)?;
+                                let mut catches = Vec::new();
+                                for catch in &mut catchit {
+                                    catches.push(catch?);
+                                }
+                                stack.push(Action::TryTable {
+                                    block_type: bt,
+                                    catches,
+                                    outer: std::mem::take(&mut ret),
+                                    outer_endset: endset,
+                                });
+                                stack.push(Action::Parse { endset: &[0x0B] });
+                                bytes = catchit.finalize();
+                                break;
+                            }
                             0x0C => {
                                 let (li, bytes) = bytes_.advance_u32()?;
                                 (Self::Br(li), bytes)
@@ -672,10 +1060,36 @@ impl Instruction {
                                 let (table, bytes) = bytes.advance_u32()?;
                                 (Self::CallIndirect { ty, table }, bytes)
                             }
+                            0x12 => {
+                                let (fi, bytes) = bytes_.advance_u32()?;
+                                (Self::ReturnCall(fi), bytes)
+                            }
+                            0x13 => {
+                                let (ty, bytes) = bytes_.advance_u32()?;
+                                let (table, bytes) = bytes.advance_u32()?;
+                                (Self::ReturnCallIndirect { ty, table }, bytes)
+                            }
+                            0x14 => {
+                                let (ty, bytes) = bytes_.advance_u32()?;
+                                (Self::CallRef(ty), bytes)
+                            }
+                            0x15 => {
+                                let (ty, bytes) = bytes_.advance_u32()?;
+                                (Self::ReturnCallRef(ty), bytes)
+                            }
+                            0xD3 => (Self::RefEq, bytes_),
+                            0xD4 => (Self::RefAsNonNull, bytes_),
+                            0xD5 => {
+                                let (l, bytes) = bytes_.advance_u32()?;
+                                (Self::BrOnNull(l), bytes)
+                            }
+                            0xD6 => {
+                                let (l, bytes) = bytes_.advance_u32()?;
+                                (Self::BrOnNonNull(l), bytes)
+                            }
                             0xD0 => {
-                                let (&[rt], bytes) = bytes_.advance()?;
-                                let rt = ReferenceType::from_byte(rt)?;
-                                (Self::RefNull(rt), bytes)
+                                let (heap, bytes) = HeapType::from_bytes(bytes_)?;
+                                (Self::RefNull(ReferenceType::new(true, heap)), bytes)
                             }
                             0xD1 => (Self::RefIsNull, bytes_),
                             0xD2 => {
@@ -685,10 +1099,7 @@ impl Instruction {
                             0x1A => (Self::Drop, bytes_),
                             0x1B => (Self::SelectNumeric, bytes_),
                             0x1C => {
-                                let mut vtit = bytes_.advance_vector(|x| {
-                                    let (&[x], bytes) = x.advance()?;
-                                    Ok((ValueType::from_byte(x)?, bytes))
-                                })?;
+                                let mut vtit = bytes_.advance_vector(ValueType::from_bytes)?;
                                 let mut vts = Vec::new();
                                 for vt in &mut vtit {
                                     vts.push(vt?);
@@ -718,11 +1129,11 @@ impl Instruction {
                             }
                             0x25 => {
                                 let (ti, bytes) = bytes_.advance_u32()?;
-                                (Self::GlobalSet(ti), bytes)
+                                (Self::TableGet(ti), bytes)
                             }
                             0x26 => {
                                 let (ti, bytes) = bytes_.advance_u32()?;
-                                (Self::GlobalSet(ti), bytes)
+                                (Self::TableSet(ti), bytes)
                             }
                             0x28 => {
                                 let (ma, bytes) = MemArg::from_bytes(bytes_)?;
@@ -817,18 +1228,12 @@ impl Instruction {
                                 (Self::I64Store32(ma), bytes)
                             }
                             0x3F => {
-                                let (&[zero], bytes) = bytes_.advance()?;
-                                if zero != 0x00 {
-                                    return Err(Error::MemoryInstruction(0x3F, zero));
-                                }
-                                (Self::MemorySize, bytes)
+                                let (mi, bytes) = bytes_.advance_u32()?;
+                                (Self::MemorySize(mi), bytes)
                             }
                             0x40 => {
-                                let (&[zero], bytes) = bytes_.advance()?;
-                                if zero != 0x00 {
-                                    return Err(Error::MemoryInstruction(0x40, zero));
-                                }
-                                (Self::MemoryGrow, bytes)
+                                let (mi, bytes) = bytes_.advance_u32()?;
+                                (Self::MemoryGrow(mi), bytes)
                             }
                             0x41 => {
                                 let (n, bytes) = bytes_.advance_s32()?;
@@ -974,6 +1379,10 @@ impl Instruction {
                             0xC2 => (Self::I64Extend8S, bytes_),
                             0xC3 => (Self::I64Extend16S, bytes_),
                             0xC4 => (Self::I64Extend32S, bytes_),
+                            0xFB => {
+                                let (subop, bytes) = bytes_.advance_u32()?;
+                                Self::gc_from_bytes(subop, bytes)?
+                            }
                             0xFC => {
                                 let (subop, bytes) = bytes_.advance_u32()?;
                                 match subop {
@@ -987,45 +1396,21 @@ impl Instruction {
                                     7 => (Self::I64TruncSatF64U, bytes),
                                     8 => {
                                         let (di, bytes) = bytes.advance_u32()?;
-                                        let (&[zero], bytes) = bytes.advance()?;
-                                        if zero != 0x00 {
-                                            return Err(Error::MemoryInstructionNoTrailingZero {
-                                                instr: "memory.init",
-                                                byte: zero,
-                                            });
-                                        }
-                                        (Self::MemoryInit(di), bytes)
+                                        let (mi, bytes) = bytes.advance_u32()?;
+                                        (Self::MemoryInit(di, mi), bytes)
                                     }
                                     9 => {
                                         let (di, bytes) = bytes.advance_u32()?;
                                         (Self::DataDrop(di), bytes)
                                     }
                                     10 => {
-                                        let (&[zero], bytes) = bytes.advance()?;
-                                        if zero != 0x00 {
-                                            return Err(Error::MemoryInstructionNoTrailingZero {
-                                                instr: "memory.copy(first)",
-                                                byte: zero,
-                                            });
-                                        }
-                                        let (&[zero], bytes) = bytes.advance()?;
-                                        if zero != 0x00 {
-                                            return Err(Error::MemoryInstructionNoTrailingZero {
-                                                instr: "memory.copy(second)",
-                                                byte: zero,
-                                            });
-                                        }
-                                        (Self::MemoryCopy, bytes)
+                                        let (mi1, bytes) = bytes.advance_u32()?;
+                                        let (mi2, bytes) = bytes.advance_u32()?;
+                                        (Self::MemoryCopy(mi1, mi2), bytes)
                                     }
                                     11 => {
-                                        let (&[zero], bytes) = bytes.advance()?;
-                                        if zero != 0x00 {
-                                            return Err(Error::MemoryInstructionNoTrailingZero {
-                                                instr: "memory.fill",
-                                                byte: zero,
-                                            });
-                                        }
-                                        (Self::MemoryFill, bytes)
+                                        let (mi, bytes) = bytes.advance_u32()?;
+                                        (Self::MemoryFill(mi), bytes)
                                     }
                                     12 => {
                                         let (ei, bytes) = bytes.advance_u32()?;
@@ -1423,154 +1808,578 @@ impl Instruction {
                                     255 => (Self::F64x2ConvertLowI32x4U, bytes),
                                     94 => (Self::F32x4DemoteF64x2Zero, bytes),
                                     95 => (Self::F64x2PromoteLowF32x4, bytes),
+                                    256 => (Self::I8x16RelaxedSwizzle, bytes),
+                                    257 => (Self::I32x4RelaxedTruncF32x4S, bytes),
+                                    258 => (Self::I32x4RelaxedTruncF32x4U, bytes),
+                                    259 => (Self::I32x4RelaxedTruncF64x2SZero, bytes),
+                                    260 => (Self::I32x4RelaxedTruncF64x2UZero, bytes),
+                                    261 => (Self::F32x4RelaxedMadd, bytes),
+                                    262 => (Self::F32x4RelaxedNmadd, bytes),
+                                    263 => (Self::F64x2RelaxedMadd, bytes),
+                                    264 => (Self::F64x2RelaxedNmadd, bytes),
+                                    265 => (Self::I8x16RelaxedLaneselect, bytes),
+                                    266 => (Self::I16x8RelaxedLaneselect, bytes),
+                                    267 => (Self::I32x4RelaxedLaneselect, bytes),
+                                    268 => (Self::I64x2RelaxedLaneselect, bytes),
+                                    269 => (Self::F32x4RelaxedMin, bytes),
+                                    270 => (Self::F32x4RelaxedMax, bytes),
+                                    271 => (Self::F64x2RelaxedMin, bytes),
+                                    272 => (Self::F64x2RelaxedMax, bytes),
+                                    273 => (Self::I16x8RelaxedQ15mulrS, bytes),
+                                    274 => (Self::I16x8RelaxedDotI8x16I7x16S, bytes),
+                                    275 => (Self::I32x4RelaxedDotI8x16I7x16AddS, bytes),
                                     _ => return Err(Error::VectorInstructionSubopcode(subop)),
                                 }
                             }
-                            _ => return Err(Error::Opcode(opcode)),
-                        }
-                    };
-                    ret.push(instr);
-                    bytes = bytes_;
-                },
-                Action::Block {
-                    block_type,
-                    mut outer,
-                    outer_endset,
-                } => {
-                    outer.push(Self::Block(block_type, std::mem::take(&mut ret)));
-                    ret = outer;
-                    stack.push(Action::Parse {
-                        endset: outer_endset,
-                    });
-                }
-                Action::Loop {
-                    block_type,
-                    mut outer,
-                    outer_endset,
-                } => {
-                    outer.push(Self::Loop(block_type, std::mem::take(&mut ret)));
-                    ret = outer;
-                    stack.push(Action::Parse {
-                        endset: outer_endset,
-                    });
-                }
-                Action::If {
-                    block_type,
-                    mut outer,
-                    outer_endset,
-                } => {
-                    let end = last_end.take().expect("empty last_end");
-                    let instrs = std::mem::take(&mut ret);
-                    if end == 0x0B {
-                        outer.push(Self::If(block_type, instrs, None));
-                        ret = outer;
-                        stack.push(Action::Parse {
-                            endset: outer_endset,
-                        });
-                    } else {
-                        stack.push(Action::Else {
-                            if_instrs: instrs,
-                            block_type,
-                            outer,
-                            outer_endset,
-                        });
-                        stack.push(Action::Parse { endset: &[0x0B] });
-                    }
-                }
-                Action::Else {
-                    if_instrs,
-                    block_type,
-                    mut outer,
-                    outer_endset,
-                } => {
-                    outer.push(Self::If(
-                        block_type,
-                        if_instrs,
-                        Some(std::mem::take(&mut ret)),
-                    ));
-                    ret = outer;
-                    stack.push(Action::Parse {
-                        endset: outer_endset,
-                    });
-                }
-            }
-        }
-        unreachable!("empty stack")
-    }
-
-    pub(crate) fn write_slice_into(
-        this: &[Self],
-        terminator: Option<u8>,
-        wr: &mut impl Write,
-    ) -> Result<(), io::Error> {
-        // We manually implement a call stack as this procedure seems vulnerable to stack overflows
-        enum Action<'a> {
-            Instruction(&'a Instruction),
-            Terminator(u8),
-        }
-        let mut stack = Vec::new();
-
-        if let Some(terminator) = terminator {
-            stack.push(Action::Terminator(terminator));
-        }
-
-        for instruction in this.iter().rev() {
-            stack.push(Action::Instruction(instruction));
-        }
-
-        while let Some(action) = stack.pop() {
-            match action {
-                Action::Instruction(x) => match x {
-                    Instruction::Unreachable => {
-                        wr.write_all(&[0x00])?;
-                    }
-                    Instruction::Nop => {
-                        wr.write_all(&[0x01])?;
-                    }
-                    Instruction::Block(bt, instrs) => {
-                        wr.write_all(&[0x02])?;
-                        bt.write_into(wr)?;
-                        stack.push(Action::Terminator(0x0B));
-                        for instruction in instrs.iter().rev() {
-                            stack.push(Action::Instruction(instruction));
-                        }
-                    }
-                    Instruction::Loop(bt, instrs) => {
-                        wr.write_all(&[0x03])?;
-                        bt.write_into(wr)?;
-                        stack.push(Action::Terminator(0x0B));
-                        for instruction in instrs.iter().rev() {
-                            stack.push(Action::Instruction(instruction));
-                        }
-                    }
-                    Instruction::If(bt, instrs, elseinstrs) => {
-                        wr.write_all(&[0x04])?;
-                        bt.write_into(wr)?;
-                        if let Some(elseinstrs) = elseinstrs {
-                            // NOTE: as we push actions into *stack*, the push order between instrs
-                            // and elseinstrs is reversed
-                            stack.push(Action::Terminator(0x0B));
-                            for instruction in elseinstrs.iter().rev() {
-                                stack.push(Action::Instruction(instruction));
-                            }
-                            stack.push(Action::Terminator(0x05));
-                            for instruction in instrs.iter().rev() {
-                                stack.push(Action::Instruction(instruction));
-                            }
-                        } else {
-                            stack.push(Action::Terminator(0x0B));
-                            for instruction in instrs.iter().rev() {
-                                stack.push(Action::Instruction(instruction));
-                            }
-                        }
-                    }
-                    Instruction::Br(li) => {
-                        wr.write_all(&[0x0C])?;
-                        wr.write_u32(*li)?;
-                    }
-                    Instruction::BrIf(li) => {
-                        wr.write_all(&[0x0D])?;
-                        wr.write_u32(*li)?;
+                            0xFE => {
+                                let (subop, bytes) = bytes_.advance_u32()?;
+                                match subop {
+                                    0 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::MemoryAtomicNotify(ma), bytes)
+                                    }
+                                    1 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::MemoryAtomicWait32(ma), bytes)
+                                    }
+                                    2 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::MemoryAtomicWait64(ma), bytes)
+                                    }
+                                    3 => {
+                                        let (&[zero], bytes) = bytes.advance()?;
+                                        if zero != 0 {
+                                            return Err(Error::AtomicFenceNoTrailingZero);
+                                        }
+                                        (Self::AtomicFence, bytes)
+                                    }
+                                    16 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicLoad(ma), bytes)
+                                    }
+                                    17 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicLoad(ma), bytes)
+                                    }
+                                    18 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicLoad8U(ma), bytes)
+                                    }
+                                    19 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicLoad16U(ma), bytes)
+                                    }
+                                    20 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicLoad8U(ma), bytes)
+                                    }
+                                    21 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicLoad16U(ma), bytes)
+                                    }
+                                    22 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicLoad32U(ma), bytes)
+                                    }
+                                    23 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicStore(ma), bytes)
+                                    }
+                                    24 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicStore(ma), bytes)
+                                    }
+                                    25 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicStore8(ma), bytes)
+                                    }
+                                    26 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicStore16(ma), bytes)
+                                    }
+                                    27 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicStore8(ma), bytes)
+                                    }
+                                    28 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicStore16(ma), bytes)
+                                    }
+                                    29 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicStore32(ma), bytes)
+                                    }
+                                    30 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwAdd(ma), bytes)
+                                    }
+                                    31 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwAdd(ma), bytes)
+                                    }
+                                    32 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8AddU(ma), bytes)
+                                    }
+                                    33 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16AddU(ma), bytes)
+                                    }
+                                    34 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8AddU(ma), bytes)
+                                    }
+                                    35 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16AddU(ma), bytes)
+                                    }
+                                    36 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32AddU(ma), bytes)
+                                    }
+                                    37 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwSub(ma), bytes)
+                                    }
+                                    38 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwSub(ma), bytes)
+                                    }
+                                    39 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8SubU(ma), bytes)
+                                    }
+                                    40 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16SubU(ma), bytes)
+                                    }
+                                    41 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8SubU(ma), bytes)
+                                    }
+                                    42 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16SubU(ma), bytes)
+                                    }
+                                    43 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32SubU(ma), bytes)
+                                    }
+                                    44 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwAnd(ma), bytes)
+                                    }
+                                    45 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwAnd(ma), bytes)
+                                    }
+                                    46 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8AndU(ma), bytes)
+                                    }
+                                    47 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16AndU(ma), bytes)
+                                    }
+                                    48 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8AndU(ma), bytes)
+                                    }
+                                    49 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16AndU(ma), bytes)
+                                    }
+                                    50 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32AndU(ma), bytes)
+                                    }
+                                    51 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwOr(ma), bytes)
+                                    }
+                                    52 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwOr(ma), bytes)
+                                    }
+                                    53 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8OrU(ma), bytes)
+                                    }
+                                    54 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16OrU(ma), bytes)
+                                    }
+                                    55 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8OrU(ma), bytes)
+                                    }
+                                    56 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16OrU(ma), bytes)
+                                    }
+                                    57 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32OrU(ma), bytes)
+                                    }
+                                    58 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwXor(ma), bytes)
+                                    }
+                                    59 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwXor(ma), bytes)
+                                    }
+                                    60 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8XorU(ma), bytes)
+                                    }
+                                    61 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16XorU(ma), bytes)
+                                    }
+                                    62 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8XorU(ma), bytes)
+                                    }
+                                    63 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16XorU(ma), bytes)
+                                    }
+                                    64 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32XorU(ma), bytes)
+                                    }
+                                    65 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwXchg(ma), bytes)
+                                    }
+                                    66 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwXchg(ma), bytes)
+                                    }
+                                    67 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8XchgU(ma), bytes)
+                                    }
+                                    68 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16XchgU(ma), bytes)
+                                    }
+                                    69 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8XchgU(ma), bytes)
+                                    }
+                                    70 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16XchgU(ma), bytes)
+                                    }
+                                    71 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32XchgU(ma), bytes)
+                                    }
+                                    72 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmwCmpxchg(ma), bytes)
+                                    }
+                                    73 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmwCmpxchg(ma), bytes)
+                                    }
+                                    74 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw8CmpxchgU(ma), bytes)
+                                    }
+                                    75 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I32AtomicRmw16CmpxchgU(ma), bytes)
+                                    }
+                                    76 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw8CmpxchgU(ma), bytes)
+                                    }
+                                    77 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw16CmpxchgU(ma), bytes)
+                                    }
+                                    78 => {
+                                        let (ma, bytes) = MemArg::from_bytes(bytes)?;
+                                        (Self::I64AtomicRmw32CmpxchgU(ma), bytes)
+                                    }
+                                    _ => return Err(Error::HexFeInstructionSubopcode(subop)),
+                                }
+                            }
+                            _ => return Err(Error::Opcode(opcode)),
+                        }
+                    };
+                    ret.push(instr);
+                    bytes = bytes_;
+                },
+                Action::Block {
+                    block_type,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    outer.push(Self::Block(block_type, std::mem::take(&mut ret)));
+                    ret = outer;
+                    stack.push(Action::Parse {
+                        endset: outer_endset,
+                    });
+                }
+                Action::Loop {
+                    block_type,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    outer.push(Self::Loop(block_type, std::mem::take(&mut ret)));
+                    ret = outer;
+                    stack.push(Action::Parse {
+                        endset: outer_endset,
+                    });
+                }
+                Action::If {
+                    block_type,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    let end = last_end.take().expect("empty last_end");
+                    let instrs = std::mem::take(&mut ret);
+                    if end == 0x0B {
+                        outer.push(Self::If(block_type, instrs, None));
+                        ret = outer;
+                        stack.push(Action::Parse {
+                            endset: outer_endset,
+                        });
+                    } else {
+                        stack.push(Action::Else {
+                            if_instrs: instrs,
+                            block_type,
+                            outer,
+                            outer_endset,
+                        });
+                        stack.push(Action::Parse { endset: &[0x0B] });
+                    }
+                }
+                Action::Else {
+                    if_instrs,
+                    block_type,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    outer.push(Self::If(
+                        block_type,
+                        if_instrs,
+                        Some(std::mem::take(&mut ret)),
+                    ));
+                    ret = outer;
+                    stack.push(Action::Parse {
+                        endset: outer_endset,
+                    });
+                }
+                Action::Try {
+                    block_type,
+                    clause,
+                    mut body,
+                    mut catches,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    let end = last_end.take().expect("empty last_end");
+                    let instrs = std::mem::take(&mut ret);
+                    let catch_all = match clause {
+                        TryClause::Body => {
+                            body = instrs;
+                            None
+                        }
+                        TryClause::Catch(tag) => {
+                            catches.push((tag, instrs));
+                            None
+                        }
+                        TryClause::CatchAll => Some(instrs),
+                    };
+                    let clause = match end {
+                        0x07 => {
+                            let (tag, bytes_) = bytes.advance_u32()?;
+                            bytes = bytes_;
+                            TryClause::Catch(tag)
+                        }
+                        0x19 => TryClause::CatchAll,
+                        0x18 => {
+                            let (li, bytes_) = bytes.advance_u32()?;
+                            bytes = bytes_;
+                            outer.push(Self::TryDelegate(block_type, body, li));
+                            ret = outer;
+                            stack.push(Action::Parse {
+                                endset: outer_endset,
+                            });
+                            continue;
+                        }
+                        _ => {
+                            outer.push(Self::Try(block_type, body, catches, catch_all));
+                            ret = outer;
+                            stack.push(Action::Parse {
+                                endset: outer_endset,
+                            });
+                            continue;
+                        }
+                    };
+                    let endset: &[u8] = match clause {
+                        TryClause::CatchAll => &[0x0B],
+                        _ => &[0x07, 0x19, 0x0B],
+                    };
+                    stack.push(Action::Try {
+                        block_type,
+                        clause,
+                        body,
+                        catches,
+                        outer,
+                        outer_endset,
+                    });
+                    stack.push(Action::Parse { endset });
+                }
+                Action::TryTable {
+                    block_type,
+                    catches,
+                    mut outer,
+                    outer_endset,
+                } => {
+                    outer.push(Self::TryTable(
+                        block_type,
+                        catches,
+                        std::mem::take(&mut ret),
+                    ));
+                    ret = outer;
+                    stack.push(Action::Parse {
+                        endset: outer_endset,
+                    });
+                }
+            }
+        }
+        unreachable!("empty stack")
+    }
+
+    pub(crate) fn write_slice_into(
+        this: &[Self],
+        terminator: Option<u8>,
+        wr: &mut impl Write,
+    ) -> Result<(), io::Error> {
+        // We manually implement a call stack as this procedure seems vulnerable to stack overflows
+        enum Action<'a> {
+            Instruction(&'a Instruction),
+            Terminator(u8),
+            Catch(u32),
+            Delegate(u32),
+        }
+        let mut stack = Vec::new();
+
+        if let Some(terminator) = terminator {
+            stack.push(Action::Terminator(terminator));
+        }
+
+        for instruction in this.iter().rev() {
+            stack.push(Action::Instruction(instruction));
+        }
+
+        while let Some(action) = stack.pop() {
+            match action {
+                Action::Instruction(x) => match x {
+                    Instruction::Unreachable => {
+                        wr.write_all(&[0x00])?;
+                    }
+                    Instruction::Nop => {
+                        wr.write_all(&[0x01])?;
+                    }
+                    Instruction::Block(bt, instrs) => {
+                        wr.write_all(&[0x02])?;
+                        bt.write_into(wr)?;
+                        stack.push(Action::Terminator(0x0B));
+                        for instruction in instrs.iter().rev() {
+                            stack.push(Action::Instruction(instruction));
+                        }
+                    }
+                    Instruction::Loop(bt, instrs) => {
+                        wr.write_all(&[0x03])?;
+                        bt.write_into(wr)?;
+                        stack.push(Action::Terminator(0x0B));
+                        for instruction in instrs.iter().rev() {
+                            stack.push(Action::Instruction(instruction));
+                        }
+                    }
+                    Instruction::If(bt, instrs, elseinstrs) => {
+                        wr.write_all(&[0x04])?;
+                        bt.write_into(wr)?;
+                        if let Some(elseinstrs) = elseinstrs {
+                            // NOTE: as we push actions into *stack*, the push order between instrs
+                            // and elseinstrs is reversed
+                            stack.push(Action::Terminator(0x0B));
+                            for instruction in elseinstrs.iter().rev() {
+                                stack.push(Action::Instruction(instruction));
+                            }
+                            stack.push(Action::Terminator(0x05));
+                            for instruction in instrs.iter().rev() {
+                                stack.push(Action::Instruction(instruction));
+                            }
+                        } else {
+                            stack.push(Action::Terminator(0x0B));
+                            for instruction in instrs.iter().rev() {
+                                stack.push(Action::Instruction(instruction));
+                            }
+                        }
+                    }
+                    Instruction::Try(bt, instrs, catches, catch_all) => {
+                        wr.write_all(&[0x06])?;
+                        bt.write_into(wr)?;
+                        stack.push(Action::Terminator(0x0B));
+                        if let Some(catch_all) = catch_all {
+                            for instruction in catch_all.iter().rev() {
+                                stack.push(Action::Instruction(instruction));
+                            }
+                            stack.push(Action::Terminator(0x19));
+                        }
+                        for (tag, catch) in catches.iter().rev() {
+                            for instruction in catch.iter().rev() {
+                                stack.push(Action::Instruction(instruction));
+                            }
+                            stack.push(Action::Catch(*tag));
+                        }
+                        for instruction in instrs.iter().rev() {
+                            stack.push(Action::Instruction(instruction));
+                        }
+                    }
+                    Instruction::TryDelegate(bt, instrs, li) => {
+                        wr.write_all(&[0x06])?;
+                        bt.write_into(wr)?;
+                        stack.push(Action::Delegate(*li));
+                        for instruction in instrs.iter().rev() {
+                            stack.push(Action::Instruction(instruction));
+                        }
+                    }
+                    Instruction::Throw(tag) => {
+                        wr.write_all(&[0x08])?;
+                        wr.write_u32(*tag)?;
+                    }
+                    Instruction::Rethrow(li) => {
+                        wr.write_all(&[0x09])?;
+                        wr.write_u32(*li)?;
+                    }
+                    Instruction::ThrowRef => {
+                        wr.write_all(&[0x0A])?;
+                    }
+                    Instruction::TryTable(bt, catches, instrs) => {
+                        wr.write_all(&[0x1F])?;
+                        bt.write_into(wr)?;
+                        wr.write_vector(catches, |catch, wr| catch.write_into(wr))?;
+                        stack.push(Action::Terminator(0x0B));
+                        for instruction in instrs.iter().rev() {
+                            stack.push(Action::Instruction(instruction));
+                        }
+                    }
+                    Instruction::Br(li) => {
+                        wr.write_all(&[0x0C])?;
+                        wr.write_u32(*li)?;
+                    }
+                    Instruction::BrIf(li) => {
+                        wr.write_all(&[0x0D])?;
+                        wr.write_u32(*li)?;
                     }
                     Instruction::BrTable(lis, ln) => {
                         wr.write_all(&[0x0E])?;
@@ -1589,8 +2398,40 @@ impl Instruction {
                         wr.write_u32(*ty)?;
                         wr.write_u32(*table)?;
                     }
-                    Instruction::RefNull(_) => {
+                    Instruction::ReturnCall(fi) => {
+                        wr.write_all(&[0x12])?;
+                        wr.write_u32(*fi)?;
+                    }
+                    Instruction::ReturnCallIndirect { ty, table } => {
+                        wr.write_all(&[0x13])?;
+                        wr.write_u32(*ty)?;
+                        wr.write_u32(*table)?;
+                    }
+                    Instruction::CallRef(ty) => {
+                        wr.write_all(&[0x14])?;
+                        wr.write_u32(*ty)?;
+                    }
+                    Instruction::ReturnCallRef(ty) => {
+                        wr.write_all(&[0x15])?;
+                        wr.write_u32(*ty)?;
+                    }
+                    Instruction::RefNull(rt) => {
                         wr.write_all(&[0xD0])?;
+                        rt.heap_type().write_into(wr)?;
+                    }
+                    Instruction::RefEq => {
+                        wr.write_all(&[0xD3])?;
+                    }
+                    Instruction::RefAsNonNull => {
+                        wr.write_all(&[0xD4])?;
+                    }
+                    Instruction::BrOnNull(l) => {
+                        wr.write_all(&[0xD5])?;
+                        wr.write_u32(*l)?;
+                    }
+                    Instruction::BrOnNonNull(l) => {
+                        wr.write_all(&[0xD6])?;
+                        wr.write_u32(*l)?;
                     }
                     Instruction::RefIsNull => {
                         wr.write_all(&[0xD1])?;
@@ -1761,32 +2602,35 @@ impl Instruction {
                         wr.write_all(&[0x3E])?;
                         ma.write_into(wr)?;
                     }
-                    Instruction::MemorySize => {
-                        wr.write_all(&[0x3F, 0x00])?;
+                    Instruction::MemorySize(mi) => {
+                        wr.write_all(&[0x3F])?;
+                        wr.write_u32(*mi)?;
                     }
-                    Instruction::MemoryGrow => {
-                        wr.write_all(&[0x40, 0x00])?;
+                    Instruction::MemoryGrow(mi) => {
+                        wr.write_all(&[0x40])?;
+                        wr.write_u32(*mi)?;
                     }
-                    Instruction::MemoryInit(di) => {
+                    Instruction::MemoryInit(di, mi) => {
                         wr.write_all(&[0xFC])?;
                         wr.write_u32(8)?;
                         wr.write_u32(*di)?;
-                        wr.write_all(&[0x00])?;
+                        wr.write_u32(*mi)?;
                     }
                     Instruction::DataDrop(di) => {
                         wr.write_all(&[0xFC])?;
                         wr.write_u32(9)?;
                         wr.write_u32(*di)?;
                     }
-                    Instruction::MemoryCopy => {
+                    Instruction::MemoryCopy(mi1, mi2) => {
                         wr.write_all(&[0xFC])?;
                         wr.write_u32(10)?;
-                        wr.write_all(&[0x00, 0x00])?;
+                        wr.write_u32(*mi1)?;
+                        wr.write_u32(*mi2)?;
                     }
-                    Instruction::MemoryFill => {
+                    Instruction::MemoryFill(mi) => {
                         wr.write_all(&[0xFC])?;
                         wr.write_u32(11)?;
-                        wr.write_all(&[0x00])?;
+                        wr.write_u32(*mi)?;
                     }
                     Instruction::I32Const(n) => {
                         wr.write_all(&[0x41])?;
@@ -2862,389 +3706,1312 @@ impl Instruction {
                     }
                     Instruction::I16x8AvgrU => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(155)?;
+                        wr.write_u32(155)?;
+                    }
+                    Instruction::I16x8ExtmulLowI8x16S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(156)?;
+                    }
+                    Instruction::I16x8ExtmulHighI8x16S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(157)?;
+                    }
+                    Instruction::I16x8ExtmulLowI8x16U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(158)?;
+                    }
+                    Instruction::I16x8ExtmulHighI8x16U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(159)?;
+                    }
+                    Instruction::I32x4ExtAddPairwiseI16x8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(126)?;
+                    }
+                    Instruction::I32x4ExtAddPairwiseI16x8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(127)?;
+                    }
+                    Instruction::I32x4Abs => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(160)?;
+                    }
+                    Instruction::I32x4Neg => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(161)?;
+                    }
+                    Instruction::I32x4AllTrue => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(163)?;
+                    }
+                    Instruction::I32x4Bitmask => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(164)?;
+                    }
+                    Instruction::I32x4ExtendLowI16X8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(167)?;
+                    }
+                    Instruction::I32x4ExtendHighI16X8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(168)?;
+                    }
+                    Instruction::I32x4ExtendLowI16X8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(169)?;
+                    }
+                    Instruction::I32x4ExtendHighI16X8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(170)?;
+                    }
+                    Instruction::I32x4Shl => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(171)?;
+                    }
+                    Instruction::I32x4ShrS => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(172)?;
+                    }
+                    Instruction::I32x4ShrU => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(173)?;
+                    }
+                    Instruction::I32x4Add => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(174)?;
+                    }
+                    Instruction::I32x4Sub => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(177)?;
+                    }
+                    Instruction::I32x4Mul => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(181)?;
+                    }
+                    Instruction::I32x4MinS => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(182)?;
+                    }
+                    Instruction::I32x4MinU => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(183)?;
+                    }
+                    Instruction::I32x4MaxS => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(184)?;
+                    }
+                    Instruction::I32x4MaxU => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(185)?;
+                    }
+                    Instruction::I32x4DotI16x8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(186)?;
+                    }
+                    Instruction::I32x4ExtmulLowI16x8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(188)?;
+                    }
+                    Instruction::I32x4ExtmulHighI16x8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(189)?;
+                    }
+                    Instruction::I32x4ExtmulLowI16x8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(190)?;
+                    }
+                    Instruction::I32x4ExtmulHighI16x8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(191)?;
+                    }
+                    Instruction::I64x2Abs => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(192)?;
+                    }
+                    Instruction::I64x2Neg => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(193)?;
+                    }
+                    Instruction::I64x2AllTrue => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(195)?;
+                    }
+                    Instruction::I64x2Bitmask => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(196)?;
+                    }
+                    Instruction::I64x2ExtendLowI16X8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(199)?;
+                    }
+                    Instruction::I64x2ExtendHighI16X8S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(200)?;
+                    }
+                    Instruction::I64x2ExtendLowI16X8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(201)?;
+                    }
+                    Instruction::I64x2ExtendHighI16X8U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(202)?;
+                    }
+                    Instruction::I64x2Shl => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(203)?;
+                    }
+                    Instruction::I64x2ShrS => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(204)?;
+                    }
+                    Instruction::I64x2ShrU => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(205)?;
+                    }
+                    Instruction::I64x2Add => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(206)?;
+                    }
+                    Instruction::I64x2Sub => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(209)?;
+                    }
+                    Instruction::I64x2Mul => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(213)?;
+                    }
+                    Instruction::I64x2ExtmulLowI32x4S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(220)?;
+                    }
+                    Instruction::I64x2ExtmulHighI32x4S => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(221)?;
+                    }
+                    Instruction::I64x2ExtmulLowI32x4U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(222)?;
+                    }
+                    Instruction::I64x2ExtmulHighI32x4U => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(223)?;
+                    }
+                    Instruction::F32x4Ceil => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(103)?;
+                    }
+                    Instruction::F32x4Floor => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(104)?;
+                    }
+                    Instruction::F32x4Trunc => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(105)?;
+                    }
+                    Instruction::F32x4Nearest => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(106)?;
+                    }
+                    Instruction::F32x4Abs => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(224)?;
+                    }
+                    Instruction::F32x4Neg => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(225)?;
+                    }
+                    Instruction::F32x4Sqrt => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(227)?;
+                    }
+                    Instruction::F32x4Add => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(228)?;
+                    }
+                    Instruction::F32x4Sub => {
+                        wr.write_all(&[0xFD])?;
+                        wr.write_u32(229)?;
                     }
-                    Instruction::I16x8ExtmulLowI8x16S => {
+                    Instruction::F32x4Mul => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(156)?;
+                        wr.write_u32(230)?;
                     }
-                    Instruction::I16x8ExtmulHighI8x16S => {
+                    Instruction::F32x4Div => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(157)?;
+                        wr.write_u32(231)?;
                     }
-                    Instruction::I16x8ExtmulLowI8x16U => {
+                    Instruction::F32x4Min => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(158)?;
+                        wr.write_u32(232)?;
                     }
-                    Instruction::I16x8ExtmulHighI8x16U => {
+                    Instruction::F32x4Max => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(159)?;
+                        wr.write_u32(233)?;
                     }
-                    Instruction::I32x4ExtAddPairwiseI16x8S => {
+                    Instruction::F32x4Pmin => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(126)?;
+                        wr.write_u32(234)?;
                     }
-                    Instruction::I32x4ExtAddPairwiseI16x8U => {
+                    Instruction::F32x4Pmax => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(127)?;
+                        wr.write_u32(235)?;
                     }
-                    Instruction::I32x4Abs => {
+                    Instruction::F64x2Ceil => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(160)?;
+                        wr.write_u32(116)?;
                     }
-                    Instruction::I32x4Neg => {
+                    Instruction::F64x2Floor => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(161)?;
+                        wr.write_u32(117)?;
                     }
-                    Instruction::I32x4AllTrue => {
+                    Instruction::F64x2Trunc => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(163)?;
+                        wr.write_u32(122)?;
                     }
-                    Instruction::I32x4Bitmask => {
+                    Instruction::F64x2Nearest => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(164)?;
+                        wr.write_u32(148)?;
                     }
-                    Instruction::I32x4ExtendLowI16X8S => {
+                    Instruction::F64x2Abs => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(167)?;
+                        wr.write_u32(236)?;
                     }
-                    Instruction::I32x4ExtendHighI16X8S => {
+                    Instruction::F64x2Neg => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(168)?;
+                        wr.write_u32(237)?;
                     }
-                    Instruction::I32x4ExtendLowI16X8U => {
+                    Instruction::F64x2Sqrt => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(169)?;
+                        wr.write_u32(239)?;
                     }
-                    Instruction::I32x4ExtendHighI16X8U => {
+                    Instruction::F64x2Add => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(170)?;
+                        wr.write_u32(240)?;
                     }
-                    Instruction::I32x4Shl => {
+                    Instruction::F64x2Sub => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(171)?;
+                        wr.write_u32(241)?;
                     }
-                    Instruction::I32x4ShrS => {
+                    Instruction::F64x2Mul => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(172)?;
+                        wr.write_u32(242)?;
                     }
-                    Instruction::I32x4ShrU => {
+                    Instruction::F64x2Div => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(173)?;
+                        wr.write_u32(243)?;
                     }
-                    Instruction::I32x4Add => {
+                    Instruction::F64x2Min => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(174)?;
+                        wr.write_u32(244)?;
                     }
-                    Instruction::I32x4Sub => {
+                    Instruction::F64x2Max => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(177)?;
+                        wr.write_u32(245)?;
                     }
-                    Instruction::I32x4Mul => {
+                    Instruction::F64x2Pmin => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(181)?;
+                        wr.write_u32(246)?;
                     }
-                    Instruction::I32x4MinS => {
+                    Instruction::F64x2Pmax => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(182)?;
+                        wr.write_u32(247)?;
                     }
-                    Instruction::I32x4MinU => {
+                    Instruction::I32x4TruncSatF32x4S => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(183)?;
+                        wr.write_u32(248)?;
                     }
-                    Instruction::I32x4MaxS => {
+                    Instruction::I32x4TruncSatF32x4U => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(184)?;
+                        wr.write_u32(249)?;
                     }
-                    Instruction::I32x4MaxU => {
+                    Instruction::F32x4ConvertI32x4S => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(185)?;
+                        wr.write_u32(250)?;
                     }
-                    Instruction::I32x4DotI16x8S => {
+                    Instruction::F32x4ConvertI32x4U => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(186)?;
+                        wr.write_u32(251)?;
                     }
-                    Instruction::I32x4ExtmulLowI16x8S => {
+                    Instruction::I32x4TruncSatF64x2SZero => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(188)?;
+                        wr.write_u32(252)?;
                     }
-                    Instruction::I32x4ExtmulHighI16x8S => {
+                    Instruction::I32x4TruncSatF64x2UZero => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(189)?;
+                        wr.write_u32(253)?;
                     }
-                    Instruction::I32x4ExtmulLowI16x8U => {
+                    Instruction::F64x2ConvertLowI32x4S => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(190)?;
+                        wr.write_u32(254)?;
                     }
-                    Instruction::I32x4ExtmulHighI16x8U => {
+                    Instruction::F64x2ConvertLowI32x4U => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(191)?;
+                        wr.write_u32(255)?;
                     }
-                    Instruction::I64x2Abs => {
+                    Instruction::F32x4DemoteF64x2Zero => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(192)?;
+                        wr.write_u32(94)?;
                     }
-                    Instruction::I64x2Neg => {
+                    Instruction::F64x2PromoteLowF32x4 => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(193)?;
+                        wr.write_u32(95)?;
                     }
-                    Instruction::I64x2AllTrue => {
+                    Instruction::I8x16RelaxedSwizzle => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(195)?;
+                        wr.write_u32(256)?;
                     }
-                    Instruction::I64x2Bitmask => {
+                    Instruction::I32x4RelaxedTruncF32x4S => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(196)?;
+                        wr.write_u32(257)?;
                     }
-                    Instruction::I64x2ExtendLowI16X8S => {
+                    Instruction::I32x4RelaxedTruncF32x4U => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(199)?;
+                        wr.write_u32(258)?;
                     }
-                    Instruction::I64x2ExtendHighI16X8S => {
+                    Instruction::I32x4RelaxedTruncF64x2SZero => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(200)?;
+                        wr.write_u32(259)?;
                     }
-                    Instruction::I64x2ExtendLowI16X8U => {
+                    Instruction::I32x4RelaxedTruncF64x2UZero => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(201)?;
+                        wr.write_u32(260)?;
                     }
-                    Instruction::I64x2ExtendHighI16X8U => {
+                    Instruction::F32x4RelaxedMadd => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(202)?;
+                        wr.write_u32(261)?;
                     }
-                    Instruction::I64x2Shl => {
+                    Instruction::F32x4RelaxedNmadd => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(203)?;
+                        wr.write_u32(262)?;
                     }
-                    Instruction::I64x2ShrS => {
+                    Instruction::F64x2RelaxedMadd => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(204)?;
+                        wr.write_u32(263)?;
                     }
-                    Instruction::I64x2ShrU => {
+                    Instruction::F64x2RelaxedNmadd => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(205)?;
+                        wr.write_u32(264)?;
                     }
-                    Instruction::I64x2Add => {
+                    Instruction::I8x16RelaxedLaneselect => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(206)?;
+                        wr.write_u32(265)?;
                     }
-                    Instruction::I64x2Sub => {
+                    Instruction::I16x8RelaxedLaneselect => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(209)?;
+                        wr.write_u32(266)?;
                     }
-                    Instruction::I64x2Mul => {
+                    Instruction::I32x4RelaxedLaneselect => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(213)?;
+                        wr.write_u32(267)?;
                     }
-                    Instruction::I64x2ExtmulLowI32x4S => {
+                    Instruction::I64x2RelaxedLaneselect => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(220)?;
+                        wr.write_u32(268)?;
                     }
-                    Instruction::I64x2ExtmulHighI32x4S => {
+                    Instruction::F32x4RelaxedMin => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(221)?;
+                        wr.write_u32(269)?;
                     }
-                    Instruction::I64x2ExtmulLowI32x4U => {
+                    Instruction::F32x4RelaxedMax => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(222)?;
+                        wr.write_u32(270)?;
                     }
-                    Instruction::I64x2ExtmulHighI32x4U => {
+                    Instruction::F64x2RelaxedMin => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(223)?;
+                        wr.write_u32(271)?;
                     }
-                    Instruction::F32x4Ceil => {
+                    Instruction::F64x2RelaxedMax => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(103)?;
+                        wr.write_u32(272)?;
                     }
-                    Instruction::F32x4Floor => {
+                    Instruction::I16x8RelaxedQ15mulrS => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(104)?;
+                        wr.write_u32(273)?;
                     }
-                    Instruction::F32x4Trunc => {
+                    Instruction::I16x8RelaxedDotI8x16I7x16S => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(105)?;
+                        wr.write_u32(274)?;
                     }
-                    Instruction::F32x4Nearest => {
+                    Instruction::I32x4RelaxedDotI8x16I7x16AddS => {
                         wr.write_all(&[0xFD])?;
-                        wr.write_u32(106)?;
+                        wr.write_u32(275)?;
+                    }
+                    Instruction::MemoryAtomicNotify(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(0)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::MemoryAtomicWait32(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(1)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::MemoryAtomicWait64(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(2)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::AtomicFence => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(3)?;
+                        wr.write_all(&[0x00])?;
+                    }
+                    Instruction::I32AtomicLoad(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(16)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicLoad(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(17)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicLoad8U(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(18)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicLoad16U(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(19)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicLoad8U(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(20)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicLoad16U(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(21)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicLoad32U(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(22)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicStore(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(23)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicStore(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(24)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicStore8(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(25)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicStore16(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(26)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicStore8(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(27)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicStore16(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(28)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicStore32(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(29)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmwAdd(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(30)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmwAdd(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(31)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmw8AddU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(32)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmw16AddU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(33)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmw8AddU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(34)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmw16AddU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(35)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmw32AddU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(36)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmwSub(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(37)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmwSub(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(38)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmw8SubU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(39)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I32AtomicRmw16SubU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(40)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmw8SubU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(41)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Abs => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(224)?;
+                    Instruction::I64AtomicRmw16SubU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(42)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Neg => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(225)?;
+                    Instruction::I64AtomicRmw32SubU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(43)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Sqrt => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(227)?;
+                    Instruction::I32AtomicRmwAnd(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(44)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Add => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(228)?;
+                    Instruction::I64AtomicRmwAnd(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(45)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Sub => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(229)?;
+                    Instruction::I32AtomicRmw8AndU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(46)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Mul => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(230)?;
+                    Instruction::I32AtomicRmw16AndU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(47)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Div => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(231)?;
+                    Instruction::I64AtomicRmw8AndU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(48)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Min => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(232)?;
+                    Instruction::I64AtomicRmw16AndU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(49)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Max => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(233)?;
+                    Instruction::I64AtomicRmw32AndU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(50)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Pmin => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(234)?;
+                    Instruction::I32AtomicRmwOr(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(51)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4Pmax => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(235)?;
+                    Instruction::I64AtomicRmwOr(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(52)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Ceil => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(116)?;
+                    Instruction::I32AtomicRmw8OrU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(53)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Floor => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(117)?;
+                    Instruction::I32AtomicRmw16OrU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(54)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Trunc => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(122)?;
+                    Instruction::I64AtomicRmw8OrU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(55)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Nearest => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(148)?;
+                    Instruction::I64AtomicRmw16OrU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(56)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Abs => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(236)?;
+                    Instruction::I64AtomicRmw32OrU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(57)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Neg => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(237)?;
+                    Instruction::I32AtomicRmwXor(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(58)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Sqrt => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(239)?;
+                    Instruction::I64AtomicRmwXor(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(59)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Add => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(240)?;
+                    Instruction::I32AtomicRmw8XorU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(60)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Sub => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(241)?;
+                    Instruction::I32AtomicRmw16XorU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(61)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Mul => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(242)?;
+                    Instruction::I64AtomicRmw8XorU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(62)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Div => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(243)?;
+                    Instruction::I64AtomicRmw16XorU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(63)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Min => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(244)?;
+                    Instruction::I64AtomicRmw32XorU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(64)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Max => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(245)?;
+                    Instruction::I32AtomicRmwXchg(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(65)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Pmin => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(246)?;
+                    Instruction::I64AtomicRmwXchg(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(66)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2Pmax => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(247)?;
+                    Instruction::I32AtomicRmw8XchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(67)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::I32x4TruncSatF32x4S => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(248)?;
+                    Instruction::I32AtomicRmw16XchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(68)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::I32x4TruncSatF32x4U => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(249)?;
+                    Instruction::I64AtomicRmw8XchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(69)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4ConvertI32x4S => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(250)?;
+                    Instruction::I64AtomicRmw16XchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(70)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4ConvertI32x4U => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(251)?;
+                    Instruction::I64AtomicRmw32XchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(71)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::I32x4TruncSatF64x2SZero => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(252)?;
+                    Instruction::I32AtomicRmwCmpxchg(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(72)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::I32x4TruncSatF64x2UZero => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(253)?;
+                    Instruction::I64AtomicRmwCmpxchg(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(73)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2ConvertLowI32x4S => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(254)?;
+                    Instruction::I32AtomicRmw8CmpxchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(74)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2ConvertLowI32x4U => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(255)?;
+                    Instruction::I32AtomicRmw16CmpxchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(75)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F32x4DemoteF64x2Zero => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(94)?;
+                    Instruction::I64AtomicRmw8CmpxchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(76)?;
+                        ma.write_into(wr)?;
                     }
-                    Instruction::F64x2PromoteLowF32x4 => {
-                        wr.write_all(&[0xFD])?;
-                        wr.write_u32(95)?;
+                    Instruction::I64AtomicRmw16CmpxchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(77)?;
+                        ma.write_into(wr)?;
+                    }
+                    Instruction::I64AtomicRmw32CmpxchgU(ma) => {
+                        wr.write_all(&[0xFE])?;
+                        wr.write_u32(78)?;
+                        ma.write_into(wr)?;
                     }
+                    gc => gc.write_gc_into(wr)?,
                 },
                 Action::Terminator(terminator) => {
                     wr.write_all(&[terminator])?;
                 }
+                Action::Catch(tag) => {
+                    wr.write_all(&[0x07])?;
+                    wr.write_u32(tag)?;
+                }
+                Action::Delegate(li) => {
+                    wr.write_all(&[0x18])?;
+                    wr.write_u32(li)?;
+                }
             }
         }
         Ok(())
     }
 
-    pub(crate) fn visit_func_indices(&mut self, mut func: impl FnMut(&mut u32) + Copy) {
+    /// Returns the memory argument of a load or store instruction.
+    pub fn memarg(&self) -> Option<&MemArg> {
         match self {
-            Self::Block(_, instrs) => {
-                for instr in instrs {
-                    instr.visit_func_indices(func);
-                }
+            Self::I32Load(ma)
+            | Self::I64Load(ma)
+            | Self::F32Load(ma)
+            | Self::F64Load(ma)
+            | Self::I32Load8S(ma)
+            | Self::I32Load8U(ma)
+            | Self::I32Load16S(ma)
+            | Self::I32Load16U(ma)
+            | Self::I64Load8S(ma)
+            | Self::I64Load8U(ma)
+            | Self::I64Load16S(ma)
+            | Self::I64Load16U(ma)
+            | Self::I64Load32S(ma)
+            | Self::I64Load32U(ma)
+            | Self::I32Store(ma)
+            | Self::I64Store(ma)
+            | Self::F32Store(ma)
+            | Self::F64Store(ma)
+            | Self::I32Store8(ma)
+            | Self::I32Store16(ma)
+            | Self::I64Store8(ma)
+            | Self::I64Store16(ma)
+            | Self::I64Store32(ma)
+            | Self::V128Load(ma)
+            | Self::V128Load8x8S(ma)
+            | Self::V128Load8x8U(ma)
+            | Self::V128Load16x4S(ma)
+            | Self::V128Load16x4U(ma)
+            | Self::V128Load32x2S(ma)
+            | Self::V128Load32x2U(ma)
+            | Self::V128Load8Splat(ma)
+            | Self::V128Load16Splat(ma)
+            | Self::V128Load32Splat(ma)
+            | Self::V128Load64Splat(ma)
+            | Self::V128Load32Zero(ma)
+            | Self::V128Load64Zero(ma)
+            | Self::V128Store(ma) => Some(ma),
+            Self::V128Load8Lane(ma, _)
+            | Self::V128Load16Lane(ma, _)
+            | Self::V128Load32Lane(ma, _)
+            | Self::V128Load64Lane(ma, _)
+            | Self::V128Store8Lane(ma, _)
+            | Self::V128Store16Lane(ma, _)
+            | Self::V128Store32Lane(ma, _)
+            | Self::V128Store64Lane(ma, _) => Some(ma),
+            Self::MemoryAtomicNotify(ma)
+            | Self::MemoryAtomicWait32(ma)
+            | Self::MemoryAtomicWait64(ma)
+            | Self::I32AtomicLoad(ma)
+            | Self::I64AtomicLoad(ma)
+            | Self::I32AtomicLoad8U(ma)
+            | Self::I32AtomicLoad16U(ma)
+            | Self::I64AtomicLoad8U(ma)
+            | Self::I64AtomicLoad16U(ma)
+            | Self::I64AtomicLoad32U(ma)
+            | Self::I32AtomicStore(ma)
+            | Self::I64AtomicStore(ma)
+            | Self::I32AtomicStore8(ma)
+            | Self::I32AtomicStore16(ma)
+            | Self::I64AtomicStore8(ma)
+            | Self::I64AtomicStore16(ma)
+            | Self::I64AtomicStore32(ma)
+            | Self::I32AtomicRmwAdd(ma)
+            | Self::I64AtomicRmwAdd(ma)
+            | Self::I32AtomicRmw8AddU(ma)
+            | Self::I32AtomicRmw16AddU(ma)
+            | Self::I64AtomicRmw8AddU(ma)
+            | Self::I64AtomicRmw16AddU(ma)
+            | Self::I64AtomicRmw32AddU(ma)
+            | Self::I32AtomicRmwSub(ma)
+            | Self::I64AtomicRmwSub(ma)
+            | Self::I32AtomicRmw8SubU(ma)
+            | Self::I32AtomicRmw16SubU(ma)
+            | Self::I64AtomicRmw8SubU(ma)
+            | Self::I64AtomicRmw16SubU(ma)
+            | Self::I64AtomicRmw32SubU(ma)
+            | Self::I32AtomicRmwAnd(ma)
+            | Self::I64AtomicRmwAnd(ma)
+            | Self::I32AtomicRmw8AndU(ma)
+            | Self::I32AtomicRmw16AndU(ma)
+            | Self::I64AtomicRmw8AndU(ma)
+            | Self::I64AtomicRmw16AndU(ma)
+            | Self::I64AtomicRmw32AndU(ma)
+            | Self::I32AtomicRmwOr(ma)
+            | Self::I64AtomicRmwOr(ma)
+            | Self::I32AtomicRmw8OrU(ma)
+            | Self::I32AtomicRmw16OrU(ma)
+            | Self::I64AtomicRmw8OrU(ma)
+            | Self::I64AtomicRmw16OrU(ma)
+            | Self::I64AtomicRmw32OrU(ma)
+            | Self::I32AtomicRmwXor(ma)
+            | Self::I64AtomicRmwXor(ma)
+            | Self::I32AtomicRmw8XorU(ma)
+            | Self::I32AtomicRmw16XorU(ma)
+            | Self::I64AtomicRmw8XorU(ma)
+            | Self::I64AtomicRmw16XorU(ma)
+            | Self::I64AtomicRmw32XorU(ma)
+            | Self::I32AtomicRmwXchg(ma)
+            | Self::I64AtomicRmwXchg(ma)
+            | Self::I32AtomicRmw8XchgU(ma)
+            | Self::I32AtomicRmw16XchgU(ma)
+            | Self::I64AtomicRmw8XchgU(ma)
+            | Self::I64AtomicRmw16XchgU(ma)
+            | Self::I64AtomicRmw32XchgU(ma)
+            | Self::I32AtomicRmwCmpxchg(ma)
+            | Self::I64AtomicRmwCmpxchg(ma)
+            | Self::I32AtomicRmw8CmpxchgU(ma)
+            | Self::I32AtomicRmw16CmpxchgU(ma)
+            | Self::I64AtomicRmw8CmpxchgU(ma)
+            | Self::I64AtomicRmw16CmpxchgU(ma)
+            | Self::I64AtomicRmw32CmpxchgU(ma) => Some(ma),
+            _ => None,
+        }
+    }
+
+    /// Returns the memory argument of a load or store instruction mutably.
+    pub fn memarg_mut(&mut self) -> Option<&mut MemArg> {
+        match self {
+            Self::I32Load(ma)
+            | Self::I64Load(ma)
+            | Self::F32Load(ma)
+            | Self::F64Load(ma)
+            | Self::I32Load8S(ma)
+            | Self::I32Load8U(ma)
+            | Self::I32Load16S(ma)
+            | Self::I32Load16U(ma)
+            | Self::I64Load8S(ma)
+            | Self::I64Load8U(ma)
+            | Self::I64Load16S(ma)
+            | Self::I64Load16U(ma)
+            | Self::I64Load32S(ma)
+            | Self::I64Load32U(ma)
+            | Self::I32Store(ma)
+            | Self::I64Store(ma)
+            | Self::F32Store(ma)
+            | Self::F64Store(ma)
+            | Self::I32Store8(ma)
+            | Self::I32Store16(ma)
+            | Self::I64Store8(ma)
+            | Self::I64Store16(ma)
+            | Self::I64Store32(ma)
+            | Self::V128Load(ma)
+            | Self::V128Load8x8S(ma)
+            | Self::V128Load8x8U(ma)
+            | Self::V128Load16x4S(ma)
+            | Self::V128Load16x4U(ma)
+            | Self::V128Load32x2S(ma)
+            | Self::V128Load32x2U(ma)
+            | Self::V128Load8Splat(ma)
+            | Self::V128Load16Splat(ma)
+            | Self::V128Load32Splat(ma)
+            | Self::V128Load64Splat(ma)
+            | Self::V128Load32Zero(ma)
+            | Self::V128Load64Zero(ma)
+            | Self::V128Store(ma) => Some(ma),
+            Self::V128Load8Lane(ma, _)
+            | Self::V128Load16Lane(ma, _)
+            | Self::V128Load32Lane(ma, _)
+            | Self::V128Load64Lane(ma, _)
+            | Self::V128Store8Lane(ma, _)
+            | Self::V128Store16Lane(ma, _)
+            | Self::V128Store32Lane(ma, _)
+            | Self::V128Store64Lane(ma, _) => Some(ma),
+            Self::MemoryAtomicNotify(ma)
+            | Self::MemoryAtomicWait32(ma)
+            | Self::MemoryAtomicWait64(ma)
+            | Self::I32AtomicLoad(ma)
+            | Self::I64AtomicLoad(ma)
+            | Self::I32AtomicLoad8U(ma)
+            | Self::I32AtomicLoad16U(ma)
+            | Self::I64AtomicLoad8U(ma)
+            | Self::I64AtomicLoad16U(ma)
+            | Self::I64AtomicLoad32U(ma)
+            | Self::I32AtomicStore(ma)
+            | Self::I64AtomicStore(ma)
+            | Self::I32AtomicStore8(ma)
+            | Self::I32AtomicStore16(ma)
+            | Self::I64AtomicStore8(ma)
+            | Self::I64AtomicStore16(ma)
+            | Self::I64AtomicStore32(ma)
+            | Self::I32AtomicRmwAdd(ma)
+            | Self::I64AtomicRmwAdd(ma)
+            | Self::I32AtomicRmw8AddU(ma)
+            | Self::I32AtomicRmw16AddU(ma)
+            | Self::I64AtomicRmw8AddU(ma)
+            | Self::I64AtomicRmw16AddU(ma)
+            | Self::I64AtomicRmw32AddU(ma)
+            | Self::I32AtomicRmwSub(ma)
+            | Self::I64AtomicRmwSub(ma)
+            | Self::I32AtomicRmw8SubU(ma)
+            | Self::I32AtomicRmw16SubU(ma)
+            | Self::I64AtomicRmw8SubU(ma)
+            | Self::I64AtomicRmw16SubU(ma)
+            | Self::I64AtomicRmw32SubU(ma)
+            | Self::I32AtomicRmwAnd(ma)
+            | Self::I64AtomicRmwAnd(ma)
+            | Self::I32AtomicRmw8AndU(ma)
+            | Self::I32AtomicRmw16AndU(ma)
+            | Self::I64AtomicRmw8AndU(ma)
+            | Self::I64AtomicRmw16AndU(ma)
+            | Self::I64AtomicRmw32AndU(ma)
+            | Self::I32AtomicRmwOr(ma)
+            | Self::I64AtomicRmwOr(ma)
+            | Self::I32AtomicRmw8OrU(ma)
+            | Self::I32AtomicRmw16OrU(ma)
+            | Self::I64AtomicRmw8OrU(ma)
+            | Self::I64AtomicRmw16OrU(ma)
+            | Self::I64AtomicRmw32OrU(ma)
+            | Self::I32AtomicRmwXor(ma)
+            | Self::I64AtomicRmwXor(ma)
+            | Self::I32AtomicRmw8XorU(ma)
+            | Self::I32AtomicRmw16XorU(ma)
+            | Self::I64AtomicRmw8XorU(ma)
+            | Self::I64AtomicRmw16XorU(ma)
+            | Self::I64AtomicRmw32XorU(ma)
+            | Self::I32AtomicRmwXchg(ma)
+            | Self::I64AtomicRmwXchg(ma)
+            | Self::I32AtomicRmw8XchgU(ma)
+            | Self::I32AtomicRmw16XchgU(ma)
+            | Self::I64AtomicRmw8XchgU(ma)
+            | Self::I64AtomicRmw16XchgU(ma)
+            | Self::I64AtomicRmw32XchgU(ma)
+            | Self::I32AtomicRmwCmpxchg(ma)
+            | Self::I64AtomicRmwCmpxchg(ma)
+            | Self::I32AtomicRmw8CmpxchgU(ma)
+            | Self::I32AtomicRmw16CmpxchgU(ma)
+            | Self::I64AtomicRmw8CmpxchgU(ma)
+            | Self::I64AtomicRmw16CmpxchgU(ma)
+            | Self::I64AtomicRmw32CmpxchgU(ma) => Some(ma),
+            _ => None,
+        }
+    }
+
+    /// Reads a GC instruction of the `0xFB` prefix after its subopcode.
+    fn gc_from_bytes(subop: u32, bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        fn one(bytes: &[u8], f: fn(u32) -> Instruction) -> Result<(Instruction, &[u8]), Error> {
+            let (x, bytes) = bytes.advance_u32()?;
+            Ok((f(x), bytes))
+        }
+
+        fn two(
+            bytes: &[u8],
+            f: fn(u32, u32) -> Instruction,
+        ) -> Result<(Instruction, &[u8]), Error> {
+            let (x, bytes) = bytes.advance_u32()?;
+            let (y, bytes) = bytes.advance_u32()?;
+            Ok((f(x, y), bytes))
+        }
+
+        fn cast(
+            bytes: &[u8],
+            nullable: bool,
+            f: fn(ReferenceType) -> Instruction,
+        ) -> Result<(Instruction, &[u8]), Error> {
+            let (heap, bytes) = HeapType::from_bytes(bytes)?;
+            Ok((f(ReferenceType::new(nullable, heap)), bytes))
+        }
+
+        fn br_on_cast(
+            bytes: &[u8],
+            f: fn(u32, ReferenceType, ReferenceType) -> Instruction,
+        ) -> Result<(Instruction, &[u8]), Error> {
+            let (&[flags], bytes) = bytes.advance()?;
+            if flags & !0x03 != 0 {
+                return Err(Error::CastFlags(flags));
+            }
+            let (l, bytes) = bytes.advance_u32()?;
+            let (ht1, bytes) = HeapType::from_bytes(bytes)?;
+            let (ht2, bytes) = HeapType::from_bytes(bytes)?;
+            let rt1 = ReferenceType::new(flags & 0x01 != 0, ht1);
+            let rt2 = ReferenceType::new(flags & 0x02 != 0, ht2);
+            Ok((f(l, rt1, rt2), bytes))
+        }
+
+        match subop {
+            0 => one(bytes, Self::StructNew),
+            1 => one(bytes, Self::StructNewDefault),
+            2 => two(bytes, Self::StructGet),
+            3 => two(bytes, Self::StructGetS),
+            4 => two(bytes, Self::StructGetU),
+            5 => two(bytes, Self::StructSet),
+            6 => one(bytes, Self::ArrayNew),
+            7 => one(bytes, Self::ArrayNewDefault),
+            8 => two(bytes, Self::ArrayNewFixed),
+            9 => two(bytes, Self::ArrayNewData),
+            10 => two(bytes, Self::ArrayNewElem),
+            11 => one(bytes, Self::ArrayGet),
+            12 => one(bytes, Self::ArrayGetS),
+            13 => one(bytes, Self::ArrayGetU),
+            14 => one(bytes, Self::ArraySet),
+            15 => Ok((Self::ArrayLen, bytes)),
+            16 => one(bytes, Self::ArrayFill),
+            17 => two(bytes, Self::ArrayCopy),
+            18 => two(bytes, Self::ArrayInitData),
+            19 => two(bytes, Self::ArrayInitElem),
+            20 => cast(bytes, false, Self::RefTest),
+            21 => cast(bytes, true, Self::RefTest),
+            22 => cast(bytes, false, Self::RefCast),
+            23 => cast(bytes, true, Self::RefCast),
+            24 => br_on_cast(bytes, Self::BrOnCast),
+            25 => br_on_cast(bytes, Self::BrOnCastFail),
+            26 => Ok((Self::AnyConvertExtern, bytes)),
+            27 => Ok((Self::ExternConvertAny, bytes)),
+            28 => Ok((Self::RefI31, bytes)),
+            29 => Ok((Self::I31GetS, bytes)),
+            30 => Ok((Self::I31GetU, bytes)),
+            _ => Err(Error::HexFbInstructionSubopcode(subop)),
+        }
+    }
+
+    /// Writes a GC instruction of the `0xFB` prefix.
+    fn write_gc_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        fn cast(wr: &mut impl Write, subop: u32, rt: &ReferenceType) -> Result<(), io::Error> {
+            wr.write_u32(subop + u32::from(rt.nullable()))?;
+            rt.heap_type().write_into(wr)
+        }
+
+        fn br_on_cast(
+            wr: &mut impl Write,
+            l: u32,
+            rt1: &ReferenceType,
+            rt2: &ReferenceType,
+        ) -> Result<(), io::Error> {
+            let flags = u8::from(rt1.nullable()) | u8::from(rt2.nullable()) << 1;
+            wr.write_all(&[flags])?;
+            wr.write_u32(l)?;
+            rt1.heap_type().write_into(wr)?;
+            rt2.heap_type().write_into(wr)
+        }
+
+        let (subop, immediates): (u32, &[u32]) = match self {
+            Self::StructNew(x) => (0, &[*x]),
+            Self::StructNewDefault(x) => (1, &[*x]),
+            Self::StructGet(x, y) => (2, &[*x, *y]),
+            Self::StructGetS(x, y) => (3, &[*x, *y]),
+            Self::StructGetU(x, y) => (4, &[*x, *y]),
+            Self::StructSet(x, y) => (5, &[*x, *y]),
+            Self::ArrayNew(x) => (6, &[*x]),
+            Self::ArrayNewDefault(x) => (7, &[*x]),
+            Self::ArrayNewFixed(x, n) => (8, &[*x, *n]),
+            Self::ArrayNewData(x, d) => (9, &[*x, *d]),
+            Self::ArrayNewElem(x, e) => (10, &[*x, *e]),
+            Self::ArrayGet(x) => (11, &[*x]),
+            Self::ArrayGetS(x) => (12, &[*x]),
+            Self::ArrayGetU(x) => (13, &[*x]),
+            Self::ArraySet(x) => (14, &[*x]),
+            Self::ArrayLen => (15, &[]),
+            Self::ArrayFill(x) => (16, &[*x]),
+            Self::ArrayCopy(x, y) => (17, &[*x, *y]),
+            Self::ArrayInitData(x, d) => (18, &[*x, *d]),
+            Self::ArrayInitElem(x, e) => (19, &[*x, *e]),
+            Self::RefTest(rt) => {
+                wr.write_all(&[0xFB])?;
+                return cast(wr, 20, rt);
+            }
+            Self::RefCast(rt) => {
+                wr.write_all(&[0xFB])?;
+                return cast(wr, 22, rt);
             }
-            Self::Loop(_, instrs) => {
+            Self::BrOnCast(l, rt1, rt2) | Self::BrOnCastFail(l, rt1, rt2) => {
+                wr.write_all(&[0xFB])?;
+                wr.write_u32(if matches!(self, Self::BrOnCast(..)) {
+                    24
+                } else {
+                    25
+                })?;
+                return br_on_cast(wr, *l, rt1, rt2);
+            }
+            Self::AnyConvertExtern => (26, &[]),
+            Self::ExternConvertAny => (27, &[]),
+            Self::RefI31 => (28, &[]),
+            Self::I31GetS => (29, &[]),
+            Self::I31GetU => (30, &[]),
+            _ => unreachable!("not a GC instruction"),
+        };
+        wr.write_all(&[0xFB])?;
+        wr.write_u32(subop)?;
+        for x in immediates {
+            wr.write_u32(*x)?;
+        }
+        Ok(())
+    }
+
+    /// Visits every index immediate of this instruction and its nested instructions, with the
+    /// index space each index belongs to.
+    pub(crate) fn visit_indices(&self, func: &mut impl FnMut(IndexSpace, u32)) {
+        if let Some(memarg) = self.memarg() {
+            func(IndexSpace::Memory, memarg.memory);
+        }
+        match self {
+            Self::Block(bt, instrs) | Self::Loop(bt, instrs) => {
+                bt.visit_indices(func);
                 for instr in instrs {
-                    instr.visit_func_indices(func);
+                    instr.visit_indices(func);
                 }
             }
-            Self::If(_, instrs, elseinstrs) => {
+            Self::If(bt, instrs, elseinstrs) => {
+                bt.visit_indices(func);
                 for instr in instrs {
-                    instr.visit_func_indices(func);
+                    instr.visit_indices(func);
                 }
                 if let Some(elseinstrs) = elseinstrs {
                     for instr in elseinstrs {
-                        instr.visit_func_indices(func);
+                        instr.visit_indices(func);
+                    }
+                }
+            }
+            Self::Try(bt, instrs, catches, catch_all) => {
+                bt.visit_indices(func);
+                for instr in instrs {
+                    instr.visit_indices(func);
+                }
+                for (tag, catch) in catches {
+                    func(IndexSpace::Tag, *tag);
+                    for instr in catch {
+                        instr.visit_indices(func);
                     }
                 }
+                if let Some(catch_all) = catch_all {
+                    for instr in catch_all {
+                        instr.visit_indices(func);
+                    }
+                }
+            }
+            Self::TryDelegate(bt, instrs, _) => {
+                bt.visit_indices(func);
+                for instr in instrs {
+                    instr.visit_indices(func);
+                }
+            }
+            Self::TryTable(bt, catches, instrs) => {
+                bt.visit_indices(func);
+                for tag in catches.iter().filter_map(Catch::tag) {
+                    func(IndexSpace::Tag, tag);
+                }
+                for instr in instrs {
+                    instr.visit_indices(func);
+                }
+            }
+            Self::Throw(tag) => func(IndexSpace::Tag, *tag),
+            Self::Call(x) | Self::ReturnCall(x) | Self::RefFunc(x) => {
+                func(IndexSpace::Function, *x)
+            }
+            Self::CallIndirect { ty, table } | Self::ReturnCallIndirect { ty, table } => {
+                func(IndexSpace::Type, *ty);
+                func(IndexSpace::Table, *table);
+            }
+            Self::LocalGet(x) | Self::LocalSet(x) | Self::LocalTee(x) => {
+                func(IndexSpace::Local, *x)
+            }
+            Self::GlobalGet(x) | Self::GlobalSet(x) => func(IndexSpace::Global, *x),
+            Self::TableGet(x)
+            | Self::TableSet(x)
+            | Self::TableGrow(x)
+            | Self::TableSize(x)
+            | Self::TableFill(x) => func(IndexSpace::Table, *x),
+            Self::TableInit(e, t) => {
+                func(IndexSpace::Element, *e);
+                func(IndexSpace::Table, *t);
+            }
+            Self::ElemDrop(e) => func(IndexSpace::Element, *e),
+            Self::TableCopy(t1, t2) => {
+                func(IndexSpace::Table, *t1);
+                func(IndexSpace::Table, *t2);
+            }
+            Self::MemorySize(m) | Self::MemoryGrow(m) | Self::MemoryFill(m) => {
+                func(IndexSpace::Memory, *m)
+            }
+            Self::MemoryCopy(m1, m2) => {
+                func(IndexSpace::Memory, *m1);
+                func(IndexSpace::Memory, *m2);
+            }
+            Self::MemoryInit(d, m) => {
+                func(IndexSpace::Data, *d);
+                func(IndexSpace::Memory, *m);
+            }
+            Self::DataDrop(d) => func(IndexSpace::Data, *d),
+            Self::Select(ts) => {
+                for x in ts.iter().filter_map(ValueType::type_index) {
+                    func(IndexSpace::Type, x);
+                }
+            }
+            Self::RefNull(rt) | Self::RefTest(rt) | Self::RefCast(rt) => {
+                if let Some(x) = rt.type_index() {
+                    func(IndexSpace::Type, x);
+                }
+            }
+            Self::BrOnCast(_, rt1, rt2) | Self::BrOnCastFail(_, rt1, rt2) => {
+                for x in [rt1.type_index(), rt2.type_index()].into_iter().flatten() {
+                    func(IndexSpace::Type, x);
+                }
+            }
+            Self::CallRef(x)
+            | Self::ReturnCallRef(x)
+            | Self::StructNew(x)
+            | Self::StructNewDefault(x)
+            | Self::StructGet(x, _)
+            | Self::StructGetS(x, _)
+            | Self::StructGetU(x, _)
+            | Self::StructSet(x, _)
+            | Self::ArrayNew(x)
+            | Self::ArrayNewDefault(x)
+            | Self::ArrayNewFixed(x, _)
+            | Self::ArrayGet(x)
+            | Self::ArrayGetS(x)
+            | Self::ArrayGetU(x)
+            | Self::ArraySet(x)
+            | Self::ArrayFill(x) => func(IndexSpace::Type, *x),
+            Self::ArrayCopy(x, y) => {
+                func(IndexSpace::Type, *x);
+                func(IndexSpace::Type, *y);
+            }
+            Self::ArrayNewData(x, d) | Self::ArrayInitData(x, d) => {
+                func(IndexSpace::Type, *x);
+                func(IndexSpace::Data, *d);
+            }
+            Self::ArrayNewElem(x, e) | Self::ArrayInitElem(x, e) => {
+                func(IndexSpace::Type, *x);
+                func(IndexSpace::Element, *e);
             }
-            Self::Call(x) => func(x),
-            Self::RefFunc(x) => func(x),
             _ => (),
         }
     }
@@ -3254,23 +5021,40 @@ impl Instruction {
 pub struct Expression(pub(crate) Vec<Instruction>);
 
 impl Expression {
-    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+    pub(crate) fn from_bytes(bytes: &[u8], max_depth: usize) -> Result<(Self, &[u8]), Error> {
         log::trace!("expression from bytes: start reading instructions");
-        let (instrs, _, bytes) = Instruction::from_bytes_vec(bytes, &[0x0B])?;
+        let (instrs, _, bytes) = Instruction::from_bytes_vec(bytes, &[0x0B], None, max_depth)?;
 
         Ok((Self(instrs), bytes))
     }
 
+    /// Reads an expression, also returning offsets of its instructions relative to `bytes` in the
+    /// order of a pre-order walk.
+    pub(crate) fn from_bytes_with_offsets(
+        bytes: &[u8],
+        max_depth: usize,
+    ) -> Result<(Self, Vec<usize>, &[u8]), Error> {
+        let mut offsets = Vec::new();
+        let (instrs, _, bytes) =
+            Instruction::from_bytes_vec(bytes, &[0x0B], Some(&mut offsets), max_depth)?;
+
+        Ok((Self(instrs), offsets, bytes))
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
         Instruction::write_slice_into(&self.0, Some(0x0B), wr)
     }
 
-    pub(crate) fn visit_func_indices(&mut self, func: impl FnMut(&mut u32) + Copy) {
-        for instruction in &mut self.0 {
-            instruction.visit_func_indices(func);
+    pub(crate) fn visit_indices(&self, func: &mut impl FnMut(IndexSpace, u32)) {
+        for instruction in &self.0 {
+            instruction.visit_indices(func);
         }
     }
 
+    pub(crate) fn visit_indices_mut(&mut self, func: &mut impl FnMut(IndexSpace, &mut u32)) {
+        self.visit_mut(&mut IndexVisitor(func));
+    }
+
     pub fn instructions(&self) -> &[Instruction] {
         &self.0
     }
@@ -0,0 +1,852 @@
+use crate::{
+    instructions::{BlockType, Catch, Expression, Instruction, MemArg},
+    synth::IndexSpace,
+    wasm_types::ValueType,
+};
+
+/// A visitor which may modify instructions in place.
+///
+/// Every method has a default implementation which does nothing, so implementors only override
+/// callbacks for the immediates they are interested in. Use [`Expression::visit_mut`] or
+/// `visit_mut` methods of `Synth*` types to drive the visitor.
+#[allow(unused_variables)]
+pub trait VisitMut {
+    /// Called for every instruction before its immediates and nested instructions are visited.
+    ///
+    /// Returning `Some` replaces the instruction with the returned sequence, which is not visited
+    /// further.
+    fn visit_instruction(&mut self, instr: &mut Instruction) -> Option<Vec<Instruction>> {
+        None
+    }
+
+    fn visit_block_type(&mut self, bt: &mut BlockType) {
+        match bt {
+            BlockType::TypeIndex(x) => {
+                // out-of-range s33 indices cannot refer to any type, so they are left untouched
+                if let Ok(mut idx) = u32::try_from(*x) {
+                    self.visit_type_idx(&mut idx);
+                    *x = idx.into();
+                }
+            }
+            BlockType::Value(t) => {
+                if let Some(idx) = t.type_index_mut() {
+                    self.visit_type_idx(idx);
+                }
+            }
+            BlockType::Empty => (),
+        }
+    }
+
+    fn visit_func_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_type_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_local_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_global_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_table_idx(&mut self, idx: &mut u32) {}
+
+    /// Called for memory indices, including those of [`MemArg`]s.
+    fn visit_memory_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_elem_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_data_idx(&mut self, idx: &mut u32) {}
+
+    fn visit_tag_idx(&mut self, idx: &mut u32) {}
+
+    /// Called for relative label indices of branch instructions.
+    fn visit_label_idx(&mut self, idx: &mut u32) {}
+}
+
+/// Adapts a closure over [`IndexSpace`]s into a [`VisitMut`]. Labels are not visited.
+pub(crate) struct IndexVisitor<F>(pub(crate) F);
+
+impl<F: FnMut(IndexSpace, &mut u32)> VisitMut for IndexVisitor<F> {
+    fn visit_func_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Function, idx)
+    }
+
+    fn visit_type_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Type, idx)
+    }
+
+    fn visit_local_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Local, idx)
+    }
+
+    fn visit_global_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Global, idx)
+    }
+
+    fn visit_table_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Table, idx)
+    }
+
+    fn visit_memory_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Memory, idx)
+    }
+
+    fn visit_elem_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Element, idx)
+    }
+
+    fn visit_data_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Data, idx)
+    }
+
+    fn visit_tag_idx(&mut self, idx: &mut u32) {
+        (self.0)(IndexSpace::Tag, idx)
+    }
+}
+
+impl Instruction {
+    /// Visits immediates and nested instructions of this instruction, without calling
+    /// [`VisitMut::visit_instruction`] on itself.
+    ///
+    /// Every instruction is listed explicitly, so that a new instruction cannot be added without
+    /// deciding which of its immediates are visited.
+    fn visit_immediates_mut(&mut self, visitor: &mut impl VisitMut) {
+        if let Some(MemArg { memory, .. }) = self.memarg_mut() {
+            visitor.visit_memory_idx(memory);
+        }
+        match self {
+            Self::Block(bt, instrs) | Self::Loop(bt, instrs) => {
+                visitor.visit_block_type(bt);
+                visit_instructions_mut(instrs, visitor);
+            }
+            Self::If(bt, instrs, elseinstrs) => {
+                visitor.visit_block_type(bt);
+                visit_instructions_mut(instrs, visitor);
+                if let Some(elseinstrs) = elseinstrs {
+                    visit_instructions_mut(elseinstrs, visitor);
+                }
+            }
+            Self::Try(bt, instrs, catches, catch_all) => {
+                visitor.visit_block_type(bt);
+                visit_instructions_mut(instrs, visitor);
+                for (tag, catch) in catches {
+                    visitor.visit_tag_idx(tag);
+                    visit_instructions_mut(catch, visitor);
+                }
+                if let Some(catch_all) = catch_all {
+                    visit_instructions_mut(catch_all, visitor);
+                }
+            }
+            Self::TryDelegate(bt, instrs, l) => {
+                visitor.visit_block_type(bt);
+                visit_instructions_mut(instrs, visitor);
+                visitor.visit_label_idx(l);
+            }
+            Self::TryTable(bt, catches, instrs) => {
+                visitor.visit_block_type(bt);
+                for catch in catches {
+                    match catch {
+                        Catch::Tag { tag, label } | Catch::TagRef { tag, label } => {
+                            visitor.visit_tag_idx(tag);
+                            visitor.visit_label_idx(label);
+                        }
+                        Catch::All { label } | Catch::AllRef { label } => {
+                            visitor.visit_label_idx(label)
+                        }
+                    }
+                }
+                visit_instructions_mut(instrs, visitor);
+            }
+            Self::Throw(tag) => visitor.visit_tag_idx(tag),
+            Self::Br(l) | Self::BrIf(l) | Self::Rethrow(l) => visitor.visit_label_idx(l),
+            Self::BrTable(ls, l) => {
+                for l in ls {
+                    visitor.visit_label_idx(l);
+                }
+                visitor.visit_label_idx(l);
+            }
+            Self::Call(x) | Self::ReturnCall(x) | Self::RefFunc(x) => visitor.visit_func_idx(x),
+            Self::CallIndirect { ty, table } | Self::ReturnCallIndirect { ty, table } => {
+                visitor.visit_type_idx(ty);
+                visitor.visit_table_idx(table);
+            }
+            Self::LocalGet(x) | Self::LocalSet(x) | Self::LocalTee(x) => visitor.visit_local_idx(x),
+            Self::GlobalGet(x) | Self::GlobalSet(x) => visitor.visit_global_idx(x),
+            Self::TableGet(x)
+            | Self::TableSet(x)
+            | Self::TableGrow(x)
+            | Self::TableSize(x)
+            | Self::TableFill(x) => visitor.visit_table_idx(x),
+            Self::TableInit(e, t) => {
+                visitor.visit_elem_idx(e);
+                visitor.visit_table_idx(t);
+            }
+            Self::ElemDrop(e) => visitor.visit_elem_idx(e),
+            Self::TableCopy(t1, t2) => {
+                visitor.visit_table_idx(t1);
+                visitor.visit_table_idx(t2);
+            }
+            Self::MemorySize(m) | Self::MemoryGrow(m) | Self::MemoryFill(m) => {
+                visitor.visit_memory_idx(m)
+            }
+            Self::MemoryCopy(m1, m2) => {
+                visitor.visit_memory_idx(m1);
+                visitor.visit_memory_idx(m2);
+            }
+            Self::MemoryInit(d, m) => {
+                visitor.visit_data_idx(d);
+                visitor.visit_memory_idx(m);
+            }
+            Self::DataDrop(d) => visitor.visit_data_idx(d),
+            Self::Select(ts) => {
+                for idx in ts.iter_mut().filter_map(ValueType::type_index_mut) {
+                    visitor.visit_type_idx(idx);
+                }
+            }
+            Self::RefNull(rt) | Self::RefTest(rt) | Self::RefCast(rt) => {
+                if let Some(idx) = rt.type_index_mut() {
+                    visitor.visit_type_idx(idx);
+                }
+            }
+            Self::BrOnNull(l) | Self::BrOnNonNull(l) => visitor.visit_label_idx(l),
+            Self::BrOnCast(l, rt1, rt2) | Self::BrOnCastFail(l, rt1, rt2) => {
+                visitor.visit_label_idx(l);
+                for idx in [rt1.type_index_mut(), rt2.type_index_mut()]
+                    .into_iter()
+                    .flatten()
+                {
+                    visitor.visit_type_idx(idx);
+                }
+            }
+            Self::CallRef(x)
+            | Self::ReturnCallRef(x)
+            | Self::StructNew(x)
+            | Self::StructNewDefault(x)
+            | Self::StructGet(x, _)
+            | Self::StructGetS(x, _)
+            | Self::StructGetU(x, _)
+            | Self::StructSet(x, _)
+            | Self::ArrayNew(x)
+            | Self::ArrayNewDefault(x)
+            | Self::ArrayNewFixed(x, _)
+            | Self::ArrayGet(x)
+            | Self::ArrayGetS(x)
+            | Self::ArrayGetU(x)
+            | Self::ArraySet(x)
+            | Self::ArrayFill(x) => visitor.visit_type_idx(x),
+            Self::ArrayCopy(x, y) => {
+                visitor.visit_type_idx(x);
+                visitor.visit_type_idx(y);
+            }
+            Self::ArrayNewData(x, d) | Self::ArrayInitData(x, d) => {
+                visitor.visit_type_idx(x);
+                visitor.visit_data_idx(d);
+            }
+            Self::ArrayNewElem(x, e) | Self::ArrayInitElem(x, e) => {
+                visitor.visit_type_idx(x);
+                visitor.visit_elem_idx(e);
+            }
+            // memory indices of memory arguments are visited above
+            Self::I32Load(..)
+            | Self::I64Load(..)
+            | Self::F32Load(..)
+            | Self::F64Load(..)
+            | Self::I32Load8S(..)
+            | Self::I32Load8U(..)
+            | Self::I32Load16S(..)
+            | Self::I32Load16U(..)
+            | Self::I64Load8S(..)
+            | Self::I64Load8U(..)
+            | Self::I64Load16S(..)
+            | Self::I64Load16U(..)
+            | Self::I64Load32S(..)
+            | Self::I64Load32U(..)
+            | Self::I32Store(..)
+            | Self::I64Store(..)
+            | Self::F32Store(..)
+            | Self::F64Store(..)
+            | Self::I32Store8(..)
+            | Self::I32Store16(..)
+            | Self::I64Store8(..)
+            | Self::I64Store16(..)
+            | Self::I64Store32(..)
+            | Self::V128Load(..)
+            | Self::V128Load8x8S(..)
+            | Self::V128Load8x8U(..)
+            | Self::V128Load16x4S(..)
+            | Self::V128Load16x4U(..)
+            | Self::V128Load32x2S(..)
+            | Self::V128Load32x2U(..)
+            | Self::V128Load8Splat(..)
+            | Self::V128Load16Splat(..)
+            | Self::V128Load32Splat(..)
+            | Self::V128Load64Splat(..)
+            | Self::V128Load32Zero(..)
+            | Self::V128Load64Zero(..)
+            | Self::V128Store(..)
+            | Self::V128Load8Lane(..)
+            | Self::V128Load16Lane(..)
+            | Self::V128Load32Lane(..)
+            | Self::V128Load64Lane(..)
+            | Self::V128Store8Lane(..)
+            | Self::V128Store16Lane(..)
+            | Self::V128Store32Lane(..)
+            | Self::V128Store64Lane(..)
+            | Self::MemoryAtomicNotify(..)
+            | Self::MemoryAtomicWait32(..)
+            | Self::MemoryAtomicWait64(..)
+            | Self::I32AtomicLoad(..)
+            | Self::I64AtomicLoad(..)
+            | Self::I32AtomicLoad8U(..)
+            | Self::I32AtomicLoad16U(..)
+            | Self::I64AtomicLoad8U(..)
+            | Self::I64AtomicLoad16U(..)
+            | Self::I64AtomicLoad32U(..)
+            | Self::I32AtomicStore(..)
+            | Self::I64AtomicStore(..)
+            | Self::I32AtomicStore8(..)
+            | Self::I32AtomicStore16(..)
+            | Self::I64AtomicStore8(..)
+            | Self::I64AtomicStore16(..)
+            | Self::I64AtomicStore32(..)
+            | Self::I32AtomicRmwAdd(..)
+            | Self::I64AtomicRmwAdd(..)
+            | Self::I32AtomicRmw8AddU(..)
+            | Self::I32AtomicRmw16AddU(..)
+            | Self::I64AtomicRmw8AddU(..)
+            | Self::I64AtomicRmw16AddU(..)
+            | Self::I64AtomicRmw32AddU(..)
+            | Self::I32AtomicRmwSub(..)
+            | Self::I64AtomicRmwSub(..)
+            | Self::I32AtomicRmw8SubU(..)
+            | Self::I32AtomicRmw16SubU(..)
+            | Self::I64AtomicRmw8SubU(..)
+            | Self::I64AtomicRmw16SubU(..)
+            | Self::I64AtomicRmw32SubU(..)
+            | Self::I32AtomicRmwAnd(..)
+            | Self::I64AtomicRmwAnd(..)
+            | Self::I32AtomicRmw8AndU(..)
+            | Self::I32AtomicRmw16AndU(..)
+            | Self::I64AtomicRmw8AndU(..)
+            | Self::I64AtomicRmw16AndU(..)
+            | Self::I64AtomicRmw32AndU(..)
+            | Self::I32AtomicRmwOr(..)
+            | Self::I64AtomicRmwOr(..)
+            | Self::I32AtomicRmw8OrU(..)
+            | Self::I32AtomicRmw16OrU(..)
+            | Self::I64AtomicRmw8OrU(..)
+            | Self::I64AtomicRmw16OrU(..)
+            | Self::I64AtomicRmw32OrU(..)
+            | Self::I32AtomicRmwXor(..)
+            | Self::I64AtomicRmwXor(..)
+            | Self::I32AtomicRmw8XorU(..)
+            | Self::I32AtomicRmw16XorU(..)
+            | Self::I64AtomicRmw8XorU(..)
+            | Self::I64AtomicRmw16XorU(..)
+            | Self::I64AtomicRmw32XorU(..)
+            | Self::I32AtomicRmwXchg(..)
+            | Self::I64AtomicRmwXchg(..)
+            | Self::I32AtomicRmw8XchgU(..)
+            | Self::I32AtomicRmw16XchgU(..)
+            | Self::I64AtomicRmw8XchgU(..)
+            | Self::I64AtomicRmw16XchgU(..)
+            | Self::I64AtomicRmw32XchgU(..)
+            | Self::I32AtomicRmwCmpxchg(..)
+            | Self::I64AtomicRmwCmpxchg(..)
+            | Self::I32AtomicRmw8CmpxchgU(..)
+            | Self::I32AtomicRmw16CmpxchgU(..)
+            | Self::I64AtomicRmw8CmpxchgU(..)
+            | Self::I64AtomicRmw16CmpxchgU(..)
+            | Self::I64AtomicRmw32CmpxchgU(..) => (),
+            // instructions without immediates referring to indices
+            Self::Unreachable
+            | Self::Nop
+            | Self::ThrowRef
+            | Self::Return
+            | Self::RefIsNull
+            | Self::RefEq
+            | Self::RefAsNonNull
+            | Self::ArrayLen
+            | Self::AnyConvertExtern
+            | Self::ExternConvertAny
+            | Self::RefI31
+            | Self::I31GetS
+            | Self::I31GetU
+            | Self::Drop
+            | Self::SelectNumeric
+            | Self::AtomicFence => (),
+            // numeric and vector instructions, whose immediates are constants or lanes
+            Self::I32Const(..)
+            | Self::I64Const(..)
+            | Self::F32Const(..)
+            | Self::F64Const(..)
+            | Self::I32Eqz
+            | Self::I32Eq
+            | Self::I32Ne
+            | Self::I32LtS
+            | Self::I32LtU
+            | Self::I32GtS
+            | Self::I32GtU
+            | Self::I32LeS
+            | Self::I32LeU
+            | Self::I32GeS
+            | Self::I32GeU
+            | Self::I64Eqz
+            | Self::I64Eq
+            | Self::I64Ne
+            | Self::I64LtS
+            | Self::I64LtU
+            | Self::I64GtS
+            | Self::I64GtU
+            | Self::I64LeS
+            | Self::I64LeU
+            | Self::I64GeS
+            | Self::I64GeU
+            | Self::F32Eq
+            | Self::F32Ne
+            | Self::F32Lt
+            | Self::F32Gt
+            | Self::F32Le
+            | Self::F32Ge
+            | Self::F64Eq
+            | Self::F64Ne
+            | Self::F64Lt
+            | Self::F64Gt
+            | Self::F64Le
+            | Self::F64Ge
+            | Self::I32Clz
+            | Self::I32Ctz
+            | Self::I32Popcnt
+            | Self::I32Add
+            | Self::I32Sub
+            | Self::I32Mul
+            | Self::I32DivS
+            | Self::I32DivU
+            | Self::I32RemS
+            | Self::I32RemU
+            | Self::I32And
+            | Self::I32Or
+            | Self::I32Xor
+            | Self::I32Shl
+            | Self::I32ShrS
+            | Self::I32ShrU
+            | Self::I32Rotl
+            | Self::I32Rotr
+            | Self::I64Clz
+            | Self::I64Ctz
+            | Self::I64Popcnt
+            | Self::I64Add
+            | Self::I64Sub
+            | Self::I64Mul
+            | Self::I64DivS
+            | Self::I64DivU
+            | Self::I64RemS
+            | Self::I64RemU
+            | Self::I64And
+            | Self::I64Or
+            | Self::I64Xor
+            | Self::I64Shl
+            | Self::I64ShrS
+            | Self::I64ShrU
+            | Self::I64Rotl
+            | Self::I64Rotr
+            | Self::F32Abs
+            | Self::F32Neg
+            | Self::F32Ceil
+            | Self::F32Floor
+            | Self::F32Trunc
+            | Self::F32Nearest
+            | Self::F32Sqrt
+            | Self::F32Add
+            | Self::F32Sub
+            | Self::F32Mul
+            | Self::F32Div
+            | Self::F32Min
+            | Self::F32Max
+            | Self::F32Copysign
+            | Self::F64Abs
+            | Self::F64Neg
+            | Self::F64Ceil
+            | Self::F64Floor
+            | Self::F64Trunc
+            | Self::F64Nearest
+            | Self::F64Sqrt
+            | Self::F64Add
+            | Self::F64Sub
+            | Self::F64Mul
+            | Self::F64Div
+            | Self::F64Min
+            | Self::F64Max
+            | Self::F64Copysign
+            | Self::I32WrapI64
+            | Self::I32TruncF32S
+            | Self::I32TruncF32U
+            | Self::I32TruncF64S
+            | Self::I32TruncF64U
+            | Self::I64ExtendI32S
+            | Self::I64ExtendI32U
+            | Self::I64TruncF32S
+            | Self::I64TruncF32U
+            | Self::I64TruncF64S
+            | Self::I64TruncF64U
+            | Self::F32ConvertI32S
+            | Self::F32ConvertI32U
+            | Self::F32ConvertI64S
+            | Self::F32ConvertI64U
+            | Self::F32DemoteF64
+            | Self::F64ConvertI32S
+            | Self::F64ConvertI32U
+            | Self::F64ConvertI64S
+            | Self::F64ConvertI64U
+            | Self::F64PromoteF32
+            | Self::I32ReinterpretF32
+            | Self::I64ReinterpretF64
+            | Self::F32ReinterpretI32
+            | Self::F64ReinterpretI64
+            | Self::I32Extend8S
+            | Self::I32Extend16S
+            | Self::I64Extend8S
+            | Self::I64Extend16S
+            | Self::I64Extend32S
+            | Self::I32TruncSatF32S
+            | Self::I32TruncSatF32U
+            | Self::I32TruncSatF64S
+            | Self::I32TruncSatF64U
+            | Self::I64TruncSatF32S
+            | Self::I64TruncSatF32U
+            | Self::I64TruncSatF64S
+            | Self::I64TruncSatF64U
+            | Self::V128Const(..)
+            | Self::I8x16Shuffle(..)
+            | Self::I8x16ExtractLaneS(..)
+            | Self::I8x16ExtractLaneU(..)
+            | Self::I8x16ReplaceLane(..)
+            | Self::I16x8ExtractLaneS(..)
+            | Self::I16x8ExtractLaneU(..)
+            | Self::I16x8ReplaceLane(..)
+            | Self::I32x4ExtractLane(..)
+            | Self::I32x4ReplaceLane(..)
+            | Self::I64x2ExtractLane(..)
+            | Self::I64x2ReplaceLane(..)
+            | Self::F32x4ExtractLane(..)
+            | Self::F32x4ReplaceLane(..)
+            | Self::F64x2ExtractLane(..)
+            | Self::F64x2ReplaceLane(..)
+            | Self::I8X16Swizzle
+            | Self::I8x16Splat
+            | Self::I16x8Splat
+            | Self::I32x4Splat
+            | Self::I64x2Splat
+            | Self::F32x4Splat
+            | Self::F64x2Splat
+            | Self::I8x16Eq
+            | Self::I8x16Ne
+            | Self::I8X16LtS
+            | Self::I8X16LtU
+            | Self::I8X16GtS
+            | Self::I8X16GtU
+            | Self::I8X16LeS
+            | Self::I8X16LeU
+            | Self::I8X16GeS
+            | Self::I8X16GeU
+            | Self::I16x8Eq
+            | Self::I16x8Ne
+            | Self::I16x8LtS
+            | Self::I16x8LtU
+            | Self::I16x8GtS
+            | Self::I16x8GtU
+            | Self::I16x8LeS
+            | Self::I16x8LeU
+            | Self::I16x8GeS
+            | Self::I16x8GeU
+            | Self::I32x4Eq
+            | Self::I32x4Ne
+            | Self::I32x4LtS
+            | Self::I32x4LtU
+            | Self::I32x4GtS
+            | Self::I32x4GtU
+            | Self::I32x4LeS
+            | Self::I32x4LeU
+            | Self::I32x4GeS
+            | Self::I32x4GeU
+            | Self::I64x2Eq
+            | Self::I64x2Ne
+            | Self::I64x2LtS
+            | Self::I64x2GtS
+            | Self::I64x2LeS
+            | Self::I64x2GeS
+            | Self::F32x4Eq
+            | Self::F32x4Ne
+            | Self::F32x4Lt
+            | Self::F32x4Gt
+            | Self::F32x4Le
+            | Self::F32x4Ge
+            | Self::F64x2Eq
+            | Self::F64x2Ne
+            | Self::F64x2Lt
+            | Self::F64x2Gt
+            | Self::F64x2Le
+            | Self::F64x2Ge
+            | Self::V128Not
+            | Self::V128And
+            | Self::V128AndNot
+            | Self::V128Or
+            | Self::V128Xor
+            | Self::V128Bitselect
+            | Self::V128AnyTrue
+            | Self::I8x16Abs
+            | Self::I8x16Neg
+            | Self::I8x16Popcnt
+            | Self::I8x16AllTrue
+            | Self::I8x16Bitmask
+            | Self::I8x16NarrowI16x8S
+            | Self::I8x16NarrowI16x8U
+            | Self::I8x16Shl
+            | Self::I8x16ShrS
+            | Self::I8x16ShrU
+            | Self::I8x16Add
+            | Self::I8x16AddSatS
+            | Self::I8x16AddSatU
+            | Self::I8x16Sub
+            | Self::I8x16SubSatS
+            | Self::I8x16SubSatU
+            | Self::I8x16MinS
+            | Self::I8x16MinU
+            | Self::I8x16MaxS
+            | Self::I8x16MaxU
+            | Self::I8x16AvgrU
+            | Self::I16x8ExtAddPairwiseI8x16S
+            | Self::I16x8ExtAddPairwiseI8x16U
+            | Self::I16x8Abs
+            | Self::I16x8Neg
+            | Self::I16x8Q15MulrSatS
+            | Self::I16x8AllTrue
+            | Self::I16x8Bitmask
+            | Self::I16x8NarrowI32x4S
+            | Self::I16x8NarrowI32x4U
+            | Self::I16x8ExtendLowI8X16S
+            | Self::I16x8ExtendHighI8X16S
+            | Self::I16x8ExtendLowI8X16U
+            | Self::I16x8ExtendHighI8X16U
+            | Self::I16x8Shl
+            | Self::I16x8ShrS
+            | Self::I16x8ShrU
+            | Self::I16x8Add
+            | Self::I16x8AddSatS
+            | Self::I16x8AddSatU
+            | Self::I16x8Sub
+            | Self::I16x8SubSatS
+            | Self::I16x8SubSatU
+            | Self::I16X8Mul
+            | Self::I16x8MinS
+            | Self::I16x8MinU
+            | Self::I16x8MaxS
+            | Self::I16x8MaxU
+            | Self::I16x8AvgrU
+            | Self::I16x8ExtmulLowI8x16S
+            | Self::I16x8ExtmulHighI8x16S
+            | Self::I16x8ExtmulLowI8x16U
+            | Self::I16x8ExtmulHighI8x16U
+            | Self::I32x4ExtAddPairwiseI16x8S
+            | Self::I32x4ExtAddPairwiseI16x8U
+            | Self::I32x4Abs
+            | Self::I32x4Neg
+            | Self::I32x4AllTrue
+            | Self::I32x4Bitmask
+            | Self::I32x4ExtendLowI16X8S
+            | Self::I32x4ExtendHighI16X8S
+            | Self::I32x4ExtendLowI16X8U
+            | Self::I32x4ExtendHighI16X8U
+            | Self::I32x4Shl
+            | Self::I32x4ShrS
+            | Self::I32x4ShrU
+            | Self::I32x4Add
+            | Self::I32x4Sub
+            | Self::I32x4Mul
+            | Self::I32x4MinS
+            | Self::I32x4MinU
+            | Self::I32x4MaxS
+            | Self::I32x4MaxU
+            | Self::I32x4DotI16x8S
+            | Self::I32x4ExtmulLowI16x8S
+            | Self::I32x4ExtmulHighI16x8S
+            | Self::I32x4ExtmulLowI16x8U
+            | Self::I32x4ExtmulHighI16x8U
+            | Self::I64x2Abs
+            | Self::I64x2Neg
+            | Self::I64x2AllTrue
+            | Self::I64x2Bitmask
+            | Self::I64x2ExtendLowI16X8S
+            | Self::I64x2ExtendHighI16X8S
+            | Self::I64x2ExtendLowI16X8U
+            | Self::I64x2ExtendHighI16X8U
+            | Self::I64x2Shl
+            | Self::I64x2ShrS
+            | Self::I64x2ShrU
+            | Self::I64x2Add
+            | Self::I64x2Sub
+            | Self::I64x2Mul
+            | Self::I64x2ExtmulLowI32x4S
+            | Self::I64x2ExtmulHighI32x4S
+            | Self::I64x2ExtmulLowI32x4U
+            | Self::I64x2ExtmulHighI32x4U
+            | Self::F32x4Ceil
+            | Self::F32x4Floor
+            | Self::F32x4Trunc
+            | Self::F32x4Nearest
+            | Self::F32x4Abs
+            | Self::F32x4Neg
+            | Self::F32x4Sqrt
+            | Self::F32x4Add
+            | Self::F32x4Sub
+            | Self::F32x4Mul
+            | Self::F32x4Div
+            | Self::F32x4Min
+            | Self::F32x4Max
+            | Self::F32x4Pmin
+            | Self::F32x4Pmax
+            | Self::F64x2Ceil
+            | Self::F64x2Floor
+            | Self::F64x2Trunc
+            | Self::F64x2Nearest
+            | Self::F64x2Abs
+            | Self::F64x2Neg
+            | Self::F64x2Sqrt
+            | Self::F64x2Add
+            | Self::F64x2Sub
+            | Self::F64x2Mul
+            | Self::F64x2Div
+            | Self::F64x2Min
+            | Self::F64x2Max
+            | Self::F64x2Pmin
+            | Self::F64x2Pmax
+            | Self::I32x4TruncSatF32x4S
+            | Self::I32x4TruncSatF32x4U
+            | Self::F32x4ConvertI32x4S
+            | Self::F32x4ConvertI32x4U
+            | Self::I32x4TruncSatF64x2SZero
+            | Self::I32x4TruncSatF64x2UZero
+            | Self::F64x2ConvertLowI32x4S
+            | Self::F64x2ConvertLowI32x4U
+            | Self::F32x4DemoteF64x2Zero
+            | Self::F64x2PromoteLowF32x4
+            | Self::I8x16RelaxedSwizzle
+            | Self::I32x4RelaxedTruncF32x4S
+            | Self::I32x4RelaxedTruncF32x4U
+            | Self::I32x4RelaxedTruncF64x2SZero
+            | Self::I32x4RelaxedTruncF64x2UZero
+            | Self::F32x4RelaxedMadd
+            | Self::F32x4RelaxedNmadd
+            | Self::F64x2RelaxedMadd
+            | Self::F64x2RelaxedNmadd
+            | Self::I8x16RelaxedLaneselect
+            | Self::I16x8RelaxedLaneselect
+            | Self::I32x4RelaxedLaneselect
+            | Self::I64x2RelaxedLaneselect
+            | Self::F32x4RelaxedMin
+            | Self::F32x4RelaxedMax
+            | Self::F64x2RelaxedMin
+            | Self::F64x2RelaxedMax
+            | Self::I16x8RelaxedQ15mulrS
+            | Self::I16x8RelaxedDotI8x16I7x16S
+            | Self::I32x4RelaxedDotI8x16I7x16AddS => (),
+        }
+    }
+
+    /// Visits this instruction with `visitor`, returning the replacement sequence if
+    /// [`VisitMut::visit_instruction`] requested one.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) -> Option<Vec<Instruction>> {
+        if let Some(replacement) = visitor.visit_instruction(self) {
+            return Some(replacement);
+        }
+        self.visit_immediates_mut(visitor);
+        None
+    }
+}
+
+/// Visits instructions in place, splicing replacement sequences.
+fn visit_instructions_mut(instrs: &mut Vec<Instruction>, visitor: &mut impl VisitMut) {
+    let mut i = 0;
+    while i < instrs.len() {
+        match instrs[i].visit_mut(visitor) {
+            Some(replacement) => {
+                let len = replacement.len();
+                instrs.splice(i..=i, replacement);
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+impl Expression {
+    /// Visits every instruction of this expression with `visitor`.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        visit_instructions_mut(&mut self.0, visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VisitMut;
+    use crate::{
+        instructions::{Expression, Instruction},
+        parse::Module,
+    };
+
+    /// Replaces every `i32.const` with two halves added together, and records label indices.
+    struct TestVisitor {
+        labels: Vec<u32>,
+    }
+
+    impl VisitMut for TestVisitor {
+        fn visit_instruction(&mut self, instr: &mut Instruction) -> Option<Vec<Instruction>> {
+            match *instr {
+                Instruction::I32Const(n) => Some(vec![
+                    Instruction::I32Const(n / 2),
+                    Instruction::I32Const(n - n / 2),
+                    Instruction::I32Add,
+                ]),
+                _ => None,
+            }
+        }
+
+        fn visit_label_idx(&mut self, idx: &mut u32) {
+            self.labels.push(*idx);
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (result i32)
+                    block (result i32)
+                        i32.const 3
+                        br 0
+                    end
+                )
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        let mut visitor = TestVisitor { labels: Vec::new() };
+        module.visit_mut(&mut visitor);
+        assert_eq!(visitor.labels, [0]);
+
+        let Expression(instrs) = &module.code_section.as_ref().unwrap().codes[0].func_expr;
+        let Instruction::Block(_, block) = &instrs[0] else {
+            panic!("unexpected instruction {:?}", instrs[0]);
+        };
+        assert!(matches!(
+            block[..],
+            [
+                Instruction::I32Const(1),
+                Instruction::I32Const(2),
+                Instruction::I32Add,
+                Instruction::Br(0)
+            ]
+        ));
+        module.validate().expect("visited module is invalid");
+    }
+}
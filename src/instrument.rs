@@ -1,221 +1,1495 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::{self, Write},
+    sync::Arc,
+};
+
 use crate::{
-    instructions::{Expression, Instruction},
+    instructions::{BlockType, Catch, Expression, Instruction, VisitMut},
     synth::{
         sections::{
-            SynthCode, SynthData, SynthElemInit::FuncIndices, SynthExportDescription, SynthImport,
+            SynthCode, SynthCustomSection, SynthExportDescription, SynthGlobal, SynthImport,
             SynthImportDescription, SynthNameAssoc,
         },
-        SynthModule,
+        IndexSpace, SynthModule,
     },
-    wasm_types::{FuncType, ResultType, ValueType},
-    Error,
+    validate::ValidationError,
+    wasm_types::{
+        CompositeType, FuncType, GlobalType, HeapType, ReferenceType, ResultType, SubType,
+        ValueType,
+    },
+    Bytes, Error, WriteExt,
 };
 
+/// Configuration of the instrumentation hooks installed by [`InstrumentConfig::install_all`].
+///
+/// By default, hooks are imported as `wasynth_hooks.enter` and `wasynth_hooks.leave` with type
+/// `(i32) -> ()`, receiving the index of the instrumented function.
+///
+/// If arguments (or results) are passed to the hooks, the hook takes them after the function
+/// index. As a function import has a single type, a distinct hook is imported for each list of
+/// passed value types, with the value types appended to the field name, e.g. `enter_i32_f64` for
+/// `(i32, i32, f64) -> ()`. Hooks of functions without any parameters (or results) keep the plain
+/// field name. References are passed as nullable references to the top type of their hierarchy,
+/// i.e. `funcref`, `externref`, `exnref` or `anyref`, so that hook names and types do not depend on
+/// type indices.
+///
+/// The call to the instrumented function may be wrapped in a block catching exceptions of the
+/// exception handling proposal, so that the plain leave hook is also called without any values
+/// when an exception unwinds through the trampoline. See [`InstrumentConfig::unwinding`].
+///
+/// A direct tail call (`return_call`) from an instrumented function to another one reaches a tail
+/// trampoline instead, which calls the plain leave hook for the caller and the enter hook for the
+/// callee, then tail calls the original callee. The function running a chain of tail calls is
+/// tracked in an added mutable `i32` global, so the trampoline which started the chain calls the
+/// leave hook for the last function of the chain, and the chain runs in constant stack space.
+/// Other tail calls to an instrumented function, i.e. `return_call_indirect`, `return_call_ref`
+/// and tail calls from functions which are not instrumented, reach its trampoline, which calls the
+/// original function with a plain `call`.
+#[derive(Clone)]
+pub struct InstrumentConfig {
+    hook_module: String,
+    enter_name: String,
+    leave_name: String,
+    pass_arguments: bool,
+    pass_results: bool,
+    unwinding: Unwinding,
+    filter: Option<Arc<FunctionFilter>>,
+}
+
+/// How trampolines catch exceptions unwinding through them, to call the leave hook.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unwinding {
+    /// Catches exceptions if the module uses the exception handling proposal, i.e. it declares or
+    /// imports a tag or has exception instructions.
+    ///
+    /// The legacy encoding is used if the module only has legacy exception instructions (`try`,
+    /// `delegate` and `rethrow`), and the `exnref` encoding otherwise.
+    #[default]
+    Auto,
+    /// Does not catch exceptions, so the leave hook is skipped when an exception unwinds.
+    Ignore,
+    /// Catches exceptions with a legacy `try` block and rethrows them with `rethrow`.
+    Legacy,
+    /// Catches exceptions with `try_table` and `catch_all_ref`, and rethrows them with
+    /// `throw_ref`.
+    Exnref,
+}
+
+type FunctionFilter = dyn Fn(&FunctionInfo<'_>) -> bool + Send + Sync;
+
+impl Debug for InstrumentConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InstrumentConfig")
+            .field("hook_module", &self.hook_module)
+            .field("enter_name", &self.enter_name)
+            .field("leave_name", &self.leave_name)
+            .field("pass_arguments", &self.pass_arguments)
+            .field("pass_results", &self.pass_results)
+            .field("unwinding", &self.unwinding)
+            .field("filter", &self.filter.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl Default for InstrumentConfig {
+    fn default() -> Self {
+        Self {
+            hook_module: String::from("wasynth_hooks"),
+            enter_name: String::from("enter"),
+            leave_name: String::from("leave"),
+            pass_arguments: false,
+            pass_results: false,
+            unwinding: Unwinding::default(),
+            filter: None,
+        }
+    }
+}
+
+impl InstrumentConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the module name of hook imports. Defaults to `wasynth_hooks`.
+    pub fn hook_module(mut self, name: impl Into<String>) -> Self {
+        self.hook_module = name.into();
+        self
+    }
+
+    /// Sets the field name of the hook called before entering a function. Defaults to `enter`.
+    pub fn enter_name(mut self, name: impl Into<String>) -> Self {
+        self.enter_name = name.into();
+        self
+    }
+
+    /// Sets the field name of the hook called after leaving a function. Defaults to `leave`.
+    pub fn leave_name(mut self, name: impl Into<String>) -> Self {
+        self.leave_name = name.into();
+        self
+    }
+
+    /// Passes the arguments of the instrumented function to the enter hook.
+    pub fn pass_arguments(mut self, pass: bool) -> Self {
+        self.pass_arguments = pass;
+        self
+    }
+
+    /// Passes the return values of the instrumented function to the leave hook.
+    pub fn pass_results(mut self, pass: bool) -> Self {
+        self.pass_results = pass;
+        self
+    }
+
+    /// Sets how exceptions unwinding through trampolines are caught. Defaults to
+    /// [`Unwinding::Auto`].
+    ///
+    /// Exceptions may unwind through modules which do not use the exception handling proposal
+    /// themselves, e.g. when an imported function throws. Choose an encoding explicitly to call
+    /// the leave hook in that case too.
+    pub fn unwinding(mut self, unwinding: Unwinding) -> Self {
+        self.unwinding = unwinding;
+        self
+    }
+
+    /// Instruments only the defined functions for which `filter` returns `true`.
+    ///
+    /// Functions that are not selected keep their bodies, and references to them are only shifted
+    /// by the number of hook imports.
+    pub fn filter(
+        mut self,
+        filter: impl Fn(&FunctionInfo<'_>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+/// A defined function to be instrumented, passed to the [`InstrumentConfig::filter`] predicate.
+#[derive(Clone, Debug)]
+pub struct FunctionInfo<'a> {
+    index: u32,
+    export_names: Vec<&'a str>,
+    name: Option<&'a str>,
+}
+
+impl<'a> FunctionInfo<'a> {
+    /// Returns a function info lookup of every defined function, scanning the export section and
+    /// the function names once.
+    fn lookup(module: &'a SynthModule) -> impl Fn(u32) -> Self + 'a {
+        let mut export_names: HashMap<u32, Vec<&'a str>> = HashMap::new();
+        for export in module.export_section.iter().flat_map(|x| &x.exports) {
+            if let SynthExportDescription::Func(x) = export.desc {
+                export_names
+                    .entry(x)
+                    .or_default()
+                    .push(export.name.as_str());
+            }
+        }
+        let names: HashMap<u32, &'a str> = module
+            .name_section
+            .as_ref()
+            .and_then(|x| x.function_names())
+            .into_iter()
+            .flatten()
+            .rev() // the first name of duplicate entries wins
+            .map(|x| (x.idx, x.name.as_str()))
+            .collect();
+        move |index| Self {
+            index,
+            export_names: export_names.get(&index).cloned().unwrap_or_default(),
+            name: names.get(&index).copied(),
+        }
+    }
+
+    /// Index of the function before instrumentation, including imported functions.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Names this function is exported with.
+    pub fn export_names(&self) -> &[&'a str] {
+        &self.export_names
+    }
+
+    /// Name of this function in the `name` custom section.
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+}
+
+/// A hook function to be imported.
+struct Hook {
+    name: String,
+    ty: FuncType,
+}
+
+/// Returns the position of the hook named `base_name` passing `values`, importing it if needed.
+///
+/// `types` are the types of the module, which references to defined types are resolved with.
+fn hook_position(
+    hooks: &mut Vec<Hook>,
+    base_name: &str,
+    values: &[ValueType],
+    types: &[SubType],
+) -> usize {
+    let values = values
+        .iter()
+        .map(|&ty| hook_value_type(ty, types))
+        .collect::<Vec<_>>();
+    let mut name = String::from(base_name);
+    for ty in &values {
+        name.push('_');
+        name.push_str(&ty.to_string());
+    }
+    if let Some(pos) = hooks.iter().position(|x| x.name == name) {
+        return pos;
+    }
+    let mut param = vec![ValueType::I32];
+    param.extend(values);
+    hooks.push(Hook {
+        name,
+        ty: FuncType {
+            param: ResultType(param),
+            result: ResultType(vec![]),
+        },
+    });
+    hooks.len() - 1
+}
+
+/// Returns the type a hook takes a value of type `ty` as. References are widened to the nullable
+/// top type of their hierarchy.
+fn hook_value_type(ty: ValueType, types: &[SubType]) -> ValueType {
+    let Some(rt) = ty.reference_type() else {
+        return ty;
+    };
+    let top = match rt.heap_type() {
+        HeapType::Func | HeapType::NoFunc => HeapType::Func,
+        HeapType::Extern | HeapType::NoExtern => HeapType::Extern,
+        HeapType::Exn | HeapType::NoExn => HeapType::Exn,
+        HeapType::Concrete(x) => match types.get(x as usize).map(SubType::composite) {
+            Some(CompositeType::Func(_)) => HeapType::Func,
+            _ => HeapType::Any,
+        },
+        HeapType::Any
+        | HeapType::Eq
+        | HeapType::I31
+        | HeapType::Struct
+        | HeapType::Array
+        | HeapType::None => HeapType::Any,
+    };
+    ReferenceType::new(true, top).into()
+}
+
+/// Builds the trampoline body and its locals, which calls the hooks around `target_funcidx`.
+///
+/// If `unwind` holds the encoding to catch exceptions with, the plain leave hook and the type
+/// index of the target, the call is wrapped in a block which calls the hook before rethrowing
+/// exceptions.
+///
+/// If `current` holds the index of the global tracking the running function for tail calls, the
+/// trampoline sets it to the target before the call, passes it to the leave hooks and restores it
+/// afterwards.
 fn trampoline_instrs(
-    target_funcidx: usize,
+    target_funcidx: u32,
+    target_type: &FuncType,
+    enter_funcidx: u32,
+    leave_funcidx: u32,
+    unwind: Option<(Unwinding, u32, u32)>,
+    current: Option<u32>,
+    config: &InstrumentConfig,
+) -> (Expression, Vec<ValueType>) {
+    let hook_funcidx_arg =
+        Instruction::I32Const(target_funcidx.try_into().expect("function index overflow"));
+    let params = &target_type.param().0;
+    let results = &target_type.result().0;
+    let local_gets = |range: std::ops::Range<usize>| {
+        range.map(|local_idx| {
+            Instruction::LocalGet(local_idx.try_into().expect("local index overflow"))
+        })
+    };
+
+    let mut expr = Vec::new();
+    let mut locals = Vec::new();
+    if config.pass_results {
+        // stashed results precede the saved current function
+        locals.extend_from_slice(results);
+    }
+    // a tail call from the target may have replaced the running function
+    let (leave_funcidx_arg, restore) = match current {
+        Some(current) => {
+            let saved: u32 = (params.len() + locals.len())
+                .try_into()
+                .expect("local index overflow");
+            locals.push(ValueType::I32);
+            expr.push(Instruction::GlobalGet(current));
+            expr.push(Instruction::LocalSet(saved));
+            expr.push(hook_funcidx_arg.clone());
+            expr.push(Instruction::GlobalSet(current));
+            (
+                Instruction::GlobalGet(current),
+                vec![
+                    Instruction::LocalGet(saved),
+                    Instruction::GlobalSet(current),
+                ],
+            )
+        }
+        None => (hook_funcidx_arg.clone(), Vec::new()),
+    };
+
+    expr.push(hook_funcidx_arg);
+    if config.pass_arguments {
+        expr.extend(local_gets(0..params.len()));
+    }
+    expr.push(Instruction::Call(enter_funcidx));
+
+    let mut leave = Vec::new();
+    if config.pass_results && !results.is_empty() {
+        // stash results into locals, since they are consumed by the leave hook
+        let result_locals = params.len()..params.len() + results.len();
+        for local_idx in result_locals.clone().rev() {
+            leave.push(Instruction::LocalSet(
+                local_idx.try_into().expect("local index overflow"),
+            ));
+        }
+        leave.push(leave_funcidx_arg.clone());
+        leave.extend(local_gets(result_locals.clone()));
+        leave.push(Instruction::Call(leave_funcidx));
+        leave.extend(restore.iter().cloned());
+        leave.extend(local_gets(result_locals));
+    } else {
+        leave.push(leave_funcidx_arg.clone());
+        leave.push(Instruction::Call(leave_funcidx));
+        leave.extend(restore.iter().cloned());
+    }
+
+    // NOTE: this must not be a tail call, as the leave hook runs after the original returns
+    let call = Instruction::Call(target_funcidx);
+    match unwind {
+        Some((Unwinding::Legacy, unwind_funcidx, tyidx)) => {
+            expr.extend(local_gets(0..params.len()));
+            expr.push(Instruction::Try(
+                BlockType::TypeIndex(tyidx.into()),
+                vec![call],
+                Vec::new(),
+                Some(
+                    [leave_funcidx_arg, Instruction::Call(unwind_funcidx)]
+                        .into_iter()
+                        .chain(restore)
+                        .chain([Instruction::Rethrow(0)])
+                        .collect(),
+                ),
+            ));
+            expr.extend(leave);
+        }
+        Some((Unwinding::Exnref, unwind_funcidx, tyidx)) => {
+            // the normal path returns from inside the block, which only exits with a caught
+            // exception
+            let mut body = local_gets(0..params.len()).collect::<Vec<_>>();
+            body.push(Instruction::TryTable(
+                BlockType::TypeIndex(tyidx.into()),
+                vec![Catch::AllRef { label: 0 }],
+                vec![call],
+            ));
+            body.extend(leave);
+            body.push(Instruction::Return);
+            expr.push(Instruction::Block(
+                BlockType::Value(ValueType::ExnRef),
+                body,
+            ));
+            expr.push(leave_funcidx_arg);
+            expr.push(Instruction::Call(unwind_funcidx));
+            expr.extend(restore);
+            expr.push(Instruction::ThrowRef);
+        }
+        Some((Unwinding::Auto | Unwinding::Ignore, ..)) => {
+            unreachable!("unwinding encoding is resolved")
+        }
+        None => {
+            expr.extend(local_gets(0..params.len()));
+            expr.push(call);
+            expr.extend(leave);
+        }
+    }
+
+    (Expression(expr), locals)
+}
+
+/// Builds the body of the tail trampoline of `target_funcidx`, which direct tail calls from
+/// instrumented functions reach instead of the trampoline of the target.
+///
+/// It calls the plain leave hook for the running function recorded in the `current` global, makes
+/// the target the running function, calls the enter hook and tail calls the target. A chain of
+/// tail calls therefore runs in constant stack space above the trampoline which started it, and
+/// that trampoline calls the leave hook of the last function of the chain.
+fn tail_trampoline_instrs(
+    target_funcidx: u32,
     target_type: &FuncType,
     enter_funcidx: u32,
     leave_funcidx: u32,
+    current: u32,
+    config: &InstrumentConfig,
 ) -> Expression {
-    let target_funcidx = target_funcidx.try_into().expect("function index overflow");
-    let enter = vec![
-        Instruction::I32Const(target_funcidx),
-        Instruction::Call(enter_funcidx),
-    ];
-    let leave = vec![
-        Instruction::I32Const(target_funcidx),
+    let hook_funcidx_arg =
+        Instruction::I32Const(target_funcidx.try_into().expect("function index overflow"));
+    let local_gets = || {
+        (0..target_type.param().0.len()).map(|local_idx| {
+            Instruction::LocalGet(local_idx.try_into().expect("local index overflow"))
+        })
+    };
+
+    let mut expr = vec![
+        Instruction::GlobalGet(current),
         Instruction::Call(leave_funcidx),
+        hook_funcidx_arg.clone(),
+        Instruction::GlobalSet(current),
+        hook_funcidx_arg,
     ];
-    let mut call = Vec::new();
-    for (local_idx, _) in target_type.param().0.iter().enumerate() {
-        call.push(Instruction::LocalGet(
-            local_idx.try_into().expect("local index overflow"),
-        ));
+    if config.pass_arguments {
+        expr.extend(local_gets());
     }
-    call.push(Instruction::Call(
-        target_funcidx.try_into().expect("function index overflow"),
-    ));
-    let mut expr = Vec::new();
-    expr.extend_from_slice(&enter);
-    expr.extend_from_slice(&call);
-    expr.extend_from_slice(&leave);
+    expr.push(Instruction::Call(enter_funcidx));
+    expr.extend(local_gets());
+    expr.push(Instruction::ReturnCall(target_funcidx));
     Expression(expr)
 }
 
-/// Installs instrumentation hook for every function on the module.
+/// Replaces the function indices of `return_call` instructions by `map`.
+struct TailCallRewriter<F>(F);
+
+impl<F: FnMut(u32) -> Option<u32>> VisitMut for TailCallRewriter<F> {
+    fn visit_instruction(&mut self, instr: &mut Instruction) -> Option<Vec<Instruction>> {
+        if let Instruction::ReturnCall(funcidx) = instr {
+            if let Some(x) = (self.0)(*funcidx) {
+                *funcidx = x;
+            }
+        }
+        None
+    }
+}
+
+/// Resolves [`Unwinding::Auto`] by the exception handling encoding `module` uses.
+fn resolve_unwinding(module: &mut SynthModule) -> Unwinding {
+    #[derive(Default)]
+    struct Detector {
+        legacy: bool,
+        exnref: bool,
+        throw: bool,
+    }
+
+    impl VisitMut for Detector {
+        fn visit_instruction(&mut self, instr: &mut Instruction) -> Option<Vec<Instruction>> {
+            match instr {
+                Instruction::Try(..) | Instruction::TryDelegate(..) | Instruction::Rethrow(_) => {
+                    self.legacy = true
+                }
+                Instruction::TryTable(..) | Instruction::ThrowRef => self.exnref = true,
+                Instruction::Throw(_) => self.throw = true,
+                _ => (),
+            }
+            None
+        }
+    }
+
+    let has_tags = module
+        .tag_section
+        .as_ref()
+        .is_some_and(|x| !x.tags.is_empty())
+        || module.import_section.as_ref().is_some_and(|x| {
+            x.imports
+                .iter()
+                .any(|x| matches!(x.description, SynthImportDescription::Tag(..)))
+        });
+    let mut detector = Detector::default();
+    module.visit_mut(&mut detector);
+
+    if detector.legacy && !detector.exnref {
+        Unwinding::Legacy
+    } else if has_tags || detector.exnref || detector.throw {
+        Unwinding::Exnref
+    } else {
+        Unwinding::Ignore
+    }
+}
+
+/// Installs instrumentation hook for every function on the module, with the default
+/// [`InstrumentConfig`].
 pub fn install_all(module: &mut SynthModule) -> Result<(), Error> {
-    // TODO: ensure idempotence (by checking a certain custom section then inserting it)
+    InstrumentConfig::default().install_all(module)
+}
 
-    let tysec = &mut module
-        .type_section
-        .get_or_insert_with(Default::default)
-        .types;
-    let import_tyidx = tysec.len();
-    tysec.push(FuncType {
-        param: ResultType(vec![ValueType::I32]),
-        result: ResultType(vec![]),
-    });
+impl InstrumentConfig {
+    /// Installs instrumentation hook for every function on the module.
+    pub fn install_all(&self, module: &mut SynthModule) -> Result<(), Error> {
+        if module
+            .custom_sections
+            .iter()
+            .any(|x| x.name == MARKER_SECTION_NAME)
+        {
+            return Err(Error::AlreadyInstrumented);
+        }
 
-    let imports = &mut module
-        .import_section
-        .get_or_insert_with(Default::default)
-        .imports;
+        let function_types = {
+            let types = module
+                .type_section
+                .as_ref()
+                .map(|x| &x.types[..])
+                .unwrap_or_default();
+            module
+                .function_section
+                .as_ref()
+                .map(|x| &x.type_indices[..])
+                .unwrap_or_default()
+                .iter()
+                .map(|&tyidx| {
+                    let ty = types
+                        .get(usize::try_from(tyidx).expect("type index overflow"))
+                        .ok_or(Error::InvalidModule(ValidationError::UnknownType(
+                            tyidx.into(),
+                        )))?;
+                    ty.func_type().cloned().ok_or(Error::InvalidModule(
+                        ValidationError::CompositeType {
+                            index: tyidx,
+                            expected: "function",
+                        },
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
 
-    let enter_hook_funcidx: u32 = imports
-        .iter()
-        .filter(|x| matches!(x.description, SynthImportDescription::Type(..)))
-        .count()
-        .try_into()
-        .expect("function index overflow");
-    let leave_hook_funcidx = enter_hook_funcidx
-        .checked_add(1)
-        .expect("function index overflow");
-
-    imports.push(SynthImport {
-        module: String::from("wasynth_hooks"),
-        name: String::from("enter"),
-        description: SynthImportDescription::Type(
-            import_tyidx.try_into().expect("type index overflow"),
-        ),
-    });
-    imports.push(SynthImport {
-        module: String::from("wasynth_hooks"),
-        name: String::from("leave"),
-        description: SynthImportDescription::Type(
-            import_tyidx.try_into().expect("type index overflow"),
-        ),
-    });
+        let func_imports = module.import_section.as_ref().map_or(0, |x| {
+            x.imports
+                .iter()
+                .filter(|x| matches!(x.description, SynthImportDescription::Type(..)))
+                .count()
+        });
+        let selected = match &self.filter {
+            Some(filter) => {
+                let info = FunctionInfo::lookup(module);
+                (0..function_types.len())
+                    .map(|non_import_funcidx| {
+                        let funcidx = u32::try_from(func_imports + non_import_funcidx)
+                            .expect("function index overflow");
+                        filter(&info(funcidx))
+                    })
+                    .collect::<Vec<_>>()
+            }
+            None => vec![true; function_types.len()],
+        };
 
-    let type_indices = &module
-        .function_section
-        .get_or_insert_with(Default::default)
-        .type_indices;
+        // direct tail calls between instrumented functions are redirected to tail trampolines
+        let mut is_tail_callee = vec![false; function_types.len()];
+        if let Some(codesec) = module.code_section.as_mut() {
+            for (code, _) in codesec
+                .codes_mut()
+                .iter_mut()
+                .zip(&selected)
+                .filter(|x| *x.1)
+            {
+                code.func_expr
+                    .visit_mut(&mut TailCallRewriter(|funcidx: u32| {
+                        let callee = usize::try_from(funcidx).ok()?.checked_sub(func_imports)?;
+                        if selected.get(callee) == Some(&true) {
+                            is_tail_callee[callee] = true;
+                        }
+                        None
+                    }));
+            }
+        }
+        let tail_callees = is_tail_callee
+            .iter()
+            .enumerate()
+            .filter_map(|(non_import_funcidx, x)| x.then_some(non_import_funcidx))
+            .collect::<Vec<_>>();
 
-    let codesec = module.code_section.get_or_insert_with(Default::default);
+        let unwinding = match self.unwinding {
+            Unwinding::Auto => resolve_unwinding(module),
+            x => x,
+        };
+        let types = module
+            .type_section
+            .as_ref()
+            .map(|x| &x.types[..])
+            .unwrap_or_default();
+        let mut hooks = Vec::new();
+        let mut hook_positions = Vec::new();
+        for (ty, _) in function_types.iter().zip(&selected).filter(|x| *x.1) {
+            let enter_values = if self.pass_arguments {
+                &ty.param.0[..]
+            } else {
+                &[]
+            };
+            let leave_values = if self.pass_results {
+                &ty.result.0[..]
+            } else {
+                &[]
+            };
+            hook_positions.push((
+                hook_position(&mut hooks, &self.enter_name, enter_values, types),
+                hook_position(&mut hooks, &self.leave_name, leave_values, types),
+            ));
+        }
+        if hooks.is_empty() {
+            // keep the hook imports even if no function is instrumented
+            hook_position(&mut hooks, &self.enter_name, &[], types);
+            hook_position(&mut hooks, &self.leave_name, &[], types);
+        }
+        let unwind_pos = (unwinding != Unwinding::Ignore)
+            .then(|| hook_position(&mut hooks, &self.leave_name, &[], types));
+        let tail_leave_pos = (!tail_callees.is_empty())
+            .then(|| hook_position(&mut hooks, &self.leave_name, &[], types));
+
+        let tysec = &mut module
+            .type_section
+            .get_or_insert_with(Default::default)
+            .types;
+        let type_start = tysec.len();
+        let mut hook_tyidxs = Vec::new();
+        let mut new_types: Vec<FuncType> = Vec::new();
+        for hook in &hooks {
+            let pos = match new_types.iter().position(|x| *x == hook.ty) {
+                Some(pos) => pos,
+                None => {
+                    new_types.push(hook.ty.clone());
+                    new_types.len() - 1
+                }
+            };
+            hook_tyidxs.push(u32::try_from(tysec.len() + pos).expect("type index overflow"));
+        }
+        let type_count = new_types.len();
+        tysec.extend(new_types.into_iter().map(SubType::from));
 
-    assert_eq!(type_indices.len(), codesec.codes().len());
+        let imports = &mut module
+            .import_section
+            .get_or_insert_with(Default::default)
+            .imports;
+        let hook_import_pos = imports.len();
+        let global_imports = imports
+            .iter()
+            .filter(|x| matches!(x.description, SynthImportDescription::Global(..)))
+            .count();
 
-    let existing_non_import_functions = type_indices.len();
+        let enter_hook_funcidx: u32 = func_imports.try_into().expect("function index overflow");
+        let hook_funcidx = |pos: usize| -> u32 {
+            enter_hook_funcidx
+                .checked_add(pos.try_into().expect("function index overflow"))
+                .expect("function index overflow")
+        };
 
-    let mut funcs_to_append = Vec::new();
-    let mut codes_to_append = Vec::new();
-    for (funcidx, (tyidx, code)) in type_indices
-        .iter()
-        .copied()
-        .zip(codesec.codes_mut().iter_mut())
-        .enumerate()
-    {
-        let original_instrs = std::mem::replace(
-            &mut code.func_expr,
-            trampoline_instrs(
-                funcidx + imports.len() + type_indices.len(),
-                &tysec[usize::try_from(tyidx).expect("type index overflow")],
-                enter_hook_funcidx,
-                leave_hook_funcidx,
-            ),
-        );
+        for (hook, tyidx) in hooks.iter().zip(hook_tyidxs) {
+            imports.push(SynthImport {
+                module: self.hook_module.clone(),
+                name: hook.name.clone(),
+                description: SynthImportDescription::Type(tyidx),
+            });
+        }
 
-        codes_to_append.push(SynthCode {
-            locals: code.locals.clone(),
-            func_expr: original_instrs,
+        // the global tracking the running function is only needed for tail trampolines
+        let current = (!tail_callees.is_empty()).then(|| {
+            let globals = &mut module
+                .global_section
+                .get_or_insert_with(Default::default)
+                .globals;
+            globals.push(SynthGlobal {
+                ty: GlobalType::new(ValueType::I32, true),
+                init: Expression(vec![Instruction::I32Const(0)]),
+            });
+            to_u32(global_imports + globals.len() - 1)
         });
-        funcs_to_append.push(tyidx);
 
-        code.locals.truncate(0); // locals are not needed on trampoline
+        // trampolines installed below already refer to final function indices
+        module.remap_for_insertion(
+            IndexSpace::Function,
+            enter_hook_funcidx,
+            to_u32(hooks.len()),
+        )?;
+
+        let codesec = module.code_section.get_or_insert_with(Default::default);
+
+        assert_eq!(function_types.len(), codesec.codes().len());
+
+        let existing_non_import_functions = function_types.len();
+
+        // Selected functions are replaced in place with trampolines, and their original bodies are
+        // appended after all existing functions. Therefore all existing function indices are only
+        // shifted by the number of hook imports, and references to selected functions (including
+        // exports) reach trampolines.
+        // Tail trampolines are appended after the original bodies.
+        let first_defined_funcidx = hook_funcidx(hooks.len());
+        let first_appended_funcidx = func_imports + hooks.len() + existing_non_import_functions;
+        let first_tail_funcidx = first_appended_funcidx + hook_positions.len();
+        let tail_funcidx = |funcidx: u32| {
+            let callee = usize::try_from(funcidx.checked_sub(first_defined_funcidx)?).ok()?;
+            let pos = tail_callees.binary_search(&callee).ok()?;
+            Some(to_u32(first_tail_funcidx + pos))
+        };
+        let mut original_funcidxs = vec![None; existing_non_import_functions];
+        let mut enter_funcidxs = vec![None; existing_non_import_functions];
+        let mut codes_to_append = Vec::new();
+        let mut funcs_to_append = Vec::new();
+        let type_indices = module
+            .function_section
+            .get_or_insert_with(Default::default)
+            .type_indices_mut();
+        for ((non_import_funcidx, code), (enter_pos, leave_pos)) in codesec
+            .codes_mut()
+            .iter_mut()
+            .enumerate()
+            .filter(|(non_import_funcidx, _)| selected[*non_import_funcidx])
+            .zip(hook_positions)
+        {
+            let original_funcidx: u32 = (first_appended_funcidx + codes_to_append.len())
+                .try_into()
+                .expect("function index overflow");
+            let (trampoline, trampoline_locals) = trampoline_instrs(
+                original_funcidx,
+                &function_types[non_import_funcidx],
+                hook_funcidx(enter_pos),
+                hook_funcidx(leave_pos),
+                unwind_pos.map(|pos| {
+                    (
+                        unwinding,
+                        hook_funcidx(pos),
+                        type_indices[non_import_funcidx],
+                    )
+                }),
+                current,
+                self,
+            );
+            let mut original_instrs = std::mem::replace(&mut code.func_expr, trampoline);
+            original_instrs.visit_mut(&mut TailCallRewriter(tail_funcidx));
+            let original_locals = std::mem::replace(&mut code.locals, trampoline_locals);
+
+            codes_to_append.push(SynthCode {
+                locals: original_locals,
+                func_expr: original_instrs,
+            });
+            funcs_to_append.push(type_indices[non_import_funcidx]);
+            original_funcidxs[non_import_funcidx] = Some(original_funcidx);
+            enter_funcidxs[non_import_funcidx] = Some(hook_funcidx(enter_pos));
+        }
+
+        let mut tail_trampolines = Vec::new();
+        for (pos, &callee) in tail_callees.iter().enumerate() {
+            let (Some(target), Some(enter_funcidx), Some(leave_pos), Some(current)) = (
+                original_funcidxs[callee],
+                enter_funcidxs[callee],
+                tail_leave_pos,
+                current,
+            ) else {
+                unreachable!("tail callees are instrumented");
+            };
+            codes_to_append.push(SynthCode {
+                locals: Vec::new(),
+                func_expr: tail_trampoline_instrs(
+                    target,
+                    &function_types[callee],
+                    enter_funcidx,
+                    hook_funcidx(leave_pos),
+                    current,
+                    self,
+                ),
+            });
+            funcs_to_append.push(type_indices[callee]);
+            tail_trampolines.push((
+                first_defined_funcidx + to_u32(callee),
+                to_u32(first_tail_funcidx + pos),
+            ));
+        }
+
+        type_indices.extend_from_slice(&funcs_to_append);
+
+        codesec.codes_mut().extend(codes_to_append);
+
+        if let Some(namesec) = module.name_section.as_mut() {
+            if let Some(assocs) = namesec.function_names_mut() {
+                for (pos, hook) in hooks.iter().enumerate() {
+                    assocs.push(SynthNameAssoc {
+                        idx: hook_funcidx(pos),
+                        name: format!("{}/{}", self.hook_module, hook.name),
+                    });
+                }
+            }
+
+            // locals and labels of instrumented functions now belong to the original bodies
+            let move_indirect_idx = |x: &mut u32| {
+                let original_funcidx = x
+                    .checked_sub(first_defined_funcidx)
+                    .and_then(|x| original_funcidxs.get(usize::try_from(x).ok()?).copied())
+                    .flatten();
+                if let Some(original_funcidx) = original_funcidx {
+                    *x = original_funcidx;
+                }
+            };
+
+            for indassocs in [&mut namesec.local_names, &mut namesec.label_names] {
+                for indassoc in indassocs.iter_mut().flatten() {
+                    move_indirect_idx(&mut indassoc.idx);
+                }
+            }
+        }
+
+        let marker = InstrumentMarker {
+            hook_funcidx: enter_hook_funcidx,
+            hook_count: to_u32(hooks.len()),
+            hook_import_pos: to_u32(hook_import_pos),
+            type_start: to_u32(type_start),
+            type_count: to_u32(type_count),
+            trampolines: original_funcidxs
+                .iter()
+                .enumerate()
+                .filter_map(|(non_import_funcidx, original_funcidx)| {
+                    Some((
+                        hook_funcidx(hooks.len() + non_import_funcidx),
+                        (*original_funcidx)?,
+                    ))
+                })
+                .collect(),
+            tail_trampolines,
+            current_global: current,
+        };
+        let mut bytes = Vec::new();
+        marker.write_into(&mut bytes).map_err(Error::Io)?;
+        module
+            .custom_sections
+            .push(SynthCustomSection::new(MARKER_SECTION_NAME, bytes));
+
+        Ok(())
     }
+}
 
-    codesec.codes_mut().extend_from_slice(&codes_to_append);
+/// Removes instrumentation installed by [`install_all`] or [`InstrumentConfig::install_all`],
+/// restoring the original functions.
+///
+/// The module is restored using the marker custom section recorded on installation, so it may
+/// not be restored correctly if functions or imports were changed after instrumentation.
+pub fn uninstall(module: &mut SynthModule) -> Result<(), Error> {
+    let marker_pos = module
+        .custom_sections
+        .iter()
+        .position(|x| x.name == MARKER_SECTION_NAME)
+        .ok_or(Error::NotInstrumented)?;
+    let marker = InstrumentMarker::from_bytes(&module.custom_sections[marker_pos].bytes)?;
 
+    let hook_end = marker
+        .hook_funcidx
+        .checked_add(marker.hook_count)
+        .ok_or(Error::InstrumentMarker)?;
+    let non_import_funcidx = |funcidx: u32| {
+        funcidx
+            .checked_sub(hook_end)
+            .and_then(|x| usize::try_from(x).ok())
+            .ok_or(Error::InstrumentMarker)
+    };
+
+    let codes = &mut module
+        .code_section
+        .as_mut()
+        .ok_or(Error::MissingSection("code"))?
+        .codes;
+    let original_functions = codes
+        .len()
+        .checked_sub(marker.trampolines.len() + marker.tail_trampolines.len())
+        .ok_or(Error::InstrumentMarker)?;
+    for &(trampoline, original) in &marker.trampolines {
+        let trampoline = non_import_funcidx(trampoline)?;
+        let original = non_import_funcidx(original)?;
+        if trampoline >= original_functions || original >= codes.len() {
+            return Err(Error::InstrumentMarker);
+        }
+        // tail calls reach trampolines again
+        codes[original]
+            .func_expr
+            .visit_mut(&mut TailCallRewriter(|funcidx| {
+                marker
+                    .tail_trampolines
+                    .iter()
+                    .find(|(_, tail)| *tail == funcidx)
+                    .map(|(trampoline, _)| *trampoline)
+            }));
+        codes.swap(trampoline, original);
+    }
+    codes.truncate(original_functions);
     module
         .function_section
         .as_mut()
         .ok_or(Error::MissingSection("function"))?
-        .type_indices_mut()
-        .extend_from_slice(&funcs_to_append);
+        .type_indices
+        .truncate(original_functions);
 
-    // increment function indices greater than or equal to import_hook_funcidx
-    let fnidx_offset: u32 = (2 + funcs_to_append.len())
-        .try_into()
-        .expect("function offset overflow");
-    let increment_fnidx = |x: &mut u32| {
-        if *x >= enter_hook_funcidx {
-            *x += fnidx_offset;
+    if let Some(namesec) = module.name_section.as_mut() {
+        let first_appended = hook_end as usize + original_functions;
+        if let Some(assocs) = namesec.function_names_mut() {
+            assocs.retain(|x| (x.idx as usize) < first_appended);
         }
-    };
 
-    if let Some(glsec) = module.global_section.as_mut() {
-        for global in glsec.globals_mut() {
-            global.init.visit_func_indices(increment_fnidx);
-        }
-    }
+        let move_indirect_idx = |x: &mut u32| {
+            if let Some(&(trampoline, _)) = marker
+                .trampolines
+                .iter()
+                .find(|(_, original)| original == x)
+            {
+                *x = trampoline;
+            }
+        };
 
-    if let Some(exsec) = module.export_section.as_mut() {
-        for export in exsec.exports_mut() {
-            if let SynthExportDescription::Func(idx) = &mut export.desc {
-                increment_fnidx(idx);
+        for indassocs in [&mut namesec.local_names, &mut namesec.label_names] {
+            for indassoc in indassocs.iter_mut().flatten() {
+                move_indirect_idx(&mut indassoc.idx);
             }
         }
     }
 
-    if let Some(stsec) = module.start_section.as_mut() {
-        increment_fnidx(&mut stsec.start);
+    let imports = &mut module
+        .import_section
+        .as_mut()
+        .ok_or(Error::MissingSection("import"))?
+        .imports;
+    let hook_import_pos = usize::try_from(marker.hook_import_pos).expect("import index overflow");
+    let hook_import_end = hook_import_pos + marker.hook_count as usize;
+    if hook_import_end > imports.len() {
+        return Err(Error::InstrumentMarker);
     }
+    imports.drain(hook_import_pos..hook_import_end);
 
-    if let Some(elsec) = module.element_section.as_mut() {
-        for el in elsec.elements_mut() {
-            if let FuncIndices(x) = &mut el.init {
-                for x in x {
-                    increment_fnidx(x);
-                }
-            }
-        }
+    // hook types are removed only if no other types were added after instrumentation
+    let types = &mut module
+        .type_section
+        .as_mut()
+        .ok_or(Error::MissingSection("type"))?
+        .types;
+    if types.len() == marker.type_start as usize + marker.type_count as usize {
+        types.truncate(marker.type_start as usize);
     }
 
-    if let Some(codesec) = module.code_section.as_mut() {
-        for (non_import_funcidx, code) in codesec.codes_mut().iter_mut().enumerate() {
-            if non_import_funcidx <= existing_non_import_functions {
-                continue;
-            }
-            code.func_expr_mut().visit_func_indices(increment_fnidx);
-        }
+    if let Some(current) = marker.current_global {
+        let global_imports = imports
+            .iter()
+            .filter(|x| matches!(x.description, SynthImportDescription::Global(..)))
+            .count();
+        let globals = &mut module
+            .global_section
+            .as_mut()
+            .ok_or(Error::MissingSection("global"))?
+            .globals;
+        let pos = (current as usize)
+            .checked_sub(global_imports)
+            .filter(|&pos| pos < globals.len())
+            .ok_or(Error::InstrumentMarker)?;
+        globals.remove(pos);
+        module.remap_for_removal(IndexSpace::Global, current, 1)?;
     }
 
-    if let Some(datasec) = module.data_section.as_mut() {
-        for data in datasec.all_data_mut() {
-            match data {
-                SynthData::Active { offset, .. } => offset.visit_func_indices(increment_fnidx),
-                SynthData::Passive(_) => (),
-            }
+    module.remap_for_removal(IndexSpace::Function, marker.hook_funcidx, marker.hook_count)?;
+
+    // sections created on installation are removed again
+    if matches!(&module.import_section, Some(x) if x.imports.is_empty()) {
+        module.import_section = None;
+    }
+    if matches!(&module.type_section, Some(x) if x.types.is_empty()) {
+        module.type_section = None;
+    }
+    if matches!(&module.global_section, Some(x) if x.globals.is_empty()) {
+        module.global_section = None;
+    }
+    if matches!(&module.code_section, Some(x) if x.codes.is_empty()) {
+        module.code_section = None;
+        module.function_section = None;
+    }
+
+    module.custom_sections.remove(marker_pos);
+    if module.name_section_order > marker_pos {
+        module.name_section_order -= 1;
+    }
+
+    Ok(())
+}
+
+/// Name of the custom section recording installed instrumentation.
+pub const MARKER_SECTION_NAME: &str = "wasynth_instrument";
+
+/// Contents of the marker custom section.
+///
+/// All fields are encoded as LEB128 `u32`s, prefixed with a format version. The optional global
+/// index is encoded as a vector of at most one element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct InstrumentMarker {
+    /// Function index of the first hook import.
+    hook_funcidx: u32,
+    hook_count: u32,
+    /// Position of the first hook import in the import section.
+    hook_import_pos: u32,
+    /// Type index of the first type added for hooks.
+    type_start: u32,
+    type_count: u32,
+    /// Function indices of trampolines and the original functions they call.
+    trampolines: Vec<(u32, u32)>,
+    /// Function indices of trampolines and the tail trampolines replacing them in `return_call`s.
+    tail_trampolines: Vec<(u32, u32)>,
+    /// Global index of the running function, added if there are tail trampolines.
+    current_global: Option<u32>,
+}
+
+impl InstrumentMarker {
+    const VERSION: u32 = 2;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        // the marker section is a separate payload from the module binary
+        #[cfg(feature = "bytes_trace")]
+        {
+            crate::bytes_trace::initialize(bytes);
+        }
+
+        let (version, bytes) = bytes.advance_u32()?;
+        if version != Self::VERSION {
+            return Err(Error::InstrumentMarker);
+        }
+        let (hook_funcidx, bytes) = bytes.advance_u32()?;
+        let (hook_count, bytes) = bytes.advance_u32()?;
+        let (hook_import_pos, bytes) = bytes.advance_u32()?;
+        let (type_start, bytes) = bytes.advance_u32()?;
+        let (type_count, bytes) = bytes.advance_u32()?;
+        let mut bytes = bytes;
+        let trampolines = Self::advance_pairs(&mut bytes)?;
+        let tail_trampolines = Self::advance_pairs(&mut bytes)?;
+        let mut it = bytes.advance_vector(|bytes| bytes.advance_u32())?;
+        let current_global = it.next().transpose()?;
+        if it.next().is_some() {
+            return Err(Error::InstrumentMarker);
         }
+        if !it.finalize().is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+
+        Ok(Self {
+            hook_funcidx,
+            hook_count,
+            hook_import_pos,
+            type_start,
+            type_count,
+            trampolines,
+            tail_trampolines,
+            current_global,
+        })
     }
 
-    if let Some(namesec) = module.name_section.as_mut() {
-        if let Some(assocs) = namesec.function_names_mut() {
-            for assoc in &mut *assocs {
-                increment_fnidx(&mut assoc.idx);
-            }
-            assocs.push(SynthNameAssoc {
-                idx: enter_hook_funcidx,
-                name: String::from("wasynth_hooks/enter"),
-            });
-            assocs.push(SynthNameAssoc {
-                idx: leave_hook_funcidx,
-                name: String::from("wasynth_hooks/leave"),
-            });
+    fn advance_pairs(bytes: &mut &[u8]) -> Result<Vec<(u32, u32)>, Error> {
+        let mut it = bytes.advance_vector(|bytes| {
+            let (x, bytes) = bytes.advance_u32()?;
+            let (y, bytes) = bytes.advance_u32()?;
+            Ok(((x, y), bytes))
+        })?;
+        let mut pairs = Vec::new();
+        for x in &mut it {
+            pairs.push(x?);
         }
+        *bytes = it.finalize();
+        Ok(pairs)
+    }
 
-        if let Some(indassocs) = namesec.local_names_mut() {
-            for indassoc in &mut *indassocs {
-                increment_fnidx(&mut indassoc.idx);
+    fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        wr.write_u32(Self::VERSION)?;
+        wr.write_u32(self.hook_funcidx)?;
+        wr.write_u32(self.hook_count)?;
+        wr.write_u32(self.hook_import_pos)?;
+        wr.write_u32(self.type_start)?;
+        wr.write_u32(self.type_count)?;
+        for pairs in [&self.trampolines, &self.tail_trampolines] {
+            wr.write_u32(to_u32(pairs.len()))?;
+            for &(x, y) in pairs {
+                wr.write_u32(x)?;
+                wr.write_u32(y)?;
             }
         }
-
-        if let Some(indassocs) = namesec.label_names_mut() {
-            for indassoc in &mut *indassocs {
-                increment_fnidx(&mut indassoc.idx);
+        match self.current_global {
+            Some(x) => {
+                wr.write_u32(1)?;
+                wr.write_u32(x)?;
             }
+            None => wr.write_u32(0)?,
         }
+        Ok(())
     }
+}
 
-    Ok(())
+fn to_u32(x: usize) -> u32 {
+    x.try_into().expect("index overflow")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{install_all, uninstall, InstrumentConfig, Unwinding};
+    use crate::{
+        instructions::{BlockType, Catch, Instruction},
+        parse::Module,
+        synth::{
+            sections::{SynthExportDescription, SynthImportDescription},
+            SynthModule,
+        },
+        wasm_types::ValueType,
+        Error,
+    };
+
+    #[test]
+    fn test_hook_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (param i32 f64) (result i64)
+                    i64.const 0
+                )
+                (func)
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        InstrumentConfig::new()
+            .hook_module("tracer")
+            .pass_arguments(true)
+            .pass_results(true)
+            .install_all(&mut module)
+            .expect("install_all failed");
+
+        let imports = &module.import_section.as_ref().unwrap().imports;
+        assert!(imports.iter().all(|x| x.module == "tracer"));
+        assert_eq!(
+            imports.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(),
+            ["enter_i32_f64", "leave_i64", "enter", "leave"]
+        );
+        module.validate().expect("instrumented module is invalid");
+    }
+
+    #[test]
+    fn test_reference_hooks() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (type $s (struct))
+                (type $f (func))
+                (func (param (ref $s) (ref null $f) externref i31ref) (result (ref $f))
+                    unreachable
+                )
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        InstrumentConfig::new()
+            .pass_arguments(true)
+            .pass_results(true)
+            .install_all(&mut module)
+            .expect("install_all failed");
+
+        let imports = &module.import_section.as_ref().unwrap().imports;
+        assert_eq!(
+            imports.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(),
+            ["enter_anyref_funcref_externref_anyref", "leave_funcref"]
+        );
+        module.validate().expect("instrumented module is invalid");
+    }
+
+    #[test]
+    fn test_filter() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "f" (func))
+                (func $hot (export "hot")
+                    call 0
+                )
+                (func $traced (export "traced")
+                    call $hot
+                )
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        InstrumentConfig::new()
+            .filter(|f| f.export_names().contains(&"traced"))
+            .install_all(&mut module)
+            .expect("install_all failed");
+        module.validate().expect("instrumented module is invalid");
+
+        let imports = &module.import_section.as_ref().unwrap().imports;
+        assert_eq!(
+            imports
+                .iter()
+                .filter(|x| matches!(x.description, SynthImportDescription::Type(_)))
+                .count(),
+            3
+        );
+        // hot (3), trampoline of traced (4), original traced (5)
+        assert_eq!(
+            module.function_section.as_ref().unwrap().type_indices.len(),
+            3
+        );
+        let exports = &module.export_section.as_ref().unwrap().exports;
+        assert!(matches!(exports[0].desc, SynthExportDescription::Func(3)));
+        assert!(matches!(exports[1].desc, SynthExportDescription::Func(4)));
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert!(matches!(codes[0].func_expr.0[..], [Instruction::Call(0)]));
+        assert!(codes[1]
+            .func_expr
+            .0
+            .iter()
+            .any(|x| matches!(x, Instruction::Call(5))));
+        assert!(matches!(codes[2].func_expr.0[..], [Instruction::Call(3)]));
+    }
+
+    #[test]
+    fn test_unwind() {
+        let instrument = |wat_s: &str, unwinding| {
+            let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+            let mut module = Module::from_binary(&wasm)
+                .expect("cannot parse wasm")
+                .into_synth()
+                .expect("cannot convert into synth");
+            InstrumentConfig::new()
+                .pass_results(true)
+                .unwinding(unwinding)
+                .install_all(&mut module)
+                .expect("install_all failed");
+            module.validate().expect("instrumented module is invalid");
+            module
+        };
+        let hook_names = |module: &SynthModule| {
+            let imports = &module.import_section.as_ref().unwrap().imports;
+            imports.iter().map(|x| x.name.clone()).collect::<Vec<_>>()
+        };
+
+        let module = instrument(
+            r#"(module
+                (tag $e)
+                (func (param i32) (result i32)
+                    throw $e
+                )
+            )"#,
+            Unwinding::Auto,
+        );
+        assert_eq!(hook_names(&module), ["enter", "leave_i32", "leave"]);
+        // the plain leave hook (2) is called before rethrowing the caught exnref
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        let [.., Instruction::Block(BlockType::Value(ValueType::ExnRef), body), Instruction::I32Const(4), Instruction::Call(2), Instruction::ThrowRef] =
+            &codes[0].func_expr.0[..]
+        else {
+            panic!("trampoline does not catch exceptions");
+        };
+        let Some(Instruction::TryTable(_, catches, try_body)) =
+            body.iter().find(|x| matches!(x, Instruction::TryTable(..)))
+        else {
+            panic!("trampoline does not catch exceptions");
+        };
+        assert!(matches!(catches[..], [Catch::AllRef { label: 0 }]));
+        assert!(matches!(try_body[..], [Instruction::Call(4)]));
+        assert!(matches!(body.last(), Some(Instruction::Return)));
+
+        // a module only with legacy exception instructions is unwound with `try`
+        let module = instrument(
+            r#"(module
+                (func (param i32) (result i32)
+                    try (result i32)
+                        local.get 0
+                    catch_all
+                        i32.const 0
+                    end
+                )
+            )"#,
+            Unwinding::Auto,
+        );
+        assert_eq!(hook_names(&module), ["enter", "leave_i32", "leave"]);
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        let Some(Instruction::Try(_, body, catches, Some(catch_all))) = codes[0]
+            .func_expr
+            .0
+            .iter()
+            .find(|x| matches!(x, Instruction::Try(..)))
+        else {
+            panic!("trampoline does not catch exceptions");
+        };
+        assert!(matches!(body[..], [Instruction::Call(4)]));
+        assert!(catches.is_empty());
+        assert!(matches!(
+            catch_all[..],
+            [
+                Instruction::I32Const(4),
+                Instruction::Call(2),
+                Instruction::Rethrow(0)
+            ]
+        ));
+
+        // exceptions of imported functions may unwind through modules without any tags
+        let tagless = r#"(module
+            (import "env" "f" (func $f))
+            (func call $f)
+        )"#;
+        assert_eq!(
+            hook_names(&instrument(tagless, Unwinding::Auto)),
+            ["f", "enter", "leave"]
+        );
+        let module = instrument(tagless, Unwinding::Legacy);
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert!(codes[0]
+            .func_expr
+            .0
+            .iter()
+            .any(|x| matches!(x, Instruction::Try(..))));
+        let module = instrument(tagless, Unwinding::Exnref);
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert!(matches!(
+            codes[0].func_expr.0.last(),
+            Some(Instruction::ThrowRef)
+        ));
+    }
+
+    #[test]
+    fn test_tail_call() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func $count (param i32) (result i32)
+                    local.get 0
+                    i32.eqz
+                    if
+                        i32.const 0
+                        return
+                    end
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    return_call $count
+                )
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        install_all(&mut module).expect("install_all failed");
+        module.validate().expect("instrumented module is invalid");
+
+        // The tail call reaches the tail trampoline (4) instead of the trampoline (2), which calls
+        // the plain leave hook (1) for the running function, makes the callee the running function
+        // in the global (0), calls the enter hook (0) and tail calls the original (3). The stack
+        // depth stays constant, and the trampoline calls the leave hook for the running function
+        // once the chain returns.
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert!(matches!(
+            codes[0].func_expr.0[..],
+            [
+                Instruction::GlobalGet(0),
+                Instruction::LocalSet(1),
+                Instruction::I32Const(3),
+                Instruction::GlobalSet(0),
+                Instruction::I32Const(3),
+                Instruction::Call(0),
+                Instruction::LocalGet(0),
+                Instruction::Call(3),
+                Instruction::GlobalGet(0),
+                Instruction::Call(1),
+                Instruction::LocalGet(1),
+                Instruction::GlobalSet(0),
+            ]
+        ));
+        assert_eq!(codes[0].locals, [ValueType::I32]);
+        assert!(codes[1]
+            .func_expr
+            .0
+            .iter()
+            .any(|x| matches!(x, Instruction::ReturnCall(4))));
+        assert!(matches!(
+            codes[2].func_expr.0[..],
+            [
+                Instruction::GlobalGet(0),
+                Instruction::Call(1),
+                Instruction::I32Const(3),
+                Instruction::GlobalSet(0),
+                Instruction::I32Const(3),
+                Instruction::Call(0),
+                Instruction::LocalGet(0),
+                Instruction::ReturnCall(3),
+            ]
+        ));
+
+        uninstall(&mut module).expect("uninstall failed");
+        assert!(module.global_section.is_none());
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert_eq!(codes.len(), 1);
+        assert!(codes[0]
+            .func_expr
+            .0
+            .iter()
+            .any(|x| matches!(x, Instruction::ReturnCall(0))));
+    }
+
+    #[test]
+    fn test_reinstall() {
+        let wasm = wat::parse_str("(module (func))").expect("cannot parse wat");
+        let mut module = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        install_all(&mut module).expect("install_all failed");
+        assert!(matches!(
+            install_all(&mut module),
+            Err(Error::AlreadyInstrumented)
+        ));
+    }
+
+    #[test]
+    fn test_uninstall() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "f" (func $f (param i32)))
+                (table 2 funcref)
+                (func $a (export "a") (param $x i32) (result i32)
+                    local.get $x
+                    call $b
+                )
+                (func $b (param i32) (result i32)
+                    local.get 0
+                    call $f
+                    i32.const 1
+                )
+                (elem (i32.const 0) $a $b)
+            )"#,
+        )
+        .expect("cannot parse wat");
+        let module = Module::from_binary(&wasm).expect("cannot parse wasm");
+        let mut original = Vec::new();
+        module
+            .clone()
+            .into_synth()
+            .expect("cannot convert into synth")
+            .write_into(&mut original)
+            .unwrap();
+
+        let mut instrumented = module.into_synth().expect("cannot convert into synth");
+        InstrumentConfig::new()
+            .pass_arguments(true)
+            .filter(|f| f.name() == Some("a"))
+            .install_all(&mut instrumented)
+            .expect("install_all failed");
+        let mut buf = Vec::new();
+        instrumented.write_into(&mut buf).unwrap();
+
+        let mut restored = Module::from_binary(&buf)
+            .expect("cannot parse instrumented wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        uninstall(&mut restored).expect("uninstall failed");
+        let mut buf = Vec::new();
+        restored.write_into(&mut buf).unwrap();
+        assert_eq!(buf, original);
+
+        assert!(matches!(
+            uninstall(&mut restored),
+            Err(Error::NotInstrumented)
+        ));
+    }
 }
@@ -1,7 +1,11 @@
+pub mod eval;
 pub mod instructions;
 pub mod instrument;
 pub mod parse;
+mod print;
 pub mod synth;
+mod text;
+pub mod validate;
 pub mod wasm_types;
 
 #[cfg(feature = "bytes_trace")]
@@ -12,7 +16,10 @@ use std::{
     marker::PhantomData,
 };
 
+use eval::EvalError;
+use synth::DanglingReference;
 use thiserror::Error;
+use validate::ValidationError;
 
 #[cfg(feature = "bytes_trace")]
 use bytes_trace::{trace_end, trace_start, Action};
@@ -38,6 +45,8 @@ pub enum Error {
     SectionID(u8),
     #[error("invalid function type ID 0x{0:02x}, expected 0x60")]
     FunctionTypeId(u8),
+    #[error("invalid composite type ID 0x{0:02x}, expected 0x5e, 0x5f or 0x60")]
+    CompositeTypeId(u8),
     #[error("invalid reference type ID 0x{0:02x}")]
     ReferenceTypeId(u8),
     #[error("invalid value type ID 0x{0:02x}")]
@@ -46,16 +55,26 @@ pub enum Error {
     LimitsTag(u8),
     #[error("invalid global type mutability 0x{0:02x}")]
     GlobalTypeMutability(u8),
+    #[error("invalid field type mutability 0x{0:02x}")]
+    FieldTypeMutability(u8),
     #[error("invalid import description tag 0x{0:02x}")]
     ImportDescriptionTag(u8),
+    #[error("invalid tag attribute 0x{0:02x}")]
+    TagAttribute(u8),
+    #[error("invalid catch clause kind 0x{0:02x}")]
+    CatchKind(u8),
     #[error("invalid 0xFC instruction subopcode {0}")]
     HexFcInstructionSubopcode(u32),
     #[error("invalid vector instruction subopcode {0}")]
     VectorInstructionSubopcode(u32),
-    #[error("invalid memory instruction 0x{0:02x} 0x{1:02x}")]
-    MemoryInstruction(u8, u8),
-    #[error("{instr} instruction shoud have a trailing zero byte, got {byte}")]
-    MemoryInstructionNoTrailingZero { instr: &'static str, byte: u8 },
+    #[error("invalid atomic instruction subopcode {0}")]
+    HexFeInstructionSubopcode(u32),
+    #[error("invalid GC instruction subopcode {0}")]
+    HexFbInstructionSubopcode(u32),
+    #[error("invalid br_on_cast flags 0x{0:02x}")]
+    CastFlags(u8),
+    #[error("atomic.fence should be followed by a zero byte")]
+    AtomicFenceNoTrailingZero,
     #[error("invalid WebAssembly opcode 0x{0:02x}")]
     Opcode(u8),
     #[error("invalid data section tag {0}")]
@@ -79,6 +98,160 @@ pub enum Error {
     MissingNameSectionSubsection(&'static str),
     #[error("duplicate name section {0} subsection")]
     DuplicateNameSectionSubsection(&'static str),
+    #[error("module is already instrumented")]
+    AlreadyInstrumented,
+    #[error("module is not instrumented")]
+    NotInstrumented,
+    #[error("invalid instrumentation marker section")]
+    InstrumentMarker,
+    #[error("{} dangling index references", .0.len())]
+    DanglingReferences(Vec<DanglingReference>),
+    #[error("invalid module")]
+    InvalidModule(#[source] ValidationError),
+    #[error("cannot evaluate constant expression")]
+    ConstEval(#[source] EvalError),
+    #[error("invalid function {func}{}", display_instruction(instr_index, offset))]
+    InvalidFunction {
+        func: u32,
+        /// Position of the instruction at fault in a pre-order walk of the function body, i.e. an
+        /// instruction nested in a block comes right after the block itself. This is the number of
+        /// instructions if the end of the function is at fault, or `None` if a local declaration
+        /// is.
+        instr_index: Option<usize>,
+        /// Offset of the instruction at fault in the module binary, or of the function body if a
+        /// local declaration is at fault. Only known when validating a parsed
+        /// [`Module`](parse::Module).
+        offset: Option<usize>,
+        #[source]
+        source: ValidationError,
+    },
+    #[error("cannot parse text format at {line}:{column}: {message}")]
+    Wat {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{0} imports must be added before defining any {0}")]
+    ImportAfterDefinition(&'static str),
+    #[error("{0} exceeds the limit of {1}")]
+    LimitExceeded(&'static str, usize),
+    #[error("cannot parse {} at offset {offset:#x}", display_path(path))]
+    Parse {
+        /// Offset of the innermost entity of `path` in the module binary.
+        offset: usize,
+        /// Entities being parsed, from the outermost.
+        path: Vec<ErrorContext>,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+/// An entity of a module binary being parsed when an [`Error::Parse`] happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorContext {
+    /// A section, e.g. `"type"` or `"data count"`.
+    Section(&'static str),
+    /// An entry of a section vector, e.g. a type or a data segment.
+    Entry(u32),
+    /// A function body, by its position in the code section.
+    Function(u32),
+    /// An instruction of a function body or a constant expression.
+    Instruction,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Section(name) => write!(f, "{name} section"),
+            Self::Entry(idx) => write!(f, "entry {idx}"),
+            Self::Function(idx) => write!(f, "function {idx}"),
+            Self::Instruction => write!(f, "instruction"),
+        }
+    }
+}
+
+fn display_path(path: &[ErrorContext]) -> String {
+    if path.is_empty() {
+        return String::from("module");
+    }
+    path.iter()
+        .map(ErrorContext::to_string)
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+fn display_instruction(instr_index: &Option<usize>, offset: &Option<usize>) -> String {
+    let mut s = match instr_index {
+        Some(idx) => format!(" at instruction {idx}"),
+        None => String::from(" in local declarations"),
+    };
+    if let Some(offset) = offset {
+        s += &format!(" (offset {offset:#x})");
+    }
+    s
+}
+
+impl Error {
+    /// Locates this error at `offset`, relative to the entity enclosing the innermost entity this
+    /// error is already located at, if any.
+    pub(crate) fn at(self, offset: usize) -> Self {
+        match self {
+            Self::Parse {
+                offset: inner,
+                path,
+                source,
+            } => Self::Parse {
+                offset: offset + inner,
+                path,
+                source,
+            },
+            source => Self::Parse {
+                offset,
+                path: Vec::new(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Locates this error in `context` at `offset`, like [`Error::at`].
+    pub(crate) fn within(self, context: ErrorContext, offset: usize) -> Self {
+        let Self::Parse {
+            offset,
+            mut path,
+            source,
+        } = self.at(offset)
+        else {
+            unreachable!("located error");
+        };
+        path.insert(0, context);
+        Self::Parse {
+            offset,
+            path,
+            source,
+        }
+    }
+
+    /// Returns the error without its location, if any.
+    pub fn without_location(&self) -> &Self {
+        match self {
+            Self::Parse { source, .. } => source,
+            x => x,
+        }
+    }
+}
+
+/// Converts a length read from a binary into `usize`.
+///
+/// A length which does not fit into `usize` saturates, as it cannot fit into the binary either.
+pub(crate) fn length(n: u32) -> usize {
+    usize::try_from(n).unwrap_or(usize::MAX)
+}
+
+/// Returns the offset of `inner` in `outer`, where `inner` is a subslice of `outer`.
+pub(crate) fn offset_in(outer: &[u8], inner: &[u8]) -> usize {
+    let offset = (inner.as_ptr() as usize).wrapping_sub(outer.as_ptr() as usize);
+    debug_assert!(offset <= outer.len(), "not a subslice");
+    offset
 }
 
 /// Convenince trait for reading bytes.
@@ -253,7 +426,7 @@ impl<'a> Bytes for &'a [u8] {
 
         Ok(VectorIterator {
             bytes: this,
-            count: n.try_into().expect("vector length overflow"),
+            count: length(n),
             func,
             _phantom: PhantomData,
         })
@@ -264,7 +437,7 @@ impl<'a> Bytes for &'a [u8] {
         trace_start(Action::AdvanceName, self);
 
         let (n, this) = self.advance_u32()?;
-        let (bytes, this) = this.advance_slice(n.try_into().expect("name length overflow"))?;
+        let (bytes, this) = this.advance_slice(length(n))?;
 
         #[cfg(feature = "bytes_trace")]
         trace_end(Action::AdvanceName, this);
@@ -312,6 +485,70 @@ impl<'bytes, T, F> VectorIterator<'bytes, T, F> {
     }
 }
 
+/// Reads a vector of entries from contents of a section at `offset`, locating errors in the
+/// section and in the entry with `context`.
+pub(crate) fn section_entries<'bytes, T, F>(
+    section: &'static str,
+    bytes: &'bytes [u8],
+    offset: usize,
+    context: fn(u32) -> ErrorContext,
+    func: F,
+) -> Result<SectionEntries<'bytes, T, F>, Error>
+where
+    F: FnMut(&'bytes [u8]) -> Result<(T, &'bytes [u8]), Error>,
+{
+    let inner = bytes
+        .advance_vector(func)
+        .map_err(|e| e.within(ErrorContext::Section(section), offset))?;
+    Ok(SectionEntries {
+        inner,
+        section,
+        bytes,
+        offset,
+        context,
+        index: 0,
+    })
+}
+
+/// Iterator of section entries returned by [`section_entries`].
+pub(crate) struct SectionEntries<'bytes, T, F> {
+    inner: VectorIterator<'bytes, T, F>,
+    section: &'static str,
+    bytes: &'bytes [u8],
+    offset: usize,
+    context: fn(u32) -> ErrorContext,
+    index: u32,
+}
+
+impl<'bytes, T, F> Iterator for SectionEntries<'bytes, T, F>
+where
+    F: FnMut(&'bytes [u8]) -> Result<(T, &'bytes [u8]), Error>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry_offset = offset_in(self.bytes, self.inner.bytes);
+        let item = self.inner.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some(item.map_err(|e| {
+            e.within((self.context)(index), entry_offset)
+                .within(ErrorContext::Section(self.section), self.offset)
+        }))
+    }
+}
+
+impl<'bytes, T, F> SectionEntries<'bytes, T, F> {
+    /// Fails if the section has more than `max` entries of `kind`.
+    pub(crate) fn limit(self, kind: &'static str, max: usize) -> Result<Self, Error> {
+        if self.inner.count > max {
+            return Err(Error::LimitExceeded(kind, max)
+                .within(ErrorContext::Section(self.section), self.offset));
+        }
+        Ok(self)
+    }
+}
+
 pub trait WriteExt: Write {
     /// Writes an u32 value into this writer.
     fn write_u32(&mut self, n: u32) -> Result<(), io::Error> {
@@ -2,50 +2,70 @@
 //!
 //! <https://webassembly.github.io/spec/core/binary/modules.html>
 
+mod limits;
+mod owned;
 pub mod sections;
+mod stream;
 
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range, sync::Arc};
 
-use crate::{synth::SynthModule, Bytes, Error, WASM_MAGIC, WASM_VERSION};
+use crate::{
+    length, offset_in,
+    synth::{
+        sections::{CustomPlacement, SectionKind},
+        OriginalBytes, SectionKey, SynthModule,
+    },
+    Bytes, Error, ErrorContext, WASM_MAGIC, WASM_VERSION,
+};
 use log::trace;
 use sections::{
     CodeSection, CustomSection, DataCountSection, DataSection, ElementSection, ExportSection,
-    FunctionSection, GlobalSection, ImportSection, MemorySection, StartSection, TableSection,
-    TypeSection,
+    FunctionSection, GlobalSection, ImportDescription, ImportSection, MemorySection, StartSection,
+    TableSection, TagSection, TypeSection,
 };
 
+pub use limits::ParseLimits;
+pub use owned::OwnedModule;
+pub use stream::{FunctionBody, StreamEvent, StreamParser};
+
 use self::sections::NameSection;
 
 /// A parsed WebAssembly module.
 #[derive(Debug, Clone)]
 pub struct Module<'bytes> {
+    binary: &'bytes [u8],
     sections: Vec<Section<'bytes>>,
+    /// Ranges of each section in the binary, including its id and size.
+    section_ranges: Vec<Range<usize>>,
+    limits: ParseLimits,
 }
 
 impl<'bytes> Module<'bytes> {
-    pub fn from_binary(binary: &'bytes [u8]) -> Result<Self, Error> {
+    /// Parses a module with the default [`ParseLimits`].
+    pub fn from_binary(module: &'bytes [u8]) -> Result<Self, Error> {
+        Self::from_binary_with_limits(module, ParseLimits::default())
+    }
+
+    /// Parses a module, failing if it exceeds `limits`.
+    pub fn from_binary_with_limits(
+        module: &'bytes [u8],
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
         #[cfg(feature = "bytes_trace")]
         {
-            crate::bytes_trace::initialize(binary);
-        }
-
-        let (magic, binary) = binary.advance::<4>()?;
-        if magic != WASM_MAGIC {
-            return Err(Error::Magic(magic[0], magic[1], magic[2], magic[3]));
-        }
-
-        let (version, mut binary) = binary.advance()?;
-        let version = u32::from_le_bytes(*version);
-        if version != WASM_VERSION {
-            return Err(Error::UnsupportedVersion(version));
+            crate::bytes_trace::initialize(module);
         }
 
+        let mut binary = read_header(module)?;
         let mut sections = Vec::new();
+        let mut section_ranges = Vec::new();
 
         while !binary.is_empty() {
             trace!("start reading section, id={}", binary[0]);
-            let (section, rest) = Section::from_bytes(binary)?;
+            let offset = offset_in(module, binary);
+            let (section, rest) = Section::from_bytes(binary, offset, limits)?;
             trace!("end reading section, id={}", section.id());
+            section_ranges.push(offset..offset_in(module, rest));
             binary = rest;
             sections.push(section);
         }
@@ -54,7 +74,17 @@ impl<'bytes> Module<'bytes> {
             return Err(Error::TrailingBytes);
         }
 
-        Ok(Module { sections })
+        Ok(Module {
+            binary: module,
+            sections,
+            section_ranges,
+            limits,
+        })
+    }
+
+    /// Copies the module binary into an [`OwnedModule`], which does not borrow it.
+    pub fn into_owned(self) -> OwnedModule {
+        OwnedModule::from_parts(self.binary.into(), self.limits)
     }
 
     pub fn into_synth(self) -> Result<SynthModule, Error> {
@@ -75,6 +105,22 @@ impl<'bytes> Module<'bytes> {
             }
         }
 
+        // custom sections are placed after the last known section preceding them
+        let mut placement = CustomPlacement::First;
+        let mut custom_sections = Vec::new();
+        let mut name_section_placement = CustomPlacement::default();
+        let mut name_section_order = 0;
+        for section in &self.sections {
+            match *section {
+                Section::Custom(x) => custom_sections.push(x.into_synth(placement)),
+                Section::Name(_) => {
+                    name_section_placement = placement;
+                    name_section_order = custom_sections.len();
+                }
+                x => placement = CustomPlacement::After(x.kind().expect("known section")),
+            }
+        }
+
         Ok(SynthModule {
             type_section: self
                 .sections
@@ -126,6 +172,16 @@ impl<'bytes> Module<'bytes> {
                 .extract_element("memory")?
                 .map(|x| x.into_synth())
                 .transpose()?,
+            tag_section: self
+                .sections
+                .iter()
+                .filter_map(|x| match x {
+                    Section::Tag(x) => Some(*x),
+                    _ => None,
+                })
+                .extract_element("tag")?
+                .map(|x| x.into_synth())
+                .transpose()?,
             global_section: self
                 .sections
                 .iter()
@@ -194,15 +250,7 @@ impl<'bytes> Module<'bytes> {
                 })
                 .extract_element("data count")?
                 .map(|x| x.into_synth()),
-            custom_sections: self
-                .sections
-                .iter()
-                .filter_map(|x| match x {
-                    Section::Custom(x) => Some(*x),
-                    _ => None,
-                })
-                .map(CustomSection::into_synth)
-                .collect(),
+            custom_sections,
             name_section: self
                 .sections
                 .iter()
@@ -213,13 +261,114 @@ impl<'bytes> Module<'bytes> {
                 .extract_element("name")?
                 .map(|x| x.into_synth())
                 .transpose()?,
+            name_section_placement,
+            name_section_order,
+            original: None,
         })
     }
 
+    /// Converts into a [`SynthModule`] which remembers the original bytes of this module.
+    ///
+    /// [`SynthModule::write_into`] writes sections and function bodies which have not been
+    /// modified with their original bytes, so that the output differs from the input only where
+    /// the module has been changed. Otherwise re-encoding normalizes the binary, e.g. by removing
+    /// LEB128 padding and regrouping locals.
+    ///
+    /// The module binary is copied once into the returned module. Use
+    /// [`OwnedModule::into_synth_preserving`] to share the binary instead.
+    pub fn into_synth_preserving(self) -> Result<SynthModule, Error> {
+        let binary = Arc::from(self.binary);
+        self.into_synth_preserving_with(binary)
+    }
+
+    /// Converts into a [`SynthModule`] remembering ranges of `binary`, which must be the same as
+    /// the binary of this module.
+    pub(crate) fn into_synth_preserving_with(
+        self,
+        binary: Arc<[u8]>,
+    ) -> Result<SynthModule, Error> {
+        debug_assert_eq!(*binary, *self.binary);
+        let raw_codes = match self.sections.iter().find_map(|x| match x {
+            Section::Code(x) => Some(*x),
+            _ => None,
+        }) {
+            Some(x) => x.raw_codes()?,
+            None => Vec::new(),
+        };
+        let module_binary = self.binary;
+        let sections = self.sections.clone();
+        let section_ranges = self.section_ranges.clone();
+        let mut module = self.into_synth()?;
+
+        let mut original = OriginalBytes::new(binary);
+        let mut buf = Vec::new();
+        if let Some(sec) = &module.code_section {
+            for (i, (code, raw)) in sec.codes.iter().zip(raw_codes).enumerate() {
+                buf.clear();
+                code.write_into(&mut buf)
+                    .expect("write into Vec cannot fail");
+                let start = offset_in(module_binary, raw);
+                original.insert_code(i, &buf, start..start + raw.len());
+            }
+        }
+        // sections are recorded after function bodies, as the code section is written with
+        // original function bodies
+        module.original = Some(Box::new(original));
+        let mut customs = 0;
+        let mut recorded = Vec::new();
+        for (section, range) in sections.iter().zip(section_ranges) {
+            buf.clear();
+            let key = match section {
+                Section::Custom(_) => {
+                    customs += 1;
+                    module.custom_sections[customs - 1]
+                        .write_into(&mut buf)
+                        .expect("write into Vec cannot fail");
+                    SectionKey::Custom(customs - 1)
+                }
+                Section::Name(_) => {
+                    module
+                        .name_section
+                        .as_ref()
+                        .expect("name section")
+                        .write_into(&mut buf)
+                        .expect("write into Vec cannot fail");
+                    SectionKey::Name
+                }
+                x => {
+                    let kind = x.kind().expect("known section");
+                    module
+                        .write_section(kind, &mut buf)
+                        .expect("write into Vec cannot fail");
+                    SectionKey::Known(kind)
+                }
+            };
+            if module_binary[range.clone()] != buf[..] {
+                recorded.push((key, std::mem::take(&mut buf), range));
+            }
+        }
+        let original = module.original.as_mut().expect("original bytes");
+        for (key, encoded, range) in recorded {
+            original.insert_section(key, &encoded, range);
+        }
+
+        Ok(module)
+    }
+
     pub fn sections(&self) -> &[Section<'bytes>] {
         &self.sections
     }
 
+    /// Returns ranges of each section of [`Module::sections`] in the module binary, including
+    /// section IDs and sizes.
+    pub fn section_ranges(&self) -> &[Range<usize>] {
+        &self.section_ranges
+    }
+
+    /// Validates the module.
+    ///
+    /// Every section is decoded first, then the module is validated against the WebAssembly
+    /// specification with [`SynthModule::validate`].
     pub fn validate(&self) -> Result<(), Error> {
         trace!("validation start");
         for section in self.sections() {
@@ -227,8 +376,8 @@ impl<'bytes> Module<'bytes> {
             match section {
                 Section::Custom(_) => (),
                 Section::Type(s) => {
-                    for ty in s.types()? {
-                        ty?;
+                    for group in s.rec_groups()? {
+                        group?;
                     }
                 }
                 Section::Import(s) => {
@@ -251,6 +400,11 @@ impl<'bytes> Module<'bytes> {
                         mem?;
                     }
                 }
+                Section::Tag(s) => {
+                    for tag in s.tags()? {
+                        tag?;
+                    }
+                }
                 Section::Global(s) => {
                     for global in s.globals()? {
                         global?;
@@ -297,6 +451,11 @@ impl<'bytes> Module<'bytes> {
                                     ina?;
                                 }
                             }
+                            sections::NameSubsection::FieldNames(_) => {
+                                for ina in ss.indirect_name_assocs()? {
+                                    ina?;
+                                }
+                            }
                             sections::NameSubsection::TypeNames(_) => {
                                 for na in ss.name_assocs()? {
                                     na?;
@@ -327,14 +486,77 @@ impl<'bytes> Module<'bytes> {
                                     na?;
                                 }
                             }
+                            sections::NameSubsection::TagNames(_) => {
+                                for na in ss.name_assocs()? {
+                                    na?;
+                                }
+                            }
                         }
                     }
                 }
             }
         }
+        trace!("type checking");
+        self.clone()
+            .into_synth()?
+            .validate()
+            .map_err(|e| self.locate_invalid_function(e))?;
         trace!("validation end");
         Ok(())
     }
+
+    /// Fills the offset of an [`Error::InvalidFunction`] in the module binary.
+    fn locate_invalid_function(&self, e: Error) -> Error {
+        let Error::InvalidFunction {
+            func,
+            instr_index,
+            offset: None,
+            source,
+        } = e
+        else {
+            return e;
+        };
+        let offset = self.instruction_offset(func, instr_index);
+        Error::InvalidFunction {
+            func,
+            instr_index,
+            offset,
+            source,
+        }
+    }
+
+    /// Returns the offset of an instruction of the function `func` in the module binary, or of the
+    /// function body if `instr_index` is `None`.
+    fn instruction_offset(&self, func: u32, instr_index: Option<usize>) -> Option<usize> {
+        let mut imported_funcs = 0;
+        let mut codesec = None;
+        for section in self.sections() {
+            match section {
+                Section::Import(s) => {
+                    for import in s.imports().ok()? {
+                        if let ImportDescription::Type(_) = import.ok()?.description() {
+                            imported_funcs += 1;
+                        }
+                    }
+                }
+                Section::Code(s) => codesec = Some(s),
+                _ => (),
+            }
+        }
+        let index = (func as usize).checked_sub(imported_funcs)?;
+        let code = codesec?
+            .codes_with_instruction_offsets()
+            .ok()?
+            .nth(index)?
+            .ok()?;
+        let Some(instr_index) = instr_index else {
+            return Some(code.offset());
+        };
+        match code.instruction_offsets()?.get(instr_index) {
+            Some(&offset) => Some(offset),
+            None => Some(code.end_offset()),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -345,6 +567,7 @@ pub enum Section<'bytes> {
     Function(FunctionSection<'bytes>),
     Table(TableSection<'bytes>),
     Memory(MemorySection<'bytes>),
+    Tag(TagSection<'bytes>),
     Global(GlobalSection<'bytes>),
     Export(ExportSection<'bytes>),
     Start(StartSection),
@@ -355,37 +578,101 @@ pub enum Section<'bytes> {
     Name(NameSection<'bytes>),
 }
 
+/// Checks the magic and the version of a module, returning the bytes after them.
+pub(crate) fn read_header(module: &[u8]) -> Result<&[u8], Error> {
+    let (magic, binary) = module.advance::<4>().map_err(|e| e.at(0))?;
+    if magic != WASM_MAGIC {
+        return Err(Error::Magic(magic[0], magic[1], magic[2], magic[3]).at(0));
+    }
+
+    let (version, binary) = binary.advance().map_err(|e| e.at(4))?;
+    let version = u32::from_le_bytes(*version);
+    if version != WASM_VERSION {
+        return Err(Error::UnsupportedVersion(version).at(4));
+    }
+
+    Ok(binary)
+}
+
 impl<'bytes> Section<'bytes> {
-    fn from_bytes(bytes: &'bytes [u8]) -> Result<(Self, &'bytes [u8]), Error> {
-        let (&[id], bytes) = bytes.advance()?;
-        let (len, bytes) = bytes.advance_u32()?;
-        let (bytes, rest) = bytes.advance_slice(len.try_into().expect("section size overflow"))?;
+    /// Reads a section at `offset` in the module binary.
+    fn from_bytes(
+        section: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<(Self, &'bytes [u8]), Error> {
+        let (&[id], bytes) = section.advance().map_err(|e| e.at(offset))?;
+        let (len, bytes) = bytes.advance_u32().map_err(|e| e.at(offset))?;
+        if length(len) > limits.max_section_size {
+            return Err(Error::LimitExceeded("section size", limits.max_section_size).at(offset));
+        }
+        let (bytes, rest) = bytes.advance_slice(length(len)).map_err(|e| e.at(offset))?;
+        let offset = offset + offset_in(section, bytes);
+
+        Ok((Self::from_contents(id, bytes, offset, limits)?, rest))
+    }
 
+    /// Decodes the contents of a section with `id`, at `offset` in the module binary.
+    pub(crate) fn from_contents(
+        id: u8,
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
         let section = match id {
             0 => {
-                let custom = CustomSection::from_bytes(bytes)?;
+                let custom = CustomSection::from_bytes(bytes)
+                    .map_err(|e| e.within(ErrorContext::Section("custom"), offset))?;
                 if custom.name() == "name" {
-                    Self::Name(NameSection::from_bytes(bytes)?)
+                    Self::Name(NameSection::from_bytes(bytes, offset)?)
                 } else {
                     Self::Custom(custom)
                 }
             }
-            1 => Self::Type(TypeSection::from_bytes(bytes)?),
-            2 => Self::Import(ImportSection::from_bytes(bytes)?),
-            3 => Self::Function(FunctionSection::from_bytes(bytes)?),
-            4 => Self::Table(TableSection::from_bytes(bytes)?),
-            5 => Self::Memory(MemorySection::from_bytes(bytes)?),
-            6 => Self::Global(GlobalSection::from_bytes(bytes)?),
-            7 => Self::Export(ExportSection::from_bytes(bytes)?),
-            8 => Self::Start(StartSection::from_bytes(bytes)?),
-            9 => Self::Element(ElementSection::from_bytes(bytes)?),
-            10 => Self::Code(CodeSection::from_bytes(bytes)?),
-            11 => Self::Data(DataSection::from_bytes(bytes)?),
-            12 => Self::DataCount(DataCountSection::from_bytes(bytes)?),
-            x => return Err(Error::SectionID(x)),
+            1 => Self::Type(TypeSection::from_bytes(bytes, offset)?),
+            2 => Self::Import(ImportSection::from_bytes(bytes, offset)?),
+            3 => Self::Function(FunctionSection::from_bytes(bytes, offset, limits)?),
+            4 => Self::Table(TableSection::from_bytes(bytes, offset)?),
+            5 => Self::Memory(MemorySection::from_bytes(bytes, offset)?),
+            6 => Self::Global(GlobalSection::from_bytes(bytes, offset, limits)?),
+            7 => Self::Export(ExportSection::from_bytes(bytes, offset)?),
+            8 => Self::Start(
+                StartSection::from_bytes(bytes)
+                    .map_err(|e| e.within(ErrorContext::Section("start"), offset))?,
+            ),
+            9 => Self::Element(ElementSection::from_bytes(bytes, offset, limits)?),
+            10 => Self::Code(CodeSection::from_bytes(bytes, offset, limits)?),
+            11 => Self::Data(DataSection::from_bytes(bytes, offset, limits)?),
+            12 => Self::DataCount(
+                DataCountSection::from_bytes(bytes)
+                    .map_err(|e| e.within(ErrorContext::Section("data count"), offset))?,
+            ),
+            13 => Self::Tag(TagSection::from_bytes(bytes, offset)?),
+            x => return Err(Error::SectionID(x).at(offset)),
         };
 
-        Ok((section, rest))
+        Ok(section)
+    }
+
+    /// Returns the kind of a known section, or `None` for custom sections.
+    fn kind(self) -> Option<SectionKind> {
+        let kind = match self {
+            Self::Custom(..) | Self::Name(..) => return None,
+            Self::Type(..) => SectionKind::Type,
+            Self::Import(..) => SectionKind::Import,
+            Self::Function(..) => SectionKind::Function,
+            Self::Table(..) => SectionKind::Table,
+            Self::Memory(..) => SectionKind::Memory,
+            Self::Tag(..) => SectionKind::Tag,
+            Self::Global(..) => SectionKind::Global,
+            Self::Export(..) => SectionKind::Export,
+            Self::Start(..) => SectionKind::Start,
+            Self::Element(..) => SectionKind::Element,
+            Self::Code(..) => SectionKind::Code,
+            Self::Data(..) => SectionKind::Data,
+            Self::DataCount(..) => SectionKind::DataCount,
+        };
+        Some(kind)
     }
 
     /// Returns the ID of the section.
@@ -404,6 +691,202 @@ impl<'bytes> Section<'bytes> {
             Self::Code(..) => 10,
             Self::Data(..) => 11,
             Self::DataCount(..) => 12,
+            Self::Tag(..) => 13,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::quickcheck;
+
+    use super::{Module, ParseLimits};
+    use crate::{instructions::Instruction, wasm_types::ValueType, Error, ErrorContext, WriteExt};
+
+    /// A module with a padded type section size and locals split into two groups.
+    const PADDED: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x84, 0x80, 0x80, 0x80, 0x00, 0x01, 0x60, 0x00, 0x00, // type section
+        0x03, 0x03, 0x02, 0x00, 0x00, // function section
+        0x0a, 0x0d, 0x02, // code section
+        0x06, 0x02, 0x01, 0x7f, 0x01, 0x7f, 0x0b, // function 0
+        0x04, 0x01, 0x01, 0x7e, 0x0b, // function 1
+    ];
+
+    fn write(module: &crate::synth::SynthModule) -> Vec<u8> {
+        let mut buf = Vec::new();
+        module.write_into(&mut buf).expect("write_into fail");
+        buf
+    }
+
+    #[test]
+    fn test_into_synth_preserving() {
+        let module = Module::from_binary(PADDED).expect("cannot parse wasm");
+        assert_ne!(write(&module.clone().into_synth().unwrap()), PADDED);
+
+        let mut synth = module.into_synth_preserving().unwrap();
+        assert_eq!(write(&synth), PADDED);
+
+        synth.code_section.as_mut().unwrap().codes[1]
+            .func_expr
+            .0
+            .insert(0, Instruction::Nop);
+        let buf = write(&synth);
+        // the type section and function 0 keep their original bytes
+        assert_eq!(buf[..23], PADDED[..23]);
+        assert_eq!(
+            buf[23..],
+            [
+                0x0a, 0x0e, 0x02, 0x06, 0x02, 0x01, 0x7f, 0x01, 0x7f, 0x0b, 0x05, 0x01, 0x01, 0x7e,
+                0x01, 0x0b
+            ]
+        );
+        wasmparser::validate(&buf).expect("wasmparser validation fail");
+
+        // function 1 is encoded like function 0 now, but does not take its original bytes
+        let code = &mut synth.code_section.as_mut().unwrap().codes[1];
+        code.locals = vec![ValueType::I32; 2];
+        code.func_expr.0.clear();
+        let buf = write(&synth);
+        assert_eq!(buf[23..33], PADDED[23..33]);
+        assert_eq!(buf[33..], [0x04, 0x01, 0x02, 0x7f, 0x0b]);
+
+        let owned = Module::from_binary(PADDED).unwrap().into_owned();
+        assert_eq!(write(&owned.into_synth_preserving().unwrap()), PADDED);
+    }
+
+    #[test]
+    fn test_error_location() {
+        let mut wasm = PADDED.to_vec();
+        // replaces the `end` of function 1
+        wasm[37] = 0xff;
+        let module = Module::from_binary(&wasm).expect("cannot parse wasm");
+        assert_eq!(module.section_ranges()[2], 23..38);
+
+        let Err(err) = module.into_synth() else {
+            panic!("invalid function body is parsed");
+        };
+        assert_eq!(
+            err.to_string(),
+            "cannot parse code section > function 1 > instruction at offset 0x25"
+        );
+        let Error::Parse { offset, path, .. } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(*offset, 37);
+        assert_eq!(
+            path,
+            &[
+                ErrorContext::Section("code"),
+                ErrorContext::Function(1),
+                ErrorContext::Instruction
+            ]
+        );
+        assert!(matches!(err.without_location(), Error::Opcode(0xff)));
+
+        // the first invalid function is reported even if bodies are decoded concurrently
+        wasm[32] = 0xfe;
+        let Err(err) = Module::from_binary(&wasm).unwrap().into_synth() else {
+            panic!("invalid function body is parsed");
+        };
+        assert_eq!(
+            err.to_string(),
+            "cannot parse code section > function 0 > instruction at offset 0x20"
+        );
+    }
+
+    /// Returns a module with a single function of `body`, including its locals.
+    fn module_with_body(body: &[u8]) -> Vec<u8> {
+        let mut code = Vec::new();
+        code.write_u32(1).unwrap();
+        code.write_u32(body.len().try_into().unwrap()).unwrap();
+        code.extend_from_slice(body);
+
+        let mut wasm = PADDED[..14].to_vec();
+        wasm.extend_from_slice(&[0x01, 0x60, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00, 0x0a]);
+        wasm.write_u32(code.len().try_into().unwrap()).unwrap();
+        wasm.extend_from_slice(&code);
+        wasm
+    }
+
+    fn into_synth_err(wasm: &[u8], limits: ParseLimits) -> Error {
+        match Module::from_binary_with_limits(wasm, limits).and_then(Module::into_synth) {
+            Ok(_) => panic!("module is parsed"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        // a function body larger than the code section
+        let mut wasm = module_with_body(&[0x00, 0x0b]);
+        let len = wasm.len();
+        wasm[len - 3] = 0x7f;
+        assert!(matches!(
+            into_synth_err(&wasm, ParseLimits::default()).without_location(),
+            Error::UnexpectedEof(..)
+        ));
+
+        // a name subsection larger than the name section
+        let mut wasm = PADDED[..8].to_vec();
+        wasm.extend_from_slice(&[0x00, 0x07, 0x04, b'n', b'a', b'm', b'e', 0x01, 0x7f]);
+        assert!(matches!(
+            into_synth_err(&wasm, ParseLimits::default()).without_location(),
+            Error::UnexpectedEof(..)
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        // u32::MAX locals of i32
+        let wasm = module_with_body(&[0x01, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x7f, 0x0b]);
+        assert!(matches!(
+            into_synth_err(&wasm, ParseLimits::default()).without_location(),
+            Error::LimitExceeded("number of locals", 50_000)
+        ));
+
+        let mut body = vec![0x00];
+        for _ in 0..2000 {
+            body.extend_from_slice(&[0x02, 0x40]);
+        }
+        body.resize(body.len() + 2001, 0x0b);
+        let wasm = module_with_body(&body);
+        assert!(matches!(
+            into_synth_err(&wasm, ParseLimits::default()).without_location(),
+            Error::LimitExceeded("nesting depth", 1024)
+        ));
+        Module::from_binary_with_limits(&wasm, ParseLimits::new().max_nesting_depth(2000))
+            .and_then(Module::into_synth)
+            .expect("cannot parse nested blocks");
+
+        let limits = ParseLimits::new().max_functions(1);
+        assert!(matches!(
+            into_synth_err(PADDED, limits).without_location(),
+            Error::LimitExceeded("number of functions", 1)
+        ));
+        let limits = ParseLimits::new().max_section_size(8);
+        assert!(matches!(
+            into_synth_err(PADDED, limits).without_location(),
+            Error::LimitExceeded("section size", 8)
+        ));
+    }
+
+    quickcheck! {
+        fn parse_arbitrary_bytes(bytes: Vec<u8>) -> bool {
+            let mut wasm = PADDED[..8].to_vec();
+            wasm.extend(bytes);
+            // must not panic
+            let _ = Module::from_binary(&wasm).and_then(Module::into_synth);
+            true
+        }
+
+        fn parse_mutated_module(mutations: Vec<(usize, u8)>) -> bool {
+            let mut wasm = PADDED.to_vec();
+            for (offset, byte) in mutations {
+                wasm[8 + offset % (PADDED.len() - 8)] = byte;
+            }
+            let _ = Module::from_binary(&wasm).and_then(Module::into_synth);
+            true
         }
     }
 }
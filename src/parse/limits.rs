@@ -0,0 +1,58 @@
+/// Resource limits of [`Module::from_binary_with_limits`](super::Module::from_binary_with_limits).
+///
+/// Parsing fails with [`Error::LimitExceeded`](crate::Error::LimitExceeded) if a module exceeds
+/// any of the limits, so that untrusted binaries cannot make the parser allocate unboundedly.
+/// Function bodies and constant expressions are checked when they are read, e.g. by
+/// [`Module::into_synth`](super::Module::into_synth).
+#[derive(Clone, Copy, Debug)]
+pub struct ParseLimits {
+    pub(crate) max_section_size: usize,
+    pub(crate) max_functions: usize,
+    pub(crate) max_locals: usize,
+    pub(crate) max_nesting_depth: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_section_size: 256 << 20,
+            max_functions: 1_000_000,
+            max_locals: 50_000,
+            max_nesting_depth: 1024,
+        }
+    }
+}
+
+impl ParseLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum size of a section in bytes. Defaults to 256 MiB.
+    pub fn max_section_size(mut self, max: usize) -> Self {
+        self.max_section_size = max;
+        self
+    }
+
+    /// Sets the maximum number of functions in the function and code sections. Defaults to
+    /// 1,000,000.
+    pub fn max_functions(mut self, max: usize) -> Self {
+        self.max_functions = max;
+        self
+    }
+
+    /// Sets the maximum number of locals of a function, excluding its parameters. Defaults to
+    /// 50,000.
+    pub fn max_locals(mut self, max: usize) -> Self {
+        self.max_locals = max;
+        self
+    }
+
+    /// Sets the maximum nesting depth of blocks in an expression. Defaults to 1024.
+    ///
+    /// Deeply nested instructions may overflow the stack when they are visited or dropped.
+    pub fn max_nesting_depth(mut self, max: usize) -> Self {
+        self.max_nesting_depth = max;
+        self
+    }
+}
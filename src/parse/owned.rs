@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use crate::{synth::SynthModule, Error};
+
+use super::{Module, ParseLimits};
+
+/// A parsed module owning its binary, which can be stored and sent across threads.
+///
+/// The binary is shared by clones. Sections are decoded lazily through [`OwnedModule::module`],
+/// which borrows the binary like [`Module::from_binary`] does.
+#[derive(Clone, Debug)]
+pub struct OwnedModule {
+    binary: Arc<[u8]>,
+    limits: ParseLimits,
+}
+
+impl OwnedModule {
+    /// Parses a module with the default [`ParseLimits`].
+    pub fn from_binary(binary: impl Into<Arc<[u8]>>) -> Result<Self, Error> {
+        Self::from_binary_with_limits(binary, ParseLimits::default())
+    }
+
+    /// Parses a module, failing if it exceeds `limits`.
+    pub fn from_binary_with_limits(
+        binary: impl Into<Arc<[u8]>>,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        let binary = binary.into();
+        Module::from_binary_with_limits(&binary, limits)?;
+        Ok(Self::from_parts(binary, limits))
+    }
+
+    /// Wraps a binary already parsed with `limits`.
+    pub(crate) fn from_parts(binary: Arc<[u8]>, limits: ParseLimits) -> Self {
+        Self { binary, limits }
+    }
+
+    /// Returns the module binary.
+    pub fn binary(&self) -> &Arc<[u8]> {
+        &self.binary
+    }
+
+    /// Returns the module borrowing the binary. Section headers are decoded again on every call.
+    pub fn module(&self) -> Module<'_> {
+        Module::from_binary_with_limits(&self.binary, self.limits)
+            .expect("binary of an owned module has been parsed")
+    }
+
+    /// Converts into a [`SynthModule`] which remembers the original bytes of this module, like
+    /// [`Module::into_synth_preserving`] does. The binary is shared instead of being copied.
+    pub fn into_synth_preserving(&self) -> Result<SynthModule, Error> {
+        self.module()
+            .into_synth_preserving_with(Arc::clone(&self.binary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::OwnedModule;
+    use crate::parse::Module;
+
+    #[test]
+    fn test_owned_module() {
+        let owned = {
+            let wasm = wat::parse_str(r#"(module (func (export "f") (result i32) i32.const 1))"#)
+                .expect("cannot parse wat");
+            Module::from_binary(&wasm)
+                .expect("cannot parse wasm")
+                .into_owned()
+        };
+
+        let cloned = owned.clone();
+        let synth = thread::spawn(move || cloned.module().into_synth())
+            .join()
+            .unwrap()
+            .expect("cannot convert into synth");
+        assert_eq!(synth.code_section.unwrap().codes.len(), 1);
+        assert_eq!(owned.module().sections().len(), 4);
+
+        assert!(OwnedModule::from_binary(&owned.binary()[..10]).is_err());
+    }
+}
@@ -11,9 +11,10 @@ mod memory;
 mod name;
 mod start;
 mod table;
+mod tag;
 mod r#type;
 
 pub use {
     code::*, custom::*, data::*, data_count::*, element::*, export::*, function::*, global::*,
-    import::*, memory::*, name::*, r#type::*, start::*, table::*,
+    import::*, memory::*, name::*, r#type::*, start::*, table::*, tag::*,
 };
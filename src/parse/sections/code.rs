@@ -2,32 +2,118 @@ use std::fmt::Debug;
 
 use crate::{
     instructions::Expression,
+    length, offset_in,
+    parse::ParseLimits,
+    section_entries,
     synth::sections::{SynthCode, SynthCodeSection},
     wasm_types::ValueType,
-    Bytes, Error,
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct CodeSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
+    limits: ParseLimits,
 }
 
 impl<'bytes> CodeSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            bytes,
+            offset,
+            limits,
+        })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
+    /// Decodes function bodies, concurrently with the `parallel` feature.
     pub(crate) fn into_synth(self) -> Result<SynthCodeSection, Error> {
-        Ok(SynthCodeSection {
-            codes: self
-                .codes()?
-                .map(|x| x.map(Code::into_synth))
-                .collect::<Result<Vec<_>, Error>>()?,
-        })
+        let raw_codes = self.raw_codes()?;
+        let decode = |(index, entry): (usize, &&'bytes [u8])| {
+            let offset = offset_in(self.bytes, entry);
+            Code::from_bytes(entry, self.offset + offset, false, self.limits)
+                .map(|(code, _)| code.into_synth())
+                .map_err(|e| {
+                    e.within(ErrorContext::Function(index as u32), offset)
+                        .within(ErrorContext::Section("code"), self.offset)
+                })
+        };
+
+        #[cfg(all(feature = "parallel", not(feature = "bytes_trace")))]
+        let codes = {
+            use rayon::prelude::*;
+            // every body is decoded so that the first error is reported, as without the feature
+            raw_codes
+                .par_iter()
+                .enumerate()
+                .map(decode)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+        #[cfg(not(all(feature = "parallel", not(feature = "bytes_trace"))))]
+        let codes = raw_codes
+            .iter()
+            .enumerate()
+            .map(decode)
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(SynthCodeSection { codes })
+    }
+
+    /// Returns the raw bytes of each function body, including its size.
+    pub(crate) fn raw_codes(&self) -> Result<Vec<&'bytes [u8]>, Error> {
+        section_entries(
+            "code",
+            self.bytes,
+            self.offset,
+            ErrorContext::Function,
+            |entry: &'bytes [u8]| {
+                let (size, rest) = entry.advance_u32()?;
+                let (_, rest) = rest.advance_slice(length(size))?;
+                Ok((&entry[..entry.len() - rest.len()], rest))
+            },
+        )?
+        .limit("number of functions", self.limits.max_functions)?
+        .collect()
     }
 
     pub fn codes(&self) -> Result<impl Iterator<Item = Result<Code, Error>> + '_, Error> {
-        self.bytes.advance_vector(Code::from_bytes)
+        self.read_codes(false)
+    }
+
+    /// Returns function bodies like [`CodeSection::codes`], also recording offsets of their
+    /// instructions. See [`Code::instruction_offsets`].
+    pub fn codes_with_instruction_offsets(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Code, Error>> + '_, Error> {
+        self.read_codes(true)
+    }
+
+    fn read_codes(
+        &self,
+        instruction_offsets: bool,
+    ) -> Result<impl Iterator<Item = Result<Code, Error>> + '_, Error> {
+        section_entries(
+            "code",
+            self.bytes,
+            self.offset,
+            ErrorContext::Function,
+            move |entry| {
+                let offset = self.offset + offset_in(self.bytes, entry);
+                Code::from_bytes(entry, offset, instruction_offsets, self.limits)
+            },
+        )?
+        .limit("number of functions", self.limits.max_functions)
     }
 }
 
@@ -39,30 +125,86 @@ impl<'bytes> Debug for CodeSection<'bytes> {
 
 #[derive(Clone, Debug)]
 pub struct Code {
+    offset: usize,
+    /// Offset of the `end` of the function body in the module binary.
+    end_offset: usize,
     locals: Vec<Local>,
     func_expr: Expression,
+    instruction_offsets: Option<Vec<usize>>,
 }
 
 impl Code {
-    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (size, bytes) = bytes.advance_u32()?;
-        let size_u = usize::try_from(size).expect("code size overflow");
-        let code_bytes = &bytes[..size_u];
+    /// Reads a function body at `offset` in the module binary.
+    pub(crate) fn from_bytes(
+        entry: &[u8],
+        offset: usize,
+        instruction_offsets: bool,
+        limits: ParseLimits,
+    ) -> Result<(Self, &[u8]), Error> {
+        let (size, bytes) = entry.advance_u32()?;
+        let (code_bytes, rest) = bytes.advance_slice(length(size))?;
         log::trace!("code size = {size}, reading locals");
         let mut localit = code_bytes.advance_vector(Local::from_bytes)?;
         let mut locals = Vec::new();
+        // locals are expanded by `into_synth`, so the total count is limited rather than groups
+        let mut local_count = 0usize;
         for local in &mut localit {
-            locals.push(local?);
+            let local = local?;
+            local_count = local_count.saturating_add(length(local.n));
+            if local_count > limits.max_locals {
+                return Err(Error::LimitExceeded("number of locals", limits.max_locals));
+            }
+            locals.push(local);
         }
         let code_bytes = localit.finalize();
         log::trace!("reading func_expr");
-        let (func_expr, code_bytes) = Expression::from_bytes(code_bytes)?;
+        let expr_offset = offset_in(entry, code_bytes);
+        let (func_expr, offsets, code_bytes) = if instruction_offsets {
+            let (func_expr, offsets, code_bytes) =
+                Expression::from_bytes_with_offsets(code_bytes, limits.max_nesting_depth)
+                    .map_err(|e| e.at(expr_offset))?;
+            let offsets = offsets.into_iter().map(|x| offset + expr_offset + x);
+            (func_expr, Some(offsets.collect()), code_bytes)
+        } else {
+            let (func_expr, code_bytes) =
+                Expression::from_bytes(code_bytes, limits.max_nesting_depth)
+                    .map_err(|e| e.at(expr_offset))?;
+            (func_expr, None, code_bytes)
+        };
 
         if !code_bytes.is_empty() {
             return Err(Error::TrailingBytes);
         }
 
-        Ok((Self { locals, func_expr }, &bytes[size_u..]))
+        Ok((
+            Self {
+                offset,
+                end_offset: offset + offset_in(entry, rest) - 1,
+                locals,
+                func_expr,
+                instruction_offsets: offsets,
+            },
+            rest,
+        ))
+    }
+
+    /// Returns the offset of the function body in the module binary, starting at its size.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the offset of the `end` of the function body in the module binary.
+    pub fn end_offset(&self) -> usize {
+        self.end_offset
+    }
+
+    /// Returns offsets of instructions in the module binary, in the order of a pre-order walk of
+    /// the function body, i.e. the order [`Error::InvalidFunction`] counts instructions in.
+    ///
+    /// Offsets are only recorded by [`CodeSection::codes_with_instruction_offsets`] and
+    /// [`FunctionBody::read_with_instruction_offsets`](crate::parse::FunctionBody::read_with_instruction_offsets).
+    pub fn instruction_offsets(&self) -> Option<&[usize]> {
+        self.instruction_offsets.as_deref()
     }
 
     pub(crate) fn into_synth(self) -> SynthCode {
@@ -88,8 +230,7 @@ pub struct Local {
 impl Local {
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
         let (n, bytes) = bytes.advance_u32()?;
-        let (&[t], bytes) = bytes.advance()?;
-        let t = ValueType::from_byte(t)?;
+        let (t, bytes) = ValueType::from_bytes(bytes)?;
         Ok((Self { n, t }, bytes))
     }
 }
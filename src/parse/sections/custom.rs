@@ -1,6 +1,9 @@
 use std::fmt::Debug;
 
-use crate::{synth::sections::SynthCustomSection, Bytes, Error};
+use crate::{
+    synth::sections::{CustomPlacement, SynthCustomSection},
+    Bytes, Error,
+};
 
 #[derive(Clone, Copy)]
 pub struct CustomSection<'bytes> {
@@ -22,10 +25,11 @@ impl<'bytes> CustomSection<'bytes> {
         self.bytes
     }
 
-    pub(crate) fn into_synth(self) -> SynthCustomSection {
+    pub(crate) fn into_synth(self, placement: CustomPlacement) -> SynthCustomSection {
         SynthCustomSection {
             name: self.name.to_owned(),
             bytes: self.bytes.to_owned(),
+            placement,
         }
     }
 }
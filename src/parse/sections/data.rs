@@ -2,18 +2,36 @@ use std::fmt::Debug;
 
 use crate::{
     instructions::Expression,
+    length, offset_in,
+    parse::ParseLimits,
+    section_entries,
     synth::sections::{SynthData, SynthDataSection},
-    Bytes, Error,
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct DataSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
+    limits: ParseLimits,
 }
 
 impl<'bytes> DataSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            bytes,
+            offset,
+            limits,
+        })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthDataSection, Error> {
@@ -28,7 +46,9 @@ impl<'bytes> DataSection<'bytes> {
     pub fn all_data(
         &self,
     ) -> Result<impl Iterator<Item = Result<Data<'bytes>, Error>> + '_, Error> {
-        self.bytes.advance_vector(Data::from_bytes)
+        section_entries("data", self.bytes, self.offset, ErrorContext::Entry, |x| {
+            Data::from_bytes(x, self.limits.max_nesting_depth)
+        })
     }
 }
 
@@ -49,14 +69,17 @@ pub enum Data<'bytes> {
 }
 
 impl<'bytes> Data<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<(Self, &'bytes [u8]), Error> {
-        let (tag, bytes) = bytes.advance_u32()?;
+    pub(crate) fn from_bytes(
+        entry: &'bytes [u8],
+        max_depth: usize,
+    ) -> Result<(Self, &'bytes [u8]), Error> {
+        let (tag, bytes) = entry.advance_u32()?;
         match tag {
             0 => {
-                let (expr, bytes) = Expression::from_bytes(bytes)?;
+                let (expr, bytes) = Expression::from_bytes(bytes, max_depth)
+                    .map_err(|e| e.at(offset_in(entry, bytes)))?;
                 let (len, bytes) = bytes.advance_u32()?;
-                let (init, bytes) =
-                    bytes.advance_slice(len.try_into().expect("vector overflow"))?;
+                let (init, bytes) = bytes.advance_slice(length(len))?;
                 Ok((
                     Self::Active {
                         init,
@@ -68,16 +91,15 @@ impl<'bytes> Data<'bytes> {
             }
             1 => {
                 let (len, bytes) = bytes.advance_u32()?;
-                let (init, bytes) =
-                    bytes.advance_slice(len.try_into().expect("vector overflow"))?;
+                let (init, bytes) = bytes.advance_slice(length(len))?;
                 Ok((Self::Passive(init), bytes))
             }
             2 => {
                 let (memory_index, bytes) = bytes.advance_u32()?;
-                let (expr, bytes) = Expression::from_bytes(bytes)?;
+                let (expr, bytes) = Expression::from_bytes(bytes, max_depth)
+                    .map_err(|e| e.at(offset_in(entry, bytes)))?;
                 let (len, bytes) = bytes.advance_u32()?;
-                let (init, bytes) =
-                    bytes.advance_slice(len.try_into().expect("vector overflow"))?;
+                let (init, bytes) = bytes.advance_slice(length(len))?;
                 Ok((
                     Self::Active {
                         init,
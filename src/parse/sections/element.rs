@@ -2,21 +2,39 @@ use std::fmt::Debug;
 
 use crate::{
     instructions::Expression,
+    offset_in,
+    parse::ParseLimits,
+    section_entries,
     synth::sections::{
         SynthElem, SynthElemInit, SynthElemKind, SynthElemMode, SynthElementSection,
     },
     wasm_types::ReferenceType,
-    Bytes, Error,
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct ElementSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
+    limits: ParseLimits,
 }
 
 impl<'bytes> ElementSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            bytes,
+            offset,
+            limits,
+        })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthElementSection, Error> {
@@ -29,7 +47,13 @@ impl<'bytes> ElementSection<'bytes> {
     }
 
     pub fn elements(&self) -> Result<impl Iterator<Item = Result<Elem, Error>> + '_, Error> {
-        self.bytes.advance_vector(Elem::from_bytes)
+        section_entries(
+            "element",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            |x| Elem::from_bytes(x, self.limits.max_nesting_depth),
+        )
     }
 }
 
@@ -47,15 +71,16 @@ pub struct Elem {
 }
 
 impl Elem {
-    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (discriminator, bytes) = bytes.advance_u32()?;
+    pub(crate) fn from_bytes(entry: &[u8], max_depth: usize) -> Result<(Self, &[u8]), Error> {
+        let (discriminator, bytes) = entry.advance_u32()?;
         let (mode, bytes) = if discriminator & 0b001 == 0 {
             let (table, bytes) = if discriminator & 0b010 == 0 {
                 (0, bytes)
             } else {
                 bytes.advance_u32()?
             };
-            let (offset, bytes) = Expression::from_bytes(bytes)?;
+            let (offset, bytes) = Expression::from_bytes(bytes, max_depth)
+                .map_err(|e| e.at(offset_in(entry, bytes)))?;
             (ElemMode::Active { table, offset }, bytes)
         } else {
             let mode = if discriminator & 0b010 == 0 {
@@ -67,7 +92,8 @@ impl Elem {
         };
 
         let (kind, init, bytes) = if discriminator & 0b100 == 0 {
-            let (kind, bytes) = if discriminator & 0b010 == 0 {
+            // the elemkind is omitted for active segments of table 0
+            let (kind, bytes) = if discriminator & 0b011 == 0 {
                 (ElemKind::FuncRef, bytes)
             } else {
                 ElemKind::from_bytes(bytes)?
@@ -81,11 +107,16 @@ impl Elem {
             let bytes = it.finalize();
             (kind, ElemInit::FuncIndices(init), bytes)
         } else {
-            let (&[ty], bytes) = bytes.advance()?;
-            let ty = ReferenceType::from_byte(ty)?;
+            let (ty, bytes) = if discriminator & 0b011 == 0 {
+                (ReferenceType::FuncRef, bytes)
+            } else {
+                ReferenceType::from_bytes(bytes)?
+            };
 
             let mut init = Vec::new();
-            let mut it = bytes.advance_vector(Expression::from_bytes)?;
+            let mut it = bytes.advance_vector(|bytes| {
+                Expression::from_bytes(bytes, max_depth).map_err(|e| e.at(offset_in(entry, bytes)))
+            })?;
             for x in &mut it {
                 init.push(x?);
             }
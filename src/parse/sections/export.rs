@@ -1,18 +1,25 @@
 use std::fmt::Debug;
 
 use crate::{
+    section_entries,
     synth::sections::{SynthExport, SynthExportDescription, SynthExportSection},
-    Bytes, Error,
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct ExportSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> ExportSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthExportSection, Error> {
@@ -27,7 +34,13 @@ impl<'bytes> ExportSection<'bytes> {
     pub fn exports(
         &self,
     ) -> Result<impl Iterator<Item = Result<Export<'bytes>, Error>> + '_, Error> {
-        self.bytes.advance_vector(Export::from_bytes)
+        section_entries(
+            "export",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            Export::from_bytes,
+        )
     }
 }
 
@@ -53,6 +66,7 @@ impl<'bytes> Export<'bytes> {
             0x01 => ExportDescription::Table(idx),
             0x02 => ExportDescription::Mem(idx),
             0x03 => ExportDescription::Global(idx),
+            0x04 => ExportDescription::Tag(idx),
             other => return Err(Error::ExportDescription(other)),
         };
 
@@ -67,6 +81,7 @@ impl<'bytes> Export<'bytes> {
                 ExportDescription::Table(x) => SynthExportDescription::Table(x),
                 ExportDescription::Mem(x) => SynthExportDescription::Mem(x),
                 ExportDescription::Global(x) => SynthExportDescription::Global(x),
+                ExportDescription::Tag(x) => SynthExportDescription::Tag(x),
             },
         }
     }
@@ -78,4 +93,5 @@ pub enum ExportDescription {
     Table(u32),
     Mem(u32),
     Global(u32),
+    Tag(u32),
 }
@@ -1,15 +1,33 @@
 use std::fmt::Debug;
 
-use crate::{synth::sections::SynthFunctionSection, Bytes, Error};
+use crate::{
+    parse::ParseLimits, section_entries, synth::sections::SynthFunctionSection, Bytes, Error,
+    ErrorContext,
+};
 
 #[derive(Clone, Copy)]
 pub struct FunctionSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
+    limits: ParseLimits,
 }
 
 impl<'bytes> FunctionSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            bytes,
+            offset,
+            limits,
+        })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthFunctionSection, Error> {
@@ -19,7 +37,14 @@ impl<'bytes> FunctionSection<'bytes> {
     }
 
     pub fn type_indices(&self) -> Result<impl Iterator<Item = Result<u32, Error>> + '_, Error> {
-        self.bytes.advance_vector(<&[u8]>::advance_u32)
+        section_entries(
+            "function",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            <&[u8]>::advance_u32,
+        )?
+        .limit("number of functions", self.limits.max_functions)
     }
 }
 
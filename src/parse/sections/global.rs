@@ -2,19 +2,37 @@ use std::fmt::Debug;
 
 use crate::{
     instructions::Expression,
+    offset_in,
+    parse::ParseLimits,
+    section_entries,
     synth::sections::{SynthGlobal, SynthGlobalSection},
     wasm_types::GlobalType,
-    Bytes, Error,
+    Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct GlobalSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
+    limits: ParseLimits,
 }
 
 impl<'bytes> GlobalSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(
+        bytes: &'bytes [u8],
+        offset: usize,
+        limits: ParseLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            bytes,
+            offset,
+            limits,
+        })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthGlobalSection, Error> {
@@ -27,7 +45,13 @@ impl<'bytes> GlobalSection<'bytes> {
     }
 
     pub fn globals(&self) -> Result<impl Iterator<Item = Result<Global, Error>> + '_, Error> {
-        self.bytes.advance_vector(Global::from_bytes)
+        section_entries(
+            "global",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            |x| Global::from_bytes(x, self.limits.max_nesting_depth),
+        )
     }
 }
 
@@ -43,9 +67,10 @@ pub struct Global {
 }
 
 impl Global {
-    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (ty, bytes) = GlobalType::from_bytes(bytes)?;
-        let (init, bytes) = Expression::from_bytes(bytes)?;
+    pub(crate) fn from_bytes(entry: &[u8], max_depth: usize) -> Result<(Self, &[u8]), Error> {
+        let (ty, bytes) = GlobalType::from_bytes(entry)?;
+        let (init, bytes) =
+            Expression::from_bytes(bytes, max_depth).map_err(|e| e.at(offset_in(entry, bytes)))?;
         Ok((Self { ty, init }, bytes))
     }
 
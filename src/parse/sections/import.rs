@@ -1,19 +1,26 @@
 use std::fmt::Debug;
 
 use crate::{
+    section_entries,
     synth::sections::{SynthImport, SynthImportDescription, SynthImportSection},
-    wasm_types::{GlobalType, MemType, TableType},
-    Bytes, Error,
+    wasm_types::{GlobalType, MemType, TableType, TagType},
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct ImportSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> ImportSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthImportSection, Error> {
@@ -25,8 +32,14 @@ impl<'bytes> ImportSection<'bytes> {
         })
     }
 
-    pub fn imports(&self) -> Result<impl Iterator<Item = Result<Import, Error>> + '_, Error> {
-        self.bytes.advance_vector(Import::from_bytes)
+    pub fn imports(&self) -> Result<impl Iterator<Item = Result<Import<'_>, Error>> + '_, Error> {
+        section_entries(
+            "import",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            Import::from_bytes,
+        )
     }
 }
 
@@ -44,7 +57,7 @@ pub struct Import<'bytes> {
 }
 
 impl<'bytes> Import<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<(Self, &[u8]), Error> {
+    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<(Self, &'bytes [u8]), Error> {
         let (module, bytes) = bytes.advance_name()?;
         let (name, bytes) = bytes.advance_name()?;
         let (description, bytes) = ImportDescription::from_bytes(bytes)?;
@@ -85,6 +98,7 @@ pub enum ImportDescription {
     Table(TableType),
     Memory(MemType),
     Global(GlobalType),
+    Tag(TagType),
 }
 
 impl ImportDescription {
@@ -107,6 +121,10 @@ impl ImportDescription {
                 let (global, bytes) = GlobalType::from_bytes(bytes)?;
                 Ok((Self::Global(global), bytes))
             }
+            0x04 => {
+                let (tag, bytes) = TagType::from_bytes(bytes)?;
+                Ok((Self::Tag(tag), bytes))
+            }
             x => Err(Error::ImportDescriptionTag(x)),
         }
     }
@@ -117,6 +135,7 @@ impl ImportDescription {
             ImportDescription::Table(tt) => SynthImportDescription::Table(tt),
             ImportDescription::Memory(mt) => SynthImportDescription::Memory(mt),
             ImportDescription::Global(gt) => SynthImportDescription::Global(gt),
+            ImportDescription::Tag(tt) => SynthImportDescription::Tag(tt),
         }
     }
 }
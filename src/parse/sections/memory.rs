@@ -1,15 +1,23 @@
 use std::fmt::Debug;
 
-use crate::{synth::sections::SynthMemorySection, wasm_types::MemType, Bytes, Error};
+use crate::{
+    section_entries, synth::sections::SynthMemorySection, wasm_types::MemType, Error, ErrorContext,
+};
 
 #[derive(Clone, Copy)]
 pub struct MemorySection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> MemorySection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthMemorySection, Error> {
@@ -19,7 +27,13 @@ impl<'bytes> MemorySection<'bytes> {
     }
 
     pub fn memories(&self) -> Result<impl Iterator<Item = Result<MemType, Error>> + '_, Error> {
-        self.bytes.advance_vector(MemType::from_bytes)
+        section_entries(
+            "memory",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            MemType::from_bytes,
+        )
     }
 }
 
@@ -1,23 +1,38 @@
 use std::fmt::Debug;
 
 use crate::{
+    length, offset_in,
     synth::sections::{SynthIndirectNameAssoc, SynthNameAssoc, SynthNameSection},
-    Bytes, Error,
+    Bytes, Error, ErrorContext,
 };
 
 #[derive(Clone, Copy)]
 pub struct NameSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> NameSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        let (name, bytes) = bytes.advance_name()?;
+    pub(crate) fn from_bytes(section: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        let (name, bytes) = section.advance_name()?;
         assert_eq!(name, "name");
-        Ok(Self { bytes })
+        Ok(Self {
+            bytes,
+            offset: offset + offset_in(section, bytes),
+        })
+    }
+
+    /// Returns the offset of the name subsections in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthNameSection, Error> {
+        self.read_synth()
+            .map_err(|e| e.within(ErrorContext::Section("name"), self.offset))
+    }
+
+    fn read_synth(self) -> Result<SynthNameSection, Error> {
         trait IteratorExt: Iterator {
             fn extract_element(
                 self,
@@ -143,6 +158,28 @@ impl<'bytes> NameSection<'bytes> {
                     .collect::<Result<Vec<_>, _>>()
             })
             .transpose()?;
+        let field_names = sections
+            .iter()
+            .filter(|x| matches!(x, NameSubsection::FieldNames(_)))
+            .extract_element("field names")?
+            .map(|x| {
+                x.indirect_name_assocs()
+                    .unwrap()
+                    .map(|x| x.map(IndirectNameAssoc::into_synth))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let tag_names = sections
+            .iter()
+            .filter(|x| matches!(x, NameSubsection::TagNames(_)))
+            .extract_element("tag names")?
+            .map(|x| {
+                x.name_assocs()
+                    .unwrap()
+                    .map(|x| x.map(NameAssoc::into_synth))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
 
         Ok(SynthNameSection {
             module_name,
@@ -155,6 +192,8 @@ impl<'bytes> NameSection<'bytes> {
             global_names,
             element_segment_names,
             data_segment_names,
+            field_names,
+            tag_names,
         })
     }
 
@@ -162,7 +201,12 @@ impl<'bytes> NameSection<'bytes> {
         let mut subsections = Vec::new();
         let mut bytes = self.bytes;
         while !bytes.is_empty() {
-            let (s, bytes_) = NameSubsection::from_bytes(bytes)?;
+            let (s, bytes_) = NameSubsection::from_bytes(bytes).map_err(|e| {
+                e.within(
+                    ErrorContext::Section("name"),
+                    self.offset + offset_in(self.bytes, bytes),
+                )
+            })?;
             bytes = bytes_;
             subsections.push(s);
         }
@@ -189,15 +233,16 @@ pub enum NameSubsection<'bytes> {
     GlobalNames(&'bytes [u8]),
     ElementSegmentNames(&'bytes [u8]),
     DataSegmentNames(&'bytes [u8]),
+    /// Names of struct fields of the GC proposal, by type index.
+    FieldNames(&'bytes [u8]),
+    TagNames(&'bytes [u8]),
 }
 
 impl<'bytes> NameSubsection<'bytes> {
     pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<(Self, &'bytes [u8]), Error> {
         let (&[id], bytes) = bytes.advance()?;
         let (size, bytes) = bytes.advance_u32()?;
-        let size = size.try_into().expect("subsection size overflow");
-        let rest = &bytes[size..];
-        let bytes = &bytes[..size];
+        let (bytes, rest) = bytes.advance_slice(length(size))?;
         match id {
             0 => {
                 let (name, _bytes) = bytes.advance_name()?;
@@ -212,6 +257,8 @@ impl<'bytes> NameSubsection<'bytes> {
             7 => Ok((Self::GlobalNames(bytes), rest)),
             8 => Ok((Self::ElementSegmentNames(bytes), rest)),
             9 => Ok((Self::DataSegmentNames(bytes), rest)),
+            10 => Ok((Self::FieldNames(bytes), rest)),
+            11 => Ok((Self::TagNames(bytes), rest)),
             other => Err(Error::NameSectionSubsectionId(other)),
         }
     }
@@ -226,7 +273,8 @@ impl<'bytes> NameSubsection<'bytes> {
             | NameSubsection::MemoryNames(x)
             | NameSubsection::GlobalNames(x)
             | NameSubsection::ElementSegmentNames(x)
-            | NameSubsection::DataSegmentNames(x) => x.advance_vector(NameAssoc::from_bytes),
+            | NameSubsection::DataSegmentNames(x)
+            | NameSubsection::TagNames(x) => x.advance_vector(NameAssoc::from_bytes),
             _ => Err(Error::IncorrectSubsection),
         }
     }
@@ -235,9 +283,9 @@ impl<'bytes> NameSubsection<'bytes> {
         &self,
     ) -> Result<impl Iterator<Item = Result<IndirectNameAssoc<'bytes>, Error>> + '_, Error> {
         match self {
-            NameSubsection::LocalNames(x) | NameSubsection::LabelNames(x) => {
-                x.advance_vector(IndirectNameAssoc::from_bytes)
-            }
+            NameSubsection::LocalNames(x)
+            | NameSubsection::LabelNames(x)
+            | NameSubsection::FieldNames(x) => x.advance_vector(IndirectNameAssoc::from_bytes),
             _ => Err(Error::IncorrectSubsection),
         }
     }
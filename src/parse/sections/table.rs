@@ -1,15 +1,23 @@
 use std::fmt::Debug;
 
-use crate::{synth::sections::SynthTableSection, wasm_types::TableType, Bytes, Error};
+use crate::{
+    section_entries, synth::sections::SynthTableSection, wasm_types::TableType, Error, ErrorContext,
+};
 
 #[derive(Clone, Copy)]
 pub struct TableSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> TableSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthTableSection, Error> {
@@ -19,7 +27,13 @@ impl<'bytes> TableSection<'bytes> {
     }
 
     pub fn tables(&self) -> Result<impl Iterator<Item = Result<TableType, Error>> + '_, Error> {
-        self.bytes.advance_vector(TableType::from_bytes)
+        section_entries(
+            "table",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            TableType::from_bytes,
+        )
     }
 }
 
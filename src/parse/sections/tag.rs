@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+use crate::{
+    section_entries, synth::sections::SynthTagSection, wasm_types::TagType, Error, ErrorContext,
+};
+
+/// The tag section of the exception handling proposal.
+#[derive(Clone, Copy)]
+pub struct TagSection<'bytes> {
+    bytes: &'bytes [u8],
+    offset: usize,
+}
+
+impl<'bytes> TagSection<'bytes> {
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn into_synth(self) -> Result<SynthTagSection, Error> {
+        Ok(SynthTagSection {
+            tags: self.tags()?.collect::<Result<Vec<_>, Error>>()?,
+        })
+    }
+
+    pub fn tags(&self) -> Result<impl Iterator<Item = Result<TagType, Error>> + '_, Error> {
+        section_entries(
+            "tag",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            TagType::from_bytes,
+        )
+    }
+}
+
+impl<'bytes> Debug for TagSection<'bytes> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TagSection").finish()
+    }
+}
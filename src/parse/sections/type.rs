@@ -1,25 +1,50 @@
 use std::fmt::Debug;
 
-use crate::{synth::sections::SynthTypeSection, wasm_types::FuncType, Bytes, Error};
+use crate::{
+    section_entries, synth::sections::SynthTypeSection, wasm_types::RecGroup, Error, ErrorContext,
+};
 
 #[derive(Clone, Copy)]
 pub struct TypeSection<'bytes> {
     bytes: &'bytes [u8],
+    offset: usize,
 }
 
 impl<'bytes> TypeSection<'bytes> {
-    pub(crate) fn from_bytes(bytes: &'bytes [u8]) -> Result<Self, Error> {
-        Ok(Self { bytes })
+    pub(crate) fn from_bytes(bytes: &'bytes [u8], offset: usize) -> Result<Self, Error> {
+        Ok(Self { bytes, offset })
+    }
+
+    /// Returns the offset of the section contents in the module binary.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     pub(crate) fn into_synth(self) -> Result<SynthTypeSection, Error> {
-        Ok(SynthTypeSection {
-            types: self.types()?.collect::<Result<Vec<_>, Error>>()?,
-        })
+        let mut synth = SynthTypeSection::default();
+        for group in self.rec_groups()? {
+            let group = group?;
+            if group.explicit {
+                let start = synth.types.len() as u32;
+                synth
+                    .rec_groups
+                    .push(start..start + group.types.len() as u32);
+            }
+            synth.types.extend(group.types);
+        }
+        Ok(synth)
     }
 
-    pub fn types(&self) -> Result<impl Iterator<Item = Result<FuncType, Error>> + '_, Error> {
-        self.bytes.advance_vector(FuncType::from_bytes)
+    /// Returns the entries of the section, each of which is a recursion group of the GC proposal
+    /// or a lone type definition.
+    pub fn rec_groups(&self) -> Result<impl Iterator<Item = Result<RecGroup, Error>> + '_, Error> {
+        section_entries(
+            "type",
+            self.bytes,
+            self.offset,
+            ErrorContext::Entry,
+            RecGroup::from_bytes,
+        )
     }
 }
 
@@ -0,0 +1,488 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+    ops::Range,
+};
+
+use crate::{length, Bytes, Error, ErrorContext};
+
+use super::{read_header, sections::Code, ParseLimits, Section};
+
+/// An event-style parser reading a module from a [`Read`].
+///
+/// Unlike [`Module::from_binary`](super::Module::from_binary), only the section being decoded is
+/// held in memory. The code section is not buffered as a whole: its function bodies are yielded
+/// one by one as [`FunctionBody`]s, which are skipped without buffering unless they are read.
+/// Headers are read byte by byte, so wrapping unbuffered readers with
+/// [`BufReader`](std::io::BufReader) is recommended.
+///
+/// With the `bytes_trace` feature, offsets are traced relative to the buffered section or function
+/// body being decoded.
+///
+/// The parser should not be used further once it has returned an error.
+pub struct StreamParser<R> {
+    reader: R,
+    limits: ParseLimits,
+    /// Number of bytes consumed from the reader.
+    offset: usize,
+    /// Contents of the current section, or the current function body including its size.
+    buffer: Vec<u8>,
+    state: State,
+}
+
+enum State {
+    Header,
+    Sections,
+    Code {
+        /// Offset of the section contents in the module binary.
+        offset: usize,
+        /// Offset of the end of the section in the module binary.
+        end: usize,
+        remaining: u32,
+        index: u32,
+        /// Offset of the last yielded function body in the module binary, starting at its size.
+        last: usize,
+        /// Number of bytes of the last yielded function body not consumed yet.
+        pending: usize,
+    },
+    End,
+}
+
+/// An event yielded by [`StreamParser::next_event`].
+#[derive(Debug)]
+pub enum StreamEvent<'a, R> {
+    /// A section other than the code section, and its range in the module binary including its
+    /// id and size.
+    Section(Section<'a>, Range<usize>),
+    /// Start of the code section, which is followed by `count` [`StreamEvent::FunctionBody`]s.
+    /// `range` is the range of the whole section in the module binary.
+    CodeSectionStart {
+        count: u32,
+        range: Range<usize>,
+    },
+    FunctionBody(FunctionBody<'a, R>),
+}
+
+/// A function body in the code section, which has not been read yet.
+///
+/// Dropping it without calling [`FunctionBody::read`] skips the body.
+pub struct FunctionBody<'a, R> {
+    parser: &'a mut StreamParser<R>,
+    index: u32,
+    range: Range<usize>,
+}
+
+impl<R: Read> StreamParser<R> {
+    /// Creates a parser with the default [`ParseLimits`].
+    pub fn new(reader: R) -> Self {
+        Self::with_limits(reader, ParseLimits::default())
+    }
+
+    /// Creates a parser failing if the module exceeds `limits`.
+    pub fn with_limits(reader: R, limits: ParseLimits) -> Self {
+        Self {
+            reader,
+            limits,
+            offset: 0,
+            buffer: Vec::new(),
+            state: State::Header,
+        }
+    }
+
+    /// Returns the number of bytes consumed from the reader.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next event, or returns `None` at the end of the module.
+    pub fn next_event(&mut self) -> Result<Option<StreamEvent<'_, R>>, Error> {
+        loop {
+            match self.state {
+                State::Header => {
+                    self.buffer.clear();
+                    // a truncated header is reported by `read_header`
+                    match self.read_into_buffer(8) {
+                        Ok(()) | Err(Error::UnexpectedEof(..)) => (),
+                        Err(e) => return Err(e.at(0)),
+                    }
+                    #[cfg(feature = "bytes_trace")]
+                    {
+                        crate::bytes_trace::initialize(&self.buffer);
+                    }
+                    read_header(&self.buffer)?;
+                    self.state = State::Sections;
+                }
+                State::Sections => return self.read_section(),
+                State::Code {
+                    offset,
+                    end,
+                    remaining,
+                    index,
+                    last,
+                    pending,
+                } => {
+                    let header = self.offset - last;
+                    self.skip(pending).map_err(|e| {
+                        e.at(header)
+                            .within(ErrorContext::Function(index - 1), last - offset)
+                            .within(ErrorContext::Section("code"), offset)
+                    })?;
+                    if remaining > 0 {
+                        return self.read_function_body().map(Some);
+                    }
+                    if self.offset != end {
+                        return Err(Error::TrailingBytes
+                            .at(self.offset - offset)
+                            .within(ErrorContext::Section("code"), offset));
+                    }
+                    self.state = State::Sections;
+                }
+                State::End => return Ok(None),
+            }
+        }
+    }
+
+    fn read_section(&mut self) -> Result<Option<StreamEvent<'_, R>>, Error> {
+        let start = self.offset;
+        let Some(id) = self.read_byte().map_err(|e| e.at(start))? else {
+            self.state = State::End;
+            return Ok(None);
+        };
+        self.buffer.clear();
+        let len = length(self.read_u32().map_err(|e| e.at(start))?);
+        if len > self.limits.max_section_size {
+            return Err(
+                Error::LimitExceeded("section size", self.limits.max_section_size).at(start),
+            );
+        }
+        let offset = self.offset;
+        let end = offset.saturating_add(len);
+
+        if id == 10 {
+            let locate = |e: Error| e.within(ErrorContext::Section("code"), offset);
+            let count = self.read_u32().map_err(locate)?;
+            if length(count) > self.limits.max_functions {
+                return Err(locate(Error::LimitExceeded(
+                    "number of functions",
+                    self.limits.max_functions,
+                )));
+            }
+            if self.offset > end {
+                return Err(locate(Error::UnexpectedEof(self.offset - offset, len)));
+            }
+            self.state = State::Code {
+                offset,
+                end,
+                remaining: count,
+                index: 0,
+                last: offset,
+                pending: 0,
+            };
+            return Ok(Some(StreamEvent::CodeSectionStart {
+                count,
+                range: start..end,
+            }));
+        }
+
+        self.buffer.clear();
+        self.read_into_buffer(len).map_err(|e| e.at(start))?;
+        #[cfg(feature = "bytes_trace")]
+        {
+            crate::bytes_trace::initialize(&self.buffer);
+        }
+        let section = Section::from_contents(id, &self.buffer, offset, self.limits)?;
+        Ok(Some(StreamEvent::Section(section, start..end)))
+    }
+
+    fn read_function_body(&mut self) -> Result<StreamEvent<'_, R>, Error> {
+        let State::Code {
+            offset,
+            end,
+            ref mut remaining,
+            ref mut index,
+            ref mut last,
+            ref mut pending,
+        } = self.state
+        else {
+            unreachable!("reading function body outside the code section");
+        };
+        let start = self.offset;
+        let body_index = *index;
+        let locate = |e: Error| {
+            e.within(ErrorContext::Function(body_index), start - offset)
+                .within(ErrorContext::Section("code"), offset)
+        };
+
+        self.buffer.clear();
+        let size =
+            length(read_u32(&mut self.reader, &mut self.buffer, &mut self.offset).map_err(locate)?);
+        let body_end = self.offset.saturating_add(size);
+        if body_end > end {
+            return Err(locate(Error::UnexpectedEof(
+                size,
+                end.saturating_sub(self.offset),
+            )));
+        }
+        *remaining -= 1;
+        *index += 1;
+        *last = start;
+        *pending = size;
+
+        Ok(StreamEvent::FunctionBody(FunctionBody {
+            parser: self,
+            index: body_index,
+            range: start..body_end,
+        }))
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Error> {
+        read_byte(&mut self.reader, &mut self.offset)
+    }
+
+    /// Reads an u32 value, appending its encoding to the buffer.
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        read_u32(&mut self.reader, &mut self.buffer, &mut self.offset)
+    }
+
+    /// Reads `len` bytes, appending them to the buffer. Errors are located relative to the current
+    /// offset.
+    fn read_into_buffer(&mut self, len: usize) -> Result<(), Error> {
+        let read = (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut self.buffer)
+            .map_err(Error::Io)?;
+        self.offset += read;
+        if read < len {
+            return Err(Error::UnexpectedEof(len, read));
+        }
+        Ok(())
+    }
+
+    /// Discards `len` bytes without buffering them.
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        if len == 0 {
+            return Ok(());
+        }
+        let skipped = io::copy(&mut (&mut self.reader).take(len as u64), &mut io::sink())
+            .map_err(Error::Io)?;
+        self.offset += skipped as usize;
+        if skipped < len as u64 {
+            return Err(Error::UnexpectedEof(len, skipped as usize));
+        }
+        if let State::Code { pending, .. } = &mut self.state {
+            *pending = 0;
+        }
+        Ok(())
+    }
+}
+
+fn read_byte(reader: &mut impl Read, offset: &mut usize) -> Result<Option<u8>, Error> {
+    let mut byte = 0;
+    loop {
+        match reader.read(std::slice::from_mut(&mut byte)) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {
+                *offset += 1;
+                return Ok(Some(byte));
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+}
+
+/// Reads an u32 value, appending its encoding to `buffer`.
+fn read_u32(
+    reader: &mut impl Read,
+    buffer: &mut Vec<u8>,
+    offset: &mut usize,
+) -> Result<u32, Error> {
+    let start = buffer.len();
+    // an u32 is encoded in at most 5 bytes, and longer encodings are rejected by `advance_u32`
+    for _ in 0..5 {
+        let Some(byte) = read_byte(reader, offset)? else {
+            break;
+        };
+        buffer.push(byte);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    #[cfg(feature = "bytes_trace")]
+    {
+        crate::bytes_trace::initialize(&buffer[start..]);
+    }
+    let (n, _) = buffer[start..].advance_u32()?;
+    Ok(n)
+}
+
+impl<'a, R: Read> FunctionBody<'a, R> {
+    /// Returns the index of the function body in the code section.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the range of the function body in the module binary, including its size.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Reads the function body.
+    pub fn read(self) -> Result<Code, Error> {
+        self.read_code(false)
+    }
+
+    /// Reads the function body, also recording offsets of its instructions. See
+    /// [`Code::instruction_offsets`].
+    pub fn read_with_instruction_offsets(self) -> Result<Code, Error> {
+        self.read_code(true)
+    }
+
+    fn read_code(self, instruction_offsets: bool) -> Result<Code, Error> {
+        let State::Code {
+            offset, pending, ..
+        } = self.parser.state
+        else {
+            unreachable!("function body outside the code section");
+        };
+        let locate = |e: Error| {
+            e.within(
+                ErrorContext::Function(self.index),
+                self.range.start - offset,
+            )
+            .within(ErrorContext::Section("code"), offset)
+        };
+
+        let header = self.parser.buffer.len();
+        self.parser
+            .read_into_buffer(pending)
+            .map_err(|e| locate(e.at(header)))?;
+        if let State::Code { pending, .. } = &mut self.parser.state {
+            *pending = 0;
+        }
+        #[cfg(feature = "bytes_trace")]
+        {
+            crate::bytes_trace::initialize(&self.parser.buffer);
+        }
+        let (code, _) = Code::from_bytes(
+            &self.parser.buffer,
+            self.range.start,
+            instruction_offsets,
+            self.parser.limits,
+        )
+        .map_err(locate)?;
+        Ok(code)
+    }
+}
+
+impl<'a, R> Debug for FunctionBody<'a, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionBody")
+            .field("index", &self.index)
+            .field("range", &self.range)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::{StreamEvent, StreamParser};
+    use crate::{parse::ParseLimits, Error};
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+        0x03, 0x03, 0x02, 0x00, 0x00, // function section
+        0x0a, 0x0b, 0x02, // code section
+        0x04, 0x00, 0x41, 0x00, 0x1a, // function 0, invalid
+        0x04, 0x01, 0x01, 0x7f, 0x0b, // function 1
+    ];
+
+    /// A reader returning a byte at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            (&mut self.0).take(1).read(buf)
+        }
+    }
+
+    fn events(wasm: &[u8], limits: ParseLimits) -> Result<Vec<String>, Error> {
+        let mut parser = StreamParser::with_limits(Trickle(wasm), limits);
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_event()? {
+            let event = match event {
+                StreamEvent::Section(section, range) => format!("{} {range:?}", section.id()),
+                StreamEvent::CodeSectionStart { count, range } => format!("code {count} {range:?}"),
+                StreamEvent::FunctionBody(body) if body.index() == 0 => {
+                    format!("skip {:?}", body.range())
+                }
+                StreamEvent::FunctionBody(body) => {
+                    let range = body.range();
+                    let code = body.read()?;
+                    format!("read {range:?} {}", code.into_synth().locals.len())
+                }
+            };
+            events.push(event);
+        }
+        assert_eq!(parser.offset(), wasm.len());
+        Ok(events)
+    }
+
+    #[test]
+    fn test_stream() {
+        let events = events(MODULE, ParseLimits::default()).unwrap();
+        assert_eq!(
+            events,
+            [
+                "1 8..14",
+                "3 14..19",
+                "code 2 19..32",
+                "skip 22..27",
+                "read 27..32 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let err = |wasm: &[u8], limits| events(wasm, limits).unwrap_err().to_string();
+
+        assert_eq!(
+            err(&MODULE[..30], ParseLimits::default()),
+            "cannot parse code section > function 1 at offset 0x1c"
+        );
+        assert_eq!(
+            err(&MODULE[..24], ParseLimits::default()),
+            "cannot parse code section > function 0 at offset 0x17"
+        );
+        assert_eq!(
+            err(&MODULE[..4], ParseLimits::default()),
+            "cannot parse module at offset 0x4"
+        );
+
+        let mut trailing = MODULE.to_vec();
+        trailing[20] += 1;
+        trailing.push(0x00);
+        assert_eq!(
+            err(&trailing, ParseLimits::default()),
+            "cannot parse code section at offset 0x20"
+        );
+
+        assert_eq!(
+            err(MODULE, ParseLimits::new().max_functions(1)),
+            "cannot parse code section at offset 0x15"
+        );
+        assert_eq!(
+            err(MODULE, ParseLimits::new().max_section_size(5)),
+            "cannot parse module at offset 0x13"
+        );
+    }
+}
@@ -0,0 +1,1676 @@
+//! WebAssembly text format printer.
+//!
+//! [`SynthModule`] and [`Module`] implement [`Display`], printing the module in the text format.
+//! Names from the name section are used as identifiers when they are valid and unique, so that
+//! the printed module can be parsed back by a text format parser.
+//!
+//! <https://webassembly.github.io/spec/core/text/index.html>
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    instructions::{BlockType, Catch, Instruction, MemArg},
+    parse::Module,
+    synth::{
+        sections::{
+            CustomPlacement, SectionKind, SynthData, SynthElemInit, SynthElemKind, SynthElemMode,
+            SynthExportDescription, SynthImportDescription, SynthIndirectNameAssoc, SynthNameAssoc,
+        },
+        SynthModule,
+    },
+    wasm_types::{
+        CompositeType, GlobalType, HeapType, Limits, MemType, ReferenceType, SubType, ValueType,
+    },
+};
+
+/// Returns whether `name` can be printed as a text format identifier.
+fn is_id(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&b))
+}
+
+/// Identifiers of an index space, taken from the name section.
+///
+/// Names which are not valid identifiers, or which are already taken by another entity of the
+/// same index space, are skipped.
+#[derive(Default)]
+struct Names<'a>(HashMap<u32, &'a str>);
+
+impl<'a> Names<'a> {
+    fn new(assocs: Option<&'a [SynthNameAssoc]>) -> Self {
+        let mut taken = HashSet::new();
+        let mut map = HashMap::new();
+        for assoc in assocs.unwrap_or_default() {
+            if is_id(&assoc.name)
+                && !map.contains_key(&assoc.idx)
+                && taken.insert(assoc.name.as_str())
+            {
+                map.insert(assoc.idx, assoc.name.as_str());
+            }
+        }
+        Self(map)
+    }
+
+    fn indirect(indassocs: Option<&'a [SynthIndirectNameAssoc]>) -> HashMap<u32, Self> {
+        indassocs
+            .unwrap_or_default()
+            .iter()
+            .map(|x| (x.idx, Self::new(Some(&x.name_map))))
+            .collect()
+    }
+
+    fn get(&self, idx: u32) -> Option<&'a str> {
+        self.0.get(&idx).copied()
+    }
+
+    /// A reference to `idx`, e.g. `$f` or `3`.
+    fn index(&self, idx: u32) -> Index<'a> {
+        Index(self.get(idx), idx)
+    }
+
+    /// A definition of `idx`, e.g. `$f (;3;)` or `(;3;)`.
+    fn binding(&self, idx: u32) -> Binding<'a> {
+        Binding(self.get(idx), idx)
+    }
+}
+
+struct Index<'a>(Option<&'a str>, u32);
+
+impl Display for Index<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, "${name}"),
+            None => write!(f, "{}", self.1),
+        }
+    }
+}
+
+struct Binding<'a>(Option<&'a str>, u32);
+
+impl Display for Binding<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, "${name} (;{};)", self.1),
+            None => write!(f, "(;{};)", self.1),
+        }
+    }
+}
+
+/// A string literal.
+struct Str<'a>(&'a [u8]);
+
+impl Display for Str<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for &b in self.0 {
+            match b {
+                b'"' => write!(f, "\\\"")?,
+                b'\\' => write!(f, "\\\\")?,
+                0x20..=0x7E => write!(f, "{}", b as char)?,
+                _ => write!(f, "\\{b:02x}")?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+fn write_f32(f: &mut Formatter<'_>, x: f32) -> fmt::Result {
+    if x.is_nan() {
+        let sign = if x.is_sign_negative() { "-" } else { "" };
+        write!(f, "{sign}nan:0x{:x}", x.to_bits() & 0x7F_FFFF)
+    } else {
+        write!(f, "{x}")
+    }
+}
+
+fn write_f64(f: &mut Formatter<'_>, x: f64) -> fmt::Result {
+    if x.is_nan() {
+        let sign = if x.is_sign_negative() { "-" } else { "" };
+        write!(f, "{sign}nan:0x{:x}", x.to_bits() & 0xF_FFFF_FFFF_FFFF)
+    } else {
+        write!(f, "{x}")
+    }
+}
+
+fn write_limits(f: &mut Formatter<'_>, limits: &Limits) -> fmt::Result {
+    match *limits {
+        Limits::Unbounded { min } => write!(f, "{min}"),
+        Limits::Bounded { min, max } => write!(f, "{min} {max}"),
+    }
+}
+
+fn write_mem_type(f: &mut Formatter<'_>, mt: &MemType) -> fmt::Result {
+    if mt.is_64() {
+        write!(f, "i64 ")?;
+    }
+    write_limits(f, mt.size())?;
+    if mt.is_shared() {
+        write!(f, " shared")?;
+    }
+    Ok(())
+}
+
+fn write_global_type(f: &mut Formatter<'_>, gt: &GlobalType) -> fmt::Result {
+    if gt.mutable() {
+        write!(f, "(mut {})", gt.ty())
+    } else {
+        write!(f, "{}", gt.ty())
+    }
+}
+
+/// An instruction to print, or a clause or the end of a block.
+enum Action<'i> {
+    Instruction(&'i Instruction),
+    Else,
+    Catch(u32),
+    CatchAll,
+    Delegate(u32),
+    End,
+}
+
+struct Printer<'a> {
+    module: &'a SynthModule,
+    types: Names<'a>,
+    funcs: Names<'a>,
+    tables: Names<'a>,
+    memories: Names<'a>,
+    globals: Names<'a>,
+    elems: Names<'a>,
+    data: Names<'a>,
+    tags: Names<'a>,
+    fields: HashMap<u32, Names<'a>>,
+    locals: HashMap<u32, Names<'a>>,
+    labels: HashMap<u32, Names<'a>>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(module: &'a SynthModule) -> Self {
+        let names = module.name_section.as_ref();
+        Self {
+            module,
+            types: Names::new(names.and_then(|x| x.type_names())),
+            funcs: Names::new(names.and_then(|x| x.function_names())),
+            tables: Names::new(names.and_then(|x| x.table_names())),
+            memories: Names::new(names.and_then(|x| x.memory_names())),
+            globals: Names::new(names.and_then(|x| x.global_names())),
+            elems: Names::new(names.and_then(|x| x.element_segment_names())),
+            data: Names::new(names.and_then(|x| x.data_segment_names())),
+            tags: Names::new(names.and_then(|x| x.tag_names())),
+            fields: Names::indirect(names.and_then(|x| x.field_names())),
+            locals: Names::indirect(names.and_then(|x| x.local_names())),
+            labels: Names::indirect(names.and_then(|x| x.label_names())),
+        }
+    }
+
+    fn sub_type(&self, f: &mut Formatter<'_>, idx: u32, ty: &SubType) -> fmt::Result {
+        let is_sub = !ty.is_final() || !ty.supertypes().is_empty();
+        if is_sub {
+            write!(f, "(sub")?;
+            if ty.is_final() {
+                write!(f, " final")?;
+            }
+            for &supertype in ty.supertypes() {
+                write!(f, " {}", self.types.index(supertype))?;
+            }
+            write!(f, " ")?;
+        }
+        match ty.composite() {
+            CompositeType::Func(x) => {
+                write!(f, "(func")?;
+                write_value_types(f, " ", "param", &x.param().0, 0, None)?;
+                write_value_types(f, " ", "result", &x.result().0, 0, None)?;
+            }
+            CompositeType::Struct(fields) => {
+                write!(f, "(struct")?;
+                let names = self.fields.get(&idx);
+                for (i, field) in fields.iter().enumerate() {
+                    match names.and_then(|x| x.get(i as u32)) {
+                        Some(name) => write!(f, " (field ${name} {field})")?,
+                        None => write!(f, " (field {field})")?,
+                    }
+                }
+            }
+            CompositeType::Array(field) => write!(f, "(array {field}")?,
+        }
+        write!(f, ")")?;
+        if is_sub {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+
+    fn module(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let module = self.module;
+        let no_names = Names::default();
+
+        write!(f, "(module")?;
+        if let Some(name) = module.name_section.as_ref().and_then(|x| x.module_name()) {
+            if is_id(name) {
+                write!(f, " ${name}")?;
+            }
+        }
+
+        let types = module.type_section.as_ref().map(|x| &x.types[..]);
+        let mut rec_groups = module
+            .type_section
+            .as_ref()
+            .map(|x| &x.rec_groups[..])
+            .unwrap_or_default()
+            .iter()
+            .peekable();
+        let mut indent = "\n  ";
+        for (i, ty) in types.unwrap_or_default().iter().enumerate() {
+            let i = i as u32;
+            if rec_groups.peek().is_some_and(|x| x.start == i) {
+                write!(f, "\n  (rec")?;
+                indent = "\n    ";
+            }
+            write!(f, "{indent}(type {} ", self.types.binding(i))?;
+            self.sub_type(f, i, ty)?;
+            write!(f, ")")?;
+            if rec_groups.next_if(|x| x.end == i + 1).is_some() {
+                write!(f, ")")?;
+                indent = "\n  ";
+            }
+        }
+
+        let (mut funcs, mut tables, mut memories, mut globals, mut tags) = (0, 0, 0, 0, 0);
+        if let Some(imsec) = &module.import_section {
+            for import in &imsec.imports {
+                write!(
+                    f,
+                    "\n  (import {} {} (",
+                    Str(import.module.as_bytes()),
+                    Str(import.name.as_bytes())
+                )?;
+                match &import.description {
+                    SynthImportDescription::Type(ty) => {
+                        write!(
+                            f,
+                            "func {} (type {})",
+                            self.funcs.binding(funcs),
+                            self.types.index(*ty)
+                        )?;
+                        funcs += 1;
+                    }
+                    SynthImportDescription::Table(tt) => {
+                        write!(f, "table {} ", self.tables.binding(tables))?;
+                        write_limits(f, tt.limits())?;
+                        write!(f, " {}", tt.element())?;
+                        tables += 1;
+                    }
+                    SynthImportDescription::Memory(mt) => {
+                        write!(f, "memory {} ", self.memories.binding(memories))?;
+                        write_mem_type(f, mt)?;
+                        memories += 1;
+                    }
+                    SynthImportDescription::Global(gt) => {
+                        write!(f, "global {} ", self.globals.binding(globals))?;
+                        write_global_type(f, gt)?;
+                        globals += 1;
+                    }
+                    SynthImportDescription::Tag(tt) => {
+                        write!(
+                            f,
+                            "tag {} (type {})",
+                            self.tags.binding(tags),
+                            self.types.index(tt.type_index())
+                        )?;
+                        tags += 1;
+                    }
+                }
+                write!(f, "))")?;
+            }
+        }
+
+        let type_indices = module
+            .function_section
+            .as_ref()
+            .map(|x| &x.type_indices[..]);
+        let codes = module.code_section.as_ref().map(|x| &x.codes[..]);
+        for (i, &ty) in type_indices.unwrap_or_default().iter().enumerate() {
+            let idx = funcs + i as u32;
+            let locals = self.locals.get(&idx).unwrap_or(&no_names);
+            let labels = self.labels.get(&idx).unwrap_or(&no_names);
+            write!(
+                f,
+                "\n  (func {} (type {})",
+                self.funcs.binding(idx),
+                self.types.index(ty)
+            )?;
+            // if the type is unknown, only the type index is printed
+            let func_type = types.and_then(|x| x.get(ty as usize)?.func_type());
+            if let Some(func_type) = func_type {
+                write_value_types(f, " ", "param", &func_type.param().0, 0, Some(locals))?;
+                write_value_types(f, " ", "result", &func_type.result().0, 0, None)?;
+            }
+            if let Some(code) = codes.and_then(|x| x.get(i)) {
+                let params = func_type.map_or(0, |x| x.param().0.len() as u32);
+                write_value_types(f, "\n    ", "local", &code.locals, params, Some(locals))?;
+                self.instrs(f, &code.func_expr.0, Some(2), locals, labels)?;
+            }
+            write!(f, "\n  )")?;
+        }
+
+        if let Some(tabsec) = &module.table_section {
+            for (i, tt) in tabsec.tables.iter().enumerate() {
+                write!(f, "\n  (table {} ", self.tables.binding(tables + i as u32))?;
+                write_limits(f, tt.limits())?;
+                write!(f, " {})", tt.element())?;
+            }
+        }
+
+        if let Some(memsec) = &module.memory_section {
+            for (i, mt) in memsec.memories.iter().enumerate() {
+                write!(
+                    f,
+                    "\n  (memory {} ",
+                    self.memories.binding(memories + i as u32)
+                )?;
+                write_mem_type(f, mt)?;
+                write!(f, ")")?;
+            }
+        }
+
+        if let Some(tagsec) = &module.tag_section {
+            for (i, tt) in tagsec.tags.iter().enumerate() {
+                write!(
+                    f,
+                    "\n  (tag {} (type {}))",
+                    self.tags.binding(tags + i as u32),
+                    self.types.index(tt.type_index())
+                )?;
+            }
+        }
+
+        if let Some(glsec) = &module.global_section {
+            for (i, global) in glsec.globals.iter().enumerate() {
+                write!(
+                    f,
+                    "\n  (global {} ",
+                    self.globals.binding(globals + i as u32)
+                )?;
+                write_global_type(f, &global.ty)?;
+                write!(f, " ")?;
+                self.instrs(f, &global.init.0, None, &no_names, &no_names)?;
+                write!(f, ")")?;
+            }
+        }
+
+        if let Some(exsec) = &module.export_section {
+            for export in &exsec.exports {
+                write!(f, "\n  (export {} (", Str(export.name.as_bytes()))?;
+                match export.desc {
+                    SynthExportDescription::Func(x) => write!(f, "func {}", self.funcs.index(x))?,
+                    SynthExportDescription::Table(x) => {
+                        write!(f, "table {}", self.tables.index(x))?
+                    }
+                    SynthExportDescription::Mem(x) => {
+                        write!(f, "memory {}", self.memories.index(x))?
+                    }
+                    SynthExportDescription::Global(x) => {
+                        write!(f, "global {}", self.globals.index(x))?
+                    }
+                    SynthExportDescription::Tag(x) => write!(f, "tag {}", self.tags.index(x))?,
+                }
+                write!(f, "))")?;
+            }
+        }
+
+        if let Some(stsec) = &module.start_section {
+            write!(f, "\n  (start {})", self.funcs.index(stsec.start))?;
+        }
+
+        if let Some(elsec) = &module.element_section {
+            for (i, elem) in elsec.elements.iter().enumerate() {
+                write!(f, "\n  (elem {}", self.elems.binding(i as u32))?;
+                match &elem.mode {
+                    SynthElemMode::Active { table, offset } => {
+                        if *table != 0 {
+                            write!(f, " (table {})", self.tables.index(*table))?;
+                        }
+                        write!(f, " (offset ")?;
+                        self.instrs(f, &offset.0, None, &no_names, &no_names)?;
+                        write!(f, ")")?;
+                    }
+                    SynthElemMode::Passive => (),
+                    SynthElemMode::Declarative => write!(f, " declare")?,
+                }
+                match &elem.init {
+                    SynthElemInit::FuncIndices(x) => {
+                        write!(f, " func")?;
+                        for funcidx in x {
+                            write!(f, " {}", self.funcs.index(*funcidx))?;
+                        }
+                    }
+                    SynthElemInit::Expressions(x) => {
+                        let rt = match elem.kind {
+                            SynthElemKind::FuncRef => ReferenceType::FuncRef,
+                            SynthElemKind::ReferenceType(rt) => rt,
+                        };
+                        write!(f, " {rt}")?;
+                        for expr in x {
+                            write!(f, " (item ")?;
+                            self.instrs(f, &expr.0, None, &no_names, &no_names)?;
+                            write!(f, ")")?;
+                        }
+                    }
+                }
+                write!(f, ")")?;
+            }
+        }
+
+        if let Some(datasec) = &module.data_section {
+            for (i, data) in datasec.all_data.iter().enumerate() {
+                write!(f, "\n  (data {}", self.data.binding(i as u32))?;
+                let init = match data {
+                    SynthData::Active {
+                        init,
+                        memory_index,
+                        offset,
+                    } => {
+                        if *memory_index != 0 {
+                            write!(f, " (memory {})", self.memories.index(*memory_index))?;
+                        }
+                        write!(f, " (offset ")?;
+                        self.instrs(f, &offset.0, None, &no_names, &no_names)?;
+                        write!(f, ")")?;
+                        init
+                    }
+                    SynthData::Passive(init) => init,
+                };
+                write!(f, " {})", Str(init))?;
+            }
+        }
+
+        // custom sections are printed as annotations, which are ignored by parsers without
+        // support for them
+        for custom in &module.custom_sections {
+            write!(f, "\n  (@custom {}", Str(custom.name.as_bytes()))?;
+            match custom.placement {
+                CustomPlacement::First => write!(f, " (before first)")?,
+                CustomPlacement::After(SectionKind::Data) => (),
+                CustomPlacement::After(kind) => write!(f, " (after {})", kind.keyword())?,
+            }
+            write!(f, " {})", Str(&custom.bytes))?;
+        }
+
+        write!(f, "\n)")
+    }
+
+    /// Prints instructions on separate lines indented by `indent` levels, or on the current line
+    /// separated by spaces if `indent` is `None`.
+    fn instrs(
+        &self,
+        f: &mut Formatter<'_>,
+        instrs: &[Instruction],
+        indent: Option<usize>,
+        locals: &Names<'_>,
+        labels: &Names<'_>,
+    ) -> fmt::Result {
+        // NOTE: an explicit stack is used to print deeply nested blocks, as in write_slice_into
+        let mut stack: Vec<_> = instrs.iter().rev().map(Action::Instruction).collect();
+        // label names of enclosing blocks, innermost last
+        let mut scopes = Vec::new();
+        let mut label_idx = 0;
+        let mut first = true;
+
+        while let Some(action) = stack.pop() {
+            let depth = match action {
+                Action::Instruction(_) => scopes.len(),
+                _ => scopes.len().saturating_sub(1),
+            };
+            match indent {
+                Some(indent) => write!(f, "\n{:width$}", "", width = 2 * (indent + depth))?,
+                None if !first => write!(f, " ")?,
+                None => (),
+            }
+            first = false;
+
+            let instr = match action {
+                Action::Instruction(x) => x,
+                Action::Else => {
+                    write!(f, "else")?;
+                    continue;
+                }
+                Action::Catch(tag) => {
+                    write!(f, "catch {}", self.tags.index(tag))?;
+                    continue;
+                }
+                Action::CatchAll => {
+                    write!(f, "catch_all")?;
+                    continue;
+                }
+                Action::Delegate(l) => {
+                    // the label is relative to the blocks enclosing the try block
+                    scopes.pop();
+                    write!(f, "delegate {}", label(&scopes, l))?;
+                    continue;
+                }
+                Action::End => {
+                    scopes.pop();
+                    write!(f, "end")?;
+                    continue;
+                }
+            };
+            write!(f, "{}", instr.mnemonic())?;
+            match instr {
+                Instruction::Block(bt, body) | Instruction::Loop(bt, body) => {
+                    let name = labels.get(label_idx);
+                    label_idx += 1;
+                    if let Some(name) = name {
+                        write!(f, " ${name}")?;
+                    }
+                    self.block_type(f, bt)?;
+                    scopes.push(name);
+                    stack.push(Action::End);
+                    stack.extend(body.iter().rev().map(Action::Instruction));
+                }
+                Instruction::If(bt, body, else_body) => {
+                    let name = labels.get(label_idx);
+                    label_idx += 1;
+                    if let Some(name) = name {
+                        write!(f, " ${name}")?;
+                    }
+                    self.block_type(f, bt)?;
+                    scopes.push(name);
+                    stack.push(Action::End);
+                    if let Some(else_body) = else_body {
+                        stack.extend(else_body.iter().rev().map(Action::Instruction));
+                        stack.push(Action::Else);
+                    }
+                    stack.extend(body.iter().rev().map(Action::Instruction));
+                }
+                Instruction::Try(bt, body, catches, catch_all) => {
+                    let name = labels.get(label_idx);
+                    label_idx += 1;
+                    if let Some(name) = name {
+                        write!(f, " ${name}")?;
+                    }
+                    self.block_type(f, bt)?;
+                    scopes.push(name);
+                    stack.push(Action::End);
+                    if let Some(catch_all) = catch_all {
+                        stack.extend(catch_all.iter().rev().map(Action::Instruction));
+                        stack.push(Action::CatchAll);
+                    }
+                    for (tag, catch) in catches.iter().rev() {
+                        stack.extend(catch.iter().rev().map(Action::Instruction));
+                        stack.push(Action::Catch(*tag));
+                    }
+                    stack.extend(body.iter().rev().map(Action::Instruction));
+                }
+                Instruction::TryDelegate(bt, body, l) => {
+                    let name = labels.get(label_idx);
+                    label_idx += 1;
+                    if let Some(name) = name {
+                        write!(f, " ${name}")?;
+                    }
+                    self.block_type(f, bt)?;
+                    scopes.push(name);
+                    stack.push(Action::Delegate(*l));
+                    stack.extend(body.iter().rev().map(Action::Instruction));
+                }
+                Instruction::TryTable(bt, catches, body) => {
+                    let name = labels.get(label_idx);
+                    label_idx += 1;
+                    if let Some(name) = name {
+                        write!(f, " ${name}")?;
+                    }
+                    self.block_type(f, bt)?;
+                    // catch labels are relative to the blocks enclosing the try_table block
+                    for catch in catches {
+                        let l = label(&scopes, catch.label());
+                        match catch {
+                            Catch::Tag { tag, .. } => {
+                                write!(f, " (catch {} {l})", self.tags.index(*tag))?
+                            }
+                            Catch::TagRef { tag, .. } => {
+                                write!(f, " (catch_ref {} {l})", self.tags.index(*tag))?
+                            }
+                            Catch::All { .. } => write!(f, " (catch_all {l})")?,
+                            Catch::AllRef { .. } => write!(f, " (catch_all_ref {l})")?,
+                        }
+                    }
+                    scopes.push(name);
+                    stack.push(Action::End);
+                    stack.extend(body.iter().rev().map(Action::Instruction));
+                }
+                _ => self.immediates(f, instr, &scopes, locals)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn block_type(&self, f: &mut Formatter<'_>, bt: &BlockType) -> fmt::Result {
+        match *bt {
+            BlockType::Empty => Ok(()),
+            BlockType::Value(vt) => write!(f, " (result {vt})"),
+            BlockType::TypeIndex(x) => match u32::try_from(x) {
+                Ok(x) => write!(f, " (type {})", self.types.index(x)),
+                Err(_) => write!(f, " (type {x})"),
+            },
+        }
+    }
+
+    /// Prints a reference type, using the name of a concrete heap type.
+    fn ref_type(&self, f: &mut Formatter<'_>, rt: &ReferenceType) -> fmt::Result {
+        match rt.heap_type() {
+            HeapType::Concrete(x) => {
+                let null = if rt.nullable() { " null" } else { "" };
+                write!(f, "(ref{null} {})", self.types.index(x))
+            }
+            _ => write!(f, "{rt}"),
+        }
+    }
+
+    /// Prints a field of the struct type `ty`.
+    fn field(&self, f: &mut Formatter<'_>, ty: u32, field: u32) -> fmt::Result {
+        let name = self.fields.get(&ty).and_then(|x| x.get(field));
+        write!(f, " {} {}", self.types.index(ty), Index(name, field))
+    }
+
+    /// Prints immediates of a non-block instruction.
+    fn immediates(
+        &self,
+        f: &mut Formatter<'_>,
+        instr: &Instruction,
+        scopes: &[Option<&str>],
+        locals: &Names<'_>,
+    ) -> fmt::Result {
+        let label = |l: u32| label(scopes, l);
+
+        if let Some(memarg) = instr.memarg() {
+            if memarg.memory != 0 {
+                write!(f, " {}", self.memories.index(memarg.memory))?;
+            }
+            write_memarg(f, memarg, instr.natural_alignment())?;
+        }
+
+        match instr {
+            Instruction::Br(l) | Instruction::BrIf(l) | Instruction::Rethrow(l) => {
+                write!(f, " {}", label(*l))
+            }
+            Instruction::Throw(x) => write!(f, " {}", self.tags.index(*x)),
+            Instruction::BrTable(ls, l) => {
+                for l in ls {
+                    write!(f, " {}", label(*l))?;
+                }
+                write!(f, " {}", label(*l))
+            }
+            Instruction::Call(x) | Instruction::ReturnCall(x) | Instruction::RefFunc(x) => {
+                write!(f, " {}", self.funcs.index(*x))
+            }
+            Instruction::CallIndirect { ty, table }
+            | Instruction::ReturnCallIndirect { ty, table } => {
+                if *table != 0 {
+                    write!(f, " {}", self.tables.index(*table))?;
+                }
+                write!(f, " (type {})", self.types.index(*ty))
+            }
+            Instruction::RefNull(rt) => match rt.heap_type() {
+                HeapType::Concrete(x) => write!(f, " {}", self.types.index(x)),
+                ht => write!(f, " {ht}"),
+            },
+            Instruction::CallRef(x)
+            | Instruction::ReturnCallRef(x)
+            | Instruction::StructNew(x)
+            | Instruction::StructNewDefault(x)
+            | Instruction::ArrayNew(x)
+            | Instruction::ArrayNewDefault(x)
+            | Instruction::ArrayGet(x)
+            | Instruction::ArrayGetS(x)
+            | Instruction::ArrayGetU(x)
+            | Instruction::ArraySet(x)
+            | Instruction::ArrayFill(x) => write!(f, " {}", self.types.index(*x)),
+            Instruction::BrOnNull(l) | Instruction::BrOnNonNull(l) => write!(f, " {}", label(*l)),
+            Instruction::StructGet(x, y)
+            | Instruction::StructGetS(x, y)
+            | Instruction::StructGetU(x, y)
+            | Instruction::StructSet(x, y) => self.field(f, *x, *y),
+            Instruction::ArrayNewFixed(x, n) => write!(f, " {} {n}", self.types.index(*x)),
+            Instruction::ArrayCopy(x, y) => {
+                write!(f, " {} {}", self.types.index(*x), self.types.index(*y))
+            }
+            Instruction::ArrayNewData(x, d) | Instruction::ArrayInitData(x, d) => {
+                write!(f, " {} {}", self.types.index(*x), self.data.index(*d))
+            }
+            Instruction::ArrayNewElem(x, e) | Instruction::ArrayInitElem(x, e) => {
+                write!(f, " {} {}", self.types.index(*x), self.elems.index(*e))
+            }
+            Instruction::RefTest(rt) | Instruction::RefCast(rt) => {
+                write!(f, " ")?;
+                self.ref_type(f, rt)
+            }
+            Instruction::BrOnCast(l, rt1, rt2) | Instruction::BrOnCastFail(l, rt1, rt2) => {
+                write!(f, " {} ", label(*l))?;
+                self.ref_type(f, rt1)?;
+                write!(f, " ")?;
+                self.ref_type(f, rt2)
+            }
+            Instruction::Select(tys) => {
+                write!(f, " (result")?;
+                for ty in tys {
+                    write!(f, " {ty}")?;
+                }
+                write!(f, ")")
+            }
+            Instruction::LocalGet(x) | Instruction::LocalSet(x) | Instruction::LocalTee(x) => {
+                write!(f, " {}", locals.index(*x))
+            }
+            Instruction::GlobalGet(x) | Instruction::GlobalSet(x) => {
+                write!(f, " {}", self.globals.index(*x))
+            }
+            Instruction::TableGet(x)
+            | Instruction::TableSet(x)
+            | Instruction::TableGrow(x)
+            | Instruction::TableSize(x)
+            | Instruction::TableFill(x) => write!(f, " {}", self.tables.index(*x)),
+            Instruction::TableInit(e, t) => {
+                write!(f, " {} {}", self.tables.index(*t), self.elems.index(*e))
+            }
+            Instruction::ElemDrop(e) => write!(f, " {}", self.elems.index(*e)),
+            Instruction::TableCopy(t1, t2) => {
+                write!(f, " {} {}", self.tables.index(*t1), self.tables.index(*t2))
+            }
+            // memory 0 is implicit
+            Instruction::MemorySize(0)
+            | Instruction::MemoryGrow(0)
+            | Instruction::MemoryFill(0)
+            | Instruction::MemoryCopy(0, 0) => Ok(()),
+            Instruction::MemorySize(m)
+            | Instruction::MemoryGrow(m)
+            | Instruction::MemoryFill(m) => {
+                write!(f, " {}", self.memories.index(*m))
+            }
+            Instruction::MemoryCopy(m1, m2) => {
+                let (m1, m2) = (self.memories.index(*m1), self.memories.index(*m2));
+                write!(f, " {m1} {m2}")
+            }
+            Instruction::MemoryInit(d, m) => {
+                if *m != 0 {
+                    write!(f, " {}", self.memories.index(*m))?;
+                }
+                write!(f, " {}", self.data.index(*d))
+            }
+            Instruction::DataDrop(d) => write!(f, " {}", self.data.index(*d)),
+            Instruction::I32Const(x) => write!(f, " {x}"),
+            Instruction::I64Const(x) => write!(f, " {x}"),
+            Instruction::F32Const(x) => {
+                write!(f, " ")?;
+                write_f32(f, *x)
+            }
+            Instruction::F64Const(x) => {
+                write!(f, " ")?;
+                write_f64(f, *x)
+            }
+            Instruction::V128Const(x) => {
+                write!(f, " i32x4")?;
+                for lane in x.to_le_bytes().chunks_exact(4) {
+                    let lane = u32::from_le_bytes(lane.try_into().unwrap());
+                    write!(f, " 0x{lane:08x}")?;
+                }
+                Ok(())
+            }
+            Instruction::I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    write!(f, " {lane}")?;
+                }
+                Ok(())
+            }
+            Instruction::V128Load8Lane(_, l)
+            | Instruction::V128Load16Lane(_, l)
+            | Instruction::V128Load32Lane(_, l)
+            | Instruction::V128Load64Lane(_, l)
+            | Instruction::V128Store8Lane(_, l)
+            | Instruction::V128Store16Lane(_, l)
+            | Instruction::V128Store32Lane(_, l)
+            | Instruction::V128Store64Lane(_, l)
+            | Instruction::I8x16ExtractLaneS(l)
+            | Instruction::I8x16ExtractLaneU(l)
+            | Instruction::I8x16ReplaceLane(l)
+            | Instruction::I16x8ExtractLaneS(l)
+            | Instruction::I16x8ExtractLaneU(l)
+            | Instruction::I16x8ReplaceLane(l)
+            | Instruction::I32x4ExtractLane(l)
+            | Instruction::I32x4ReplaceLane(l)
+            | Instruction::I64x2ExtractLane(l)
+            | Instruction::I64x2ReplaceLane(l)
+            | Instruction::F32x4ExtractLane(l)
+            | Instruction::F32x4ReplaceLane(l)
+            | Instruction::F64x2ExtractLane(l)
+            | Instruction::F64x2ReplaceLane(l) => write!(f, " {l}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A reference to the relative label `l`, given label names of enclosing blocks, innermost last.
+fn label<'a>(scopes: &[Option<&'a str>], l: u32) -> Index<'a> {
+    let name = (scopes.len().checked_sub(l as usize + 1)).and_then(|i| scopes[i]);
+    Index(name, l)
+}
+
+/// Prints `(param ...)`-like groups of `kind`, each preceded by `sep`. Entries named in `names`
+/// get their own group; `first` is the index of the first entry.
+fn write_value_types(
+    f: &mut Formatter<'_>,
+    sep: &str,
+    kind: &str,
+    types: &[ValueType],
+    first: u32,
+    names: Option<&Names<'_>>,
+) -> fmt::Result {
+    let mut open = false;
+    for (i, ty) in types.iter().enumerate() {
+        match names.and_then(|x| x.get(first + i as u32)) {
+            Some(name) => {
+                if open {
+                    write!(f, ")")?;
+                    open = false;
+                }
+                write!(f, "{sep}({kind} ${name} {ty})")?;
+            }
+            None => {
+                if !open {
+                    write!(f, "{sep}({kind}")?;
+                    open = true;
+                }
+                write!(f, " {ty}")?;
+            }
+        }
+    }
+    if open {
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+fn write_memarg(f: &mut Formatter<'_>, memarg: &MemArg, natural_alignment: u32) -> fmt::Result {
+    if memarg.offset != 0 {
+        write!(f, " offset={}", memarg.offset)?;
+    }
+    if memarg.align != natural_alignment {
+        match 1u64.checked_shl(memarg.align) {
+            Some(align) => write!(f, " align={align}")?,
+            None => write!(f, " (;align=2**{};)", memarg.align)?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints the module in the text format.
+impl Display for SynthModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Printer::new(self).module(f)
+    }
+}
+
+/// Prints the module in the text format. If the module cannot be converted into a
+/// [`SynthModule`], the error is printed as a comment instead.
+impl Display for Module<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.clone().into_synth() {
+            Ok(module) => module.fmt(f),
+            Err(err) => write!(f, ";; cannot print module: {err}"),
+        }
+    }
+}
+
+impl Instruction {
+    /// Returns the text format name of the instruction, e.g. `i32.add`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Unreachable => "unreachable",
+            Self::Nop => "nop",
+            Self::Block(..) => "block",
+            Self::Loop(..) => "loop",
+            Self::If(..) => "if",
+            Self::Try(..) | Self::TryDelegate(..) => "try",
+            Self::Throw(..) => "throw",
+            Self::Rethrow(..) => "rethrow",
+            Self::ThrowRef => "throw_ref",
+            Self::TryTable(..) => "try_table",
+            Self::Br(..) => "br",
+            Self::BrIf(..) => "br_if",
+            Self::BrTable(..) => "br_table",
+            Self::Return => "return",
+            Self::Call(..) => "call",
+            Self::CallIndirect { .. } => "call_indirect",
+            Self::ReturnCall(..) => "return_call",
+            Self::ReturnCallIndirect { .. } => "return_call_indirect",
+            Self::RefNull(..) => "ref.null",
+            Self::RefIsNull => "ref.is_null",
+            Self::RefFunc(..) => "ref.func",
+            Self::CallRef(..) => "call_ref",
+            Self::ReturnCallRef(..) => "return_call_ref",
+            Self::RefEq => "ref.eq",
+            Self::RefAsNonNull => "ref.as_non_null",
+            Self::BrOnNull(..) => "br_on_null",
+            Self::BrOnNonNull(..) => "br_on_non_null",
+            Self::StructNew(..) => "struct.new",
+            Self::StructNewDefault(..) => "struct.new_default",
+            Self::StructGet(..) => "struct.get",
+            Self::StructGetS(..) => "struct.get_s",
+            Self::StructGetU(..) => "struct.get_u",
+            Self::StructSet(..) => "struct.set",
+            Self::ArrayNew(..) => "array.new",
+            Self::ArrayNewDefault(..) => "array.new_default",
+            Self::ArrayNewFixed(..) => "array.new_fixed",
+            Self::ArrayNewData(..) => "array.new_data",
+            Self::ArrayNewElem(..) => "array.new_elem",
+            Self::ArrayGet(..) => "array.get",
+            Self::ArrayGetS(..) => "array.get_s",
+            Self::ArrayGetU(..) => "array.get_u",
+            Self::ArraySet(..) => "array.set",
+            Self::ArrayLen => "array.len",
+            Self::ArrayFill(..) => "array.fill",
+            Self::ArrayCopy(..) => "array.copy",
+            Self::ArrayInitData(..) => "array.init_data",
+            Self::ArrayInitElem(..) => "array.init_elem",
+            Self::RefTest(..) => "ref.test",
+            Self::RefCast(..) => "ref.cast",
+            Self::BrOnCast(..) => "br_on_cast",
+            Self::BrOnCastFail(..) => "br_on_cast_fail",
+            Self::AnyConvertExtern => "any.convert_extern",
+            Self::ExternConvertAny => "extern.convert_any",
+            Self::RefI31 => "ref.i31",
+            Self::I31GetS => "i31.get_s",
+            Self::I31GetU => "i31.get_u",
+            Self::Drop => "drop",
+            Self::SelectNumeric => "select",
+            Self::Select(..) => "select",
+            Self::LocalGet(..) => "local.get",
+            Self::LocalSet(..) => "local.set",
+            Self::LocalTee(..) => "local.tee",
+            Self::GlobalGet(..) => "global.get",
+            Self::GlobalSet(..) => "global.set",
+            Self::TableGet(..) => "table.get",
+            Self::TableSet(..) => "table.set",
+            Self::TableInit(..) => "table.init",
+            Self::ElemDrop(..) => "elem.drop",
+            Self::TableCopy(..) => "table.copy",
+            Self::TableGrow(..) => "table.grow",
+            Self::TableSize(..) => "table.size",
+            Self::TableFill(..) => "table.fill",
+            Self::I32Load(..) => "i32.load",
+            Self::I64Load(..) => "i64.load",
+            Self::F32Load(..) => "f32.load",
+            Self::F64Load(..) => "f64.load",
+            Self::I32Load8S(..) => "i32.load8_s",
+            Self::I32Load8U(..) => "i32.load8_u",
+            Self::I32Load16S(..) => "i32.load16_s",
+            Self::I32Load16U(..) => "i32.load16_u",
+            Self::I64Load8S(..) => "i64.load8_s",
+            Self::I64Load8U(..) => "i64.load8_u",
+            Self::I64Load16S(..) => "i64.load16_s",
+            Self::I64Load16U(..) => "i64.load16_u",
+            Self::I64Load32S(..) => "i64.load32_s",
+            Self::I64Load32U(..) => "i64.load32_u",
+            Self::I32Store(..) => "i32.store",
+            Self::I64Store(..) => "i64.store",
+            Self::F32Store(..) => "f32.store",
+            Self::F64Store(..) => "f64.store",
+            Self::I32Store8(..) => "i32.store8",
+            Self::I32Store16(..) => "i32.store16",
+            Self::I64Store8(..) => "i64.store8",
+            Self::I64Store16(..) => "i64.store16",
+            Self::I64Store32(..) => "i64.store32",
+            Self::MemorySize(..) => "memory.size",
+            Self::MemoryGrow(..) => "memory.grow",
+            Self::MemoryInit(..) => "memory.init",
+            Self::DataDrop(..) => "data.drop",
+            Self::MemoryCopy(..) => "memory.copy",
+            Self::MemoryFill(..) => "memory.fill",
+            Self::I32Const(..) => "i32.const",
+            Self::I64Const(..) => "i64.const",
+            Self::F32Const(..) => "f32.const",
+            Self::F64Const(..) => "f64.const",
+            Self::I32Eqz => "i32.eqz",
+            Self::I32Eq => "i32.eq",
+            Self::I32Ne => "i32.ne",
+            Self::I32LtS => "i32.lt_s",
+            Self::I32LtU => "i32.lt_u",
+            Self::I32GtS => "i32.gt_s",
+            Self::I32GtU => "i32.gt_u",
+            Self::I32LeS => "i32.le_s",
+            Self::I32LeU => "i32.le_u",
+            Self::I32GeS => "i32.ge_s",
+            Self::I32GeU => "i32.ge_u",
+            Self::I64Eqz => "i64.eqz",
+            Self::I64Eq => "i64.eq",
+            Self::I64Ne => "i64.ne",
+            Self::I64LtS => "i64.lt_s",
+            Self::I64LtU => "i64.lt_u",
+            Self::I64GtS => "i64.gt_s",
+            Self::I64GtU => "i64.gt_u",
+            Self::I64LeS => "i64.le_s",
+            Self::I64LeU => "i64.le_u",
+            Self::I64GeS => "i64.ge_s",
+            Self::I64GeU => "i64.ge_u",
+            Self::F32Eq => "f32.eq",
+            Self::F32Ne => "f32.ne",
+            Self::F32Lt => "f32.lt",
+            Self::F32Gt => "f32.gt",
+            Self::F32Le => "f32.le",
+            Self::F32Ge => "f32.ge",
+            Self::F64Eq => "f64.eq",
+            Self::F64Ne => "f64.ne",
+            Self::F64Lt => "f64.lt",
+            Self::F64Gt => "f64.gt",
+            Self::F64Le => "f64.le",
+            Self::F64Ge => "f64.ge",
+            Self::I32Clz => "i32.clz",
+            Self::I32Ctz => "i32.ctz",
+            Self::I32Popcnt => "i32.popcnt",
+            Self::I32Add => "i32.add",
+            Self::I32Sub => "i32.sub",
+            Self::I32Mul => "i32.mul",
+            Self::I32DivS => "i32.div_s",
+            Self::I32DivU => "i32.div_u",
+            Self::I32RemS => "i32.rem_s",
+            Self::I32RemU => "i32.rem_u",
+            Self::I32And => "i32.and",
+            Self::I32Or => "i32.or",
+            Self::I32Xor => "i32.xor",
+            Self::I32Shl => "i32.shl",
+            Self::I32ShrS => "i32.shr_s",
+            Self::I32ShrU => "i32.shr_u",
+            Self::I32Rotl => "i32.rotl",
+            Self::I32Rotr => "i32.rotr",
+            Self::I64Clz => "i64.clz",
+            Self::I64Ctz => "i64.ctz",
+            Self::I64Popcnt => "i64.popcnt",
+            Self::I64Add => "i64.add",
+            Self::I64Sub => "i64.sub",
+            Self::I64Mul => "i64.mul",
+            Self::I64DivS => "i64.div_s",
+            Self::I64DivU => "i64.div_u",
+            Self::I64RemS => "i64.rem_s",
+            Self::I64RemU => "i64.rem_u",
+            Self::I64And => "i64.and",
+            Self::I64Or => "i64.or",
+            Self::I64Xor => "i64.xor",
+            Self::I64Shl => "i64.shl",
+            Self::I64ShrS => "i64.shr_s",
+            Self::I64ShrU => "i64.shr_u",
+            Self::I64Rotl => "i64.rotl",
+            Self::I64Rotr => "i64.rotr",
+            Self::F32Abs => "f32.abs",
+            Self::F32Neg => "f32.neg",
+            Self::F32Ceil => "f32.ceil",
+            Self::F32Floor => "f32.floor",
+            Self::F32Trunc => "f32.trunc",
+            Self::F32Nearest => "f32.nearest",
+            Self::F32Sqrt => "f32.sqrt",
+            Self::F32Add => "f32.add",
+            Self::F32Sub => "f32.sub",
+            Self::F32Mul => "f32.mul",
+            Self::F32Div => "f32.div",
+            Self::F32Min => "f32.min",
+            Self::F32Max => "f32.max",
+            Self::F32Copysign => "f32.copysign",
+            Self::F64Abs => "f64.abs",
+            Self::F64Neg => "f64.neg",
+            Self::F64Ceil => "f64.ceil",
+            Self::F64Floor => "f64.floor",
+            Self::F64Trunc => "f64.trunc",
+            Self::F64Nearest => "f64.nearest",
+            Self::F64Sqrt => "f64.sqrt",
+            Self::F64Add => "f64.add",
+            Self::F64Sub => "f64.sub",
+            Self::F64Mul => "f64.mul",
+            Self::F64Div => "f64.div",
+            Self::F64Min => "f64.min",
+            Self::F64Max => "f64.max",
+            Self::F64Copysign => "f64.copysign",
+            Self::I32WrapI64 => "i32.wrap_i64",
+            Self::I32TruncF32S => "i32.trunc_f32_s",
+            Self::I32TruncF32U => "i32.trunc_f32_u",
+            Self::I32TruncF64S => "i32.trunc_f64_s",
+            Self::I32TruncF64U => "i32.trunc_f64_u",
+            Self::I64ExtendI32S => "i64.extend_i32_s",
+            Self::I64ExtendI32U => "i64.extend_i32_u",
+            Self::I64TruncF32S => "i64.trunc_f32_s",
+            Self::I64TruncF32U => "i64.trunc_f32_u",
+            Self::I64TruncF64S => "i64.trunc_f64_s",
+            Self::I64TruncF64U => "i64.trunc_f64_u",
+            Self::F32ConvertI32S => "f32.convert_i32_s",
+            Self::F32ConvertI32U => "f32.convert_i32_u",
+            Self::F32ConvertI64S => "f32.convert_i64_s",
+            Self::F32ConvertI64U => "f32.convert_i64_u",
+            Self::F32DemoteF64 => "f32.demote_f64",
+            Self::F64ConvertI32S => "f64.convert_i32_s",
+            Self::F64ConvertI32U => "f64.convert_i32_u",
+            Self::F64ConvertI64S => "f64.convert_i64_s",
+            Self::F64ConvertI64U => "f64.convert_i64_u",
+            Self::F64PromoteF32 => "f64.promote_f32",
+            Self::I32ReinterpretF32 => "i32.reinterpret_f32",
+            Self::I64ReinterpretF64 => "i64.reinterpret_f64",
+            Self::F32ReinterpretI32 => "f32.reinterpret_i32",
+            Self::F64ReinterpretI64 => "f64.reinterpret_i64",
+            Self::I32Extend8S => "i32.extend8_s",
+            Self::I32Extend16S => "i32.extend16_s",
+            Self::I64Extend8S => "i64.extend8_s",
+            Self::I64Extend16S => "i64.extend16_s",
+            Self::I64Extend32S => "i64.extend32_s",
+            Self::I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            Self::I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            Self::I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            Self::I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            Self::I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            Self::I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            Self::I64TruncSatF64S => "i64.trunc_sat_f64_s",
+            Self::I64TruncSatF64U => "i64.trunc_sat_f64_u",
+            Self::V128Load(..) => "v128.load",
+            Self::V128Load8x8S(..) => "v128.load8x8_s",
+            Self::V128Load8x8U(..) => "v128.load8x8_u",
+            Self::V128Load16x4S(..) => "v128.load16x4_s",
+            Self::V128Load16x4U(..) => "v128.load16x4_u",
+            Self::V128Load32x2S(..) => "v128.load32x2_s",
+            Self::V128Load32x2U(..) => "v128.load32x2_u",
+            Self::V128Load8Splat(..) => "v128.load8_splat",
+            Self::V128Load16Splat(..) => "v128.load16_splat",
+            Self::V128Load32Splat(..) => "v128.load32_splat",
+            Self::V128Load64Splat(..) => "v128.load64_splat",
+            Self::V128Load32Zero(..) => "v128.load32_zero",
+            Self::V128Load64Zero(..) => "v128.load64_zero",
+            Self::V128Store(..) => "v128.store",
+            Self::V128Load8Lane(..) => "v128.load8_lane",
+            Self::V128Load16Lane(..) => "v128.load16_lane",
+            Self::V128Load32Lane(..) => "v128.load32_lane",
+            Self::V128Load64Lane(..) => "v128.load64_lane",
+            Self::V128Store8Lane(..) => "v128.store8_lane",
+            Self::V128Store16Lane(..) => "v128.store16_lane",
+            Self::V128Store32Lane(..) => "v128.store32_lane",
+            Self::V128Store64Lane(..) => "v128.store64_lane",
+            Self::V128Const(..) => "v128.const",
+            Self::I8x16Shuffle(..) => "i8x16.shuffle",
+            Self::I8x16ExtractLaneS(..) => "i8x16.extract_lane_s",
+            Self::I8x16ExtractLaneU(..) => "i8x16.extract_lane_u",
+            Self::I8x16ReplaceLane(..) => "i8x16.replace_lane",
+            Self::I16x8ExtractLaneS(..) => "i16x8.extract_lane_s",
+            Self::I16x8ExtractLaneU(..) => "i16x8.extract_lane_u",
+            Self::I16x8ReplaceLane(..) => "i16x8.replace_lane",
+            Self::I32x4ExtractLane(..) => "i32x4.extract_lane",
+            Self::I32x4ReplaceLane(..) => "i32x4.replace_lane",
+            Self::I64x2ExtractLane(..) => "i64x2.extract_lane",
+            Self::I64x2ReplaceLane(..) => "i64x2.replace_lane",
+            Self::F32x4ExtractLane(..) => "f32x4.extract_lane",
+            Self::F32x4ReplaceLane(..) => "f32x4.replace_lane",
+            Self::F64x2ExtractLane(..) => "f64x2.extract_lane",
+            Self::F64x2ReplaceLane(..) => "f64x2.replace_lane",
+            Self::I8X16Swizzle => "i8x16.swizzle",
+            Self::I8x16Splat => "i8x16.splat",
+            Self::I16x8Splat => "i16x8.splat",
+            Self::I32x4Splat => "i32x4.splat",
+            Self::I64x2Splat => "i64x2.splat",
+            Self::F32x4Splat => "f32x4.splat",
+            Self::F64x2Splat => "f64x2.splat",
+            Self::I8x16Eq => "i8x16.eq",
+            Self::I8x16Ne => "i8x16.ne",
+            Self::I8X16LtS => "i8x16.lt_s",
+            Self::I8X16LtU => "i8x16.lt_u",
+            Self::I8X16GtS => "i8x16.gt_s",
+            Self::I8X16GtU => "i8x16.gt_u",
+            Self::I8X16LeS => "i8x16.le_s",
+            Self::I8X16LeU => "i8x16.le_u",
+            Self::I8X16GeS => "i8x16.ge_s",
+            Self::I8X16GeU => "i8x16.ge_u",
+            Self::I16x8Eq => "i16x8.eq",
+            Self::I16x8Ne => "i16x8.ne",
+            Self::I16x8LtS => "i16x8.lt_s",
+            Self::I16x8LtU => "i16x8.lt_u",
+            Self::I16x8GtS => "i16x8.gt_s",
+            Self::I16x8GtU => "i16x8.gt_u",
+            Self::I16x8LeS => "i16x8.le_s",
+            Self::I16x8LeU => "i16x8.le_u",
+            Self::I16x8GeS => "i16x8.ge_s",
+            Self::I16x8GeU => "i16x8.ge_u",
+            Self::I32x4Eq => "i32x4.eq",
+            Self::I32x4Ne => "i32x4.ne",
+            Self::I32x4LtS => "i32x4.lt_s",
+            Self::I32x4LtU => "i32x4.lt_u",
+            Self::I32x4GtS => "i32x4.gt_s",
+            Self::I32x4GtU => "i32x4.gt_u",
+            Self::I32x4LeS => "i32x4.le_s",
+            Self::I32x4LeU => "i32x4.le_u",
+            Self::I32x4GeS => "i32x4.ge_s",
+            Self::I32x4GeU => "i32x4.ge_u",
+            Self::I64x2Eq => "i64x2.eq",
+            Self::I64x2Ne => "i64x2.ne",
+            Self::I64x2LtS => "i64x2.lt_s",
+            Self::I64x2GtS => "i64x2.gt_s",
+            Self::I64x2LeS => "i64x2.le_s",
+            Self::I64x2GeS => "i64x2.ge_s",
+            Self::F32x4Eq => "f32x4.eq",
+            Self::F32x4Ne => "f32x4.ne",
+            Self::F32x4Lt => "f32x4.lt",
+            Self::F32x4Gt => "f32x4.gt",
+            Self::F32x4Le => "f32x4.le",
+            Self::F32x4Ge => "f32x4.ge",
+            Self::F64x2Eq => "f64x2.eq",
+            Self::F64x2Ne => "f64x2.ne",
+            Self::F64x2Lt => "f64x2.lt",
+            Self::F64x2Gt => "f64x2.gt",
+            Self::F64x2Le => "f64x2.le",
+            Self::F64x2Ge => "f64x2.ge",
+            Self::V128Not => "v128.not",
+            Self::V128And => "v128.and",
+            Self::V128AndNot => "v128.andnot",
+            Self::V128Or => "v128.or",
+            Self::V128Xor => "v128.xor",
+            Self::V128Bitselect => "v128.bitselect",
+            Self::V128AnyTrue => "v128.any_true",
+            Self::I8x16Abs => "i8x16.abs",
+            Self::I8x16Neg => "i8x16.neg",
+            Self::I8x16Popcnt => "i8x16.popcnt",
+            Self::I8x16AllTrue => "i8x16.all_true",
+            Self::I8x16Bitmask => "i8x16.bitmask",
+            Self::I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+            Self::I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+            Self::I8x16Shl => "i8x16.shl",
+            Self::I8x16ShrS => "i8x16.shr_s",
+            Self::I8x16ShrU => "i8x16.shr_u",
+            Self::I8x16Add => "i8x16.add",
+            Self::I8x16AddSatS => "i8x16.add_sat_s",
+            Self::I8x16AddSatU => "i8x16.add_sat_u",
+            Self::I8x16Sub => "i8x16.sub",
+            Self::I8x16SubSatS => "i8x16.sub_sat_s",
+            Self::I8x16SubSatU => "i8x16.sub_sat_u",
+            Self::I8x16MinS => "i8x16.min_s",
+            Self::I8x16MinU => "i8x16.min_u",
+            Self::I8x16MaxS => "i8x16.max_s",
+            Self::I8x16MaxU => "i8x16.max_u",
+            Self::I8x16AvgrU => "i8x16.avgr_u",
+            Self::I16x8ExtAddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+            Self::I16x8ExtAddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+            Self::I16x8Abs => "i16x8.abs",
+            Self::I16x8Neg => "i16x8.neg",
+            Self::I16x8Q15MulrSatS => "i16x8.q15_mulr_sat_s",
+            Self::I16x8AllTrue => "i16x8.all_true",
+            Self::I16x8Bitmask => "i16x8.bitmask",
+            Self::I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+            Self::I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+            Self::I16x8ExtendLowI8X16S => "i16x8.extend_low_i8x16_s",
+            Self::I16x8ExtendHighI8X16S => "i16x8.extend_high_i8x16_s",
+            Self::I16x8ExtendLowI8X16U => "i16x8.extend_low_i8x16_u",
+            Self::I16x8ExtendHighI8X16U => "i16x8.extend_high_i8x16_u",
+            Self::I16x8Shl => "i16x8.shl",
+            Self::I16x8ShrS => "i16x8.shr_s",
+            Self::I16x8ShrU => "i16x8.shr_u",
+            Self::I16x8Add => "i16x8.add",
+            Self::I16x8AddSatS => "i16x8.add_sat_s",
+            Self::I16x8AddSatU => "i16x8.add_sat_u",
+            Self::I16x8Sub => "i16x8.sub",
+            Self::I16x8SubSatS => "i16x8.sub_sat_s",
+            Self::I16x8SubSatU => "i16x8.sub_sat_u",
+            Self::I16X8Mul => "i16x8.mul",
+            Self::I16x8MinS => "i16x8.min_s",
+            Self::I16x8MinU => "i16x8.min_u",
+            Self::I16x8MaxS => "i16x8.max_s",
+            Self::I16x8MaxU => "i16x8.max_u",
+            Self::I16x8AvgrU => "i16x8.avgr_u",
+            Self::I16x8ExtmulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+            Self::I16x8ExtmulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+            Self::I16x8ExtmulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+            Self::I16x8ExtmulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+            Self::I32x4ExtAddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+            Self::I32x4ExtAddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+            Self::I32x4Abs => "i32x4.abs",
+            Self::I32x4Neg => "i32x4.neg",
+            Self::I32x4AllTrue => "i32x4.all_true",
+            Self::I32x4Bitmask => "i32x4.bitmask",
+            Self::I32x4ExtendLowI16X8S => "i32x4.extend_low_i16x8_s",
+            Self::I32x4ExtendHighI16X8S => "i32x4.extend_high_i16x8_s",
+            Self::I32x4ExtendLowI16X8U => "i32x4.extend_low_i16x8_u",
+            Self::I32x4ExtendHighI16X8U => "i32x4.extend_high_i16x8_u",
+            Self::I32x4Shl => "i32x4.shl",
+            Self::I32x4ShrS => "i32x4.shr_s",
+            Self::I32x4ShrU => "i32x4.shr_u",
+            Self::I32x4Add => "i32x4.add",
+            Self::I32x4Sub => "i32x4.sub",
+            Self::I32x4Mul => "i32x4.mul",
+            Self::I32x4MinS => "i32x4.min_s",
+            Self::I32x4MinU => "i32x4.min_u",
+            Self::I32x4MaxS => "i32x4.max_s",
+            Self::I32x4MaxU => "i32x4.max_u",
+            Self::I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+            Self::I32x4ExtmulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+            Self::I32x4ExtmulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+            Self::I32x4ExtmulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+            Self::I32x4ExtmulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+            Self::I64x2Abs => "i64x2.abs",
+            Self::I64x2Neg => "i64x2.neg",
+            Self::I64x2AllTrue => "i64x2.all_true",
+            Self::I64x2Bitmask => "i64x2.bitmask",
+            Self::I64x2ExtendLowI16X8S => "i64x2.extend_low_i32x4_s",
+            Self::I64x2ExtendHighI16X8S => "i64x2.extend_high_i32x4_s",
+            Self::I64x2ExtendLowI16X8U => "i64x2.extend_low_i32x4_u",
+            Self::I64x2ExtendHighI16X8U => "i64x2.extend_high_i32x4_u",
+            Self::I64x2Shl => "i64x2.shl",
+            Self::I64x2ShrS => "i64x2.shr_s",
+            Self::I64x2ShrU => "i64x2.shr_u",
+            Self::I64x2Add => "i64x2.add",
+            Self::I64x2Sub => "i64x2.sub",
+            Self::I64x2Mul => "i64x2.mul",
+            Self::I64x2ExtmulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+            Self::I64x2ExtmulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+            Self::I64x2ExtmulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+            Self::I64x2ExtmulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+            Self::F32x4Ceil => "f32x4.ceil",
+            Self::F32x4Floor => "f32x4.floor",
+            Self::F32x4Trunc => "f32x4.trunc",
+            Self::F32x4Nearest => "f32x4.nearest",
+            Self::F32x4Abs => "f32x4.abs",
+            Self::F32x4Neg => "f32x4.neg",
+            Self::F32x4Sqrt => "f32x4.sqrt",
+            Self::F32x4Add => "f32x4.add",
+            Self::F32x4Sub => "f32x4.sub",
+            Self::F32x4Mul => "f32x4.mul",
+            Self::F32x4Div => "f32x4.div",
+            Self::F32x4Min => "f32x4.min",
+            Self::F32x4Max => "f32x4.max",
+            Self::F32x4Pmin => "f32x4.pmin",
+            Self::F32x4Pmax => "f32x4.pmax",
+            Self::F64x2Ceil => "f64x2.ceil",
+            Self::F64x2Floor => "f64x2.floor",
+            Self::F64x2Trunc => "f64x2.trunc",
+            Self::F64x2Nearest => "f64x2.nearest",
+            Self::F64x2Abs => "f64x2.abs",
+            Self::F64x2Neg => "f64x2.neg",
+            Self::F64x2Sqrt => "f64x2.sqrt",
+            Self::F64x2Add => "f64x2.add",
+            Self::F64x2Sub => "f64x2.sub",
+            Self::F64x2Mul => "f64x2.mul",
+            Self::F64x2Div => "f64x2.div",
+            Self::F64x2Min => "f64x2.min",
+            Self::F64x2Max => "f64x2.max",
+            Self::F64x2Pmin => "f64x2.pmin",
+            Self::F64x2Pmax => "f64x2.pmax",
+            Self::I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+            Self::I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+            Self::F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+            Self::F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+            Self::I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+            Self::I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+            Self::F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+            Self::F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+            Self::F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+            Self::F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+            Self::I8x16RelaxedSwizzle => "i8x16.relaxed_swizzle",
+            Self::I32x4RelaxedTruncF32x4S => "i32x4.relaxed_trunc_f32x4_s",
+            Self::I32x4RelaxedTruncF32x4U => "i32x4.relaxed_trunc_f32x4_u",
+            Self::I32x4RelaxedTruncF64x2SZero => "i32x4.relaxed_trunc_f64x2_s_zero",
+            Self::I32x4RelaxedTruncF64x2UZero => "i32x4.relaxed_trunc_f64x2_u_zero",
+            Self::F32x4RelaxedMadd => "f32x4.relaxed_madd",
+            Self::F32x4RelaxedNmadd => "f32x4.relaxed_nmadd",
+            Self::F64x2RelaxedMadd => "f64x2.relaxed_madd",
+            Self::F64x2RelaxedNmadd => "f64x2.relaxed_nmadd",
+            Self::I8x16RelaxedLaneselect => "i8x16.relaxed_laneselect",
+            Self::I16x8RelaxedLaneselect => "i16x8.relaxed_laneselect",
+            Self::I32x4RelaxedLaneselect => "i32x4.relaxed_laneselect",
+            Self::I64x2RelaxedLaneselect => "i64x2.relaxed_laneselect",
+            Self::F32x4RelaxedMin => "f32x4.relaxed_min",
+            Self::F32x4RelaxedMax => "f32x4.relaxed_max",
+            Self::F64x2RelaxedMin => "f64x2.relaxed_min",
+            Self::F64x2RelaxedMax => "f64x2.relaxed_max",
+            Self::I16x8RelaxedQ15mulrS => "i16x8.relaxed_q15mulr_s",
+            Self::I16x8RelaxedDotI8x16I7x16S => "i16x8.relaxed_dot_i8x16_i7x16_s",
+            Self::I32x4RelaxedDotI8x16I7x16AddS => "i32x4.relaxed_dot_i8x16_i7x16_add_s",
+            Self::MemoryAtomicNotify(_) => "memory.atomic.notify",
+            Self::MemoryAtomicWait32(_) => "memory.atomic.wait32",
+            Self::MemoryAtomicWait64(_) => "memory.atomic.wait64",
+            Self::AtomicFence => "atomic.fence",
+            Self::I32AtomicLoad(_) => "i32.atomic.load",
+            Self::I64AtomicLoad(_) => "i64.atomic.load",
+            Self::I32AtomicLoad8U(_) => "i32.atomic.load8_u",
+            Self::I32AtomicLoad16U(_) => "i32.atomic.load16_u",
+            Self::I64AtomicLoad8U(_) => "i64.atomic.load8_u",
+            Self::I64AtomicLoad16U(_) => "i64.atomic.load16_u",
+            Self::I64AtomicLoad32U(_) => "i64.atomic.load32_u",
+            Self::I32AtomicStore(_) => "i32.atomic.store",
+            Self::I64AtomicStore(_) => "i64.atomic.store",
+            Self::I32AtomicStore8(_) => "i32.atomic.store8",
+            Self::I32AtomicStore16(_) => "i32.atomic.store16",
+            Self::I64AtomicStore8(_) => "i64.atomic.store8",
+            Self::I64AtomicStore16(_) => "i64.atomic.store16",
+            Self::I64AtomicStore32(_) => "i64.atomic.store32",
+            Self::I32AtomicRmwAdd(_) => "i32.atomic.rmw.add",
+            Self::I64AtomicRmwAdd(_) => "i64.atomic.rmw.add",
+            Self::I32AtomicRmw8AddU(_) => "i32.atomic.rmw8.add_u",
+            Self::I32AtomicRmw16AddU(_) => "i32.atomic.rmw16.add_u",
+            Self::I64AtomicRmw8AddU(_) => "i64.atomic.rmw8.add_u",
+            Self::I64AtomicRmw16AddU(_) => "i64.atomic.rmw16.add_u",
+            Self::I64AtomicRmw32AddU(_) => "i64.atomic.rmw32.add_u",
+            Self::I32AtomicRmwSub(_) => "i32.atomic.rmw.sub",
+            Self::I64AtomicRmwSub(_) => "i64.atomic.rmw.sub",
+            Self::I32AtomicRmw8SubU(_) => "i32.atomic.rmw8.sub_u",
+            Self::I32AtomicRmw16SubU(_) => "i32.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw8SubU(_) => "i64.atomic.rmw8.sub_u",
+            Self::I64AtomicRmw16SubU(_) => "i64.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw32SubU(_) => "i64.atomic.rmw32.sub_u",
+            Self::I32AtomicRmwAnd(_) => "i32.atomic.rmw.and",
+            Self::I64AtomicRmwAnd(_) => "i64.atomic.rmw.and",
+            Self::I32AtomicRmw8AndU(_) => "i32.atomic.rmw8.and_u",
+            Self::I32AtomicRmw16AndU(_) => "i32.atomic.rmw16.and_u",
+            Self::I64AtomicRmw8AndU(_) => "i64.atomic.rmw8.and_u",
+            Self::I64AtomicRmw16AndU(_) => "i64.atomic.rmw16.and_u",
+            Self::I64AtomicRmw32AndU(_) => "i64.atomic.rmw32.and_u",
+            Self::I32AtomicRmwOr(_) => "i32.atomic.rmw.or",
+            Self::I64AtomicRmwOr(_) => "i64.atomic.rmw.or",
+            Self::I32AtomicRmw8OrU(_) => "i32.atomic.rmw8.or_u",
+            Self::I32AtomicRmw16OrU(_) => "i32.atomic.rmw16.or_u",
+            Self::I64AtomicRmw8OrU(_) => "i64.atomic.rmw8.or_u",
+            Self::I64AtomicRmw16OrU(_) => "i64.atomic.rmw16.or_u",
+            Self::I64AtomicRmw32OrU(_) => "i64.atomic.rmw32.or_u",
+            Self::I32AtomicRmwXor(_) => "i32.atomic.rmw.xor",
+            Self::I64AtomicRmwXor(_) => "i64.atomic.rmw.xor",
+            Self::I32AtomicRmw8XorU(_) => "i32.atomic.rmw8.xor_u",
+            Self::I32AtomicRmw16XorU(_) => "i32.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw8XorU(_) => "i64.atomic.rmw8.xor_u",
+            Self::I64AtomicRmw16XorU(_) => "i64.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw32XorU(_) => "i64.atomic.rmw32.xor_u",
+            Self::I32AtomicRmwXchg(_) => "i32.atomic.rmw.xchg",
+            Self::I64AtomicRmwXchg(_) => "i64.atomic.rmw.xchg",
+            Self::I32AtomicRmw8XchgU(_) => "i32.atomic.rmw8.xchg_u",
+            Self::I32AtomicRmw16XchgU(_) => "i32.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw8XchgU(_) => "i64.atomic.rmw8.xchg_u",
+            Self::I64AtomicRmw16XchgU(_) => "i64.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw32XchgU(_) => "i64.atomic.rmw32.xchg_u",
+            Self::I32AtomicRmwCmpxchg(_) => "i32.atomic.rmw.cmpxchg",
+            Self::I64AtomicRmwCmpxchg(_) => "i64.atomic.rmw.cmpxchg",
+            Self::I32AtomicRmw8CmpxchgU(_) => "i32.atomic.rmw8.cmpxchg_u",
+            Self::I32AtomicRmw16CmpxchgU(_) => "i32.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw8CmpxchgU(_) => "i64.atomic.rmw8.cmpxchg_u",
+            Self::I64AtomicRmw16CmpxchgU(_) => "i64.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw32CmpxchgU(_) => "i64.atomic.rmw32.cmpxchg_u",
+        }
+    }
+
+    /// Returns the natural alignment exponent of a load or store instruction.
+    pub(crate) fn natural_alignment(&self) -> u32 {
+        match self {
+            Self::I32Load8S(_)
+            | Self::I32Load8U(_)
+            | Self::I64Load8S(_)
+            | Self::I64Load8U(_)
+            | Self::I32Store8(_)
+            | Self::I64Store8(_)
+            | Self::V128Load8Splat(_)
+            | Self::V128Load8Lane(..)
+            | Self::V128Store8Lane(..)
+            | Self::I32AtomicLoad8U(_)
+            | Self::I64AtomicLoad8U(_)
+            | Self::I32AtomicStore8(_)
+            | Self::I64AtomicStore8(_)
+            | Self::I32AtomicRmw8AddU(_)
+            | Self::I64AtomicRmw8AddU(_)
+            | Self::I32AtomicRmw8SubU(_)
+            | Self::I64AtomicRmw8SubU(_)
+            | Self::I32AtomicRmw8AndU(_)
+            | Self::I64AtomicRmw8AndU(_)
+            | Self::I32AtomicRmw8OrU(_)
+            | Self::I64AtomicRmw8OrU(_)
+            | Self::I32AtomicRmw8XorU(_)
+            | Self::I64AtomicRmw8XorU(_)
+            | Self::I32AtomicRmw8XchgU(_)
+            | Self::I64AtomicRmw8XchgU(_)
+            | Self::I32AtomicRmw8CmpxchgU(_)
+            | Self::I64AtomicRmw8CmpxchgU(_) => 0,
+            Self::I32Load16S(_)
+            | Self::I32Load16U(_)
+            | Self::I64Load16S(_)
+            | Self::I64Load16U(_)
+            | Self::I32Store16(_)
+            | Self::I64Store16(_)
+            | Self::V128Load16Splat(_)
+            | Self::V128Load16Lane(..)
+            | Self::V128Store16Lane(..)
+            | Self::I32AtomicLoad16U(_)
+            | Self::I64AtomicLoad16U(_)
+            | Self::I32AtomicStore16(_)
+            | Self::I64AtomicStore16(_)
+            | Self::I32AtomicRmw16AddU(_)
+            | Self::I64AtomicRmw16AddU(_)
+            | Self::I32AtomicRmw16SubU(_)
+            | Self::I64AtomicRmw16SubU(_)
+            | Self::I32AtomicRmw16AndU(_)
+            | Self::I64AtomicRmw16AndU(_)
+            | Self::I32AtomicRmw16OrU(_)
+            | Self::I64AtomicRmw16OrU(_)
+            | Self::I32AtomicRmw16XorU(_)
+            | Self::I64AtomicRmw16XorU(_)
+            | Self::I32AtomicRmw16XchgU(_)
+            | Self::I64AtomicRmw16XchgU(_)
+            | Self::I32AtomicRmw16CmpxchgU(_)
+            | Self::I64AtomicRmw16CmpxchgU(_) => 1,
+            Self::I32Load(_)
+            | Self::F32Load(_)
+            | Self::I64Load32S(_)
+            | Self::I64Load32U(_)
+            | Self::I32Store(_)
+            | Self::F32Store(_)
+            | Self::I64Store32(_)
+            | Self::V128Load32Splat(_)
+            | Self::V128Load32Zero(_)
+            | Self::V128Load32Lane(..)
+            | Self::V128Store32Lane(..)
+            | Self::MemoryAtomicNotify(_)
+            | Self::MemoryAtomicWait32(_)
+            | Self::I32AtomicLoad(_)
+            | Self::I64AtomicLoad32U(_)
+            | Self::I32AtomicStore(_)
+            | Self::I64AtomicStore32(_)
+            | Self::I32AtomicRmwAdd(_)
+            | Self::I64AtomicRmw32AddU(_)
+            | Self::I32AtomicRmwSub(_)
+            | Self::I64AtomicRmw32SubU(_)
+            | Self::I32AtomicRmwAnd(_)
+            | Self::I64AtomicRmw32AndU(_)
+            | Self::I32AtomicRmwOr(_)
+            | Self::I64AtomicRmw32OrU(_)
+            | Self::I32AtomicRmwXor(_)
+            | Self::I64AtomicRmw32XorU(_)
+            | Self::I32AtomicRmwXchg(_)
+            | Self::I64AtomicRmw32XchgU(_)
+            | Self::I32AtomicRmwCmpxchg(_)
+            | Self::I64AtomicRmw32CmpxchgU(_) => 2,
+            Self::V128Load(_) | Self::V128Store(_) => 4,
+            _ => 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::Module;
+
+    fn print_wat(wat_s: &str) -> String {
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+        Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .to_string()
+    }
+
+    #[test]
+    fn test_print() {
+        let printed = print_wat(
+            r#"(module $m
+                (import "env" "log" (func $log (param i32)))
+                (memory $mem 1)
+                (global $g (mut i32) (i32.const 0))
+                (func $f (export "f") (param $x i32) (result i32) (local $y f32) (local i64)
+                    block $outer (result i32)
+                        loop $inner
+                            local.get $x
+                            br_if $outer
+                            global.get $g
+                            i32.load8_u offset=4
+                            call $log
+                            f32.const nan:0x200000
+                            local.set $y
+                            br $inner
+                        end
+                        i32.const -1
+                    end
+                )
+                (data (i32.const 16) "a\"b\00")
+            )"#,
+        );
+        let expected = r#"(module $m
+  (type (;0;) (func (param i32)))
+  (type (;1;) (func (param i32) (result i32)))
+  (import "env" "log" (func $log (;0;) (type 0)))
+  (func $f (;1;) (type 1) (param $x i32) (result i32)
+    (local $y f32)
+    (local i64)
+    block $outer (result i32)
+      loop $inner
+        local.get $x
+        br_if $outer
+        global.get $g
+        i32.load8_u offset=4
+        call $log
+        f32.const nan:0x200000
+        local.set $y
+        br $inner
+      end
+      i32.const -1
+    end
+  )
+  (memory $mem (;0;) 1)
+  (global $g (;0;) (mut i32) i32.const 0)
+  (export "f" (func $f))
+  (data (;0;) (offset i32.const 16) "a\"b\00")
+)"#;
+        assert_eq!(printed, expected);
+        assert_eq!(print_wat(&printed), expected);
+    }
+
+    #[test]
+    fn test_print_invalid_names() {
+        let wasm = wat::parse_str("(module (func $a) (func $b) (func $c))").unwrap();
+        let mut module = Module::from_binary(&wasm).unwrap().into_synth().unwrap();
+        let names = module
+            .name_section
+            .as_mut()
+            .unwrap()
+            .function_names_mut()
+            .as_mut()
+            .unwrap();
+        names[1].name = "a".into();
+        names[2].name = "not an id".into();
+
+        let printed = module.to_string();
+        assert!(printed.contains("(func $a (;0;) (type 0)"), "{printed}");
+        assert!(printed.contains("(func (;1;) (type 0)"), "{printed}");
+        assert!(printed.contains("(func (;2;) (type 0)"), "{printed}");
+        wat::parse_str(printed).expect("cannot parse printed wat");
+    }
+}
@@ -4,17 +4,28 @@
 
 use std::io::{self, Write};
 
-use crate::{WASM_MAGIC, WASM_VERSION};
+use crate::{instructions::VisitMut, WASM_MAGIC, WASM_VERSION};
 
 use self::sections::{
-    SynthCodeSection, SynthCustomSection, SynthDataCountSection, SynthDataSection,
-    SynthElementSection, SynthExportSection, SynthFunctionSection, SynthGlobalSection,
-    SynthImportSection, SynthMemorySection, SynthNameSection, SynthStartSection, SynthTableSection,
-    SynthTypeSection,
+    CustomPlacement, SectionKind, SynthCodeSection, SynthCustomSection, SynthDataCountSection,
+    SynthDataSection, SynthElementSection, SynthExportSection, SynthFunctionSection,
+    SynthGlobalSection, SynthImportSection, SynthMemorySection, SynthNameSection,
+    SynthStartSection, SynthTableSection, SynthTagSection, SynthTypeSection,
 };
 
+mod builder;
+mod check;
+mod preserve;
+mod remap;
 pub mod sections;
 
+pub use builder::{
+    DataIdx, ExportItem, FuncIdx, FunctionBuilder, GlobalIdx, MemoryIdx, ModuleBuilder, TableIdx,
+    TagIdx, TypeIdx,
+};
+pub use check::{DanglingReference, IndexSpace};
+pub(crate) use preserve::{OriginalBytes, SectionKey};
+
 /// A WebAssembly module synthesizer.
 pub struct SynthModule {
     pub(crate) type_section: Option<SynthTypeSection>,
@@ -22,6 +33,7 @@ pub struct SynthModule {
     pub(crate) function_section: Option<SynthFunctionSection>,
     pub(crate) table_section: Option<SynthTableSection>,
     pub(crate) memory_section: Option<SynthMemorySection>,
+    pub(crate) tag_section: Option<SynthTagSection>,
     pub(crate) global_section: Option<SynthGlobalSection>,
     pub(crate) export_section: Option<SynthExportSection>,
     pub(crate) start_section: Option<SynthStartSection>,
@@ -31,58 +43,215 @@ pub struct SynthModule {
     pub(crate) data_count_section: Option<SynthDataCountSection>,
     pub(crate) custom_sections: Vec<SynthCustomSection>,
     pub(crate) name_section: Option<SynthNameSection>,
+    pub(crate) name_section_placement: CustomPlacement,
+    /// Number of custom sections written before the name section if they have the same
+    /// placement.
+    pub(crate) name_section_order: usize,
+    /// Original bytes of sections and function bodies, if parsed with
+    /// [`Module::into_synth_preserving`](crate::parse::Module::into_synth_preserving).
+    pub(crate) original: Option<Box<OriginalBytes>>,
 }
 
 impl SynthModule {
-    pub fn write_into(&self, mut wr: &mut impl Write) -> Result<(), io::Error> {
-        wr.write_all(WASM_MAGIC)?;
-        wr.write_all(&WASM_VERSION.to_le_bytes())?;
-
-        if let Some(sec) = &self.type_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.import_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.function_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.table_section {
-            sec.write_into(wr)?;
+    /// Visits every global initializer, element segment, function body and data segment with
+    /// `visitor`.
+    ///
+    /// Indices outside of those, such as exports or the name section, are not visited. Use
+    /// [`SynthModule::remap_indices`] to rewrite every reference to an index space.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        if let Some(sec) = &mut self.global_section {
+            for global in &mut sec.globals {
+                global.visit_mut(visitor);
+            }
         }
-        if let Some(sec) = &self.memory_section {
-            sec.write_into(wr)?;
+        if let Some(sec) = &mut self.element_section {
+            for elem in &mut sec.elements {
+                elem.visit_mut(visitor);
+            }
         }
-        if let Some(sec) = &self.global_section {
-            sec.write_into(wr)?;
+        if let Some(sec) = &mut self.code_section {
+            for code in &mut sec.codes {
+                code.visit_mut(visitor);
+            }
         }
-        if let Some(sec) = &self.export_section {
-            sec.write_into(wr)?;
+        if let Some(sec) = &mut self.data_section {
+            for data in &mut sec.all_data {
+                data.visit_mut(visitor);
+            }
         }
-        if let Some(sec) = &self.start_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.element_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.code_section {
-            sec.write_into(wr)?;
-        }
-        if let Some(sec) = &self.data_section {
-            sec.write_into(wr)?;
+    }
+
+    pub fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        wr.write_all(WASM_MAGIC)?;
+        wr.write_all(&WASM_VERSION.to_le_bytes())?;
+
+        let mut name_written = false;
+        self.write_custom_sections(CustomPlacement::First, &mut name_written, wr)?;
+        for kind in SectionKind::ALL {
+            self.write_preserving(wr, SectionKey::Known(kind), |mut wr| {
+                self.write_section(kind, &mut wr)
+            })?;
+            self.write_custom_sections(CustomPlacement::After(kind), &mut name_written, wr)?;
         }
-        if let Some(sec) = &self.data_count_section {
-            sec.write_into(wr)?;
+
+        Ok(())
+    }
+
+    /// Writes the known section of `kind`, if present.
+    pub(crate) fn write_section(
+        &self,
+        kind: SectionKind,
+        wr: &mut impl Write,
+    ) -> Result<(), io::Error> {
+        match kind {
+            SectionKind::Type => {
+                if let Some(sec) = &self.type_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Import => {
+                if let Some(sec) = &self.import_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Function => {
+                if let Some(sec) = &self.function_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Table => {
+                if let Some(sec) = &self.table_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Memory => {
+                if let Some(sec) = &self.memory_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Tag => {
+                if let Some(sec) = &self.tag_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Global => {
+                if let Some(sec) = &self.global_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Export => {
+                if let Some(sec) = &self.export_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Start => {
+                if let Some(sec) = &self.start_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Element => {
+                if let Some(sec) = &self.element_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::DataCount => {
+                if let Some(sec) = &self.data_count_section {
+                    sec.write_into(wr)?;
+                }
+            }
+            SectionKind::Code => {
+                if let Some(sec) = &self.code_section {
+                    sec.write_into(wr, self.original.as_deref())?;
+                }
+            }
+            SectionKind::Data => {
+                if let Some(sec) = &self.data_section {
+                    sec.write_into(wr)?;
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// Writes a section with `write`, substituting its original bytes if it has not been modified
+    /// since parsing with [`Module::into_synth_preserving`](crate::parse::Module::into_synth_preserving).
+    fn write_preserving(
+        &self,
+        wr: &mut impl Write,
+        key: SectionKey,
+        write: impl FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    ) -> Result<(), io::Error> {
+        let Some(original) = &self.original else {
+            return write(wr);
+        };
+        let mut buf = Vec::new();
+        write(&mut buf)?;
+        wr.write_all(original.section(key, &buf))
+    }
 
-        for section in &self.custom_sections {
-            section.write_into(&mut wr)?;
+    /// Writes custom sections placed at `placement`, including the name section.
+    fn write_custom_sections(
+        &self,
+        placement: CustomPlacement,
+        name_written: &mut bool,
+        wr: &mut impl Write,
+    ) -> Result<(), io::Error> {
+        let mut write_name = |wr: &mut _| match &self.name_section {
+            Some(sec) if !*name_written && self.name_section_placement == placement => {
+                *name_written = true;
+                self.write_preserving(wr, SectionKey::Name, |mut wr| sec.write_into(&mut wr))
+            }
+            _ => Ok(()),
+        };
+        for (i, section) in self.custom_sections.iter().enumerate() {
+            if section.placement != placement {
+                continue;
+            }
+            if i >= self.name_section_order {
+                write_name(wr)?;
+            }
+            self.write_preserving(wr, SectionKey::Custom(i), |wr| section.write_into(wr))?;
         }
+        write_name(wr)
+    }
 
-        if let Some(sec) = &self.name_section {
-            sec.write_into(wr)?;
+    fn has_section(&self, kind: SectionKind) -> bool {
+        match kind {
+            SectionKind::Type => self.type_section.is_some(),
+            SectionKind::Import => self.import_section.is_some(),
+            SectionKind::Function => self.function_section.is_some(),
+            SectionKind::Table => self.table_section.is_some(),
+            SectionKind::Memory => self.memory_section.is_some(),
+            SectionKind::Tag => self.tag_section.is_some(),
+            SectionKind::Global => self.global_section.is_some(),
+            SectionKind::Export => self.export_section.is_some(),
+            SectionKind::Start => self.start_section.is_some(),
+            SectionKind::Element => self.element_section.is_some(),
+            SectionKind::DataCount => self.data_count_section.is_some(),
+            SectionKind::Code => self.code_section.is_some(),
+            SectionKind::Data => self.data_section.is_some(),
         }
+    }
 
-        Ok(())
+    /// Returns the equivalent placement after the last present section, or [`CustomPlacement::First`]
+    /// if no section precedes it.
+    pub(crate) fn canonical_placement(&self, placement: CustomPlacement) -> CustomPlacement {
+        let CustomPlacement::After(kind) = placement else {
+            return placement;
+        };
+        SectionKind::ALL
+            .into_iter()
+            .rev()
+            .skip_while(|x| *x > kind)
+            .find(|x| self.has_section(*x))
+            .map_or(CustomPlacement::First, CustomPlacement::After)
+    }
+
+    pub fn custom_sections(&self) -> &[SynthCustomSection] {
+        self.custom_sections.as_ref()
+    }
+
+    pub fn custom_sections_mut(&mut self) -> &mut Vec<SynthCustomSection> {
+        &mut self.custom_sections
     }
 }
@@ -0,0 +1,672 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    instructions::{Expression, Instruction},
+    synth::{
+        sections::{
+            CustomPlacement, SynthCode, SynthCodeSection, SynthData, SynthDataCountSection,
+            SynthDataSection, SynthExport, SynthExportDescription, SynthExportSection,
+            SynthFunctionSection, SynthGlobal, SynthGlobalSection, SynthImport,
+            SynthImportDescription, SynthImportSection, SynthMemorySection, SynthStartSection,
+            SynthTableSection, SynthTagSection, SynthTypeSection,
+        },
+        SynthModule,
+    },
+    wasm_types::{
+        FuncType, GlobalType, MemType, ResultType, SubType, TableType, TagType, ValueType,
+    },
+    Error,
+};
+
+macro_rules! index_handle {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        ///
+        /// The handle is only valid for the [`ModuleBuilder`] which returned it.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name {
+            builder: BuilderId,
+            index: u32,
+        }
+
+        impl $name {
+            /// Returns the index in the module.
+            pub fn index(self) -> u32 {
+                self.index
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(x: $name) -> u32 {
+                x.index
+            }
+        }
+    };
+}
+
+index_handle!(
+    /// Index of a function type added by [`ModuleBuilder::func_type`].
+    TypeIdx
+);
+index_handle!(
+    /// Index of an imported or defined function.
+    FuncIdx
+);
+index_handle!(
+    /// Index of an imported or defined table.
+    TableIdx
+);
+index_handle!(
+    /// Index of an imported or defined memory.
+    MemoryIdx
+);
+index_handle!(
+    /// Index of an imported or defined global.
+    GlobalIdx
+);
+index_handle!(
+    /// Index of a data segment.
+    DataIdx
+);
+index_handle!(
+    /// Index of an imported or defined exception tag.
+    TagIdx
+);
+
+/// A function, table, memory, global or tag exported by [`ModuleBuilder::export`].
+#[derive(Clone, Debug)]
+pub struct ExportItem {
+    builder: BuilderId,
+    desc: SynthExportDescription,
+}
+
+macro_rules! export_item {
+    ($name:ident, $variant:ident) => {
+        impl From<$name> for ExportItem {
+            fn from(x: $name) -> Self {
+                Self {
+                    builder: x.builder,
+                    desc: SynthExportDescription::$variant(x.index),
+                }
+            }
+        }
+    };
+}
+
+export_item!(FuncIdx, Func);
+export_item!(TableIdx, Table);
+export_item!(MemoryIdx, Mem);
+export_item!(GlobalIdx, Global);
+export_item!(TagIdx, Tag);
+
+/// Number of imported and defined entities of an index space.
+#[derive(Clone, Copy, Debug, Default)]
+struct Count {
+    imported: u32,
+    defined: u32,
+}
+
+impl Count {
+    fn import(&mut self, space: &'static str) -> Result<u32, Error> {
+        if self.defined > 0 {
+            return Err(Error::ImportAfterDefinition(space));
+        }
+        self.imported += 1;
+        Ok(self.imported - 1)
+    }
+
+    fn define(&mut self) -> u32 {
+        self.defined += 1;
+        self.imported + self.defined - 1
+    }
+}
+
+/// Identity of a [`ModuleBuilder`], which handles and function builders are checked against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct BuilderId(u64);
+
+impl Default for BuilderId {
+    fn default() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Builds a [`SynthModule`] from scratch.
+///
+/// Every method adding an entity returns a handle holding its index in the module. As imported
+/// entities precede defined ones in their index space, imports of a kind must be added before
+/// defining any entity of that kind, otherwise [`Error::ImportAfterDefinition`] is returned.
+///
+/// ```
+/// use wasynth::{
+///     instructions::Instruction,
+///     synth::ModuleBuilder,
+///     wasm_types::ValueType,
+/// };
+///
+/// let mut builder = ModuleBuilder::new();
+/// let log_type = builder.func_type([ValueType::I32], []);
+/// let log = builder.import_func("env", "log", log_type).unwrap();
+///
+/// let main_type = builder.func_type([], []);
+/// let mut main = builder.function(main_type);
+/// main.instructions([Instruction::I32Const(42), Instruction::Call(log.index())]);
+/// let main = main.finish(&mut builder);
+/// builder.export("main", main);
+///
+/// let module = builder.build();
+/// module.validate().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    id: BuilderId,
+    types: Vec<FuncType>,
+    imports: Vec<SynthImport>,
+    type_indices: Vec<u32>,
+    codes: Vec<SynthCode>,
+    tables: Vec<TableType>,
+    memories: Vec<MemType>,
+    globals: Vec<SynthGlobal>,
+    tags: Vec<TagType>,
+    exports: Vec<SynthExport>,
+    start: Option<u32>,
+    data: Vec<SynthData>,
+
+    func_count: Count,
+    table_count: Count,
+    memory_count: Count,
+    global_count: Count,
+    tag_count: Count,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function type, or returns the existing one if an identical type was added already.
+    pub fn func_type(
+        &mut self,
+        params: impl IntoIterator<Item = ValueType>,
+        results: impl IntoIterator<Item = ValueType>,
+    ) -> TypeIdx {
+        let ty = FuncType::new(
+            ResultType(params.into_iter().collect()),
+            ResultType(results.into_iter().collect()),
+        );
+        let idx = match self.types.iter().position(|x| *x == ty) {
+            Some(idx) => idx,
+            None => {
+                self.types.push(ty);
+                self.types.len() - 1
+            }
+        };
+        TypeIdx {
+            builder: self.id,
+            index: idx.try_into().expect("too many types"),
+        }
+    }
+
+    fn import(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        description: SynthImportDescription,
+    ) {
+        self.imports.push(SynthImport {
+            module: module.into(),
+            name: name.into(),
+            description,
+        });
+    }
+
+    /// Fails with [`Error::ImportAfterDefinition`] if any function is defined already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a type of this builder.
+    pub fn import_func(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        ty: TypeIdx,
+    ) -> Result<FuncIdx, Error> {
+        self.check(ty.builder);
+        let idx = self.func_count.import("function")?;
+        self.import(module, name, SynthImportDescription::Type(ty.index));
+        Ok(FuncIdx {
+            builder: self.id,
+            index: idx,
+        })
+    }
+
+    /// Fails with [`Error::ImportAfterDefinition`] if any table is defined already.
+    pub fn import_table(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        ty: TableType,
+    ) -> Result<TableIdx, Error> {
+        let idx = self.table_count.import("table")?;
+        self.import(module, name, SynthImportDescription::Table(ty));
+        Ok(TableIdx {
+            builder: self.id,
+            index: idx,
+        })
+    }
+
+    /// Fails with [`Error::ImportAfterDefinition`] if any memory is defined already.
+    pub fn import_memory(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        ty: MemType,
+    ) -> Result<MemoryIdx, Error> {
+        let idx = self.memory_count.import("memory")?;
+        self.import(module, name, SynthImportDescription::Memory(ty));
+        Ok(MemoryIdx {
+            builder: self.id,
+            index: idx,
+        })
+    }
+
+    /// Fails with [`Error::ImportAfterDefinition`] if any global is defined already.
+    pub fn import_global(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        ty: GlobalType,
+    ) -> Result<GlobalIdx, Error> {
+        let idx = self.global_count.import("global")?;
+        self.import(module, name, SynthImportDescription::Global(ty));
+        Ok(GlobalIdx {
+            builder: self.id,
+            index: idx,
+        })
+    }
+
+    /// Imports an exception tag whose exceptions carry the parameters of `ty`.
+    ///
+    /// Fails with [`Error::ImportAfterDefinition`] if any tag is defined already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a type of this builder.
+    pub fn import_tag(
+        &mut self,
+        module: impl Into<String>,
+        name: impl Into<String>,
+        ty: TypeIdx,
+    ) -> Result<TagIdx, Error> {
+        self.check(ty.builder);
+        let idx = self.tag_count.import("tag")?;
+        self.import(
+            module,
+            name,
+            SynthImportDescription::Tag(TagType::new(ty.index)),
+        );
+        Ok(TagIdx {
+            builder: self.id,
+            index: idx,
+        })
+    }
+
+    /// Defines a function of type `ty`, returning a builder of its body.
+    ///
+    /// The index of the function is reserved immediately, so the body may refer to the function
+    /// itself or to functions defined later. Until [`FunctionBuilder::finish`] is called, the
+    /// function has no locals and an empty body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a type of this builder.
+    pub fn function(&mut self, ty: TypeIdx) -> FunctionBuilder {
+        self.check(ty.builder);
+        let params = self.types[ty.index as usize].param().0.len() as u32;
+        let idx = self.func_count.define();
+        self.type_indices.push(ty.index);
+        self.codes.push(SynthCode {
+            locals: Vec::new(),
+            func_expr: Expression(Vec::new()),
+        });
+        FunctionBuilder {
+            builder: self.id,
+            idx: FuncIdx {
+                builder: self.id,
+                index: idx,
+            },
+            params,
+            locals: Vec::new(),
+            instrs: Vec::new(),
+        }
+    }
+
+    pub fn table(&mut self, ty: TableType) -> TableIdx {
+        self.tables.push(ty);
+        TableIdx {
+            builder: self.id,
+            index: self.table_count.define(),
+        }
+    }
+
+    pub fn memory(&mut self, ty: MemType) -> MemoryIdx {
+        self.memories.push(ty);
+        MemoryIdx {
+            builder: self.id,
+            index: self.memory_count.define(),
+        }
+    }
+
+    /// Defines a global initialized with the constant expression `init`.
+    pub fn global(
+        &mut self,
+        ty: GlobalType,
+        init: impl IntoIterator<Item = Instruction>,
+    ) -> GlobalIdx {
+        self.globals.push(SynthGlobal {
+            ty,
+            init: Expression(init.into_iter().collect()),
+        });
+        GlobalIdx {
+            builder: self.id,
+            index: self.global_count.define(),
+        }
+    }
+
+    /// Defines an exception tag whose exceptions carry the parameters of `ty`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a type of this builder.
+    pub fn tag(&mut self, ty: TypeIdx) -> TagIdx {
+        self.check(ty.builder);
+        self.tags.push(TagType::new(ty.index));
+        TagIdx {
+            builder: self.id,
+            index: self.tag_count.define(),
+        }
+    }
+
+    /// Exports a function, table, memory, global or tag as `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `item` is not an entity of this builder.
+    pub fn export(&mut self, name: impl Into<String>, item: impl Into<ExportItem>) {
+        let item = item.into();
+        self.check(item.builder);
+        self.exports.push(SynthExport {
+            name: name.into(),
+            desc: item.desc,
+        });
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `func` is not a function of this builder.
+    pub fn start(&mut self, func: FuncIdx) {
+        self.check(func.builder);
+        self.start = Some(func.index);
+    }
+
+    /// Adds a data segment copied into `memory` at the constant expression `offset` on
+    /// instantiation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `memory` is not a memory of this builder.
+    pub fn active_data(
+        &mut self,
+        memory: MemoryIdx,
+        offset: impl IntoIterator<Item = Instruction>,
+        init: impl Into<Vec<u8>>,
+    ) -> DataIdx {
+        self.check(memory.builder);
+        self.add_data(SynthData::Active {
+            init: init.into(),
+            memory_index: memory.index,
+            offset: Expression(offset.into_iter().collect()),
+        })
+    }
+
+    /// Adds a data segment used by `memory.init` only.
+    pub fn passive_data(&mut self, init: impl Into<Vec<u8>>) -> DataIdx {
+        self.add_data(SynthData::Passive(init.into()))
+    }
+
+    fn check(&self, builder: BuilderId) {
+        assert_eq!(
+            builder, self.id,
+            "handle was not created by this module builder"
+        );
+    }
+
+    fn add_data(&mut self, data: SynthData) -> DataIdx {
+        self.data.push(data);
+        DataIdx {
+            builder: self.id,
+            index: (self.data.len() - 1)
+                .try_into()
+                .expect("too many data segments"),
+        }
+    }
+
+    /// Builds the module. The data count section is added if any function body refers to a data
+    /// segment.
+    pub fn build(self) -> SynthModule {
+        let data_count_section =
+            self.codes
+                .iter()
+                .any(SynthCode::refers_to_data)
+                .then_some(SynthDataCountSection {
+                    data_count: self.data.len() as u32,
+                });
+
+        SynthModule {
+            type_section: (!self.types.is_empty()).then_some(SynthTypeSection {
+                types: self.types.into_iter().map(SubType::from).collect(),
+                rec_groups: Vec::new(),
+            }),
+            import_section: (!self.imports.is_empty()).then_some(SynthImportSection {
+                imports: self.imports,
+            }),
+            function_section: (!self.type_indices.is_empty()).then_some(SynthFunctionSection {
+                type_indices: self.type_indices,
+            }),
+            table_section: (!self.tables.is_empty()).then_some(SynthTableSection {
+                tables: self.tables,
+            }),
+            memory_section: (!self.memories.is_empty()).then_some(SynthMemorySection {
+                memories: self.memories,
+            }),
+            tag_section: (!self.tags.is_empty()).then_some(SynthTagSection { tags: self.tags }),
+            global_section: (!self.globals.is_empty()).then_some(SynthGlobalSection {
+                globals: self.globals,
+            }),
+            export_section: (!self.exports.is_empty()).then_some(SynthExportSection {
+                exports: self.exports,
+            }),
+            start_section: self.start.map(|start| SynthStartSection { start }),
+            element_section: None,
+            data_count_section,
+            code_section: (!self.codes.is_empty())
+                .then_some(SynthCodeSection { codes: self.codes }),
+            data_section: (!self.data.is_empty()).then_some(SynthDataSection {
+                all_data: self.data,
+            }),
+            custom_sections: Vec::new(),
+            name_section: None,
+            name_section_placement: CustomPlacement::default(),
+            name_section_order: 0,
+            original: None,
+        }
+    }
+}
+
+/// Builds locals and the body of a function defined by [`ModuleBuilder::function`].
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    builder: BuilderId,
+    idx: FuncIdx,
+    params: u32,
+    locals: Vec<ValueType>,
+    instrs: Vec<Instruction>,
+}
+
+impl FunctionBuilder {
+    /// Returns the index of the function being built.
+    pub fn index(&self) -> FuncIdx {
+        self.idx
+    }
+
+    /// Adds a local of type `ty`, returning its local index after the parameters.
+    pub fn local(&mut self, ty: ValueType) -> u32 {
+        self.locals.push(ty);
+        self.params + self.locals.len() as u32 - 1
+    }
+
+    /// Appends an instruction to the body.
+    pub fn instruction(&mut self, instr: Instruction) -> &mut Self {
+        self.instrs.push(instr);
+        self
+    }
+
+    /// Appends instructions to the body.
+    pub fn instructions(&mut self, instrs: impl IntoIterator<Item = Instruction>) -> &mut Self {
+        self.instrs.extend(instrs);
+        self
+    }
+
+    /// Stores the locals and body into the function of `builder`, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function was not created by `builder`.
+    pub fn finish(self, builder: &mut ModuleBuilder) -> FuncIdx {
+        assert_eq!(
+            self.builder, builder.id,
+            "function was not created by this module builder"
+        );
+        let code = &mut builder.codes[(self.idx.index - builder.func_count.imported) as usize];
+        code.locals = self.locals;
+        code.func_expr = Expression(self.instrs);
+        self.idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModuleBuilder;
+    use crate::{
+        instructions::{BlockType, Instruction, MemArg},
+        wasm_types::{GlobalType, Limits, MemType, ValueType},
+        Error,
+    };
+
+    #[test]
+    fn test_builder() {
+        let mut builder = ModuleBuilder::new();
+        let unary = builder.func_type([ValueType::I32], [ValueType::I32]);
+        assert_eq!(builder.func_type([ValueType::I32], [ValueType::I32]), unary);
+        let imported = builder.import_func("env", "double", unary).unwrap();
+        let base = builder
+            .import_global("env", "base", GlobalType::new(ValueType::I32, false))
+            .unwrap();
+        let memory = builder.memory(MemType::new(Limits::Unbounded { min: 1 }));
+        let counter = builder.global(
+            GlobalType::new(ValueType::I32, true),
+            [Instruction::I32Const(0)],
+        );
+        let data = builder.passive_data(*b"\x2a\0\0\0");
+        builder.active_data(memory, [Instruction::GlobalGet(base.index())], "abc");
+
+        // defined before `sum` to check forward references
+        let void = builder.func_type([], []);
+        let mut init = builder.function(void);
+        let mut sum = builder.function(unary);
+        let acc = sum.local(ValueType::I32);
+        assert_eq!(acc, 1);
+        sum.instructions([
+            Instruction::LocalGet(0),
+            Instruction::If(
+                BlockType::Empty,
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::I32Const(1),
+                    Instruction::I32Sub,
+                    Instruction::Call(sum.index().into()),
+                    Instruction::Call(imported.into()),
+                    Instruction::LocalSet(acc),
+                ],
+                None,
+            ),
+            Instruction::LocalGet(acc),
+            Instruction::GlobalGet(counter.index()),
+            Instruction::I32Add,
+        ]);
+        let sum = sum.finish(&mut builder);
+        init.instructions([
+            Instruction::I32Const(0),
+            Instruction::I32Const(0),
+            Instruction::I32Const(4),
+            Instruction::MemoryInit(data.index(), memory.index()),
+            Instruction::I32Const(0),
+            Instruction::I32Load(MemArg {
+                align: 2,
+                memory: memory.index(),
+                offset: 0,
+            }),
+            Instruction::Call(sum.index()),
+            Instruction::GlobalSet(counter.index()),
+        ]);
+        let init = init.finish(&mut builder);
+        builder.start(init);
+        builder.export("sum", sum);
+        builder.export("memory", memory);
+
+        assert_eq!((imported.index(), init.index(), sum.index()), (0, 1, 2));
+        assert_eq!((base.index(), counter.index()), (0, 1));
+
+        let module = builder.build();
+        assert!(module.data_count_section.is_some());
+        module.validate().expect("built module is invalid");
+        let mut buf = Vec::new();
+        module.write_into(&mut buf).unwrap();
+        wasmparser::validate(&buf).expect("wasmparser validation fail");
+    }
+
+    #[test]
+    fn test_import_after_definition() {
+        let mut builder = ModuleBuilder::new();
+        let ty = builder.func_type([], []);
+        builder.function(ty);
+        let err = builder.import_func("env", "f", ty).unwrap_err();
+        assert!(matches!(err, Error::ImportAfterDefinition("function")));
+        // other index spaces are not affected
+        builder
+            .import_global("env", "g", GlobalType::new(ValueType::I32, false))
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "function was not created by this module builder")]
+    fn test_finish_other_builder() {
+        let mut builder = ModuleBuilder::new();
+        let mut other = ModuleBuilder::new();
+        let ty = builder.func_type([], []);
+        builder.function(ty);
+        let ty = other.func_type([], []);
+        other.function(ty).finish(&mut builder);
+    }
+
+    #[test]
+    #[should_panic(expected = "handle was not created by this module builder")]
+    fn test_handle_other_builder() {
+        let mut builder = ModuleBuilder::new();
+        let mut other = ModuleBuilder::new();
+        builder.func_type([], []);
+        let ty = other.func_type([ValueType::I32], []);
+        builder.function(ty);
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt::Display;
+
+use crate::{
+    instructions::Expression,
+    synth::{
+        sections::{
+            SynthData, SynthElemInit, SynthElemKind, SynthElemMode, SynthExportDescription,
+            SynthImportDescription, SynthNameAssoc,
+        },
+        SynthModule,
+    },
+    wasm_types::ValueType,
+    Error,
+};
+
+/// A WebAssembly index space.
+///
+/// <https://webassembly.github.io/spec/core/syntax/modules.html#indices>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexSpace {
+    Type,
+    Function,
+    Table,
+    Memory,
+    Global,
+    Element,
+    Data,
+    Local,
+    /// Exception tags of the exception handling proposal.
+    Tag,
+}
+
+impl Display for IndexSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IndexSpace::Type => "type",
+            IndexSpace::Function => "function",
+            IndexSpace::Table => "table",
+            IndexSpace::Memory => "memory",
+            IndexSpace::Global => "global",
+            IndexSpace::Element => "element segment",
+            IndexSpace::Data => "data segment",
+            IndexSpace::Local => "local",
+            IndexSpace::Tag => "tag",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// An index which points past the end of its index space, found by [`SynthModule::check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DanglingReference {
+    /// Name of the section holding the reference.
+    pub section: &'static str,
+    /// Position of the entity holding the reference in its section, e.g. `2` for the third
+    /// export of the export section. For the name section this is the position in the subsection.
+    pub entity: usize,
+    pub space: IndexSpace,
+    pub index: u32,
+}
+
+impl Display for DanglingReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} section entity {} refers to unknown {} {}",
+            self.section, self.entity, self.space, self.index
+        )
+    }
+}
+
+/// Sizes of every index space of a module.
+#[derive(Clone, Copy, Debug, Default)]
+struct IndexSpaceSizes {
+    types: usize,
+    functions: usize,
+    tables: usize,
+    memories: usize,
+    globals: usize,
+    elements: usize,
+    data: usize,
+    tags: usize,
+}
+
+impl IndexSpaceSizes {
+    fn contains(&self, space: IndexSpace, index: u32) -> bool {
+        let len = match space {
+            IndexSpace::Type => self.types,
+            IndexSpace::Function => self.functions,
+            IndexSpace::Table => self.tables,
+            IndexSpace::Memory => self.memories,
+            IndexSpace::Global => self.globals,
+            IndexSpace::Element => self.elements,
+            IndexSpace::Data => self.data,
+            IndexSpace::Tag => self.tags,
+            // locals are checked per function in `SynthModule::check`
+            IndexSpace::Local => return true,
+        };
+        (index as usize) < len
+    }
+}
+
+struct Checker {
+    sizes: IndexSpaceSizes,
+    dangling: Vec<DanglingReference>,
+}
+
+impl Checker {
+    fn check(&mut self, section: &'static str, entity: usize, space: IndexSpace, index: u32) {
+        if !self.sizes.contains(space, index) {
+            self.dangling.push(DanglingReference {
+                section,
+                entity,
+                space,
+                index,
+            });
+        }
+    }
+
+    fn check_expr(&mut self, section: &'static str, entity: usize, expr: &Expression) {
+        expr.visit_indices(&mut |space, index| self.check(section, entity, space, index));
+    }
+
+    fn check_names(&mut self, space: IndexSpace, assocs: Option<&[SynthNameAssoc]>) {
+        for (i, assoc) in assocs.unwrap_or_default().iter().enumerate() {
+            self.check("name", i, space, assoc.idx);
+        }
+    }
+}
+
+impl SynthModule {
+    /// Checks that every index in the module refers to an existing entity of its index space.
+    ///
+    /// Unlike [`SynthModule::validate`], this does not type-check function bodies, but reports
+    /// every dangling reference at once with [`Error::DanglingReferences`].
+    pub fn check(&self) -> Result<(), Error> {
+        let mut sizes = IndexSpaceSizes {
+            types: self.type_section.as_ref().map_or(0, |x| x.types.len()),
+            elements: self
+                .element_section
+                .as_ref()
+                .map_or(0, |x| x.elements.len()),
+            data: self.data_section.as_ref().map_or(0, |x| x.all_data.len()),
+            ..Default::default()
+        };
+        if let Some(imsec) = &self.import_section {
+            for import in &imsec.imports {
+                match import.description {
+                    SynthImportDescription::Type(_) => sizes.functions += 1,
+                    SynthImportDescription::Table(_) => sizes.tables += 1,
+                    SynthImportDescription::Memory(_) => sizes.memories += 1,
+                    SynthImportDescription::Global(_) => sizes.globals += 1,
+                    SynthImportDescription::Tag(_) => sizes.tags += 1,
+                }
+            }
+        }
+        sizes.functions += self
+            .function_section
+            .as_ref()
+            .map_or(0, |x| x.type_indices.len());
+        sizes.tables += self.table_section.as_ref().map_or(0, |x| x.tables.len());
+        sizes.memories += self.memory_section.as_ref().map_or(0, |x| x.memories.len());
+        sizes.globals += self.global_section.as_ref().map_or(0, |x| x.globals.len());
+        sizes.tags += self.tag_section.as_ref().map_or(0, |x| x.tags.len());
+
+        let mut checker = Checker {
+            sizes,
+            dangling: Vec::new(),
+        };
+
+        if let Some(tysec) = &self.type_section {
+            for (i, ty) in tysec.types.iter().enumerate() {
+                ty.visit_type_indices(&mut |x| checker.check("type", i, IndexSpace::Type, x));
+            }
+        }
+
+        if let Some(imsec) = &self.import_section {
+            for (i, import) in imsec.imports.iter().enumerate() {
+                let tyidx = match import.description {
+                    SynthImportDescription::Type(x) => Some(x),
+                    SynthImportDescription::Tag(x) => Some(x.type_index()),
+                    SynthImportDescription::Table(x) => x.element().type_index(),
+                    SynthImportDescription::Global(x) => x.ty().type_index(),
+                    SynthImportDescription::Memory(_) => None,
+                };
+                if let Some(tyidx) = tyidx {
+                    checker.check("import", i, IndexSpace::Type, tyidx);
+                }
+            }
+        }
+
+        if let Some(funcsec) = &self.function_section {
+            for (i, tyidx) in funcsec.type_indices.iter().enumerate() {
+                checker.check("function", i, IndexSpace::Type, *tyidx);
+            }
+        }
+
+        if let Some(tagsec) = &self.tag_section {
+            for (i, tag) in tagsec.tags.iter().enumerate() {
+                checker.check("tag", i, IndexSpace::Type, tag.type_index());
+            }
+        }
+
+        if let Some(tabsec) = &self.table_section {
+            for (i, table) in tabsec.tables.iter().enumerate() {
+                if let Some(tyidx) = table.element().type_index() {
+                    checker.check("table", i, IndexSpace::Type, tyidx);
+                }
+            }
+        }
+
+        if let Some(glsec) = &self.global_section {
+            for (i, global) in glsec.globals.iter().enumerate() {
+                if let Some(tyidx) = global.ty.ty().type_index() {
+                    checker.check("global", i, IndexSpace::Type, tyidx);
+                }
+                checker.check_expr("global", i, &global.init);
+            }
+        }
+
+        if let Some(exsec) = &self.export_section {
+            for (i, export) in exsec.exports.iter().enumerate() {
+                let (space, index) = match export.desc {
+                    SynthExportDescription::Func(x) => (IndexSpace::Function, x),
+                    SynthExportDescription::Table(x) => (IndexSpace::Table, x),
+                    SynthExportDescription::Mem(x) => (IndexSpace::Memory, x),
+                    SynthExportDescription::Global(x) => (IndexSpace::Global, x),
+                    SynthExportDescription::Tag(x) => (IndexSpace::Tag, x),
+                };
+                checker.check("export", i, space, index);
+            }
+        }
+
+        if let Some(stsec) = &self.start_section {
+            checker.check("start", 0, IndexSpace::Function, stsec.start);
+        }
+
+        if let Some(elsec) = &self.element_section {
+            for (i, elem) in elsec.elements.iter().enumerate() {
+                if let SynthElemKind::ReferenceType(rt) = elem.kind {
+                    if let Some(tyidx) = rt.type_index() {
+                        checker.check("element", i, IndexSpace::Type, tyidx);
+                    }
+                }
+                if let SynthElemMode::Active { table, offset } = &elem.mode {
+                    checker.check("element", i, IndexSpace::Table, *table);
+                    checker.check_expr("element", i, offset);
+                }
+                match &elem.init {
+                    SynthElemInit::FuncIndices(x) => {
+                        for funcidx in x {
+                            checker.check("element", i, IndexSpace::Function, *funcidx);
+                        }
+                    }
+                    SynthElemInit::Expressions(x) => {
+                        for expr in x {
+                            checker.check_expr("element", i, expr);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(codesec) = &self.code_section {
+            let types = self.type_section.as_ref().map(|x| &x.types[..]);
+            let type_indices = self.function_section.as_ref().map(|x| &x.type_indices[..]);
+            for (i, code) in codesec.codes.iter().enumerate() {
+                // if the function type cannot be resolved it was already reported above, so
+                // local indices are checked against the declared locals only
+                let params = type_indices
+                    .and_then(|x| x.get(i))
+                    .and_then(|&tyidx| types?.get(tyidx as usize)?.func_type())
+                    .map_or(0, |x| x.param.0.len());
+                let locals = params + code.locals.len();
+                for tyidx in code.locals.iter().filter_map(ValueType::type_index) {
+                    checker.check("code", i, IndexSpace::Type, tyidx);
+                }
+                code.func_expr.visit_indices(&mut |space, index| {
+                    if space == IndexSpace::Local {
+                        if index as usize >= locals {
+                            checker.dangling.push(DanglingReference {
+                                section: "code",
+                                entity: i,
+                                space,
+                                index,
+                            });
+                        }
+                    } else {
+                        checker.check("code", i, space, index);
+                    }
+                });
+            }
+        }
+
+        if let Some(datasec) = &self.data_section {
+            for (i, data) in datasec.all_data.iter().enumerate() {
+                if let SynthData::Active {
+                    memory_index,
+                    offset,
+                    ..
+                } = data
+                {
+                    checker.check("data", i, IndexSpace::Memory, *memory_index);
+                    checker.check_expr("data", i, offset);
+                }
+            }
+        }
+
+        if let Some(namesec) = &self.name_section {
+            checker.check_names(IndexSpace::Function, namesec.function_names());
+            for space_names in [namesec.local_names(), namesec.label_names()] {
+                for (i, indirect) in space_names.unwrap_or_default().iter().enumerate() {
+                    checker.check("name", i, IndexSpace::Function, indirect.idx);
+                }
+            }
+            checker.check_names(IndexSpace::Type, namesec.type_names());
+            checker.check_names(IndexSpace::Table, namesec.table_names());
+            checker.check_names(IndexSpace::Memory, namesec.memory_names());
+            checker.check_names(IndexSpace::Global, namesec.global_names());
+            checker.check_names(IndexSpace::Element, namesec.element_segment_names());
+            checker.check_names(IndexSpace::Data, namesec.data_segment_names());
+            checker.check_names(IndexSpace::Tag, namesec.tag_names());
+            for (i, indirect) in namesec.field_names().unwrap_or_default().iter().enumerate() {
+                checker.check("name", i, IndexSpace::Type, indirect.idx);
+            }
+        }
+
+        if checker.dangling.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DanglingReferences(checker.dangling))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse::Module,
+        synth::{DanglingReference, IndexSpace},
+        Error,
+    };
+
+    fn check_wat(wat_s: &str) -> Result<(), Error> {
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+        Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth")
+            .check()
+    }
+
+    #[test]
+    fn test_no_dangling() {
+        check_wat(
+            r#"(module
+                (import "env" "g" (func $g (param i32)))
+                (memory 1)
+                (global $x (mut i32) (i32.const 0))
+                (func $f (param i32) (local i64)
+                    local.get 0
+                    call $g
+                    global.get $x
+                    i32.load
+                    drop
+                )
+                (export "f" (func $f))
+                (data (i32.const 0) "abc")
+            )"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_dangling() {
+        let err = check_wat(
+            r#"(module
+                (func (param i32)
+                    local.get 2
+                    call 3
+                )
+                (export "f" (func 1))
+            )"#,
+        )
+        .unwrap_err();
+        let Error::DanglingReferences(dangling) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(
+            dangling,
+            vec![
+                DanglingReference {
+                    section: "export",
+                    entity: 0,
+                    space: IndexSpace::Function,
+                    index: 1,
+                },
+                DanglingReference {
+                    section: "code",
+                    entity: 0,
+                    space: IndexSpace::Local,
+                    index: 2,
+                },
+                DanglingReference {
+                    section: "code",
+                    entity: 0,
+                    space: IndexSpace::Function,
+                    index: 3,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use crate::synth::sections::{SectionKind, SynthCode};
+
+/// A section of a module, as written by [`SynthModule::write_into`](super::SynthModule::write_into).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SectionKey {
+    Known(SectionKind),
+    Name,
+    /// A custom section, by its position in the custom sections of the module.
+    Custom(usize),
+}
+
+/// The range of an entity in the module binary, and its encoding right after parsing.
+#[derive(Clone, Debug)]
+struct Original {
+    range: Range<usize>,
+    encoded: Box<[u8]>,
+}
+
+/// Original encodings of sections and function bodies of a parsed module, as ranges of the module
+/// binary.
+///
+/// A section or a function body is written with its original bytes if re-encoding it yields the
+/// same bytes as it did right after parsing, i.e. if it has not been modified. Function bodies
+/// are identified by their position in the code section. Entries whose original bytes are already
+/// in the normalized encoding are not recorded, so only the encodings of the other entries are
+/// kept for the comparison.
+#[derive(Clone, Debug)]
+pub(crate) struct OriginalBytes {
+    binary: Arc<[u8]>,
+    sections: HashMap<SectionKey, Original>,
+    codes: HashMap<usize, Original>,
+}
+
+impl OriginalBytes {
+    pub(crate) fn new(binary: Arc<[u8]>) -> Self {
+        Self {
+            binary,
+            sections: HashMap::new(),
+            codes: HashMap::new(),
+        }
+    }
+
+    /// Records the original bytes at `range` of the section `key`, which is encoded as `encoded`.
+    pub(crate) fn insert_section(&mut self, key: SectionKey, encoded: &[u8], range: Range<usize>) {
+        if let Some(original) = self.original(encoded, range) {
+            self.sections.insert(key, original);
+        }
+    }
+
+    /// Records the original bytes at `range` of the function body at `index` of the code section,
+    /// which is encoded as `encoded`.
+    pub(crate) fn insert_code(&mut self, index: usize, encoded: &[u8], range: Range<usize>) {
+        if let Some(original) = self.original(encoded, range) {
+            self.codes.insert(index, original);
+        }
+    }
+
+    fn original(&self, encoded: &[u8], range: Range<usize>) -> Option<Original> {
+        (self.binary[range.clone()] != *encoded).then(|| Original {
+            range,
+            encoded: encoded.into(),
+        })
+    }
+
+    /// Returns the original bytes of the section `key` if it is encoded as `encoded` still, or
+    /// `encoded` itself.
+    pub(crate) fn section<'a>(&'a self, key: SectionKey, encoded: &'a [u8]) -> &'a [u8] {
+        match self.sections.get(&key) {
+            Some(original) if *original.encoded == *encoded => &self.binary[original.range.clone()],
+            _ => encoded,
+        }
+    }
+
+    /// Returns the original bytes of the function body at `index` of the code section, if it has
+    /// not been modified.
+    pub(crate) fn code(&self, index: usize, code: &SynthCode) -> Option<&[u8]> {
+        let original = self.codes.get(&index)?;
+        let mut encoded = Vec::with_capacity(original.encoded.len());
+        code.write_into(&mut encoded)
+            .expect("writing into a vector cannot fail");
+        (*encoded == *original.encoded).then(|| &self.binary[original.range.clone()])
+    }
+}
@@ -0,0 +1,631 @@
+use std::ops::Range;
+
+use crate::{
+    instructions::Expression,
+    synth::{
+        sections::{
+            SynthData, SynthElemInit, SynthElemKind, SynthElemMode, SynthExportDescription,
+            SynthImportDescription, SynthIndirectNameAssoc, SynthNameAssoc,
+        },
+        DanglingReference, IndexSpace, SynthModule,
+    },
+    validate::ValidationError,
+    wasm_types::ValueType,
+    Error,
+};
+
+struct Remapper<'a> {
+    space: IndexSpace,
+    remap: &'a mut dyn FnMut(u32) -> Option<u32>,
+    dangling: Vec<DanglingReference>,
+}
+
+impl<'a> Remapper<'a> {
+    fn index(&mut self, section: &'static str, entity: usize, space: IndexSpace, index: &mut u32) {
+        if space != self.space {
+            return;
+        }
+        match (self.remap)(*index) {
+            Some(x) => *index = x,
+            None => self.dangling.push(DanglingReference {
+                section,
+                entity,
+                space,
+                index: *index,
+            }),
+        }
+    }
+
+    fn expr(&mut self, section: &'static str, entity: usize, expr: &mut Expression) {
+        expr.visit_indices_mut(&mut |space, index| self.index(section, entity, space, index));
+    }
+
+    /// Remaps the index of a name section entry, returning `false` if the entity is removed.
+    fn name(&mut self, space: IndexSpace, index: &mut u32) -> bool {
+        if space != self.space {
+            return true;
+        }
+        match (self.remap)(*index) {
+            Some(x) => {
+                *index = x;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remaps name map entries, dropping entries of removed entities.
+    fn names(&mut self, space: IndexSpace, assocs: &mut Option<Vec<SynthNameAssoc>>) {
+        if let Some(assocs) = assocs {
+            assocs.retain_mut(|assoc| self.name(space, &mut assoc.idx));
+        }
+    }
+
+    /// Remaps the outer indices of indirect name map entries, dropping entries of removed
+    /// entities. Indices of the inner name maps, e.g. locals and labels, are not module index
+    /// spaces and are kept.
+    fn indirect_names(
+        &mut self,
+        space: IndexSpace,
+        indassocs: &mut Option<Vec<SynthIndirectNameAssoc>>,
+    ) {
+        if let Some(indassocs) = indassocs {
+            indassocs.retain_mut(|indassoc| self.name(space, &mut indassoc.idx));
+        }
+    }
+
+    /// Remaps the names of locals of the function `func`, dropping entries of removed locals.
+    fn local_names(&mut self, func: u32, indassocs: &mut Option<Vec<SynthIndirectNameAssoc>>) {
+        for indassoc in indassocs.iter_mut().flatten().filter(|x| x.idx == func) {
+            indassoc
+                .name_map
+                .retain_mut(|assoc| self.name(IndexSpace::Local, &mut assoc.idx));
+        }
+    }
+}
+
+/// Remaps the ranges of rec groups to span the remaining types of each group, dropping groups
+/// whose types are all removed.
+fn remap_rec_groups(groups: &mut Vec<Range<u32>>, remap: &mut dyn FnMut(u32) -> Option<u32>) {
+    *groups = groups
+        .iter()
+        .filter_map(|group| {
+            let mut indices = group.clone().filter_map(&mut *remap);
+            let first = indices.next()?;
+            let last = indices.last().unwrap_or(first);
+            Some(first..last + 1)
+        })
+        .collect();
+    groups.sort_by_key(|x| x.start);
+}
+
+impl SynthModule {
+    /// Rewrites every reference into the index space `space`.
+    ///
+    /// `remap` returns the new index of an entity, or `None` if it is removed. This only rewrites
+    /// references; the entities themselves should be inserted or removed by the caller.
+    ///
+    /// Remapping types also remaps the ranges of
+    /// [`SynthTypeSection::rec_groups`](super::sections::SynthTypeSection::rec_groups): each group
+    /// spans the remaining types of it, so `remap` should keep the order of types within a group.
+    ///
+    /// Name section entries of removed entities are dropped. Other references to removed entities
+    /// are left untouched and reported with [`Error::DanglingReferences`], after rewriting all
+    /// other references.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `space` is [`IndexSpace::Local`], as each function has its own local index space.
+    /// Use [`SynthModule::insert_locals`] and [`SynthModule::remove_locals`] instead.
+    pub fn remap_indices(
+        &mut self,
+        space: IndexSpace,
+        mut remap: impl FnMut(u32) -> Option<u32>,
+    ) -> Result<(), Error> {
+        assert_ne!(
+            space,
+            IndexSpace::Local,
+            "local indices cannot be remapped across functions"
+        );
+        let mut remapper = Remapper {
+            space,
+            remap: &mut remap,
+            dangling: Vec::new(),
+        };
+
+        if let Some(tysec) = &mut self.type_section {
+            for (i, ty) in tysec.types.iter_mut().enumerate() {
+                ty.visit_type_indices_mut(&mut |x| remapper.index("type", i, IndexSpace::Type, x));
+            }
+            if space == IndexSpace::Type {
+                remap_rec_groups(&mut tysec.rec_groups, &mut *remapper.remap);
+            }
+        }
+
+        if let Some(imsec) = &mut self.import_section {
+            for (i, import) in imsec.imports.iter_mut().enumerate() {
+                let tyidx = match &mut import.description {
+                    SynthImportDescription::Type(x) => Some(x),
+                    SynthImportDescription::Tag(x) => Some(x.type_index_mut()),
+                    SynthImportDescription::Table(x) => x.type_index_mut(),
+                    SynthImportDescription::Global(x) => x.type_index_mut(),
+                    SynthImportDescription::Memory(_) => None,
+                };
+                if let Some(tyidx) = tyidx {
+                    remapper.index("import", i, IndexSpace::Type, tyidx);
+                }
+            }
+        }
+
+        if let Some(funcsec) = &mut self.function_section {
+            for (i, tyidx) in funcsec.type_indices.iter_mut().enumerate() {
+                remapper.index("function", i, IndexSpace::Type, tyidx);
+            }
+        }
+
+        if let Some(tagsec) = &mut self.tag_section {
+            for (i, tag) in tagsec.tags.iter_mut().enumerate() {
+                remapper.index("tag", i, IndexSpace::Type, tag.type_index_mut());
+            }
+        }
+
+        if let Some(tabsec) = &mut self.table_section {
+            for (i, table) in tabsec.tables.iter_mut().enumerate() {
+                if let Some(tyidx) = table.type_index_mut() {
+                    remapper.index("table", i, IndexSpace::Type, tyidx);
+                }
+            }
+        }
+
+        if let Some(glsec) = &mut self.global_section {
+            for (i, global) in glsec.globals.iter_mut().enumerate() {
+                if let Some(tyidx) = global.ty.type_index_mut() {
+                    remapper.index("global", i, IndexSpace::Type, tyidx);
+                }
+                remapper.expr("global", i, &mut global.init);
+            }
+        }
+
+        if let Some(exsec) = &mut self.export_section {
+            for (i, export) in exsec.exports.iter_mut().enumerate() {
+                let (space, index) = match &mut export.desc {
+                    SynthExportDescription::Func(x) => (IndexSpace::Function, x),
+                    SynthExportDescription::Table(x) => (IndexSpace::Table, x),
+                    SynthExportDescription::Mem(x) => (IndexSpace::Memory, x),
+                    SynthExportDescription::Global(x) => (IndexSpace::Global, x),
+                    SynthExportDescription::Tag(x) => (IndexSpace::Tag, x),
+                };
+                remapper.index("export", i, space, index);
+            }
+        }
+
+        if let Some(stsec) = &mut self.start_section {
+            remapper.index("start", 0, IndexSpace::Function, &mut stsec.start);
+        }
+
+        if let Some(elsec) = &mut self.element_section {
+            for (i, elem) in elsec.elements.iter_mut().enumerate() {
+                if let SynthElemKind::ReferenceType(rt) = &mut elem.kind {
+                    if let Some(tyidx) = rt.type_index_mut() {
+                        remapper.index("element", i, IndexSpace::Type, tyidx);
+                    }
+                }
+                if let SynthElemMode::Active { table, offset } = &mut elem.mode {
+                    remapper.index("element", i, IndexSpace::Table, table);
+                    remapper.expr("element", i, offset);
+                }
+                match &mut elem.init {
+                    SynthElemInit::FuncIndices(x) => {
+                        for funcidx in x {
+                            remapper.index("element", i, IndexSpace::Function, funcidx);
+                        }
+                    }
+                    SynthElemInit::Expressions(x) => {
+                        for expr in x {
+                            remapper.expr("element", i, expr);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(codesec) = &mut self.code_section {
+            for (i, code) in codesec.codes.iter_mut().enumerate() {
+                for tyidx in code.locals.iter_mut().filter_map(ValueType::type_index_mut) {
+                    remapper.index("code", i, IndexSpace::Type, tyidx);
+                }
+                remapper.expr("code", i, &mut code.func_expr);
+            }
+        }
+
+        if let Some(datasec) = &mut self.data_section {
+            for (i, data) in datasec.all_data.iter_mut().enumerate() {
+                if let SynthData::Active {
+                    memory_index,
+                    offset,
+                    ..
+                } = data
+                {
+                    remapper.index("data", i, IndexSpace::Memory, memory_index);
+                    remapper.expr("data", i, offset);
+                }
+            }
+        }
+
+        if let Some(namesec) = &mut self.name_section {
+            remapper.names(IndexSpace::Function, namesec.function_names_mut());
+            remapper.indirect_names(IndexSpace::Function, namesec.local_names_mut());
+            remapper.indirect_names(IndexSpace::Function, namesec.label_names_mut());
+            remapper.indirect_names(IndexSpace::Type, namesec.field_names_mut());
+            remapper.names(IndexSpace::Type, namesec.type_names_mut());
+            remapper.names(IndexSpace::Table, namesec.table_names_mut());
+            remapper.names(IndexSpace::Memory, namesec.memory_names_mut());
+            remapper.names(IndexSpace::Global, namesec.global_names_mut());
+            remapper.names(IndexSpace::Element, namesec.element_segment_names_mut());
+            remapper.names(IndexSpace::Data, namesec.data_segment_names_mut());
+            remapper.names(IndexSpace::Tag, namesec.tag_names_mut());
+        }
+
+        if remapper.dangling.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DanglingReferences(remapper.dangling))
+        }
+    }
+
+    /// Rewrites references as if `count` entities were inserted into `space` at index `at`.
+    ///
+    /// The entities are not inserted; the caller inserts them into the sections defining `space`,
+    /// e.g. the import section or the function and code sections for functions. Types inserted
+    /// inside a rec group become part of it. See [`SynthModule::remap_indices`].
+    pub fn remap_for_insertion(
+        &mut self,
+        space: IndexSpace,
+        at: u32,
+        count: u32,
+    ) -> Result<(), Error> {
+        self.remap_indices(space, |x| {
+            if x >= at {
+                Some(x.checked_add(count).expect("index overflow"))
+            } else {
+                Some(x)
+            }
+        })
+    }
+
+    /// Rewrites references as if `count` entities were removed from `space` at index `at`.
+    ///
+    /// The entities are not removed; the caller removes them from the sections defining `space`.
+    /// See [`SynthModule::remap_indices`].
+    pub fn remap_for_removal(
+        &mut self,
+        space: IndexSpace,
+        at: u32,
+        count: u32,
+    ) -> Result<(), Error> {
+        let end = at.checked_add(count).expect("index overflow");
+        self.remap_indices(space, |x| {
+            if x < at {
+                Some(x)
+            } else if x < end {
+                None
+            } else {
+                Some(x - count)
+            }
+        })
+    }
+
+    /// Inserts locals of `types` into the defined function `func` at local index `at`.
+    ///
+    /// References to the following locals in the function body and names of them in the name
+    /// section are rewritten. Other functions are not affected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is an index of a parameter, or is past the last local of the function.
+    pub fn insert_locals(&mut self, func: u32, at: u32, types: &[ValueType]) -> Result<(), Error> {
+        let count = u32::try_from(types.len()).expect("local index overflow");
+        let (codeidx, params) = self.function_locals(func)?;
+        let locals = &mut self.code_section.as_mut().expect("code section").codes[codeidx].locals;
+        let pos = local_position(at, params, locals.len());
+        locals.splice(pos..pos, types.iter().cloned());
+
+        self.remap_locals(func, codeidx, |x| {
+            if x >= at {
+                Some(x.checked_add(count).expect("local index overflow"))
+            } else {
+                Some(x)
+            }
+        })
+    }
+
+    /// Removes `count` locals from the defined function `func` at local index `at`.
+    ///
+    /// References to the following locals in the function body and names of them in the name
+    /// section are rewritten, and names of removed locals are dropped. References to removed
+    /// locals are left untouched and reported with [`Error::DanglingReferences`]. Other functions
+    /// are not affected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of removed locals includes a parameter, or is past the last local of the
+    /// function.
+    pub fn remove_locals(&mut self, func: u32, at: u32, count: u32) -> Result<(), Error> {
+        let end = at.checked_add(count).expect("local index overflow");
+        let (codeidx, params) = self.function_locals(func)?;
+        let locals = &mut self.code_section.as_mut().expect("code section").codes[codeidx].locals;
+        let start = local_position(at, params, locals.len());
+        locals.drain(start..local_position(end, params, locals.len()));
+
+        self.remap_locals(func, codeidx, |x| {
+            if x < at {
+                Some(x)
+            } else if x < end {
+                None
+            } else {
+                Some(x - count)
+            }
+        })
+    }
+
+    /// Returns the position of the defined function `func` in the code section, and the number of
+    /// its parameters.
+    fn function_locals(&self, func: u32) -> Result<(usize, usize), Error> {
+        let imported = self.import_section.as_ref().map_or(0, |x| {
+            x.imports
+                .iter()
+                .filter(|x| matches!(x.description, SynthImportDescription::Type(_)))
+                .count()
+        });
+        let codeidx = (func as usize)
+            .checked_sub(imported)
+            .filter(|&x| {
+                self.code_section
+                    .as_ref()
+                    .is_some_and(|sec| x < sec.codes.len())
+            })
+            .ok_or(Error::InvalidModule(ValidationError::UnknownFunction(func)))?;
+        let tyidx = *self
+            .function_section
+            .as_ref()
+            .and_then(|x| x.type_indices.get(codeidx))
+            .ok_or(Error::InvalidModule(ValidationError::UnknownFunction(func)))?;
+        let ty = self
+            .type_section
+            .as_ref()
+            .and_then(|x| x.types.get(tyidx as usize))
+            .ok_or(Error::InvalidModule(ValidationError::UnknownType(
+                tyidx.into(),
+            )))?
+            .func_type()
+            .ok_or(Error::InvalidModule(ValidationError::CompositeType {
+                index: tyidx,
+                expected: "function",
+            }))?;
+        Ok((codeidx, ty.param.0.len()))
+    }
+
+    /// Rewrites local references of the function `func` at `codeidx` of the code section.
+    fn remap_locals(
+        &mut self,
+        func: u32,
+        codeidx: usize,
+        mut remap: impl FnMut(u32) -> Option<u32>,
+    ) -> Result<(), Error> {
+        let mut remapper = Remapper {
+            space: IndexSpace::Local,
+            remap: &mut remap,
+            dangling: Vec::new(),
+        };
+
+        let code = &mut self.code_section.as_mut().expect("code section").codes[codeidx];
+        remapper.expr("code", codeidx, &mut code.func_expr);
+        if let Some(namesec) = &mut self.name_section {
+            remapper.local_names(func, namesec.local_names_mut());
+        }
+
+        if remapper.dangling.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DanglingReferences(remapper.dangling))
+        }
+    }
+}
+
+/// Returns the position of the local index `at` among the declared locals of a function with
+/// `params` parameters and `locals` declared locals.
+fn local_position(at: u32, params: usize, locals: usize) -> usize {
+    let pos = (at as usize)
+        .checked_sub(params)
+        .unwrap_or_else(|| panic!("local {at} is a parameter"));
+    assert!(pos <= locals, "local {at} is out of range");
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        instructions::{BlockType, Instruction},
+        parse::Module,
+        synth::{DanglingReference, IndexSpace, SynthModule},
+        wasm_types::ValueType,
+        Error,
+    };
+
+    fn synth_wat(wat_s: &str) -> SynthModule {
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+        Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth")
+    }
+
+    #[test]
+    fn test_insert_types() {
+        let mut module = synth_wat(
+            r#"(module
+                (type $t (func (param i32) (result i32)))
+                (table 1 funcref)
+                (func (type $t)
+                    local.get 0
+                    block (type $t)
+                        i32.const 0
+                        call_indirect (type $t)
+                    end
+                )
+            )"#,
+        );
+        module.remap_for_insertion(IndexSpace::Type, 0, 2).unwrap();
+
+        assert_eq!(module.function_section.as_ref().unwrap().type_indices, [2]);
+        let instrs = &module.code_section.as_ref().unwrap().codes[0].func_expr.0;
+        let Instruction::Block(BlockType::TypeIndex(2), block) = &instrs[1] else {
+            panic!("unexpected instruction {:?}", instrs[1]);
+        };
+        assert!(matches!(
+            block[1],
+            Instruction::CallIndirect { ty: 2, table: 0 }
+        ));
+    }
+
+    #[test]
+    fn test_remap_rec_groups() {
+        let mut module = synth_wat(
+            r#"(module
+                (type (func))
+                (rec (type (struct)) (type (array i8)) (type (struct (field i32))))
+                (rec (type (array i32)))
+            )"#,
+        );
+        let rec_groups =
+            |module: &SynthModule| module.type_section.as_ref().unwrap().rec_groups.clone();
+        assert_eq!(rec_groups(&module), [1..4, 4..5]);
+
+        module.remap_for_insertion(IndexSpace::Type, 0, 1).unwrap();
+        assert_eq!(rec_groups(&module), [2..5, 5..6]);
+        module.remap_for_insertion(IndexSpace::Type, 3, 2).unwrap();
+        assert_eq!(rec_groups(&module), [2..7, 7..8]);
+        module.remap_for_removal(IndexSpace::Type, 2, 3).unwrap();
+        assert_eq!(rec_groups(&module), [2..4, 4..5]);
+        module.remap_for_removal(IndexSpace::Type, 4, 1).unwrap();
+        assert_eq!(rec_groups(&module), vec![2..4]);
+    }
+
+    #[test]
+    fn test_remove_globals() {
+        let mut module = synth_wat(
+            r#"(module
+                (global $a i32 (i32.const 0))
+                (global $b i32 (i32.const 1))
+                (global $c i32 (i32.const 2))
+                (func (result i32)
+                    global.get $a
+                    drop
+                    global.get $c
+                )
+                (export "c" (global $c))
+            )"#,
+        );
+        let err = module
+            .remap_for_removal(IndexSpace::Global, 0, 2)
+            .unwrap_err();
+        let Error::DanglingReferences(dangling) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(
+            dangling,
+            [DanglingReference {
+                section: "code",
+                entity: 0,
+                space: IndexSpace::Global,
+                index: 0,
+            }]
+        );
+
+        let instrs = &module.code_section.as_ref().unwrap().codes[0].func_expr.0;
+        assert!(matches!(instrs[2], Instruction::GlobalGet(0)));
+        let names = module
+            .name_section
+            .as_ref()
+            .unwrap()
+            .global_names()
+            .unwrap();
+        assert_eq!(names.len(), 1);
+        assert_eq!((names[0].idx, names[0].name.as_str()), (0, "c"));
+    }
+
+    #[test]
+    fn test_insert_remove_locals() {
+        let mut module = synth_wat(
+            r#"(module
+                (import "env" "f" (func))
+                (func $a (param $p i32) (local $x i32)
+                    local.get $x
+                    local.set $p
+                )
+                (func $b (param $p i32) (local $x i32) (local $y f32)
+                    local.get $y
+                    drop
+                    local.get $x
+                    local.set 0
+                )
+            )"#,
+        );
+        module
+            .insert_locals(2, 2, &[ValueType::I64, ValueType::F64])
+            .unwrap();
+
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert_eq!(codes[0].locals, [ValueType::I32]);
+        assert!(matches!(codes[0].func_expr.0[0], Instruction::LocalGet(1)));
+        assert_eq!(
+            codes[1].locals,
+            [
+                ValueType::I32,
+                ValueType::I64,
+                ValueType::F64,
+                ValueType::F32
+            ]
+        );
+        assert!(matches!(codes[1].func_expr.0[0], Instruction::LocalGet(4)));
+        assert!(matches!(codes[1].func_expr.0[2], Instruction::LocalGet(1)));
+        let local_names = |module: &SynthModule, func| {
+            let namesec = module.name_section.as_ref().unwrap();
+            let indassoc = namesec
+                .local_names()
+                .unwrap()
+                .iter()
+                .find(|x| x.idx == func)
+                .unwrap();
+            indassoc
+                .name_map
+                .iter()
+                .map(|x| (x.idx, x.name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(local_names(&module, 1), [(0, "p".into()), (1, "x".into())]);
+        assert_eq!(
+            local_names(&module, 2),
+            [(0, "p".into()), (1, "x".into()), (4, "y".into())]
+        );
+        module.validate().expect("module is invalid");
+
+        let err = module.remove_locals(2, 1, 3).unwrap_err();
+        let Error::DanglingReferences(dangling) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(
+            dangling,
+            [DanglingReference {
+                section: "code",
+                entity: 1,
+                space: IndexSpace::Local,
+                index: 1,
+            }]
+        );
+        let codes = &module.code_section.as_ref().unwrap().codes;
+        assert_eq!(codes[1].locals, [ValueType::F32]);
+        assert!(matches!(codes[1].func_expr.0[0], Instruction::LocalGet(1)));
+        assert_eq!(local_names(&module, 2), [(0, "p".into()), (1, "y".into())]);
+    }
+}
@@ -1,6 +1,13 @@
+#[cfg(feature = "parallel")]
+use std::borrow::Cow;
 use std::io::{self, Write};
 
-use crate::{instructions::Expression, wasm_types::ValueType, WriteExt};
+use crate::{
+    instructions::{Expression, VisitMut},
+    synth::{preserve::OriginalBytes, IndexSpace},
+    wasm_types::ValueType,
+    WriteExt,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct SynthCodeSection {
@@ -16,9 +23,26 @@ impl SynthCodeSection {
         &mut self.codes
     }
 
-    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+    /// Writes the section, substituting original bytes of unmodified function bodies if
+    /// `original` is given.
+    pub(crate) fn write_into(
+        &self,
+        wr: &mut impl Write,
+        original: Option<&OriginalBytes>,
+    ) -> Result<(), io::Error> {
         let mut buf = Vec::new();
-        buf.write_vector(&self.codes, SynthCode::write_into)?;
+        #[cfg(feature = "parallel")]
+        buf.write_vector(&self.encode_codes(original)?, |code, wr| wr.write_all(code))?;
+        #[cfg(not(feature = "parallel"))]
+        {
+            buf.write_u32(self.codes.len().try_into().expect("vector length overflow"))?;
+            for (i, code) in self.codes.iter().enumerate() {
+                match original.and_then(|x| x.code(i, code)) {
+                    Some(bytes) => buf.write_all(bytes)?,
+                    None => code.write_into(&mut buf)?,
+                }
+            }
+        }
 
         wr.write_all(&[10])?;
         wr.write_u32(buf.len().try_into().expect("buffer length overflow"))?;
@@ -26,6 +50,29 @@ impl SynthCodeSection {
 
         Ok(())
     }
+
+    /// Encodes each function body concurrently. Original bytes of unmodified function bodies are
+    /// substituted if `original` is given.
+    #[cfg(feature = "parallel")]
+    fn encode_codes<'a>(
+        &'a self,
+        original: Option<&'a OriginalBytes>,
+    ) -> Result<Vec<Cow<'a, [u8]>>, io::Error> {
+        use rayon::prelude::*;
+
+        self.codes
+            .par_iter()
+            .enumerate()
+            .map(|(i, code)| {
+                if let Some(bytes) = original.and_then(|x| x.code(i, code)) {
+                    return Ok(Cow::Borrowed(bytes));
+                }
+                let mut encoded = Vec::new();
+                code.write_into(&mut encoded)?;
+                Ok(Cow::Owned(encoded))
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -43,6 +90,20 @@ impl SynthCode {
         &mut self.func_expr
     }
 
+    /// Returns whether the function body refers to a data segment, which requires the data count
+    /// section.
+    pub(crate) fn refers_to_data(&self) -> bool {
+        let mut found = false;
+        self.func_expr
+            .visit_indices(&mut |space, _| found |= space == IndexSpace::Data);
+        found
+    }
+
+    /// Visits the function body with `visitor`.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        self.func_expr.visit_mut(visitor);
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
         let mut buf = Vec::new();
         let mut locals = Vec::new();
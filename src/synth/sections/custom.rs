@@ -2,13 +2,94 @@ use std::io::{self, Write};
 
 use crate::WriteExt;
 
+/// A known section, in the order of the binary format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SectionKind {
+    Type,
+    Import,
+    Function,
+    Table,
+    Memory,
+    Tag,
+    Global,
+    Export,
+    Start,
+    Element,
+    DataCount,
+    Code,
+    Data,
+}
+
+impl SectionKind {
+    pub(crate) const ALL: [SectionKind; 13] = [
+        Self::Type,
+        Self::Import,
+        Self::Function,
+        Self::Table,
+        Self::Memory,
+        Self::Tag,
+        Self::Global,
+        Self::Export,
+        Self::Start,
+        Self::Element,
+        Self::DataCount,
+        Self::Code,
+        Self::Data,
+    ];
+
+    /// Returns the name of the section in custom section annotations of the text format.
+    pub(crate) fn keyword(self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Import => "import",
+            Self::Function => "func",
+            Self::Table => "table",
+            Self::Memory => "memory",
+            Self::Tag => "tag",
+            Self::Global => "global",
+            Self::Export => "export",
+            Self::Start => "start",
+            Self::Element => "elem",
+            Self::DataCount => "datacount",
+            Self::Code => "code",
+            Self::Data => "data",
+        }
+    }
+}
+
+/// Position of a custom section relative to the known sections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CustomPlacement {
+    /// Before every known section.
+    First,
+    /// After the known section of the kind, or where it would be if the module does not have one.
+    After(SectionKind),
+}
+
+impl Default for CustomPlacement {
+    /// Places the section at the end of the module.
+    fn default() -> Self {
+        Self::After(SectionKind::Data)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SynthCustomSection {
     pub(crate) name: String,
     pub(crate) bytes: Vec<u8>,
+    pub(crate) placement: CustomPlacement,
 }
 
 impl SynthCustomSection {
+    /// Creates a custom section placed at the end of the module.
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            bytes: bytes.into(),
+            placement: CustomPlacement::default(),
+        }
+    }
+
     pub fn name(&self) -> &str {
         self.name.as_ref()
     }
@@ -25,6 +106,14 @@ impl SynthCustomSection {
         &mut self.bytes
     }
 
+    pub fn placement(&self) -> CustomPlacement {
+        self.placement
+    }
+
+    pub fn placement_mut(&mut self) -> &mut CustomPlacement {
+        &mut self.placement
+    }
+
     pub(crate) fn write_into(&self, mut wr: impl Write) -> Result<(), io::Error> {
         let mut buf = Vec::new();
         buf.write_name(&self.name)?;
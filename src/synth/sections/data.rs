@@ -1,6 +1,9 @@
 use std::io::{self, Write};
 
-use crate::{instructions::Expression, WriteExt};
+use crate::{
+    instructions::{Expression, VisitMut},
+    WriteExt,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct SynthDataSection {
@@ -39,6 +42,19 @@ pub enum SynthData {
 }
 
 impl SynthData {
+    /// Visits the memory index and offset of an active data segment with `visitor`.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        if let SynthData::Active {
+            memory_index,
+            offset,
+            ..
+        } = self
+        {
+            visitor.visit_memory_idx(memory_index);
+            offset.visit_mut(visitor);
+        }
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
         match self {
             SynthData::Active {
@@ -1,6 +1,10 @@
 use std::io::{self, Write};
 
-use crate::{instructions::Expression, wasm_types::ReferenceType, WriteExt};
+use crate::{
+    instructions::{Expression, VisitMut},
+    wasm_types::ReferenceType,
+    WriteExt,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct SynthElementSection {
@@ -36,32 +40,58 @@ pub struct SynthElem {
 }
 
 impl SynthElem {
+    /// Visits the table index, offset and initializers with `visitor`.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        if let SynthElemMode::Active { table, offset } = &mut self.mode {
+            visitor.visit_table_idx(table);
+            offset.visit_mut(visitor);
+        }
+        match &mut self.init {
+            SynthElemInit::FuncIndices(x) => {
+                for funcidx in x {
+                    visitor.visit_func_idx(funcidx);
+                }
+            }
+            SynthElemInit::Expressions(x) => {
+                for expr in x {
+                    expr.visit_mut(visitor);
+                }
+            }
+        }
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
         let mut discriminator: u8 = 0b000;
         let mut buf = Vec::new();
+        // active segments of table 0 have no elemkind nor reftype, so they must be funcref
+        let funcref = match (&self.kind, &self.init) {
+            (SynthElemKind::FuncRef, _) | (_, SynthElemInit::FuncIndices(_)) => true,
+            (SynthElemKind::ReferenceType(rt), SynthElemInit::Expressions(_)) => {
+                *rt == ReferenceType::FuncRef
+            }
+        };
         match &self.mode {
             SynthElemMode::Active { table, offset } => {
-                if *table > 0 {
+                if *table > 0 || !funcref {
                     discriminator |= 0b010;
                     buf.write_u32(*table)?;
                 }
                 offset.write_into(&mut buf)?;
             }
-            mode @ SynthElemMode::Passive | mode @ SynthElemMode::Declarative => {
-                discriminator |= 0b001;
-                match mode {
-                    SynthElemMode::Active { .. } => unreachable!(),
-                    SynthElemMode::Passive => (),
-                    SynthElemMode::Declarative => {
-                        discriminator |= 0b010;
-                    }
-                }
-            }
+            SynthElemMode::Passive => discriminator |= 0b001,
+            SynthElemMode::Declarative => discriminator |= 0b011,
         }
 
-        match &self.kind {
-            SynthElemKind::FuncRef => (),
-            SynthElemKind::ReferenceType(rt) => rt.write_into(&mut buf)?,
+        if discriminator & 0b011 != 0 {
+            match (&self.kind, &self.init) {
+                (_, SynthElemInit::FuncIndices(_)) => buf.write_all(&[0x00])?,
+                (SynthElemKind::FuncRef, SynthElemInit::Expressions(_)) => {
+                    ReferenceType::FuncRef.write_into(&mut buf)?
+                }
+                (SynthElemKind::ReferenceType(rt), SynthElemInit::Expressions(_)) => {
+                    rt.write_into(&mut buf)?
+                }
+            }
         }
 
         match &self.init {
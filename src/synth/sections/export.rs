@@ -54,6 +54,10 @@ impl SynthExport {
                 wr.write_all(&[0x03])?;
                 wr.write_u32(x)?;
             }
+            SynthExportDescription::Tag(x) => {
+                wr.write_all(&[0x04])?;
+                wr.write_u32(x)?;
+            }
         }
         Ok(())
     }
@@ -65,4 +69,5 @@ pub enum SynthExportDescription {
     Table(u32),
     Mem(u32),
     Global(u32),
+    Tag(u32),
 }
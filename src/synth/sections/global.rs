@@ -1,6 +1,10 @@
 use std::io::{self, Write};
 
-use crate::{instructions::Expression, wasm_types::GlobalType, WriteExt};
+use crate::{
+    instructions::{Expression, VisitMut},
+    wasm_types::GlobalType,
+    WriteExt,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct SynthGlobalSection {
@@ -35,6 +39,11 @@ pub struct SynthGlobal {
 }
 
 impl SynthGlobal {
+    /// Visits the initializer expression with `visitor`.
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitMut) {
+        self.init.visit_mut(visitor);
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
         self.ty.write_into(wr)?;
         self.init.write_into(wr)?;
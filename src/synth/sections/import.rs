@@ -1,7 +1,7 @@
 use std::io::{self, Write};
 
 use crate::{
-    wasm_types::{GlobalType, MemType, TableType},
+    wasm_types::{GlobalType, MemType, TableType, TagType},
     WriteExt,
 };
 
@@ -77,6 +77,7 @@ pub enum SynthImportDescription {
     Table(TableType),
     Memory(MemType),
     Global(GlobalType),
+    Tag(TagType),
 }
 
 impl SynthImportDescription {
@@ -102,6 +103,11 @@ impl SynthImportDescription {
                 x.write_into(wr)?;
                 Ok(())
             }
+            SynthImportDescription::Tag(x) => {
+                wr.write_all(&[0x04])?;
+                x.write_into(wr)?;
+                Ok(())
+            }
         }
     }
 }
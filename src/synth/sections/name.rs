@@ -14,6 +14,8 @@ pub struct SynthNameSection {
     pub(crate) global_names: Option<Vec<SynthNameAssoc>>,
     pub(crate) element_segment_names: Option<Vec<SynthNameAssoc>>,
     pub(crate) data_segment_names: Option<Vec<SynthNameAssoc>>,
+    pub(crate) field_names: Option<Vec<SynthIndirectNameAssoc>>,
+    pub(crate) tag_names: Option<Vec<SynthNameAssoc>>,
 }
 
 impl SynthNameSection {
@@ -92,6 +94,18 @@ impl SynthNameSection {
             })?;
         }
 
+        if let Some(indirect_assocs) = self.field_names.as_ref() {
+            write_subsection(10, &mut buf, |wr| {
+                wr.write_vector(indirect_assocs, SynthIndirectNameAssoc::write_into)
+            })?;
+        }
+
+        if let Some(assocs) = self.tag_names.as_ref() {
+            write_subsection(11, &mut buf, |wr| {
+                wr.write_vector(assocs, SynthNameAssoc::write_into)
+            })?;
+        }
+
         wr.write_all(&[0])?;
         wr.write_u32(buf.len().try_into().expect("buffer length overflow"))?;
         wr.write_all(&buf)?;
@@ -178,6 +192,23 @@ impl SynthNameSection {
     pub fn data_segment_names_mut(&mut self) -> &mut Option<Vec<SynthNameAssoc>> {
         &mut self.data_segment_names
     }
+
+    /// Returns names of struct fields, by type index and field index.
+    pub fn field_names(&self) -> Option<&[SynthIndirectNameAssoc]> {
+        self.field_names.as_deref()
+    }
+
+    pub fn field_names_mut(&mut self) -> &mut Option<Vec<SynthIndirectNameAssoc>> {
+        &mut self.field_names
+    }
+
+    pub fn tag_names(&self) -> Option<&[SynthNameAssoc]> {
+        self.tag_names.as_deref()
+    }
+
+    pub fn tag_names_mut(&mut self) -> &mut Option<Vec<SynthNameAssoc>> {
+        &mut self.tag_names
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,29 @@
+use std::io::{self, Write};
+
+use crate::{wasm_types::TagType, WriteExt};
+
+#[derive(Clone, Debug, Default)]
+pub struct SynthTagSection {
+    pub(crate) tags: Vec<TagType>,
+}
+
+impl SynthTagSection {
+    pub fn tags(&self) -> &[TagType] {
+        self.tags.as_ref()
+    }
+
+    pub fn tags_mut(&mut self) -> &mut Vec<TagType> {
+        &mut self.tags
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        let mut buf = Vec::new();
+        buf.write_vector(&self.tags, TagType::write_into)?;
+
+        wr.write_all(&[13])?;
+        wr.write_u32(buf.len().try_into().expect("buffer length overflow"))?;
+        wr.write_all(&buf)?;
+
+        Ok(())
+    }
+}
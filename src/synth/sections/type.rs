@@ -1,24 +1,63 @@
-use std::io::{self, Write};
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
 
-use crate::{wasm_types::FuncType, WriteExt};
+use crate::{wasm_types::SubType, WriteExt};
 
 #[derive(Clone, Debug, Default)]
 pub struct SynthTypeSection {
-    pub(crate) types: Vec<FuncType>,
+    pub(crate) types: Vec<SubType>,
+    pub(crate) rec_groups: Vec<Range<u32>>,
 }
 
 impl SynthTypeSection {
-    pub fn types(&self) -> &[FuncType] {
+    pub fn types(&self) -> &[SubType] {
         self.types.as_ref()
     }
 
-    pub fn types_mut(&mut self) -> &mut Vec<FuncType> {
+    pub fn types_mut(&mut self) -> &mut Vec<SubType> {
         &mut self.types
     }
 
+    /// Returns the ranges of type indices written in `rec` groups of the GC proposal, in order.
+    ///
+    /// Types outside of these ranges are groups of their own. The ranges are remapped along with
+    /// references to types by [`SynthModule::remap_indices`](crate::synth::SynthModule::remap_indices).
+    pub fn rec_groups(&self) -> &[Range<u32>] {
+        &self.rec_groups
+    }
+
+    pub fn rec_groups_mut(&mut self) -> &mut Vec<Range<u32>> {
+        &mut self.rec_groups
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        let mut entries = Vec::new();
+        let mut groups = self.rec_groups.iter().peekable();
+        let mut i = 0;
+        while i < self.types.len() {
+            match groups.next_if(|x| x.start as usize == i) {
+                Some(group) => {
+                    entries.push((true, &self.types[i..group.end as usize]));
+                    i = group.end as usize;
+                }
+                None => {
+                    entries.push((false, &self.types[i..i + 1]));
+                    i += 1;
+                }
+            }
+        }
+
         let mut buf = Vec::new();
-        buf.write_vector(&self.types, FuncType::write_into)?;
+        buf.write_vector(&entries, |&(explicit, types), wr| {
+            if explicit {
+                wr.write_all(&[0x4E])?;
+                wr.write_vector(types, SubType::write_into)
+            } else {
+                types[0].write_into(wr)
+            }
+        })?;
 
         wr.write_all(&[1])?;
         wr.write_u32(buf.len().try_into().expect("buffer length overflow"))?;
@@ -0,0 +1,1670 @@
+//! WebAssembly text format parser.
+//!
+//! [`SynthModule::from_wat`] parses a module in the text format, including folded instructions,
+//! symbolic identifiers and inline imports and exports. Identifiers are recorded into the name
+//! section.
+//!
+//! <https://webassembly.github.io/spec/core/text/index.html>
+
+mod instr;
+mod lex;
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    instructions::{BlockType, Expression, Instruction},
+    synth::{
+        sections::{
+            CustomPlacement, SectionKind, SynthCode, SynthCodeSection, SynthCustomSection,
+            SynthData, SynthDataCountSection, SynthDataSection, SynthElem, SynthElemInit,
+            SynthElemKind, SynthElemMode, SynthElementSection, SynthExport, SynthExportDescription,
+            SynthExportSection, SynthFunctionSection, SynthGlobal, SynthGlobalSection, SynthImport,
+            SynthImportDescription, SynthImportSection, SynthIndirectNameAssoc, SynthMemorySection,
+            SynthNameAssoc, SynthNameSection, SynthStartSection, SynthTableSection,
+            SynthTagSection, SynthTypeSection,
+        },
+        IndexSpace, SynthModule,
+    },
+    wasm_types::{
+        CompositeType, FieldType, FuncType, GlobalType, HeapType, Limits, MemType, ReferenceType,
+        ResultType, StorageType, SubType, TableType, TagType, ValueType,
+    },
+    Error,
+};
+
+use self::lex::{Atom, Sexpr};
+
+const PAGE_SIZE: usize = 65536;
+
+/// Abbreviations of nullable references to abstract heap types.
+const REFERENCE_TYPES: &[(&str, HeapType)] = &[
+    ("funcref", HeapType::Func),
+    ("externref", HeapType::Extern),
+    ("exnref", HeapType::Exn),
+    ("anyref", HeapType::Any),
+    ("eqref", HeapType::Eq),
+    ("i31ref", HeapType::I31),
+    ("structref", HeapType::Struct),
+    ("arrayref", HeapType::Array),
+    ("nullref", HeapType::None),
+    ("nullfuncref", HeapType::NoFunc),
+    ("nullexternref", HeapType::NoExtern),
+    ("nullexnref", HeapType::NoExn),
+];
+
+/// Abstract heap types.
+const HEAP_TYPES: &[(&str, HeapType)] = &[
+    ("func", HeapType::Func),
+    ("extern", HeapType::Extern),
+    ("exn", HeapType::Exn),
+    ("any", HeapType::Any),
+    ("eq", HeapType::Eq),
+    ("i31", HeapType::I31),
+    ("struct", HeapType::Struct),
+    ("array", HeapType::Array),
+    ("none", HeapType::None),
+    ("nofunc", HeapType::NoFunc),
+    ("noextern", HeapType::NoExtern),
+    ("noexn", HeapType::NoExn),
+];
+
+/// An error with the byte offset where it occurred.
+#[derive(Debug)]
+pub(crate) struct TextError {
+    offset: usize,
+    message: String,
+}
+
+impl TextError {
+    pub(crate) fn new(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    fn into_error(self, src: &str) -> Error {
+        let before = &src[..self.offset.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+        Error::Wat {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: self.message,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, TextError>;
+
+/// A symbolic or numeric index, with the offset where it appears.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Idx<'a> {
+    Num(u32),
+    Id(&'a str, usize),
+}
+
+/// A cursor over items of a list.
+#[derive(Clone, Copy)]
+pub(crate) struct Cursor<'s, 'a> {
+    items: &'s [Sexpr<'a>],
+    /// Offset reported for errors at the end of the list.
+    end: usize,
+}
+
+impl<'s, 'a> Cursor<'s, 'a> {
+    pub(crate) fn new(items: &'s [Sexpr<'a>], end: usize) -> Self {
+        Self { items, end }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub(crate) fn peek(&self) -> Option<&'s Sexpr<'a>> {
+        self.items.first()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<&'s Sexpr<'a>> {
+        let (first, rest) = self.items.split_first()?;
+        self.items = rest;
+        Some(first)
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.peek().map_or(self.end, Sexpr::offset)
+    }
+
+    pub(crate) fn error(&self, message: impl Into<String>) -> TextError {
+        TextError::new(self.offset(), message)
+    }
+
+    pub(crate) fn peek_atom(&self) -> Option<&'s Atom<'a>> {
+        match self.peek()? {
+            Sexpr::Atom(atom, _) => Some(atom),
+            Sexpr::List(..) => None,
+        }
+    }
+
+    pub(crate) fn peek_keyword(&self) -> Option<&'a str> {
+        match self.peek_atom()? {
+            Atom::Keyword(kw) => Some(kw),
+            _ => None,
+        }
+    }
+
+    /// Consumes the keyword `kw` if it is the next item.
+    pub(crate) fn keyword(&mut self, kw: &str) -> bool {
+        if self.peek_keyword() == Some(kw) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes an identifier if it is the next item.
+    pub(crate) fn id(&mut self) -> Option<&'a str> {
+        match self.peek_atom()? {
+            Atom::Id(id) => {
+                let id = *id;
+                self.next();
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes a string if it is the next item.
+    pub(crate) fn string(&mut self) -> Option<&'s [u8]> {
+        match self.peek_atom()? {
+            Atom::String(s) => {
+                self.next();
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(&mut self) -> Result<String> {
+        let offset = self.offset();
+        let s = self
+            .string()
+            .ok_or_else(|| self.error("expected a string"))?;
+        String::from_utf8(s.to_vec())
+            .map_err(|_| TextError::new(offset, "malformed UTF-8 encoding"))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        match self.peek_atom() {
+            Some(Atom::Number(n)) => {
+                let n = lex::parse_u32(n).ok_or_else(|| self.error("invalid u32 literal"))?;
+                self.next();
+                Ok(n)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        match self.peek_atom() {
+            Some(Atom::Number(n)) => {
+                let n = lex::parse_u64(n).ok_or_else(|| self.error("invalid u64 literal"))?;
+                self.next();
+                Ok(n)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    /// Returns the keyword and the items of the next item if it is a list.
+    pub(crate) fn peek_list(&self) -> Option<(&'a str, Cursor<'s, 'a>)> {
+        let sexpr = self.peek()?;
+        let (kw, items) = sexpr.as_list()?;
+        Some((kw, Cursor::new(items, sexpr.offset())))
+    }
+
+    /// Consumes the next item if it is a list starting with `kw`, returning its items.
+    pub(crate) fn list(&mut self, kw: &str) -> Option<Cursor<'s, 'a>> {
+        match self.peek_list() {
+            Some((x, items)) if x == kw => {
+                self.next();
+                Some(items)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes an index if it is the next item.
+    pub(crate) fn index(&mut self) -> Option<Idx<'a>> {
+        let offset = self.offset();
+        let idx = match self.peek_atom()? {
+            Atom::Id(id) => Idx::Id(id, offset),
+            Atom::Number(n) => Idx::Num(lex::parse_u32(n)?),
+            _ => return None,
+        };
+        self.next();
+        Some(idx)
+    }
+
+    pub(crate) fn expect_index(&mut self) -> Result<Idx<'a>> {
+        self.index().ok_or_else(|| self.error("expected an index"))
+    }
+
+    /// Returns whether a reference type follows, without resolving its heap type.
+    pub(crate) fn is_reference_type(&self) -> bool {
+        match self.peek_list() {
+            Some((kw, _)) => kw == "ref",
+            None => self
+                .peek_keyword()
+                .is_some_and(|kw| REFERENCE_TYPES.iter().any(|(x, _)| *x == kw)),
+        }
+    }
+
+    /// Fails if any item is left.
+    pub(crate) fn finish(&self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected token"))
+        }
+    }
+}
+
+/// Identifiers of an index space.
+#[derive(Default)]
+pub(crate) struct Space<'a> {
+    ids: HashMap<&'a str, u32>,
+    len: u32,
+    names: Vec<SynthNameAssoc>,
+}
+
+impl<'a> Space<'a> {
+    /// Defines the next entity of the space, returning its index.
+    pub(crate) fn define(&mut self, id: Option<&'a str>, offset: usize) -> Result<u32> {
+        let idx = self.len;
+        if let Some(id) = id {
+            if self.ids.insert(id, idx).is_some() {
+                return Err(TextError::new(
+                    offset,
+                    format!("duplicate identifier ${id}"),
+                ));
+            }
+            self.names.push(SynthNameAssoc {
+                idx,
+                name: id.to_string(),
+            });
+        }
+        self.len = self
+            .len
+            .checked_add(1)
+            .ok_or_else(|| TextError::new(offset, "too many definitions"))?;
+        Ok(idx)
+    }
+
+    /// Resolves `idx`. Numeric indices are not checked against the number of entities.
+    pub(crate) fn resolve(&self, idx: Idx<'a>, space: IndexSpace) -> Result<u32> {
+        match idx {
+            Idx::Num(x) => Ok(x),
+            Idx::Id(id, offset) => self
+                .ids
+                .get(id)
+                .copied()
+                .ok_or_else(|| TextError::new(offset, format!("unknown {space} ${id}"))),
+        }
+    }
+
+    fn into_names(self) -> Option<Vec<SynthNameAssoc>> {
+        (!self.names.is_empty()).then_some(self.names)
+    }
+}
+
+/// Locals and labels of a function body, or of a constant expression.
+#[derive(Default)]
+pub(crate) struct Body<'a> {
+    pub(crate) locals: Space<'a>,
+    /// Labels of enclosing blocks, innermost last.
+    pub(crate) labels: Vec<Option<&'a str>>,
+    /// Number of labels defined so far, used as the index of the next label in the name section.
+    pub(crate) label_count: u32,
+    pub(crate) label_names: Vec<SynthNameAssoc>,
+}
+
+/// Fields of a module being parsed.
+///
+/// Identifiers are defined by [`ModuleParser::declare`] first, so that fields may refer to
+/// entities defined later.
+#[derive(Default)]
+struct ModuleParser<'a> {
+    type_ids: Space<'a>,
+    func_ids: Space<'a>,
+    table_ids: Space<'a>,
+    memory_ids: Space<'a>,
+    global_ids: Space<'a>,
+    elem_ids: Space<'a>,
+    data_ids: Space<'a>,
+    tag_ids: Space<'a>,
+
+    types: Vec<SubType>,
+    rec_groups: Vec<Range<u32>>,
+    /// Identifiers of struct fields, by type index.
+    field_ids: HashMap<u32, Space<'a>>,
+    imports: Vec<SynthImport>,
+    type_indices: Vec<u32>,
+    codes: Vec<SynthCode>,
+    tables: Vec<TableType>,
+    memories: Vec<MemType>,
+    globals: Vec<SynthGlobal>,
+    tags: Vec<TagType>,
+    exports: Vec<SynthExport>,
+    start: Option<u32>,
+    elements: Vec<SynthElem>,
+    data: Vec<SynthData>,
+    /// Custom sections, with their placement unless placed after the last section.
+    custom_sections: Vec<(Option<CustomPlacement>, SynthCustomSection)>,
+    local_names: Vec<SynthIndirectNameAssoc>,
+    label_names: Vec<SynthIndirectNameAssoc>,
+
+    /// Number of functions parsed by [`ModuleParser::define`] so far.
+    funcs: u32,
+}
+
+/// Returns the keyword of a module field and a cursor over the rest of it.
+fn field<'s, 'a>(sexpr: &'s Sexpr<'a>) -> Result<(&'a str, Cursor<'s, 'a>)> {
+    let (kw, items) = sexpr
+        .as_list()
+        .ok_or_else(|| TextError::new(sexpr.offset(), "expected a module field"))?;
+    Ok((kw, Cursor::new(items, sexpr.offset())))
+}
+
+/// Skips inline exports, returning whether an inline import follows.
+fn has_inline_import(mut c: Cursor) -> bool {
+    while c.list("export").is_some() {}
+    c.list("import").is_some()
+}
+
+impl<'a> ModuleParser<'a> {
+    /// Defines identifiers of every field.
+    fn declare(&mut self, fields: &[Sexpr<'a>]) -> Result<()> {
+        let mut has_definitions = false;
+        // type definitions may refer to types defined later, so they are parsed last
+        let mut type_defs = Vec::new();
+        for sexpr in fields {
+            let (kw, mut c) = field(sexpr)?;
+            let offset = sexpr.offset();
+            let id = c.id();
+            let is_import = match kw {
+                "type" => {
+                    self.type_ids.define(id, offset)?;
+                    type_defs.push(c);
+                    continue;
+                }
+                "rec" => {
+                    let start = self.type_ids.len;
+                    while let Some(ty) = c.next() {
+                        let (kw, mut ty) = field(ty)?;
+                        if kw != "type" {
+                            return Err(c.error("expected `(type`"));
+                        }
+                        self.type_ids.define(ty.id(), ty.offset())?;
+                        type_defs.push(ty);
+                    }
+                    self.rec_groups.push(start..self.type_ids.len);
+                    continue;
+                }
+                "import" => {
+                    // `id` is the module name here
+                    c.name()?;
+                    c.name()?;
+                    let (kw, mut desc) = c
+                        .peek_list()
+                        .ok_or_else(|| c.error("expected an import description"))?;
+                    let id = desc.id();
+                    let space = match kw {
+                        "func" => &mut self.func_ids,
+                        "table" => &mut self.table_ids,
+                        "memory" => &mut self.memory_ids,
+                        "global" => &mut self.global_ids,
+                        "tag" => &mut self.tag_ids,
+                        _ => return Err(c.error("expected an import description")),
+                    };
+                    space.define(id, offset)?;
+                    true
+                }
+                "func" => {
+                    self.func_ids.define(id, offset)?;
+                    has_inline_import(c)
+                }
+                "table" => {
+                    self.table_ids.define(id, offset)?;
+                    let is_import = has_inline_import(c);
+                    while c.list("export").is_some() {}
+                    if c.is_reference_type() {
+                        self.elem_ids.define(None, offset)?;
+                    }
+                    is_import
+                }
+                "memory" => {
+                    self.memory_ids.define(id, offset)?;
+                    let is_import = has_inline_import(c);
+                    while c.list("export").is_some() {}
+                    if c.list("data").is_some() {
+                        self.data_ids.define(None, offset)?;
+                    }
+                    is_import
+                }
+                "global" => {
+                    self.global_ids.define(id, offset)?;
+                    has_inline_import(c)
+                }
+                "tag" => {
+                    self.tag_ids.define(id, offset)?;
+                    has_inline_import(c)
+                }
+                "elem" => {
+                    self.elem_ids.define(id, offset)?;
+                    continue;
+                }
+                "data" => {
+                    self.data_ids.define(id, offset)?;
+                    continue;
+                }
+                "export" | "start" | "@custom" => continue,
+                _ => {
+                    return Err(TextError::new(
+                        offset,
+                        format!("unknown module field `{kw}`"),
+                    ))
+                }
+            };
+            if !is_import {
+                has_definitions = true;
+            } else if has_definitions {
+                return Err(TextError::new(offset, "import after definition"));
+            }
+        }
+
+        for mut c in type_defs {
+            let idx = self.types.len() as u32;
+            let mut field_ids = Space::default();
+            let ty = self.sub_type(&mut c, &mut field_ids)?;
+            self.types.push(ty);
+            if !field_ids.names.is_empty() {
+                self.field_ids.insert(idx, field_ids);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses every field into sections.
+    fn define(&mut self, fields: &[Sexpr<'a>]) -> Result<()> {
+        let (mut tables, mut memories, mut globals, mut tags) = (0, 0, 0, 0);
+        for sexpr in fields {
+            let (kw, mut c) = field(sexpr)?;
+            match kw {
+                "type" | "rec" => (),
+                "import" => {
+                    let module = c.name()?;
+                    let name = c.name()?;
+                    let (kw, mut desc) = c.peek_list().unwrap();
+                    c.next();
+                    c.finish()?;
+                    desc.id();
+                    let description = match kw {
+                        "func" => {
+                            self.funcs += 1;
+                            self.import_func(&mut desc)?
+                        }
+                        "table" => {
+                            tables += 1;
+                            SynthImportDescription::Table(self.table_type(&mut desc)?)
+                        }
+                        "memory" => {
+                            memories += 1;
+                            SynthImportDescription::Memory(self.mem_type(&mut desc)?)
+                        }
+                        "tag" => {
+                            tags += 1;
+                            SynthImportDescription::Tag(self.tag_type(&mut desc)?)
+                        }
+                        _ => {
+                            globals += 1;
+                            SynthImportDescription::Global(self.global_type(&mut desc)?)
+                        }
+                    };
+                    desc.finish()?;
+                    self.imports.push(SynthImport {
+                        module,
+                        name,
+                        description,
+                    });
+                }
+                "func" => {
+                    c.id();
+                    let idx = self.funcs;
+                    self.funcs += 1;
+                    self.exports(&mut c, SynthExportDescription::Func(idx))?;
+                    match self.inline_import(&mut c)? {
+                        Some((module, name)) => {
+                            let description = self.import_func(&mut c)?;
+                            c.finish()?;
+                            self.imports.push(SynthImport {
+                                module,
+                                name,
+                                description,
+                            });
+                        }
+                        None => self.func(&mut c, idx)?,
+                    }
+                }
+                "table" => {
+                    c.id();
+                    let idx = tables;
+                    tables += 1;
+                    self.exports(&mut c, SynthExportDescription::Table(idx))?;
+                    if let Some((module, name)) = self.inline_import(&mut c)? {
+                        let description = SynthImportDescription::Table(self.table_type(&mut c)?);
+                        c.finish()?;
+                        self.imports.push(SynthImport {
+                            module,
+                            name,
+                            description,
+                        });
+                    } else if let Some(element) = self.reference_type(&mut c)? {
+                        let mut items =
+                            c.list("elem").ok_or_else(|| c.error("expected `(elem`"))?;
+                        c.finish()?;
+                        let init = if matches!(items.peek(), Some(Sexpr::List(..))) {
+                            SynthElemInit::Expressions(self.elem_exprs(&mut items)?)
+                        } else {
+                            SynthElemInit::FuncIndices(self.func_indices(&mut items)?)
+                        };
+                        let len = match &init {
+                            SynthElemInit::FuncIndices(x) => x.len(),
+                            SynthElemInit::Expressions(x) => x.len(),
+                        };
+                        let len = u32::try_from(len).map_err(|_| c.error("too many elements"))?;
+                        let kind = match init {
+                            SynthElemInit::FuncIndices(_) => SynthElemKind::FuncRef,
+                            SynthElemInit::Expressions(_) => SynthElemKind::ReferenceType(element),
+                        };
+                        self.tables.push(TableType::new(
+                            element,
+                            Limits::Bounded {
+                                min: len.into(),
+                                max: len.into(),
+                            },
+                        ));
+                        self.elements.push(SynthElem {
+                            kind,
+                            init,
+                            mode: SynthElemMode::Active {
+                                table: idx,
+                                offset: Expression(vec![Instruction::I32Const(0)]),
+                            },
+                        });
+                    } else {
+                        let tt = self.table_type(&mut c)?;
+                        c.finish()?;
+                        self.tables.push(tt);
+                    }
+                }
+                "memory" => {
+                    c.id();
+                    let idx = memories;
+                    memories += 1;
+                    self.exports(&mut c, SynthExportDescription::Mem(idx))?;
+                    if let Some((module, name)) = self.inline_import(&mut c)? {
+                        let description = SynthImportDescription::Memory(self.mem_type(&mut c)?);
+                        c.finish()?;
+                        self.imports.push(SynthImport {
+                            module,
+                            name,
+                            description,
+                        });
+                    } else if let Some((is_64, mut data)) = inline_data(&mut c) {
+                        c.finish()?;
+                        let init = data_string(&mut data)?;
+                        let pages = u32::try_from(init.len().div_ceil(PAGE_SIZE))
+                            .map_err(|_| c.error("data too large"))?;
+                        let size = Limits::Bounded {
+                            min: pages.into(),
+                            max: pages.into(),
+                        };
+                        let (ty, offset) = if is_64 {
+                            (MemType::new64(size), Instruction::I64Const(0))
+                        } else {
+                            (MemType::new(size), Instruction::I32Const(0))
+                        };
+                        self.memories.push(ty);
+                        self.data.push(SynthData::Active {
+                            init,
+                            memory_index: idx,
+                            offset: Expression(vec![offset]),
+                        });
+                    } else {
+                        let ty = self.mem_type(&mut c)?;
+                        c.finish()?;
+                        self.memories.push(ty);
+                    }
+                }
+                "global" => {
+                    c.id();
+                    let idx = globals;
+                    globals += 1;
+                    self.exports(&mut c, SynthExportDescription::Global(idx))?;
+                    if let Some((module, name)) = self.inline_import(&mut c)? {
+                        let description = SynthImportDescription::Global(self.global_type(&mut c)?);
+                        c.finish()?;
+                        self.imports.push(SynthImport {
+                            module,
+                            name,
+                            description,
+                        });
+                    } else {
+                        let ty = self.global_type(&mut c)?;
+                        let init = self.const_expr(&mut c)?;
+                        self.globals.push(SynthGlobal { ty, init });
+                    }
+                }
+                "tag" => {
+                    c.id();
+                    let idx = tags;
+                    tags += 1;
+                    self.exports(&mut c, SynthExportDescription::Tag(idx))?;
+                    let import = self.inline_import(&mut c)?;
+                    let ty = self.tag_type(&mut c)?;
+                    c.finish()?;
+                    match import {
+                        Some((module, name)) => self.imports.push(SynthImport {
+                            module,
+                            name,
+                            description: SynthImportDescription::Tag(ty),
+                        }),
+                        None => self.tags.push(ty),
+                    }
+                }
+                "export" => {
+                    let name = c.name()?;
+                    let (kw, mut desc) = c
+                        .peek_list()
+                        .ok_or_else(|| c.error("expected an export description"))?;
+                    c.next();
+                    c.finish()?;
+                    let idx = desc.expect_index()?;
+                    desc.finish()?;
+                    let desc = match kw {
+                        "func" => SynthExportDescription::Func(
+                            self.func_ids.resolve(idx, IndexSpace::Function)?,
+                        ),
+                        "table" => SynthExportDescription::Table(
+                            self.table_ids.resolve(idx, IndexSpace::Table)?,
+                        ),
+                        "memory" => SynthExportDescription::Mem(
+                            self.memory_ids.resolve(idx, IndexSpace::Memory)?,
+                        ),
+                        "global" => SynthExportDescription::Global(
+                            self.global_ids.resolve(idx, IndexSpace::Global)?,
+                        ),
+                        "tag" => {
+                            SynthExportDescription::Tag(self.tag_ids.resolve(idx, IndexSpace::Tag)?)
+                        }
+                        _ => {
+                            return Err(TextError::new(
+                                sexpr.offset(),
+                                "expected an export description",
+                            ))
+                        }
+                    };
+                    self.exports.push(SynthExport { name, desc });
+                }
+                "start" => {
+                    if self.start.is_some() {
+                        return Err(TextError::new(sexpr.offset(), "multiple start functions"));
+                    }
+                    let idx = c.expect_index()?;
+                    c.finish()?;
+                    self.start = Some(self.func_ids.resolve(idx, IndexSpace::Function)?);
+                }
+                "elem" => {
+                    c.id();
+                    let elem = self.elem(&mut c)?;
+                    self.elements.push(elem);
+                }
+                "data" => {
+                    c.id();
+                    let data = self.data(&mut c)?;
+                    self.data.push(data);
+                }
+                "@custom" => {
+                    let name = c.name()?;
+                    let placement = custom_placement(&mut c)?;
+                    let bytes = data_string(&mut c)?;
+                    self.custom_sections.push((
+                        placement,
+                        SynthCustomSection {
+                            name,
+                            bytes,
+                            placement: placement.unwrap_or_default(),
+                        },
+                    ));
+                }
+                _ => unreachable!("unknown fields are rejected in declare"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses inline exports of the entity `desc`.
+    fn exports(&mut self, c: &mut Cursor, desc: SynthExportDescription) -> Result<()> {
+        while let Some(mut export) = c.list("export") {
+            let name = export.name()?;
+            export.finish()?;
+            self.exports.push(SynthExport {
+                name,
+                desc: desc.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn inline_import(&mut self, c: &mut Cursor) -> Result<Option<(String, String)>> {
+        let Some(mut import) = c.list("import") else {
+            return Ok(None);
+        };
+        let module = import.name()?;
+        let name = import.name()?;
+        import.finish()?;
+        Ok(Some((module, name)))
+    }
+
+    fn import_func(&mut self, c: &mut Cursor<'_, 'a>) -> Result<SynthImportDescription> {
+        let (ty, _) = self.type_use(c)?;
+        Ok(SynthImportDescription::Type(ty))
+    }
+
+    fn func(&mut self, c: &mut Cursor<'_, 'a>, idx: u32) -> Result<()> {
+        let offset = c.offset();
+        let (ty, param_ids) = self.type_use(c)?;
+        let mut body = Body::default();
+        for id in param_ids {
+            body.locals.define(id, offset)?;
+        }
+        let mut locals = Vec::new();
+        while let Some(mut local) = c.list("local") {
+            let offset = local.offset();
+            if let Some(id) = local.id() {
+                locals.push(self.value_type(&mut local)?);
+                body.locals.define(Some(id), offset)?;
+            } else {
+                while !local.is_empty() {
+                    locals.push(self.value_type(&mut local)?);
+                    body.locals.define(None, offset)?;
+                }
+            }
+            local.finish()?;
+        }
+
+        let mut instrs = Vec::new();
+        self.instrs(c, &mut body, &mut instrs)?;
+        c.finish()?;
+
+        if !body.locals.names.is_empty() {
+            self.local_names.push(SynthIndirectNameAssoc {
+                idx,
+                name_map: body.locals.names,
+            });
+        }
+        if !body.label_names.is_empty() {
+            self.label_names.push(SynthIndirectNameAssoc {
+                idx,
+                name_map: body.label_names,
+            });
+        }
+        self.type_indices.push(ty);
+        self.codes.push(SynthCode {
+            locals,
+            func_expr: Expression(instrs),
+        });
+        Ok(())
+    }
+
+    /// Parses `(param ...)*` and `(result ...)*`, returning the type and parameter identifiers.
+    fn func_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<(FuncType, Vec<Option<&'a str>>)> {
+        let mut params = Vec::new();
+        let mut param_ids = Vec::new();
+        while let Some(mut param) = c.list("param") {
+            if let Some(id) = param.id() {
+                params.push(self.value_type(&mut param)?);
+                param_ids.push(Some(id));
+            } else {
+                while !param.is_empty() {
+                    params.push(self.value_type(&mut param)?);
+                    param_ids.push(None);
+                }
+            }
+            param.finish()?;
+        }
+        let mut results = Vec::new();
+        while let Some(mut result) = c.list("result") {
+            while !result.is_empty() {
+                results.push(self.value_type(&mut result)?);
+            }
+        }
+        Ok((
+            FuncType::new(ResultType(params), ResultType(results)),
+            param_ids,
+        ))
+    }
+
+    /// Returns the index of the function type `ty`, appending it to the type section if missing.
+    ///
+    /// Types in `rec` groups are not reused, as they are distinct from lone type definitions.
+    fn type_index(&mut self, ty: FuncType, offset: usize) -> Result<u32> {
+        let ty = SubType::from(ty);
+        let in_rec_group = |idx: usize| self.rec_groups.iter().any(|x| x.contains(&(idx as u32)));
+        if let Some(idx) = (0..self.types.len()).find(|&i| self.types[i] == ty && !in_rec_group(i))
+        {
+            return Ok(idx as u32);
+        }
+        let idx = u32::try_from(self.types.len())
+            .map_err(|_| TextError::new(offset, "too many types"))?;
+        self.types.push(ty);
+        Ok(idx)
+    }
+
+    /// Parses `(type x)? (param ...)* (result ...)*`, returning the type index and parameter
+    /// identifiers.
+    pub(crate) fn type_use(
+        &mut self,
+        c: &mut Cursor<'_, 'a>,
+    ) -> Result<(u32, Vec<Option<&'a str>>)> {
+        let offset = c.offset();
+        let idx = match c.list("type") {
+            Some(mut ty) => {
+                let idx = ty.expect_index()?;
+                ty.finish()?;
+                Some(self.type_ids.resolve(idx, IndexSpace::Type)?)
+            }
+            None => None,
+        };
+        let (ty, param_ids) = self.func_type(c)?;
+
+        match idx {
+            Some(idx) => {
+                let Some(declared) = self.types.get(idx as usize) else {
+                    return Err(TextError::new(offset, format!("unknown type {idx}")));
+                };
+                let Some(declared) = declared.func_type() else {
+                    return Err(TextError::new(
+                        offset,
+                        format!("type {idx} is not a function type"),
+                    ));
+                };
+                if !param_ids.is_empty() || !ty.result().0.is_empty() {
+                    if *declared != ty {
+                        return Err(TextError::new(
+                            offset,
+                            "inline function type does not match",
+                        ));
+                    }
+                    Ok((idx, param_ids))
+                } else {
+                    Ok((idx, vec![None; declared.param().0.len()]))
+                }
+            }
+            None => Ok((self.type_index(ty, offset)?, param_ids)),
+        }
+    }
+
+    /// Parses a block type, as a type use without parameter identifiers.
+    pub(crate) fn block_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<BlockType> {
+        let offset = c.offset();
+        if matches!(c.peek_list(), Some(("type", _))) {
+            let (idx, _) = self.type_use(c)?;
+            return Ok(BlockType::TypeIndex(idx.into()));
+        }
+        let (ty, param_ids) = self.func_type(c)?;
+        if param_ids.iter().any(Option::is_some) {
+            return Err(TextError::new(
+                offset,
+                "block parameters cannot have identifiers",
+            ));
+        }
+        match (&ty.param().0[..], &ty.result().0[..]) {
+            ([], []) => Ok(BlockType::Empty),
+            ([], [vt]) => Ok(BlockType::Value(*vt)),
+            _ => Ok(BlockType::TypeIndex(self.type_index(ty, offset)?.into())),
+        }
+    }
+
+    /// Parses limits, whose values are `u64` if `is_64` is set or `u32` otherwise.
+    fn limits(&mut self, c: &mut Cursor, is_64: bool) -> Result<Limits> {
+        let value = |c: &mut Cursor| {
+            if is_64 {
+                c.u64()
+            } else {
+                c.u32().map(u64::from)
+            }
+        };
+        let min = value(c)?;
+        match c.peek_atom() {
+            Some(Atom::Number(_)) => Ok(Limits::Bounded {
+                min,
+                max: value(c)?,
+            }),
+            _ => Ok(Limits::Unbounded { min }),
+        }
+    }
+
+    /// Parses a memory type, which is a 64-bit memory if its limits follow `i64` and a shared
+    /// memory if they are followed by `shared`.
+    fn mem_type(&mut self, c: &mut Cursor) -> Result<MemType> {
+        let ty = if c.keyword("i64") {
+            MemType::new64(self.limits(c, true)?)
+        } else {
+            c.keyword("i32");
+            MemType::new(self.limits(c, false)?)
+        };
+        if c.keyword("shared") {
+            Ok(ty.shared())
+        } else {
+            Ok(ty)
+        }
+    }
+
+    fn table_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<TableType> {
+        let limits = self.limits(c, false)?;
+        let element = self
+            .reference_type(c)?
+            .ok_or_else(|| c.error("expected a reference type"))?;
+        Ok(TableType::new(element, limits))
+    }
+
+    /// Parses a tag type as a type use, whose parameters are the values the tag carries.
+    fn tag_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<TagType> {
+        let (idx, _) = self.type_use(c)?;
+        Ok(TagType::new(idx))
+    }
+
+    fn global_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<GlobalType> {
+        match c.list("mut") {
+            Some(mut ty) => {
+                let vt = self.value_type(&mut ty)?;
+                ty.finish()?;
+                Ok(GlobalType::new(vt, true))
+            }
+            None => Ok(GlobalType::new(self.value_type(c)?, false)),
+        }
+    }
+
+    pub(crate) fn value_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<ValueType> {
+        let ty = match c.peek_keyword() {
+            Some("i32") => ValueType::I32,
+            Some("i64") => ValueType::I64,
+            Some("f32") => ValueType::F32,
+            Some("f64") => ValueType::F64,
+            Some("v128") => ValueType::V128,
+            _ => {
+                return match self.reference_type(c)? {
+                    Some(rt) => Ok(rt.into()),
+                    None => Err(c.error("expected a value type")),
+                }
+            }
+        };
+        c.next();
+        Ok(ty)
+    }
+
+    /// Parses a reference type, or an abbreviation of a nullable one, if it is the next item.
+    pub(crate) fn reference_type(
+        &mut self,
+        c: &mut Cursor<'_, 'a>,
+    ) -> Result<Option<ReferenceType>> {
+        if let Some(mut rt) = c.list("ref") {
+            let nullable = rt.keyword("null");
+            let heap = self.heap_type(&mut rt)?;
+            rt.finish()?;
+            return Ok(Some(ReferenceType::new(nullable, heap)));
+        }
+        let Some(kw) = c.peek_keyword() else {
+            return Ok(None);
+        };
+        match REFERENCE_TYPES.iter().find(|(x, _)| *x == kw) {
+            Some(&(_, heap)) => {
+                c.next();
+                Ok(Some(ReferenceType::new(true, heap)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn heap_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<HeapType> {
+        if let Some(kw) = c.peek_keyword() {
+            if let Some(&(_, heap)) = HEAP_TYPES.iter().find(|(x, _)| *x == kw) {
+                c.next();
+                return Ok(heap);
+            }
+        }
+        let idx = c.index().ok_or_else(|| c.error("expected a heap type"))?;
+        Ok(HeapType::Concrete(
+            self.type_ids.resolve(idx, IndexSpace::Type)?,
+        ))
+    }
+
+    /// Parses a type definition of the type section, whose fields are named in `field_ids`.
+    fn sub_type(&mut self, c: &mut Cursor<'_, 'a>, field_ids: &mut Space<'a>) -> Result<SubType> {
+        let Some(mut sub) = c.list("sub") else {
+            let composite = self.composite_type(c, field_ids)?;
+            c.finish()?;
+            return Ok(composite.into());
+        };
+        c.finish()?;
+        let is_final = sub.keyword("final");
+        let mut supertypes = Vec::new();
+        while let Some(idx) = sub.index() {
+            supertypes.push(self.type_ids.resolve(idx, IndexSpace::Type)?);
+        }
+        let composite = self.composite_type(&mut sub, field_ids)?;
+        sub.finish()?;
+        Ok(SubType::new(is_final, supertypes, composite))
+    }
+
+    fn composite_type(
+        &mut self,
+        c: &mut Cursor<'_, 'a>,
+        field_ids: &mut Space<'a>,
+    ) -> Result<CompositeType> {
+        let offset = c.offset();
+        let composite = if let Some(mut func) = c.list("func") {
+            let (ty, _) = self.func_type(&mut func)?;
+            func.finish()?;
+            CompositeType::Func(ty)
+        } else if let Some(mut st) = c.list("struct") {
+            let mut fields = Vec::new();
+            while let Some(mut field) = st.list("field") {
+                let offset = field.offset();
+                if let Some(id) = field.id() {
+                    fields.push(self.field_type(&mut field)?);
+                    field_ids.define(Some(id), offset)?;
+                } else {
+                    while !field.is_empty() {
+                        fields.push(self.field_type(&mut field)?);
+                        field_ids.define(None, offset)?;
+                    }
+                }
+                field.finish()?;
+            }
+            st.finish()?;
+            CompositeType::Struct(fields)
+        } else if let Some(mut array) = c.list("array") {
+            let ty = self.field_type(&mut array)?;
+            array.finish()?;
+            CompositeType::Array(ty)
+        } else {
+            return Err(TextError::new(offset, "expected a composite type"));
+        };
+        Ok(composite)
+    }
+
+    fn field_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<FieldType> {
+        let Some(mut ty) = c.list("mut") else {
+            return Ok(FieldType::new(self.storage_type(c)?, false));
+        };
+        let storage = self.storage_type(&mut ty)?;
+        ty.finish()?;
+        Ok(FieldType::new(storage, true))
+    }
+
+    fn storage_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<StorageType> {
+        if c.keyword("i8") {
+            Ok(StorageType::I8)
+        } else if c.keyword("i16") {
+            Ok(StorageType::I16)
+        } else {
+            self.value_type(c).map(StorageType::Val)
+        }
+    }
+
+    /// Parses the rest of `c` as a constant expression.
+    fn const_expr(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Expression> {
+        let mut body = Body::default();
+        let mut instrs = Vec::new();
+        self.instrs(c, &mut body, &mut instrs)?;
+        c.finish()?;
+        Ok(Expression(instrs))
+    }
+
+    /// Parses `(offset instr*)` or a folded instruction.
+    fn offset(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Expression> {
+        if let Some(mut offset) = c.list("offset") {
+            return self.const_expr(&mut offset);
+        }
+        let Some(sexpr @ Sexpr::List(..)) = c.next() else {
+            return Err(c.error("expected an offset expression"));
+        };
+        let mut body = Body::default();
+        let mut instrs = Vec::new();
+        self.folded(sexpr, &mut body, &mut instrs)?;
+        Ok(Expression(instrs))
+    }
+
+    fn func_indices(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Vec<u32>> {
+        let mut indices = Vec::new();
+        while let Some(idx) = c.index() {
+            indices.push(self.func_ids.resolve(idx, IndexSpace::Function)?);
+        }
+        c.finish()?;
+        Ok(indices)
+    }
+
+    /// Parses `(item instr*)` or folded instructions until the end of `c`.
+    fn elem_exprs(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Vec<Expression>> {
+        let mut exprs = Vec::new();
+        while let Some(sexpr) = c.peek() {
+            if let Some(mut item) = c.list("item") {
+                exprs.push(self.const_expr(&mut item)?);
+            } else if let Sexpr::List(..) = sexpr {
+                c.next();
+                let mut body = Body::default();
+                let mut instrs = Vec::new();
+                self.folded(sexpr, &mut body, &mut instrs)?;
+                exprs.push(Expression(instrs));
+            } else {
+                return Err(c.error("expected an element expression"));
+            }
+        }
+        Ok(exprs)
+    }
+
+    fn elem(&mut self, c: &mut Cursor<'_, 'a>) -> Result<SynthElem> {
+        let mode = if c.keyword("declare") {
+            SynthElemMode::Declarative
+        } else if let Some(mut table) = c.list("table") {
+            let idx = table.expect_index()?;
+            table.finish()?;
+            let table = self.table_ids.resolve(idx, IndexSpace::Table)?;
+            SynthElemMode::Active {
+                table,
+                offset: self.offset(c)?,
+            }
+        } else if let Some(idx) = c.index() {
+            let table = self.table_ids.resolve(idx, IndexSpace::Table)?;
+            SynthElemMode::Active {
+                table,
+                offset: self.offset(c)?,
+            }
+        } else if let Some(Sexpr::List(..)) = c.peek() {
+            SynthElemMode::Active {
+                table: 0,
+                offset: self.offset(c)?,
+            }
+        } else {
+            SynthElemMode::Passive
+        };
+
+        if let Some(rt) = self.reference_type(c)? {
+            let exprs = self.elem_exprs(c)?;
+            return Ok(SynthElem {
+                kind: SynthElemKind::ReferenceType(rt),
+                init: SynthElemInit::Expressions(exprs),
+                mode,
+            });
+        }
+        // function indices without `func` are allowed for active segments only
+        if !c.keyword("func") && !matches!(mode, SynthElemMode::Active { .. }) {
+            return Err(c.error("expected an element list"));
+        }
+        Ok(SynthElem {
+            kind: SynthElemKind::FuncRef,
+            init: SynthElemInit::FuncIndices(self.func_indices(c)?),
+            mode,
+        })
+    }
+
+    fn data(&mut self, c: &mut Cursor<'_, 'a>) -> Result<SynthData> {
+        let memory_index = if let Some(mut memory) = c.list("memory") {
+            let idx = memory.expect_index()?;
+            memory.finish()?;
+            Some(self.memory_ids.resolve(idx, IndexSpace::Memory)?)
+        } else if let Some(idx) = c.index() {
+            Some(self.memory_ids.resolve(idx, IndexSpace::Memory)?)
+        } else {
+            None
+        };
+        if memory_index.is_none() && !matches!(c.peek(), Some(Sexpr::List(..))) {
+            return Ok(SynthData::Passive(data_string(c)?));
+        }
+        let offset = self.offset(c)?;
+        Ok(SynthData::Active {
+            init: data_string(c)?,
+            memory_index: memory_index.unwrap_or(0),
+            offset,
+        })
+    }
+
+    fn finish(self, module_id: Option<&str>) -> SynthModule {
+        let uses_data_count = self.codes.iter().any(SynthCode::refers_to_data);
+
+        let has_names = module_id.is_some()
+            || !self.local_names.is_empty()
+            || !self.label_names.is_empty()
+            || !self.field_ids.is_empty()
+            || [
+                &self.func_ids,
+                &self.type_ids,
+                &self.table_ids,
+                &self.memory_ids,
+                &self.global_ids,
+                &self.elem_ids,
+                &self.data_ids,
+                &self.tag_ids,
+            ]
+            .iter()
+            .any(|x| !x.names.is_empty());
+        let name_section = has_names.then(|| SynthNameSection {
+            module_name: module_id.map(str::to_string),
+            function_names: self.func_ids.into_names(),
+            local_names: (!self.local_names.is_empty()).then_some(self.local_names),
+            label_names: (!self.label_names.is_empty()).then_some(self.label_names),
+            type_names: self.type_ids.into_names(),
+            table_names: self.table_ids.into_names(),
+            memory_names: self.memory_ids.into_names(),
+            global_names: self.global_ids.into_names(),
+            element_segment_names: self.elem_ids.into_names(),
+            data_segment_names: self.data_ids.into_names(),
+            field_names: (!self.field_ids.is_empty()).then(|| {
+                let mut field_names = self
+                    .field_ids
+                    .into_iter()
+                    .map(|(idx, ids)| SynthIndirectNameAssoc {
+                        idx,
+                        name_map: ids.names,
+                    })
+                    .collect::<Vec<_>>();
+                field_names.sort_by_key(|x| x.idx);
+                field_names
+            }),
+            tag_names: self.tag_ids.into_names(),
+        });
+
+        // as in the binary format, sections placed after the last section follow the name section
+        let mut custom_sections = self.custom_sections;
+        custom_sections.sort_by_key(|(placement, _)| match placement {
+            Some(placement) => (false, *placement),
+            None => (true, CustomPlacement::default()),
+        });
+        let name_section_order = custom_sections.iter().filter(|(x, _)| x.is_some()).count();
+
+        let data_count = self.data.len() as u32;
+        let mut module = SynthModule {
+            type_section: (!self.types.is_empty()).then_some(SynthTypeSection {
+                types: self.types,
+                rec_groups: self.rec_groups,
+            }),
+            import_section: (!self.imports.is_empty()).then_some(SynthImportSection {
+                imports: self.imports,
+            }),
+            function_section: (!self.type_indices.is_empty()).then_some(SynthFunctionSection {
+                type_indices: self.type_indices,
+            }),
+            table_section: (!self.tables.is_empty()).then_some(SynthTableSection {
+                tables: self.tables,
+            }),
+            memory_section: (!self.memories.is_empty()).then_some(SynthMemorySection {
+                memories: self.memories,
+            }),
+            tag_section: (!self.tags.is_empty()).then_some(SynthTagSection { tags: self.tags }),
+            global_section: (!self.globals.is_empty()).then_some(SynthGlobalSection {
+                globals: self.globals,
+            }),
+            export_section: (!self.exports.is_empty()).then_some(SynthExportSection {
+                exports: self.exports,
+            }),
+            start_section: self.start.map(|start| SynthStartSection { start }),
+            element_section: (!self.elements.is_empty()).then_some(SynthElementSection {
+                elements: self.elements,
+            }),
+            data_count_section: uses_data_count.then_some(SynthDataCountSection { data_count }),
+            code_section: (!self.codes.is_empty())
+                .then_some(SynthCodeSection { codes: self.codes }),
+            data_section: (!self.data.is_empty()).then_some(SynthDataSection {
+                all_data: self.data,
+            }),
+            custom_sections: custom_sections.into_iter().map(|(_, x)| x).collect(),
+            name_section,
+            name_section_placement: CustomPlacement::default(),
+            name_section_order,
+            original: None,
+        };
+
+        // record placements as the binary parser would, after the last present section
+        for i in 0..module.custom_sections.len() {
+            let placement = module.custom_sections[i].placement;
+            module.custom_sections[i].placement = module.canonical_placement(placement);
+        }
+        module.name_section_placement = module.canonical_placement(module.name_section_placement);
+        module
+    }
+}
+
+/// Parses `(before ...)` or `(after ...)` of a custom section annotation.
+///
+/// Returns `None` if the section is placed after the last section, which is the default.
+fn custom_placement(c: &mut Cursor) -> Result<Option<CustomPlacement>> {
+    let (before, mut place) = if let Some(place) = c.list("before") {
+        (true, place)
+    } else if let Some(place) = c.list("after") {
+        (false, place)
+    } else {
+        return Ok(None);
+    };
+    let offset = place.offset();
+    let kw = place
+        .peek_keyword()
+        .ok_or_else(|| place.error("expected a section name"))?;
+    place.next();
+    place.finish()?;
+    match (before, kw) {
+        (true, "first") => return Ok(Some(CustomPlacement::First)),
+        (false, "last") => return Ok(None),
+        _ => (),
+    }
+    let pos = SectionKind::ALL
+        .iter()
+        .position(|x| x.keyword() == kw)
+        .ok_or_else(|| TextError::new(offset, format!("unknown section `{kw}`")))?;
+    Ok(Some(match (before, pos) {
+        (true, 0) => CustomPlacement::First,
+        (true, pos) => CustomPlacement::After(SectionKind::ALL[pos - 1]),
+        (false, pos) => CustomPlacement::After(SectionKind::ALL[pos]),
+    }))
+}
+
+/// Returns whether a memory with inline data is a 64-bit memory, and the items of the data.
+fn inline_data<'s, 'a>(c: &mut Cursor<'s, 'a>) -> Option<(bool, Cursor<'s, 'a>)> {
+    let mut rest = *c;
+    let is_64 = rest.keyword("i64");
+    if !is_64 {
+        rest.keyword("i32");
+    }
+    let data = rest.list("data")?;
+    *c = rest;
+    Some((is_64, data))
+}
+
+/// Concatenates the rest of `c` as strings.
+fn data_string(c: &mut Cursor) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while let Some(s) = c.string() {
+        bytes.extend_from_slice(s);
+    }
+    c.finish()?;
+    Ok(bytes)
+}
+
+fn parse_module(src: &str) -> Result<SynthModule> {
+    let sexprs = lex::parse(src)?;
+    let (module_id, fields) = match &sexprs[..] {
+        [sexpr] if matches!(sexpr.as_list(), Some(("module", _))) => {
+            let (_, items) = sexpr.as_list().unwrap();
+            let mut c = Cursor::new(items, sexpr.offset());
+            (c.id(), c.items)
+        }
+        // a module may consist of its fields only
+        fields => (None, fields),
+    };
+
+    let mut parser = ModuleParser::default();
+    parser.declare(fields)?;
+    parser.define(fields)?;
+    Ok(parser.finish(module_id))
+}
+
+impl SynthModule {
+    /// Parses a module in the WebAssembly text format.
+    ///
+    /// Identifiers are recorded in the name section. The module is not validated.
+    pub fn from_wat(src: &str) -> std::result::Result<Self, Error> {
+        parse_module(src).map_err(|err| err.into_error(src))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse::Module, synth::SynthModule, Error};
+
+    /// Parses `wat_s` with [`SynthModule::from_wat`] and with the `wat` crate, and compares the
+    /// printed results.
+    fn assert_same_as_wat_crate(wat_s: &str) {
+        let module = SynthModule::from_wat(wat_s).expect("cannot parse wat");
+        let mut buf = Vec::new();
+        module.write_into(&mut buf).unwrap();
+        wasmparser::validate(&buf).expect("parsed module is invalid");
+
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat with the wat crate");
+        let expected = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        assert_eq!(module.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_from_wat() {
+        assert_same_as_wat_crate(
+            r#"(module $m
+                (import "env" "log" (func $log (param i32)))
+                (func $get (import "env" "get") (result i32))
+                (type $binop (func (param i32 i32) (result i32)))
+                (memory $mem (export "memory") 1)
+                (table $tbl funcref (elem $add $sub))
+                (global $g (mut i32) (i32.const 8))
+                (func $add (type $binop) (i32.add (local.get 0) (local.get 1)))
+                (func $sub (type $binop) (param $a i32) (param $b i32) (result i32)
+                    local.get $a
+                    local.get $b
+                    i32.sub
+                )
+                (func $main (export "main") (param $n i32) (result i32) (local $acc i32)
+                    (block $done
+                        (loop $next
+                            (br_if $done (i32.eqz (local.get $n)))
+                            (local.set $acc
+                                (call_indirect $tbl (type $binop)
+                                    (local.get $acc)
+                                    (i32.load offset=4 (global.get $g))
+                                    (i32.and (local.get $n) (i32.const 1))))
+                            (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                            (br $next)))
+                    (if (result i32) (local.get $acc)
+                        (then (call $log (local.get $acc)) (call $get))
+                        (else i32.const -1))
+                )
+                (data $d (i32.const 8) "\01\00\00\00" "\02\00\00\00")
+                (@custom "note" "hello")
+            )"#,
+        );
+    }
+
+    #[test]
+    fn test_from_wat_plain_blocks() {
+        assert_same_as_wat_crate(
+            r#"(module
+                (memory 1)
+                (data $passive "abc")
+                (elem $e func $f)
+                (table 1 funcref)
+                (func $f (param i32) (result i64 i64)
+                    local.get 0
+                    block $a (param i32) (result i64 i64)
+                        drop
+                        i64.const 1
+                        i64.const 0xff_ff
+                        local.get 0
+                        if $b
+                            i64.const -1
+                            i64.const 0
+                            br $a
+                        else
+                            i32.const 0
+                            i32.const 0
+                            i32.const 3
+                            memory.init $passive
+                            i32.const 0
+                            i32.const 0
+                            i32.const 1
+                            table.init $e
+                        end $b
+                    end
+                    v128.const i16x8 1 -1 2 -2 0xffff 0 0 0
+                    f32x4.extract_lane 3
+                    drop
+                )
+            )"#,
+        );
+    }
+
+    #[test]
+    fn test_from_wat_exceptions() {
+        // wasmparser does not support `try_table` yet, so the module is checked by `validate`
+        let wat_s = r#"(module
+            (tag $e (param i32))
+            (func $f (param i32) (result i32 exnref)
+                block $caught (result i32 exnref)
+                    block $none
+                        try_table $t (catch_ref $e $caught) (catch_all $none)
+                            local.get 0
+                            throw $e
+                        end
+                        unreachable
+                    end
+                    unreachable
+                end
+            )
+            (func (param exnref) (result exnref)
+                (try_table (catch_all_ref 0) (throw_ref (local.get 0)))
+                ref.null exn
+            )
+        )"#;
+        let module = SynthModule::from_wat(wat_s).expect("cannot parse wat");
+        module.validate().expect("parsed module is invalid");
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat with the wat crate");
+        let expected = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        assert_eq!(module.to_string(), expected.to_string());
+
+        // folded legacy `try`, which the wat crate no longer accepts
+        let folded = SynthModule::from_wat(
+            r#"(module
+                (tag $e (param i32))
+                (func (result i32)
+                    (try $t (result i32)
+                        (do (throw $e (i32.const 1)))
+                        (catch $e)
+                        (catch_all (rethrow $t))))
+            )"#,
+        )
+        .expect("cannot parse wat");
+        folded.validate().expect("parsed module is invalid");
+        let plain = SynthModule::from_wat(
+            r#"(module
+                (tag $e (param i32))
+                (func (result i32)
+                    try $t (result i32)
+                        i32.const 1
+                        throw $e
+                    catch $e
+                    catch_all
+                        rethrow $t
+                    end)
+            )"#,
+        )
+        .expect("cannot parse wat");
+        assert_eq!(folded.to_string(), plain.to_string());
+    }
+
+    #[test]
+    fn test_from_wat_gc() {
+        // wasmparser does not support GC yet, so the module is checked by `validate`
+        let wat_s = r#"(module
+            (type $node (sub (struct (field $value (mut i32)) (field $next (ref null $node)))))
+            (type $pair (sub final $node (struct (field (mut i32)) (field (ref null $node)) (field i8))))
+            ;; the wat crate does not name fields of types in rec groups
+            (rec (type $bytes (array (mut i8))) (type $funcs (array funcref)))
+            (type $callback (func (param (ref $node)) (result i32)))
+            (elem $refs funcref (ref.func $value))
+            (data $init "abc")
+            (global $empty (ref $bytes) (array.new_fixed $bytes 2 (i32.const 1) (i32.const 2)))
+            (func $value (type $callback) (struct.get $node $value (local.get 0)))
+            (func $walk (param $n (ref null $node)) (result i32) (local $pair (ref $pair))
+                block $null
+                    local.get $n
+                    br_on_null $null
+                    (call_ref $callback (ref.func $value))
+                    return
+                end
+                (local.set $pair
+                    (struct.new $pair (i32.const 1) (ref.null none) (i32.const 255)))
+                (struct.set $node $value (local.get $pair) (i32.const 2))
+                (i32.add
+                    (struct.get_u $pair 2 (local.get $pair))
+                    (ref.test (ref $pair) (local.get $pair)))
+            )
+            (func $arrays (param $a (ref null $bytes)) (param anyref) (result i32 externref)
+                (array.copy $bytes $bytes
+                    (local.get $a) (i32.const 0)
+                    (array.new_data $bytes $init (i32.const 0) (i32.const 3)) (i32.const 0)
+                    (i32.const 3))
+                (array.fill $bytes (local.get $a) (i32.const 0) (i32.const 7) (i32.const 1))
+                (drop (array.new_elem $funcs $refs (i32.const 0) (i32.const 0)))
+                block $cast (result (ref i31))
+                    (br_on_cast $cast anyref (ref i31) (local.get 1))
+                    drop
+                    (ref.i31 (array.get_s $bytes (local.get $a) (i32.const 0)))
+                end
+                i31.get_u
+                (extern.convert_any (ref.cast (ref null eq) (local.get 1)))
+            )
+        )"#;
+        let module = SynthModule::from_wat(wat_s).expect("cannot parse wat");
+        module.validate().expect("parsed module is invalid");
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat with the wat crate");
+        let expected = Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .into_synth()
+            .expect("cannot convert into synth");
+        assert_eq!(module.to_string(), expected.to_string());
+
+        let mut buf = Vec::new();
+        module.write_into(&mut buf).unwrap();
+        assert_eq!(buf, wasm);
+
+        let Err(Error::Wat { message, .. }) = SynthModule::from_wat(
+            "(module (type $s (struct (field $a i32))) (func (param (ref $s)) (result i32) \
+             (struct.get $s $b (local.get 0))))",
+        ) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(message, "unknown field $b");
+    }
+
+    #[test]
+    fn test_from_wat_errors() {
+        let Err(Error::Wat {
+            line,
+            column,
+            message,
+        }) = SynthModule::from_wat("(module\n  (func\n    local.get $x))")
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((line, column), (3, 15));
+        assert_eq!(message, "unknown local $x");
+
+        assert!(SynthModule::from_wat("(module (func $f) (func $f))").is_err());
+        assert!(SynthModule::from_wat("(module (func) (import \"a\" \"b\" (func)))").is_err());
+        assert!(SynthModule::from_wat("(module (func block end $l))").is_err());
+    }
+}
@@ -0,0 +1,1197 @@
+//! Instructions of the text format, in plain and folded forms.
+
+use crate::{
+    instructions::{Catch, Instruction, MemArg},
+    synth::IndexSpace,
+    wasm_types::ReferenceType,
+};
+
+use super::{
+    lex::{self, Atom, Sexpr},
+    Body, Cursor, Idx, ModuleParser, Result, TextError,
+};
+
+impl<'a> ModuleParser<'a> {
+    /// Parses plain and folded instructions until the end of `c`, or until `end`, `else` or a
+    /// clause of `try`.
+    pub(super) fn instrs(
+        &mut self,
+        c: &mut Cursor<'_, 'a>,
+        body: &mut Body<'a>,
+        out: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        loop {
+            let offset = c.offset();
+            match c.peek() {
+                None => return Ok(()),
+                Some(sexpr @ Sexpr::List(..)) => {
+                    c.next();
+                    self.folded(sexpr, body, out)?;
+                }
+                Some(Sexpr::Atom(Atom::Keyword(kw), _)) => match *kw {
+                    "end" | "else" | "catch" | "catch_all" | "delegate" => return Ok(()),
+                    "block" | "loop" | "if" => {
+                        c.next();
+                        out.push(self.plain_block(kw, c, body)?);
+                    }
+                    "try" => {
+                        c.next();
+                        out.push(self.plain_try(c, body)?);
+                    }
+                    "try_table" => {
+                        c.next();
+                        let label = c.id();
+                        let bt = self.block_type(c)?;
+                        let catches = self.catches(c, body)?;
+                        body.push_label(label);
+                        let mut instrs = Vec::new();
+                        self.instrs(c, body, &mut instrs)?;
+                        if !c.keyword("end") {
+                            return Err(c.error("expected `end`"));
+                        }
+                        closing_label(c, label)?;
+                        body.labels.pop();
+                        out.push(Instruction::TryTable(bt, catches, instrs));
+                    }
+                    _ => {
+                        c.next();
+                        out.push(self.instr(kw, offset, c, body)?);
+                    }
+                },
+                Some(Sexpr::Atom(..)) => return Err(c.error("expected an instruction")),
+            }
+        }
+    }
+
+    /// Parses the rest of a plain `block`, `loop` or `if` instruction, up to its `end`.
+    fn plain_block(
+        &mut self,
+        kw: &str,
+        c: &mut Cursor<'_, 'a>,
+        body: &mut Body<'a>,
+    ) -> Result<Instruction> {
+        let label = c.id();
+        let bt = self.block_type(c)?;
+        body.push_label(label);
+        let mut instrs = Vec::new();
+        self.instrs(c, body, &mut instrs)?;
+        let mut elseinstrs = None;
+        if kw == "if" && c.keyword("else") {
+            closing_label(c, label)?;
+            let mut instrs = Vec::new();
+            self.instrs(c, body, &mut instrs)?;
+            elseinstrs = Some(instrs);
+        }
+        if !c.keyword("end") {
+            return Err(c.error("expected `end`"));
+        }
+        closing_label(c, label)?;
+        body.labels.pop();
+        Ok(match kw {
+            "block" => Instruction::Block(bt, instrs),
+            "loop" => Instruction::Loop(bt, instrs),
+            _ => Instruction::If(bt, instrs, elseinstrs),
+        })
+    }
+
+    /// Parses the rest of a plain `try` instruction, up to its `end` or `delegate`.
+    fn plain_try(&mut self, c: &mut Cursor<'_, 'a>, body: &mut Body<'a>) -> Result<Instruction> {
+        let id = c.id();
+        let bt = self.block_type(c)?;
+        body.push_label(id);
+        let mut instrs = Vec::new();
+        self.instrs(c, body, &mut instrs)?;
+        if c.keyword("delegate") {
+            // the label is relative to the blocks enclosing the try block
+            body.labels.pop();
+            return Ok(Instruction::TryDelegate(bt, instrs, label(c, body)?));
+        }
+        let mut catches = Vec::new();
+        while c.keyword("catch") {
+            let tag = self.tag_idx(c)?;
+            let mut instrs = Vec::new();
+            self.instrs(c, body, &mut instrs)?;
+            catches.push((tag, instrs));
+        }
+        let mut catch_all = None;
+        if c.keyword("catch_all") {
+            let mut instrs = Vec::new();
+            self.instrs(c, body, &mut instrs)?;
+            catch_all = Some(instrs);
+        }
+        if !c.keyword("end") {
+            return Err(c.error("expected `end`"));
+        }
+        closing_label(c, id)?;
+        body.labels.pop();
+        Ok(Instruction::Try(bt, instrs, catches, catch_all))
+    }
+
+    /// Parses catch clauses of `try_table`, whose labels are resolved outside of the block.
+    fn catches(&mut self, c: &mut Cursor<'_, 'a>, body: &Body<'a>) -> Result<Vec<Catch>> {
+        let mut catches = Vec::new();
+        while let Some((kw, mut clause)) = c.peek_list() {
+            let catch = match kw {
+                "catch" => Catch::Tag {
+                    tag: self.tag_idx(&mut clause)?,
+                    label: label(&mut clause, body)?,
+                },
+                "catch_ref" => Catch::TagRef {
+                    tag: self.tag_idx(&mut clause)?,
+                    label: label(&mut clause, body)?,
+                },
+                "catch_all" => Catch::All {
+                    label: label(&mut clause, body)?,
+                },
+                "catch_all_ref" => Catch::AllRef {
+                    label: label(&mut clause, body)?,
+                },
+                _ => break,
+            };
+            clause.finish()?;
+            c.next();
+            catches.push(catch);
+        }
+        Ok(catches)
+    }
+
+    /// Parses a folded instruction, writing it after its operands into `out`.
+    pub(super) fn folded(
+        &mut self,
+        sexpr: &Sexpr<'a>,
+        body: &mut Body<'a>,
+        out: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        let Some((kw, items)) = sexpr.as_list() else {
+            return Err(TextError::new(sexpr.offset(), "expected an instruction"));
+        };
+        let mut c = Cursor::new(items, sexpr.offset());
+        match kw {
+            "block" | "loop" => {
+                let label = c.id();
+                let bt = self.block_type(&mut c)?;
+                body.push_label(label);
+                let mut instrs = Vec::new();
+                self.instrs(&mut c, body, &mut instrs)?;
+                c.finish()?;
+                body.labels.pop();
+                out.push(match kw {
+                    "block" => Instruction::Block(bt, instrs),
+                    _ => Instruction::Loop(bt, instrs),
+                });
+            }
+            "if" => {
+                let label = c.id();
+                let bt = self.block_type(&mut c)?;
+                // conditions precede the `if` itself, including their labels
+                while let Some((kw, _)) = c.peek_list() {
+                    if kw == "then" {
+                        break;
+                    }
+                    self.folded(c.next().unwrap(), body, out)?;
+                }
+                body.push_label(label);
+                let mut then = c.list("then").ok_or_else(|| c.error("expected `(then`"))?;
+                let mut instrs = Vec::new();
+                self.instrs(&mut then, body, &mut instrs)?;
+                then.finish()?;
+                let elseinstrs = match c.list("else") {
+                    Some(mut else_) => {
+                        let mut instrs = Vec::new();
+                        self.instrs(&mut else_, body, &mut instrs)?;
+                        else_.finish()?;
+                        Some(instrs)
+                    }
+                    None => None,
+                };
+                c.finish()?;
+                body.labels.pop();
+                out.push(Instruction::If(bt, instrs, elseinstrs));
+            }
+            "try" => {
+                let id = c.id();
+                let bt = self.block_type(&mut c)?;
+                body.push_label(id);
+                let mut do_ = c.list("do").ok_or_else(|| c.error("expected `(do`"))?;
+                let mut instrs = Vec::new();
+                self.instrs(&mut do_, body, &mut instrs)?;
+                do_.finish()?;
+                if let Some(mut delegate) = c.list("delegate") {
+                    body.labels.pop();
+                    let l = label(&mut delegate, body)?;
+                    delegate.finish()?;
+                    c.finish()?;
+                    out.push(Instruction::TryDelegate(bt, instrs, l));
+                    return Ok(());
+                }
+                let mut catches = Vec::new();
+                while let Some(mut catch) = c.list("catch") {
+                    let tag = self.tag_idx(&mut catch)?;
+                    let mut instrs = Vec::new();
+                    self.instrs(&mut catch, body, &mut instrs)?;
+                    catch.finish()?;
+                    catches.push((tag, instrs));
+                }
+                let catch_all = match c.list("catch_all") {
+                    Some(mut catch_all) => {
+                        let mut instrs = Vec::new();
+                        self.instrs(&mut catch_all, body, &mut instrs)?;
+                        catch_all.finish()?;
+                        Some(instrs)
+                    }
+                    None => None,
+                };
+                c.finish()?;
+                body.labels.pop();
+                out.push(Instruction::Try(bt, instrs, catches, catch_all));
+            }
+            "try_table" => {
+                let label = c.id();
+                let bt = self.block_type(&mut c)?;
+                let catches = self.catches(&mut c, body)?;
+                body.push_label(label);
+                let mut instrs = Vec::new();
+                self.instrs(&mut c, body, &mut instrs)?;
+                c.finish()?;
+                body.labels.pop();
+                out.push(Instruction::TryTable(bt, catches, instrs));
+            }
+            _ => {
+                let instr = self.instr(kw, sexpr.offset(), &mut c, body)?;
+                while let Some(operand) = c.next() {
+                    self.folded(operand, body, out)?;
+                }
+                out.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses immediates of the instruction `kw`.
+    fn instr(
+        &mut self,
+        kw: &str,
+        offset: usize,
+        c: &mut Cursor<'_, 'a>,
+        body: &mut Body<'a>,
+    ) -> Result<Instruction> {
+        use Instruction as I;
+
+        let instr = match kw {
+            "br" => I::Br(label(c, body)?),
+            "br_if" => I::BrIf(label(c, body)?),
+            "br_table" => {
+                let mut labels = vec![label(c, body)?];
+                while let Some(Atom::Id(_) | Atom::Number(_)) = c.peek_atom() {
+                    labels.push(label(c, body)?);
+                }
+                let default = labels.pop().unwrap();
+                I::BrTable(labels, default)
+            }
+            "throw" => I::Throw(self.tag_idx(c)?),
+            "rethrow" => I::Rethrow(label(c, body)?),
+            "call" => I::Call(self.func_idx(c)?),
+            "return_call" => I::ReturnCall(self.func_idx(c)?),
+            "call_indirect" | "return_call_indirect" => {
+                let table = match c.index() {
+                    Some(idx) => self.table_ids.resolve(idx, IndexSpace::Table)?,
+                    None => 0,
+                };
+                let (ty, param_ids) = self.type_use(c)?;
+                if param_ids.iter().any(Option::is_some) {
+                    return Err(TextError::new(offset, "parameters cannot have identifiers"));
+                }
+                if kw == "call_indirect" {
+                    I::CallIndirect { ty, table }
+                } else {
+                    I::ReturnCallIndirect { ty, table }
+                }
+            }
+            "call_ref" => I::CallRef(self.type_idx(c)?),
+            "return_call_ref" => I::ReturnCallRef(self.type_idx(c)?),
+            "ref.null" => I::RefNull(ReferenceType::new(true, self.heap_type(c)?)),
+            "ref.func" => I::RefFunc(self.func_idx(c)?),
+            "br_on_null" => I::BrOnNull(label(c, body)?),
+            "br_on_non_null" => I::BrOnNonNull(label(c, body)?),
+            "struct.new" => I::StructNew(self.type_idx(c)?),
+            "struct.new_default" => I::StructNewDefault(self.type_idx(c)?),
+            "struct.get" | "struct.get_s" | "struct.get_u" | "struct.set" => {
+                let ty = self.type_idx(c)?;
+                let field = self.field_idx(c, ty)?;
+                match kw {
+                    "struct.get" => I::StructGet(ty, field),
+                    "struct.get_s" => I::StructGetS(ty, field),
+                    "struct.get_u" => I::StructGetU(ty, field),
+                    _ => I::StructSet(ty, field),
+                }
+            }
+            "array.new" => I::ArrayNew(self.type_idx(c)?),
+            "array.new_default" => I::ArrayNewDefault(self.type_idx(c)?),
+            "array.new_fixed" => {
+                let ty = self.type_idx(c)?;
+                I::ArrayNewFixed(ty, number(c, lex::parse_u32, "u32")?)
+            }
+            "array.new_data" | "array.init_data" => {
+                let ty = self.type_idx(c)?;
+                let data = self.data_ids.resolve(c.expect_index()?, IndexSpace::Data)?;
+                if kw == "array.new_data" {
+                    I::ArrayNewData(ty, data)
+                } else {
+                    I::ArrayInitData(ty, data)
+                }
+            }
+            "array.new_elem" | "array.init_elem" => {
+                let ty = self.type_idx(c)?;
+                let elem = self
+                    .elem_ids
+                    .resolve(c.expect_index()?, IndexSpace::Element)?;
+                if kw == "array.new_elem" {
+                    I::ArrayNewElem(ty, elem)
+                } else {
+                    I::ArrayInitElem(ty, elem)
+                }
+            }
+            "array.get" => I::ArrayGet(self.type_idx(c)?),
+            "array.get_s" => I::ArrayGetS(self.type_idx(c)?),
+            "array.get_u" => I::ArrayGetU(self.type_idx(c)?),
+            "array.set" => I::ArraySet(self.type_idx(c)?),
+            "array.fill" => I::ArrayFill(self.type_idx(c)?),
+            "array.copy" => {
+                let dst = self.type_idx(c)?;
+                I::ArrayCopy(dst, self.type_idx(c)?)
+            }
+            "ref.test" => I::RefTest(self.expect_reference_type(c)?),
+            "ref.cast" => I::RefCast(self.expect_reference_type(c)?),
+            "br_on_cast" | "br_on_cast_fail" => {
+                let l = label(c, body)?;
+                let rt1 = self.expect_reference_type(c)?;
+                let rt2 = self.expect_reference_type(c)?;
+                if kw == "br_on_cast" {
+                    I::BrOnCast(l, rt1, rt2)
+                } else {
+                    I::BrOnCastFail(l, rt1, rt2)
+                }
+            }
+            "select" => {
+                let mut types = None;
+                while let Some(mut result) = c.list("result") {
+                    let types = types.get_or_insert_with(Vec::new);
+                    while !result.is_empty() {
+                        types.push(self.value_type(&mut result)?);
+                    }
+                }
+                match types {
+                    Some(types) => I::Select(types),
+                    None => I::SelectNumeric,
+                }
+            }
+            "local.get" => I::LocalGet(local(c, body)?),
+            "local.set" => I::LocalSet(local(c, body)?),
+            "local.tee" => I::LocalTee(local(c, body)?),
+            "global.get" => I::GlobalGet(self.global_idx(c)?),
+            "global.set" => I::GlobalSet(self.global_idx(c)?),
+            "table.get" => I::TableGet(self.table_or_zero(c)?),
+            "table.set" => I::TableSet(self.table_or_zero(c)?),
+            "table.grow" => I::TableGrow(self.table_or_zero(c)?),
+            "table.size" => I::TableSize(self.table_or_zero(c)?),
+            "table.fill" => I::TableFill(self.table_or_zero(c)?),
+            "table.init" => {
+                let first = c.expect_index()?;
+                match c.index() {
+                    Some(elem) => I::TableInit(
+                        self.elem_ids.resolve(elem, IndexSpace::Element)?,
+                        self.table_ids.resolve(first, IndexSpace::Table)?,
+                    ),
+                    None => I::TableInit(self.elem_ids.resolve(first, IndexSpace::Element)?, 0),
+                }
+            }
+            "table.copy" => match c.index() {
+                Some(dst) => {
+                    let src = c.expect_index()?;
+                    I::TableCopy(
+                        self.table_ids.resolve(dst, IndexSpace::Table)?,
+                        self.table_ids.resolve(src, IndexSpace::Table)?,
+                    )
+                }
+                None => I::TableCopy(0, 0),
+            },
+            "elem.drop" => I::ElemDrop(
+                self.elem_ids
+                    .resolve(c.expect_index()?, IndexSpace::Element)?,
+            ),
+            "memory.init" => {
+                let mut memory = *c;
+                let first = c.expect_index()?;
+                let (data, memory) = match c.index() {
+                    Some(data) => (data, self.memory_or_zero(&mut memory)?),
+                    None => (first, 0),
+                };
+                I::MemoryInit(self.data_ids.resolve(data, IndexSpace::Data)?, memory)
+            }
+            "memory.size" => I::MemorySize(self.memory_or_zero(c)?),
+            "memory.grow" => I::MemoryGrow(self.memory_or_zero(c)?),
+            "memory.fill" => I::MemoryFill(self.memory_or_zero(c)?),
+            "memory.copy" => {
+                let dst = self.memory_or_zero(c)?;
+                I::MemoryCopy(dst, self.memory_or_zero(c)?)
+            }
+            "data.drop" => I::DataDrop(self.data_ids.resolve(c.expect_index()?, IndexSpace::Data)?),
+            "i32.const" => I::I32Const(number(c, lex::parse_i32, "i32")?),
+            "i64.const" => I::I64Const(number(c, lex::parse_i64, "i64")?),
+            "f32.const" => I::F32Const(number(c, lex::parse_f32, "f32")?),
+            "f64.const" => I::F64Const(number(c, lex::parse_f64, "f64")?),
+            "v128.const" => I::V128Const(v128(c)?),
+            "i8x16.shuffle" => {
+                let mut lanes = [0; 16];
+                for lane in &mut lanes {
+                    *lane = lane_index(c, 32)?;
+                }
+                I::I8x16Shuffle(lanes)
+            }
+            _ => {
+                if let Some((_, ctor)) = MEMARG.iter().find(|(x, _)| *x == kw) {
+                    let memory = self.memory_or_zero(c)?;
+                    let memarg = memarg(
+                        c,
+                        memory,
+                        ctor(MemArg {
+                            align: 0,
+                            memory: 0,
+                            offset: 0,
+                        }),
+                    )?;
+                    ctor(memarg)
+                } else if let Some((_, ctor)) = MEMARG_LANE.iter().find(|(x, _)| *x == kw) {
+                    // a single number is the lane index rather than a memory index
+                    let mut rest = *c;
+                    let memory = match (rest.index(), rest.peek_atom()) {
+                        (Some(_), Some(Atom::Number(_) | Atom::Keyword(_))) => {
+                            self.memory_or_zero(c)?
+                        }
+                        _ => 0,
+                    };
+                    let memarg = memarg(
+                        c,
+                        memory,
+                        ctor(
+                            MemArg {
+                                align: 0,
+                                memory: 0,
+                                offset: 0,
+                            },
+                            0,
+                        ),
+                    )?;
+                    let lanes = 16 >> ctor(memarg, 0).natural_alignment();
+                    ctor(memarg, lane_index(c, lanes)?)
+                } else if let Some((_, ctor, lanes)) = LANE.iter().find(|(x, ..)| *x == kw) {
+                    ctor(lane_index(c, *lanes)?)
+                } else if let Some((_, instr)) = PLAIN.iter().find(|(x, _)| *x == kw) {
+                    instr.clone()
+                } else {
+                    return Err(TextError::new(
+                        offset,
+                        format!("unknown instruction `{kw}`"),
+                    ));
+                }
+            }
+        };
+        Ok(instr)
+    }
+
+    fn type_idx(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        self.type_ids.resolve(c.expect_index()?, IndexSpace::Type)
+    }
+
+    /// Resolves a field of the struct type `ty`.
+    fn field_idx(&mut self, c: &mut Cursor<'_, 'a>, ty: u32) -> Result<u32> {
+        match c.expect_index()? {
+            Idx::Num(x) => Ok(x),
+            Idx::Id(id, offset) => self
+                .field_ids
+                .get(&ty)
+                .and_then(|fields| fields.ids.get(id).copied())
+                .ok_or_else(|| TextError::new(offset, format!("unknown field ${id}"))),
+        }
+    }
+
+    fn expect_reference_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<ReferenceType> {
+        self.reference_type(c)?
+            .ok_or_else(|| c.error("expected a reference type"))
+    }
+
+    fn func_idx(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        self.func_ids
+            .resolve(c.expect_index()?, IndexSpace::Function)
+    }
+
+    fn global_idx(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        self.global_ids
+            .resolve(c.expect_index()?, IndexSpace::Global)
+    }
+
+    fn tag_idx(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        self.tag_ids.resolve(c.expect_index()?, IndexSpace::Tag)
+    }
+
+    fn memory_or_zero(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        match c.index() {
+            Some(idx) => self.memory_ids.resolve(idx, IndexSpace::Memory),
+            None => Ok(0),
+        }
+    }
+
+    fn table_or_zero(&mut self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        match c.index() {
+            Some(idx) => self.table_ids.resolve(idx, IndexSpace::Table),
+            None => Ok(0),
+        }
+    }
+}
+
+impl<'a> Body<'a> {
+    /// Enters a block labeled `label`.
+    pub(super) fn push_label(&mut self, label: Option<&'a str>) {
+        if let Some(label) = label {
+            self.label_names
+                .push(crate::synth::sections::SynthNameAssoc {
+                    idx: self.label_count,
+                    name: label.to_string(),
+                });
+        }
+        self.label_count += 1;
+        self.labels.push(label);
+    }
+}
+
+/// Checks the optional label after `else` or `end` against the label of the block.
+fn closing_label(c: &mut Cursor, label: Option<&str>) -> Result<()> {
+    let offset = c.offset();
+    match c.id() {
+        Some(id) if Some(id) != label => {
+            Err(TextError::new(offset, format!("mismatching label ${id}")))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolves a label into its relative depth.
+fn label(c: &mut Cursor, body: &Body) -> Result<u32> {
+    match c.expect_index()? {
+        Idx::Num(x) => Ok(x),
+        Idx::Id(id, offset) => body
+            .labels
+            .iter()
+            .rev()
+            .position(|x| *x == Some(id))
+            .map(|x| x as u32)
+            .ok_or_else(|| TextError::new(offset, format!("unknown label ${id}"))),
+    }
+}
+
+fn local<'a>(c: &mut Cursor<'_, 'a>, body: &Body<'a>) -> Result<u32> {
+    body.locals.resolve(c.expect_index()?, IndexSpace::Local)
+}
+
+/// Parses a number literal with `parse`. Keywords are accepted for `inf` and `nan` of floats.
+fn number<T>(c: &mut Cursor, parse: fn(&str) -> Option<T>, ty: &str) -> Result<T> {
+    let token = match c.peek_atom() {
+        Some(Atom::Number(token) | Atom::Keyword(token)) => token,
+        _ => return Err(c.error(format!("expected an {ty} literal"))),
+    };
+    let n = parse(token).ok_or_else(|| c.error(format!("invalid {ty} literal")))?;
+    c.next();
+    Ok(n)
+}
+
+/// Parses an integer literal of `bits` bits, either signed or unsigned.
+fn int(c: &mut Cursor, bits: u32) -> Result<u64> {
+    let offset = c.offset();
+    let n = number(c, lex::parse_i64, "integer")?;
+    if bits < 64 && !(-(1 << (bits - 1))..(1 << bits)).contains(&n) {
+        return Err(TextError::new(offset, "integer constant out of range"));
+    }
+    Ok(n as u64 & (u64::MAX >> (64 - bits)))
+}
+
+fn lane_index(c: &mut Cursor, lanes: u32) -> Result<u32> {
+    let offset = c.offset();
+    let lane = c.u32()?;
+    if lane >= lanes {
+        return Err(TextError::new(offset, "lane index out of range"));
+    }
+    Ok(lane)
+}
+
+/// Parses `offset=` and `align=` of `instr` accessing `memory`, which defaults to its natural
+/// alignment.
+fn memarg(c: &mut Cursor, memory: u32, instr: Instruction) -> Result<MemArg> {
+    let mut memarg = MemArg {
+        align: instr.natural_alignment(),
+        memory,
+        offset: 0,
+    };
+    if let Some(offset) = c.peek_keyword().and_then(|x| x.strip_prefix("offset=")) {
+        memarg.offset = lex::parse_u64(offset).ok_or_else(|| c.error("invalid offset"))?;
+        c.next();
+    }
+    if let Some(align) = c.peek_keyword().and_then(|x| x.strip_prefix("align=")) {
+        match lex::parse_u32(align) {
+            Some(align) if align.is_power_of_two() => memarg.align = align.trailing_zeros(),
+            _ => return Err(c.error("alignment must be a power of two")),
+        }
+        c.next();
+    }
+    Ok(memarg)
+}
+
+/// Parses the shape and lanes of `v128.const` into a little-endian integer.
+fn v128(c: &mut Cursor) -> Result<u128> {
+    let shape = c
+        .peek_keyword()
+        .ok_or_else(|| c.error("expected a vector shape"))?;
+    c.next();
+    let mut bytes = Vec::with_capacity(16);
+    match shape {
+        "i8x16" | "i16x8" | "i32x4" | "i64x2" => {
+            let bits = 128
+                / shape[shape.find('x').unwrap() + 1..]
+                    .parse::<u32>()
+                    .unwrap();
+            for _ in 0..128 / bits {
+                bytes.extend_from_slice(&int(c, bits)?.to_le_bytes()[..bits as usize / 8]);
+            }
+        }
+        "f32x4" => {
+            for _ in 0..4 {
+                bytes.extend_from_slice(&number(c, lex::parse_f32, "f32")?.to_le_bytes());
+            }
+        }
+        "f64x2" => {
+            for _ in 0..2 {
+                bytes.extend_from_slice(&number(c, lex::parse_f64, "f64")?.to_le_bytes());
+            }
+        }
+        _ => return Err(c.error("unknown vector shape")),
+    }
+    Ok(u128::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+type MemArgInstruction = fn(MemArg) -> Instruction;
+type MemArgLaneInstruction = fn(MemArg, u32) -> Instruction;
+type LaneInstruction = fn(u32) -> Instruction;
+
+/// Load and store instructions with a memory argument.
+#[rustfmt::skip]
+const MEMARG: &[(&str, MemArgInstruction)] = &[
+    ("i32.load", Instruction::I32Load),
+    ("i64.load", Instruction::I64Load),
+    ("f32.load", Instruction::F32Load),
+    ("f64.load", Instruction::F64Load),
+    ("i32.load8_s", Instruction::I32Load8S),
+    ("i32.load8_u", Instruction::I32Load8U),
+    ("i32.load16_s", Instruction::I32Load16S),
+    ("i32.load16_u", Instruction::I32Load16U),
+    ("i64.load8_s", Instruction::I64Load8S),
+    ("i64.load8_u", Instruction::I64Load8U),
+    ("i64.load16_s", Instruction::I64Load16S),
+    ("i64.load16_u", Instruction::I64Load16U),
+    ("i64.load32_s", Instruction::I64Load32S),
+    ("i64.load32_u", Instruction::I64Load32U),
+    ("i32.store", Instruction::I32Store),
+    ("i64.store", Instruction::I64Store),
+    ("f32.store", Instruction::F32Store),
+    ("f64.store", Instruction::F64Store),
+    ("i32.store8", Instruction::I32Store8),
+    ("i32.store16", Instruction::I32Store16),
+    ("i64.store8", Instruction::I64Store8),
+    ("i64.store16", Instruction::I64Store16),
+    ("i64.store32", Instruction::I64Store32),
+    ("v128.load", Instruction::V128Load),
+    ("v128.load8x8_s", Instruction::V128Load8x8S),
+    ("v128.load8x8_u", Instruction::V128Load8x8U),
+    ("v128.load16x4_s", Instruction::V128Load16x4S),
+    ("v128.load16x4_u", Instruction::V128Load16x4U),
+    ("v128.load32x2_s", Instruction::V128Load32x2S),
+    ("v128.load32x2_u", Instruction::V128Load32x2U),
+    ("v128.load8_splat", Instruction::V128Load8Splat),
+    ("v128.load16_splat", Instruction::V128Load16Splat),
+    ("v128.load32_splat", Instruction::V128Load32Splat),
+    ("v128.load64_splat", Instruction::V128Load64Splat),
+    ("v128.load32_zero", Instruction::V128Load32Zero),
+    ("v128.load64_zero", Instruction::V128Load64Zero),
+    ("v128.store", Instruction::V128Store),
+    ("memory.atomic.notify", Instruction::MemoryAtomicNotify),
+    ("memory.atomic.wait32", Instruction::MemoryAtomicWait32),
+    ("memory.atomic.wait64", Instruction::MemoryAtomicWait64),
+    ("i32.atomic.load", Instruction::I32AtomicLoad),
+    ("i64.atomic.load", Instruction::I64AtomicLoad),
+    ("i32.atomic.load8_u", Instruction::I32AtomicLoad8U),
+    ("i32.atomic.load16_u", Instruction::I32AtomicLoad16U),
+    ("i64.atomic.load8_u", Instruction::I64AtomicLoad8U),
+    ("i64.atomic.load16_u", Instruction::I64AtomicLoad16U),
+    ("i64.atomic.load32_u", Instruction::I64AtomicLoad32U),
+    ("i32.atomic.store", Instruction::I32AtomicStore),
+    ("i64.atomic.store", Instruction::I64AtomicStore),
+    ("i32.atomic.store8", Instruction::I32AtomicStore8),
+    ("i32.atomic.store16", Instruction::I32AtomicStore16),
+    ("i64.atomic.store8", Instruction::I64AtomicStore8),
+    ("i64.atomic.store16", Instruction::I64AtomicStore16),
+    ("i64.atomic.store32", Instruction::I64AtomicStore32),
+    ("i32.atomic.rmw.add", Instruction::I32AtomicRmwAdd),
+    ("i64.atomic.rmw.add", Instruction::I64AtomicRmwAdd),
+    ("i32.atomic.rmw8.add_u", Instruction::I32AtomicRmw8AddU),
+    ("i32.atomic.rmw16.add_u", Instruction::I32AtomicRmw16AddU),
+    ("i64.atomic.rmw8.add_u", Instruction::I64AtomicRmw8AddU),
+    ("i64.atomic.rmw16.add_u", Instruction::I64AtomicRmw16AddU),
+    ("i64.atomic.rmw32.add_u", Instruction::I64AtomicRmw32AddU),
+    ("i32.atomic.rmw.sub", Instruction::I32AtomicRmwSub),
+    ("i64.atomic.rmw.sub", Instruction::I64AtomicRmwSub),
+    ("i32.atomic.rmw8.sub_u", Instruction::I32AtomicRmw8SubU),
+    ("i32.atomic.rmw16.sub_u", Instruction::I32AtomicRmw16SubU),
+    ("i64.atomic.rmw8.sub_u", Instruction::I64AtomicRmw8SubU),
+    ("i64.atomic.rmw16.sub_u", Instruction::I64AtomicRmw16SubU),
+    ("i64.atomic.rmw32.sub_u", Instruction::I64AtomicRmw32SubU),
+    ("i32.atomic.rmw.and", Instruction::I32AtomicRmwAnd),
+    ("i64.atomic.rmw.and", Instruction::I64AtomicRmwAnd),
+    ("i32.atomic.rmw8.and_u", Instruction::I32AtomicRmw8AndU),
+    ("i32.atomic.rmw16.and_u", Instruction::I32AtomicRmw16AndU),
+    ("i64.atomic.rmw8.and_u", Instruction::I64AtomicRmw8AndU),
+    ("i64.atomic.rmw16.and_u", Instruction::I64AtomicRmw16AndU),
+    ("i64.atomic.rmw32.and_u", Instruction::I64AtomicRmw32AndU),
+    ("i32.atomic.rmw.or", Instruction::I32AtomicRmwOr),
+    ("i64.atomic.rmw.or", Instruction::I64AtomicRmwOr),
+    ("i32.atomic.rmw8.or_u", Instruction::I32AtomicRmw8OrU),
+    ("i32.atomic.rmw16.or_u", Instruction::I32AtomicRmw16OrU),
+    ("i64.atomic.rmw8.or_u", Instruction::I64AtomicRmw8OrU),
+    ("i64.atomic.rmw16.or_u", Instruction::I64AtomicRmw16OrU),
+    ("i64.atomic.rmw32.or_u", Instruction::I64AtomicRmw32OrU),
+    ("i32.atomic.rmw.xor", Instruction::I32AtomicRmwXor),
+    ("i64.atomic.rmw.xor", Instruction::I64AtomicRmwXor),
+    ("i32.atomic.rmw8.xor_u", Instruction::I32AtomicRmw8XorU),
+    ("i32.atomic.rmw16.xor_u", Instruction::I32AtomicRmw16XorU),
+    ("i64.atomic.rmw8.xor_u", Instruction::I64AtomicRmw8XorU),
+    ("i64.atomic.rmw16.xor_u", Instruction::I64AtomicRmw16XorU),
+    ("i64.atomic.rmw32.xor_u", Instruction::I64AtomicRmw32XorU),
+    ("i32.atomic.rmw.xchg", Instruction::I32AtomicRmwXchg),
+    ("i64.atomic.rmw.xchg", Instruction::I64AtomicRmwXchg),
+    ("i32.atomic.rmw8.xchg_u", Instruction::I32AtomicRmw8XchgU),
+    ("i32.atomic.rmw16.xchg_u", Instruction::I32AtomicRmw16XchgU),
+    ("i64.atomic.rmw8.xchg_u", Instruction::I64AtomicRmw8XchgU),
+    ("i64.atomic.rmw16.xchg_u", Instruction::I64AtomicRmw16XchgU),
+    ("i64.atomic.rmw32.xchg_u", Instruction::I64AtomicRmw32XchgU),
+    ("i32.atomic.rmw.cmpxchg", Instruction::I32AtomicRmwCmpxchg),
+    ("i64.atomic.rmw.cmpxchg", Instruction::I64AtomicRmwCmpxchg),
+    ("i32.atomic.rmw8.cmpxchg_u", Instruction::I32AtomicRmw8CmpxchgU),
+    ("i32.atomic.rmw16.cmpxchg_u", Instruction::I32AtomicRmw16CmpxchgU),
+    ("i64.atomic.rmw8.cmpxchg_u", Instruction::I64AtomicRmw8CmpxchgU),
+    ("i64.atomic.rmw16.cmpxchg_u", Instruction::I64AtomicRmw16CmpxchgU),
+    ("i64.atomic.rmw32.cmpxchg_u", Instruction::I64AtomicRmw32CmpxchgU),
+];
+
+/// Load and store instructions with a memory argument and a lane index.
+#[rustfmt::skip]
+const MEMARG_LANE: &[(&str, MemArgLaneInstruction)] = &[
+    ("v128.load8_lane", Instruction::V128Load8Lane),
+    ("v128.load16_lane", Instruction::V128Load16Lane),
+    ("v128.load32_lane", Instruction::V128Load32Lane),
+    ("v128.load64_lane", Instruction::V128Load64Lane),
+    ("v128.store8_lane", Instruction::V128Store8Lane),
+    ("v128.store16_lane", Instruction::V128Store16Lane),
+    ("v128.store32_lane", Instruction::V128Store32Lane),
+    ("v128.store64_lane", Instruction::V128Store64Lane),
+];
+
+/// Instructions with a lane index, and the number of lanes.
+#[rustfmt::skip]
+const LANE: &[(&str, LaneInstruction, u32)] = &[
+    ("i8x16.extract_lane_s", Instruction::I8x16ExtractLaneS, 16),
+    ("i8x16.extract_lane_u", Instruction::I8x16ExtractLaneU, 16),
+    ("i8x16.replace_lane", Instruction::I8x16ReplaceLane, 16),
+    ("i16x8.extract_lane_s", Instruction::I16x8ExtractLaneS, 8),
+    ("i16x8.extract_lane_u", Instruction::I16x8ExtractLaneU, 8),
+    ("i16x8.replace_lane", Instruction::I16x8ReplaceLane, 8),
+    ("i32x4.extract_lane", Instruction::I32x4ExtractLane, 4),
+    ("i32x4.replace_lane", Instruction::I32x4ReplaceLane, 4),
+    ("i64x2.extract_lane", Instruction::I64x2ExtractLane, 2),
+    ("i64x2.replace_lane", Instruction::I64x2ReplaceLane, 2),
+    ("f32x4.extract_lane", Instruction::F32x4ExtractLane, 4),
+    ("f32x4.replace_lane", Instruction::F32x4ReplaceLane, 4),
+    ("f64x2.extract_lane", Instruction::F64x2ExtractLane, 2),
+    ("f64x2.replace_lane", Instruction::F64x2ReplaceLane, 2),
+];
+
+/// Instructions without immediates.
+#[rustfmt::skip]
+const PLAIN: &[(&str, Instruction)] = &[
+    ("unreachable", Instruction::Unreachable),
+    ("nop", Instruction::Nop),
+    ("throw_ref", Instruction::ThrowRef),
+    ("return", Instruction::Return),
+    ("ref.is_null", Instruction::RefIsNull),
+    ("ref.eq", Instruction::RefEq),
+    ("ref.as_non_null", Instruction::RefAsNonNull),
+    ("array.len", Instruction::ArrayLen),
+    ("any.convert_extern", Instruction::AnyConvertExtern),
+    ("extern.convert_any", Instruction::ExternConvertAny),
+    ("ref.i31", Instruction::RefI31),
+    ("i31.get_s", Instruction::I31GetS),
+    ("i31.get_u", Instruction::I31GetU),
+    ("drop", Instruction::Drop),
+    ("select", Instruction::SelectNumeric),
+    ("i32.eqz", Instruction::I32Eqz),
+    ("i32.eq", Instruction::I32Eq),
+    ("i32.ne", Instruction::I32Ne),
+    ("i32.lt_s", Instruction::I32LtS),
+    ("i32.lt_u", Instruction::I32LtU),
+    ("i32.gt_s", Instruction::I32GtS),
+    ("i32.gt_u", Instruction::I32GtU),
+    ("i32.le_s", Instruction::I32LeS),
+    ("i32.le_u", Instruction::I32LeU),
+    ("i32.ge_s", Instruction::I32GeS),
+    ("i32.ge_u", Instruction::I32GeU),
+    ("i64.eqz", Instruction::I64Eqz),
+    ("i64.eq", Instruction::I64Eq),
+    ("i64.ne", Instruction::I64Ne),
+    ("i64.lt_s", Instruction::I64LtS),
+    ("i64.lt_u", Instruction::I64LtU),
+    ("i64.gt_s", Instruction::I64GtS),
+    ("i64.gt_u", Instruction::I64GtU),
+    ("i64.le_s", Instruction::I64LeS),
+    ("i64.le_u", Instruction::I64LeU),
+    ("i64.ge_s", Instruction::I64GeS),
+    ("i64.ge_u", Instruction::I64GeU),
+    ("f32.eq", Instruction::F32Eq),
+    ("f32.ne", Instruction::F32Ne),
+    ("f32.lt", Instruction::F32Lt),
+    ("f32.gt", Instruction::F32Gt),
+    ("f32.le", Instruction::F32Le),
+    ("f32.ge", Instruction::F32Ge),
+    ("f64.eq", Instruction::F64Eq),
+    ("f64.ne", Instruction::F64Ne),
+    ("f64.lt", Instruction::F64Lt),
+    ("f64.gt", Instruction::F64Gt),
+    ("f64.le", Instruction::F64Le),
+    ("f64.ge", Instruction::F64Ge),
+    ("i32.clz", Instruction::I32Clz),
+    ("i32.ctz", Instruction::I32Ctz),
+    ("i32.popcnt", Instruction::I32Popcnt),
+    ("i32.add", Instruction::I32Add),
+    ("i32.sub", Instruction::I32Sub),
+    ("i32.mul", Instruction::I32Mul),
+    ("i32.div_s", Instruction::I32DivS),
+    ("i32.div_u", Instruction::I32DivU),
+    ("i32.rem_s", Instruction::I32RemS),
+    ("i32.rem_u", Instruction::I32RemU),
+    ("i32.and", Instruction::I32And),
+    ("i32.or", Instruction::I32Or),
+    ("i32.xor", Instruction::I32Xor),
+    ("i32.shl", Instruction::I32Shl),
+    ("i32.shr_s", Instruction::I32ShrS),
+    ("i32.shr_u", Instruction::I32ShrU),
+    ("i32.rotl", Instruction::I32Rotl),
+    ("i32.rotr", Instruction::I32Rotr),
+    ("i64.clz", Instruction::I64Clz),
+    ("i64.ctz", Instruction::I64Ctz),
+    ("i64.popcnt", Instruction::I64Popcnt),
+    ("i64.add", Instruction::I64Add),
+    ("i64.sub", Instruction::I64Sub),
+    ("i64.mul", Instruction::I64Mul),
+    ("i64.div_s", Instruction::I64DivS),
+    ("i64.div_u", Instruction::I64DivU),
+    ("i64.rem_s", Instruction::I64RemS),
+    ("i64.rem_u", Instruction::I64RemU),
+    ("i64.and", Instruction::I64And),
+    ("i64.or", Instruction::I64Or),
+    ("i64.xor", Instruction::I64Xor),
+    ("i64.shl", Instruction::I64Shl),
+    ("i64.shr_s", Instruction::I64ShrS),
+    ("i64.shr_u", Instruction::I64ShrU),
+    ("i64.rotl", Instruction::I64Rotl),
+    ("i64.rotr", Instruction::I64Rotr),
+    ("f32.abs", Instruction::F32Abs),
+    ("f32.neg", Instruction::F32Neg),
+    ("f32.ceil", Instruction::F32Ceil),
+    ("f32.floor", Instruction::F32Floor),
+    ("f32.trunc", Instruction::F32Trunc),
+    ("f32.nearest", Instruction::F32Nearest),
+    ("f32.sqrt", Instruction::F32Sqrt),
+    ("f32.add", Instruction::F32Add),
+    ("f32.sub", Instruction::F32Sub),
+    ("f32.mul", Instruction::F32Mul),
+    ("f32.div", Instruction::F32Div),
+    ("f32.min", Instruction::F32Min),
+    ("f32.max", Instruction::F32Max),
+    ("f32.copysign", Instruction::F32Copysign),
+    ("f64.abs", Instruction::F64Abs),
+    ("f64.neg", Instruction::F64Neg),
+    ("f64.ceil", Instruction::F64Ceil),
+    ("f64.floor", Instruction::F64Floor),
+    ("f64.trunc", Instruction::F64Trunc),
+    ("f64.nearest", Instruction::F64Nearest),
+    ("f64.sqrt", Instruction::F64Sqrt),
+    ("f64.add", Instruction::F64Add),
+    ("f64.sub", Instruction::F64Sub),
+    ("f64.mul", Instruction::F64Mul),
+    ("f64.div", Instruction::F64Div),
+    ("f64.min", Instruction::F64Min),
+    ("f64.max", Instruction::F64Max),
+    ("f64.copysign", Instruction::F64Copysign),
+    ("i32.wrap_i64", Instruction::I32WrapI64),
+    ("i32.trunc_f32_s", Instruction::I32TruncF32S),
+    ("i32.trunc_f32_u", Instruction::I32TruncF32U),
+    ("i32.trunc_f64_s", Instruction::I32TruncF64S),
+    ("i32.trunc_f64_u", Instruction::I32TruncF64U),
+    ("i64.extend_i32_s", Instruction::I64ExtendI32S),
+    ("i64.extend_i32_u", Instruction::I64ExtendI32U),
+    ("i64.trunc_f32_s", Instruction::I64TruncF32S),
+    ("i64.trunc_f32_u", Instruction::I64TruncF32U),
+    ("i64.trunc_f64_s", Instruction::I64TruncF64S),
+    ("i64.trunc_f64_u", Instruction::I64TruncF64U),
+    ("f32.convert_i32_s", Instruction::F32ConvertI32S),
+    ("f32.convert_i32_u", Instruction::F32ConvertI32U),
+    ("f32.convert_i64_s", Instruction::F32ConvertI64S),
+    ("f32.convert_i64_u", Instruction::F32ConvertI64U),
+    ("f32.demote_f64", Instruction::F32DemoteF64),
+    ("f64.convert_i32_s", Instruction::F64ConvertI32S),
+    ("f64.convert_i32_u", Instruction::F64ConvertI32U),
+    ("f64.convert_i64_s", Instruction::F64ConvertI64S),
+    ("f64.convert_i64_u", Instruction::F64ConvertI64U),
+    ("f64.promote_f32", Instruction::F64PromoteF32),
+    ("i32.reinterpret_f32", Instruction::I32ReinterpretF32),
+    ("i64.reinterpret_f64", Instruction::I64ReinterpretF64),
+    ("f32.reinterpret_i32", Instruction::F32ReinterpretI32),
+    ("f64.reinterpret_i64", Instruction::F64ReinterpretI64),
+    ("i32.extend8_s", Instruction::I32Extend8S),
+    ("i32.extend16_s", Instruction::I32Extend16S),
+    ("i64.extend8_s", Instruction::I64Extend8S),
+    ("i64.extend16_s", Instruction::I64Extend16S),
+    ("i64.extend32_s", Instruction::I64Extend32S),
+    ("i32.trunc_sat_f32_s", Instruction::I32TruncSatF32S),
+    ("i32.trunc_sat_f32_u", Instruction::I32TruncSatF32U),
+    ("i32.trunc_sat_f64_s", Instruction::I32TruncSatF64S),
+    ("i32.trunc_sat_f64_u", Instruction::I32TruncSatF64U),
+    ("i64.trunc_sat_f32_s", Instruction::I64TruncSatF32S),
+    ("i64.trunc_sat_f32_u", Instruction::I64TruncSatF32U),
+    ("i64.trunc_sat_f64_s", Instruction::I64TruncSatF64S),
+    ("i64.trunc_sat_f64_u", Instruction::I64TruncSatF64U),
+    ("i8x16.swizzle", Instruction::I8X16Swizzle),
+    ("i8x16.splat", Instruction::I8x16Splat),
+    ("i16x8.splat", Instruction::I16x8Splat),
+    ("i32x4.splat", Instruction::I32x4Splat),
+    ("i64x2.splat", Instruction::I64x2Splat),
+    ("f32x4.splat", Instruction::F32x4Splat),
+    ("f64x2.splat", Instruction::F64x2Splat),
+    ("i8x16.eq", Instruction::I8x16Eq),
+    ("i8x16.ne", Instruction::I8x16Ne),
+    ("i8x16.lt_s", Instruction::I8X16LtS),
+    ("i8x16.lt_u", Instruction::I8X16LtU),
+    ("i8x16.gt_s", Instruction::I8X16GtS),
+    ("i8x16.gt_u", Instruction::I8X16GtU),
+    ("i8x16.le_s", Instruction::I8X16LeS),
+    ("i8x16.le_u", Instruction::I8X16LeU),
+    ("i8x16.ge_s", Instruction::I8X16GeS),
+    ("i8x16.ge_u", Instruction::I8X16GeU),
+    ("i16x8.eq", Instruction::I16x8Eq),
+    ("i16x8.ne", Instruction::I16x8Ne),
+    ("i16x8.lt_s", Instruction::I16x8LtS),
+    ("i16x8.lt_u", Instruction::I16x8LtU),
+    ("i16x8.gt_s", Instruction::I16x8GtS),
+    ("i16x8.gt_u", Instruction::I16x8GtU),
+    ("i16x8.le_s", Instruction::I16x8LeS),
+    ("i16x8.le_u", Instruction::I16x8LeU),
+    ("i16x8.ge_s", Instruction::I16x8GeS),
+    ("i16x8.ge_u", Instruction::I16x8GeU),
+    ("i32x4.eq", Instruction::I32x4Eq),
+    ("i32x4.ne", Instruction::I32x4Ne),
+    ("i32x4.lt_s", Instruction::I32x4LtS),
+    ("i32x4.lt_u", Instruction::I32x4LtU),
+    ("i32x4.gt_s", Instruction::I32x4GtS),
+    ("i32x4.gt_u", Instruction::I32x4GtU),
+    ("i32x4.le_s", Instruction::I32x4LeS),
+    ("i32x4.le_u", Instruction::I32x4LeU),
+    ("i32x4.ge_s", Instruction::I32x4GeS),
+    ("i32x4.ge_u", Instruction::I32x4GeU),
+    ("i64x2.eq", Instruction::I64x2Eq),
+    ("i64x2.ne", Instruction::I64x2Ne),
+    ("i64x2.lt_s", Instruction::I64x2LtS),
+    ("i64x2.gt_s", Instruction::I64x2GtS),
+    ("i64x2.le_s", Instruction::I64x2LeS),
+    ("i64x2.ge_s", Instruction::I64x2GeS),
+    ("f32x4.eq", Instruction::F32x4Eq),
+    ("f32x4.ne", Instruction::F32x4Ne),
+    ("f32x4.lt", Instruction::F32x4Lt),
+    ("f32x4.gt", Instruction::F32x4Gt),
+    ("f32x4.le", Instruction::F32x4Le),
+    ("f32x4.ge", Instruction::F32x4Ge),
+    ("f64x2.eq", Instruction::F64x2Eq),
+    ("f64x2.ne", Instruction::F64x2Ne),
+    ("f64x2.lt", Instruction::F64x2Lt),
+    ("f64x2.gt", Instruction::F64x2Gt),
+    ("f64x2.le", Instruction::F64x2Le),
+    ("f64x2.ge", Instruction::F64x2Ge),
+    ("v128.not", Instruction::V128Not),
+    ("v128.and", Instruction::V128And),
+    ("v128.andnot", Instruction::V128AndNot),
+    ("v128.or", Instruction::V128Or),
+    ("v128.xor", Instruction::V128Xor),
+    ("v128.bitselect", Instruction::V128Bitselect),
+    ("v128.any_true", Instruction::V128AnyTrue),
+    ("i8x16.abs", Instruction::I8x16Abs),
+    ("i8x16.neg", Instruction::I8x16Neg),
+    ("i8x16.popcnt", Instruction::I8x16Popcnt),
+    ("i8x16.all_true", Instruction::I8x16AllTrue),
+    ("i8x16.bitmask", Instruction::I8x16Bitmask),
+    ("i8x16.narrow_i16x8_s", Instruction::I8x16NarrowI16x8S),
+    ("i8x16.narrow_i16x8_u", Instruction::I8x16NarrowI16x8U),
+    ("i8x16.shl", Instruction::I8x16Shl),
+    ("i8x16.shr_s", Instruction::I8x16ShrS),
+    ("i8x16.shr_u", Instruction::I8x16ShrU),
+    ("i8x16.add", Instruction::I8x16Add),
+    ("i8x16.add_sat_s", Instruction::I8x16AddSatS),
+    ("i8x16.add_sat_u", Instruction::I8x16AddSatU),
+    ("i8x16.sub", Instruction::I8x16Sub),
+    ("i8x16.sub_sat_s", Instruction::I8x16SubSatS),
+    ("i8x16.sub_sat_u", Instruction::I8x16SubSatU),
+    ("i8x16.min_s", Instruction::I8x16MinS),
+    ("i8x16.min_u", Instruction::I8x16MinU),
+    ("i8x16.max_s", Instruction::I8x16MaxS),
+    ("i8x16.max_u", Instruction::I8x16MaxU),
+    ("i8x16.avgr_u", Instruction::I8x16AvgrU),
+    ("i16x8.extadd_pairwise_i8x16_s", Instruction::I16x8ExtAddPairwiseI8x16S),
+    ("i16x8.extadd_pairwise_i8x16_u", Instruction::I16x8ExtAddPairwiseI8x16U),
+    ("i16x8.abs", Instruction::I16x8Abs),
+    ("i16x8.neg", Instruction::I16x8Neg),
+    ("i16x8.q15_mulr_sat_s", Instruction::I16x8Q15MulrSatS),
+    ("i16x8.all_true", Instruction::I16x8AllTrue),
+    ("i16x8.bitmask", Instruction::I16x8Bitmask),
+    ("i16x8.narrow_i32x4_s", Instruction::I16x8NarrowI32x4S),
+    ("i16x8.narrow_i32x4_u", Instruction::I16x8NarrowI32x4U),
+    ("i16x8.extend_low_i8x16_s", Instruction::I16x8ExtendLowI8X16S),
+    ("i16x8.extend_high_i8x16_s", Instruction::I16x8ExtendHighI8X16S),
+    ("i16x8.extend_low_i8x16_u", Instruction::I16x8ExtendLowI8X16U),
+    ("i16x8.extend_high_i8x16_u", Instruction::I16x8ExtendHighI8X16U),
+    ("i16x8.shl", Instruction::I16x8Shl),
+    ("i16x8.shr_s", Instruction::I16x8ShrS),
+    ("i16x8.shr_u", Instruction::I16x8ShrU),
+    ("i16x8.add", Instruction::I16x8Add),
+    ("i16x8.add_sat_s", Instruction::I16x8AddSatS),
+    ("i16x8.add_sat_u", Instruction::I16x8AddSatU),
+    ("i16x8.sub", Instruction::I16x8Sub),
+    ("i16x8.sub_sat_s", Instruction::I16x8SubSatS),
+    ("i16x8.sub_sat_u", Instruction::I16x8SubSatU),
+    ("i16x8.mul", Instruction::I16X8Mul),
+    ("i16x8.min_s", Instruction::I16x8MinS),
+    ("i16x8.min_u", Instruction::I16x8MinU),
+    ("i16x8.max_s", Instruction::I16x8MaxS),
+    ("i16x8.max_u", Instruction::I16x8MaxU),
+    ("i16x8.avgr_u", Instruction::I16x8AvgrU),
+    ("i16x8.extmul_low_i8x16_s", Instruction::I16x8ExtmulLowI8x16S),
+    ("i16x8.extmul_high_i8x16_s", Instruction::I16x8ExtmulHighI8x16S),
+    ("i16x8.extmul_low_i8x16_u", Instruction::I16x8ExtmulLowI8x16U),
+    ("i16x8.extmul_high_i8x16_u", Instruction::I16x8ExtmulHighI8x16U),
+    ("i32x4.extadd_pairwise_i16x8_s", Instruction::I32x4ExtAddPairwiseI16x8S),
+    ("i32x4.extadd_pairwise_i16x8_u", Instruction::I32x4ExtAddPairwiseI16x8U),
+    ("i32x4.abs", Instruction::I32x4Abs),
+    ("i32x4.neg", Instruction::I32x4Neg),
+    ("i32x4.all_true", Instruction::I32x4AllTrue),
+    ("i32x4.bitmask", Instruction::I32x4Bitmask),
+    ("i32x4.extend_low_i16x8_s", Instruction::I32x4ExtendLowI16X8S),
+    ("i32x4.extend_high_i16x8_s", Instruction::I32x4ExtendHighI16X8S),
+    ("i32x4.extend_low_i16x8_u", Instruction::I32x4ExtendLowI16X8U),
+    ("i32x4.extend_high_i16x8_u", Instruction::I32x4ExtendHighI16X8U),
+    ("i32x4.shl", Instruction::I32x4Shl),
+    ("i32x4.shr_s", Instruction::I32x4ShrS),
+    ("i32x4.shr_u", Instruction::I32x4ShrU),
+    ("i32x4.add", Instruction::I32x4Add),
+    ("i32x4.sub", Instruction::I32x4Sub),
+    ("i32x4.mul", Instruction::I32x4Mul),
+    ("i32x4.min_s", Instruction::I32x4MinS),
+    ("i32x4.min_u", Instruction::I32x4MinU),
+    ("i32x4.max_s", Instruction::I32x4MaxS),
+    ("i32x4.max_u", Instruction::I32x4MaxU),
+    ("i32x4.dot_i16x8_s", Instruction::I32x4DotI16x8S),
+    ("i32x4.extmul_low_i16x8_s", Instruction::I32x4ExtmulLowI16x8S),
+    ("i32x4.extmul_high_i16x8_s", Instruction::I32x4ExtmulHighI16x8S),
+    ("i32x4.extmul_low_i16x8_u", Instruction::I32x4ExtmulLowI16x8U),
+    ("i32x4.extmul_high_i16x8_u", Instruction::I32x4ExtmulHighI16x8U),
+    ("i64x2.abs", Instruction::I64x2Abs),
+    ("i64x2.neg", Instruction::I64x2Neg),
+    ("i64x2.all_true", Instruction::I64x2AllTrue),
+    ("i64x2.bitmask", Instruction::I64x2Bitmask),
+    ("i64x2.extend_low_i32x4_s", Instruction::I64x2ExtendLowI16X8S),
+    ("i64x2.extend_high_i32x4_s", Instruction::I64x2ExtendHighI16X8S),
+    ("i64x2.extend_low_i32x4_u", Instruction::I64x2ExtendLowI16X8U),
+    ("i64x2.extend_high_i32x4_u", Instruction::I64x2ExtendHighI16X8U),
+    ("i64x2.shl", Instruction::I64x2Shl),
+    ("i64x2.shr_s", Instruction::I64x2ShrS),
+    ("i64x2.shr_u", Instruction::I64x2ShrU),
+    ("i64x2.add", Instruction::I64x2Add),
+    ("i64x2.sub", Instruction::I64x2Sub),
+    ("i64x2.mul", Instruction::I64x2Mul),
+    ("i64x2.extmul_low_i32x4_s", Instruction::I64x2ExtmulLowI32x4S),
+    ("i64x2.extmul_high_i32x4_s", Instruction::I64x2ExtmulHighI32x4S),
+    ("i64x2.extmul_low_i32x4_u", Instruction::I64x2ExtmulLowI32x4U),
+    ("i64x2.extmul_high_i32x4_u", Instruction::I64x2ExtmulHighI32x4U),
+    ("f32x4.ceil", Instruction::F32x4Ceil),
+    ("f32x4.floor", Instruction::F32x4Floor),
+    ("f32x4.trunc", Instruction::F32x4Trunc),
+    ("f32x4.nearest", Instruction::F32x4Nearest),
+    ("f32x4.abs", Instruction::F32x4Abs),
+    ("f32x4.neg", Instruction::F32x4Neg),
+    ("f32x4.sqrt", Instruction::F32x4Sqrt),
+    ("f32x4.add", Instruction::F32x4Add),
+    ("f32x4.sub", Instruction::F32x4Sub),
+    ("f32x4.mul", Instruction::F32x4Mul),
+    ("f32x4.div", Instruction::F32x4Div),
+    ("f32x4.min", Instruction::F32x4Min),
+    ("f32x4.max", Instruction::F32x4Max),
+    ("f32x4.pmin", Instruction::F32x4Pmin),
+    ("f32x4.pmax", Instruction::F32x4Pmax),
+    ("f64x2.ceil", Instruction::F64x2Ceil),
+    ("f64x2.floor", Instruction::F64x2Floor),
+    ("f64x2.trunc", Instruction::F64x2Trunc),
+    ("f64x2.nearest", Instruction::F64x2Nearest),
+    ("f64x2.abs", Instruction::F64x2Abs),
+    ("f64x2.neg", Instruction::F64x2Neg),
+    ("f64x2.sqrt", Instruction::F64x2Sqrt),
+    ("f64x2.add", Instruction::F64x2Add),
+    ("f64x2.sub", Instruction::F64x2Sub),
+    ("f64x2.mul", Instruction::F64x2Mul),
+    ("f64x2.div", Instruction::F64x2Div),
+    ("f64x2.min", Instruction::F64x2Min),
+    ("f64x2.max", Instruction::F64x2Max),
+    ("f64x2.pmin", Instruction::F64x2Pmin),
+    ("f64x2.pmax", Instruction::F64x2Pmax),
+    ("i32x4.trunc_sat_f32x4_s", Instruction::I32x4TruncSatF32x4S),
+    ("i32x4.trunc_sat_f32x4_u", Instruction::I32x4TruncSatF32x4U),
+    ("f32x4.convert_i32x4_s", Instruction::F32x4ConvertI32x4S),
+    ("f32x4.convert_i32x4_u", Instruction::F32x4ConvertI32x4U),
+    ("i32x4.trunc_sat_f64x2_s_zero", Instruction::I32x4TruncSatF64x2SZero),
+    ("i32x4.trunc_sat_f64x2_u_zero", Instruction::I32x4TruncSatF64x2UZero),
+    ("f64x2.convert_low_i32x4_s", Instruction::F64x2ConvertLowI32x4S),
+    ("f64x2.convert_low_i32x4_u", Instruction::F64x2ConvertLowI32x4U),
+    ("f32x4.demote_f64x2_zero", Instruction::F32x4DemoteF64x2Zero),
+    ("f64x2.promote_low_f32x4", Instruction::F64x2PromoteLowF32x4),
+    ("i8x16.relaxed_swizzle", Instruction::I8x16RelaxedSwizzle),
+    ("i32x4.relaxed_trunc_f32x4_s", Instruction::I32x4RelaxedTruncF32x4S),
+    ("i32x4.relaxed_trunc_f32x4_u", Instruction::I32x4RelaxedTruncF32x4U),
+    ("i32x4.relaxed_trunc_f64x2_s_zero", Instruction::I32x4RelaxedTruncF64x2SZero),
+    ("i32x4.relaxed_trunc_f64x2_u_zero", Instruction::I32x4RelaxedTruncF64x2UZero),
+    ("f32x4.relaxed_madd", Instruction::F32x4RelaxedMadd),
+    ("f32x4.relaxed_nmadd", Instruction::F32x4RelaxedNmadd),
+    ("f64x2.relaxed_madd", Instruction::F64x2RelaxedMadd),
+    ("f64x2.relaxed_nmadd", Instruction::F64x2RelaxedNmadd),
+    ("i8x16.relaxed_laneselect", Instruction::I8x16RelaxedLaneselect),
+    ("i16x8.relaxed_laneselect", Instruction::I16x8RelaxedLaneselect),
+    ("i32x4.relaxed_laneselect", Instruction::I32x4RelaxedLaneselect),
+    ("i64x2.relaxed_laneselect", Instruction::I64x2RelaxedLaneselect),
+    ("f32x4.relaxed_min", Instruction::F32x4RelaxedMin),
+    ("f32x4.relaxed_max", Instruction::F32x4RelaxedMax),
+    ("f64x2.relaxed_min", Instruction::F64x2RelaxedMin),
+    ("f64x2.relaxed_max", Instruction::F64x2RelaxedMax),
+    ("i16x8.relaxed_q15mulr_s", Instruction::I16x8RelaxedQ15mulrS),
+    ("i16x8.relaxed_dot_i8x16_i7x16_s", Instruction::I16x8RelaxedDotI8x16I7x16S),
+    ("i32x4.relaxed_dot_i8x16_i7x16_add_s", Instruction::I32x4RelaxedDotI8x16I7x16AddS),
+    ("atomic.fence", Instruction::AtomicFence),
+];
@@ -0,0 +1,417 @@
+//! Tokens and S-expressions of the text format.
+//!
+//! <https://webassembly.github.io/spec/core/text/lexical.html>
+
+use super::TextError;
+
+/// An atom of the text format.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Atom<'a> {
+    /// A keyword, e.g. `i32.add`, `offset=4` or `inf`.
+    Keyword(&'a str),
+    /// A token starting with a digit or a sign.
+    Number(&'a str),
+    /// An identifier without the leading `$`.
+    Id(&'a str),
+    String(Vec<u8>),
+}
+
+/// A parenthesized list or an atom, with the byte offset where it starts.
+#[derive(Clone, Debug)]
+pub(crate) enum Sexpr<'a> {
+    List(Vec<Sexpr<'a>>, usize),
+    Atom(Atom<'a>, usize),
+}
+
+impl<'a> Sexpr<'a> {
+    pub(crate) fn offset(&self) -> usize {
+        match self {
+            Sexpr::List(_, offset) | Sexpr::Atom(_, offset) => *offset,
+        }
+    }
+
+    /// Returns the leading keyword and the rest of a list, e.g. `func` of `(func $f)`.
+    pub(crate) fn as_list(&self) -> Option<(&'a str, &[Sexpr<'a>])> {
+        match self {
+            Sexpr::List(items, _) => match items.first() {
+                Some(Sexpr::Atom(Atom::Keyword(kw), _)) => Some((kw, &items[1..])),
+                _ => None,
+            },
+            Sexpr::Atom(..) => None,
+        }
+    }
+}
+
+fn is_idchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&b)
+}
+
+/// Parses the source into top-level S-expressions.
+///
+/// Comments and annotations other than `@custom` are skipped.
+pub(crate) fn parse(src: &str) -> Result<Vec<Sexpr<'_>>, TextError> {
+    let bytes = src.as_bytes();
+    let mut pos = 0;
+    // lists being parsed, with their starting offset
+    let mut stack: Vec<(Vec<Sexpr>, usize)> = vec![(Vec::new(), 0)];
+
+    while pos < bytes.len() {
+        let start = pos;
+        match bytes[pos] {
+            b' ' | b'\t' | b'\n' | b'\r' => pos += 1,
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            }
+            b'(' if bytes.get(pos + 1) == Some(&b';') => {
+                let mut depth = 0;
+                loop {
+                    match (bytes.get(pos), bytes.get(pos + 1)) {
+                        (Some(b'('), Some(b';')) => {
+                            depth += 1;
+                            pos += 2;
+                        }
+                        (Some(b';'), Some(b')')) => {
+                            depth -= 1;
+                            pos += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        (Some(_), _) => pos += 1,
+                        (None, _) => {
+                            return Err(TextError::new(start, "unterminated block comment"))
+                        }
+                    }
+                }
+            }
+            b'(' => {
+                stack.push((Vec::new(), start));
+                pos += 1;
+            }
+            b')' => {
+                if stack.len() == 1 {
+                    return Err(TextError::new(start, "unexpected `)`"));
+                }
+                let (items, offset) = stack.pop().unwrap();
+                let is_annotation = matches!(
+                    items.first(),
+                    Some(Sexpr::Atom(Atom::Keyword(kw), _)) if kw.starts_with('@') && *kw != "@custom"
+                );
+                if !is_annotation {
+                    stack.last_mut().unwrap().0.push(Sexpr::List(items, offset));
+                }
+                pos += 1;
+            }
+            b'"' => {
+                let (s, end) = string(bytes, pos)?;
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .0
+                    .push(Sexpr::Atom(Atom::String(s), start));
+                pos = end;
+            }
+            b if is_idchar(b) => {
+                while pos < bytes.len() && is_idchar(bytes[pos]) {
+                    pos += 1;
+                }
+                let token = &src[start..pos];
+                let atom = match token.as_bytes()[0] {
+                    b'$' if token.len() > 1 => Atom::Id(&token[1..]),
+                    b'$' => return Err(TextError::new(start, "empty identifier")),
+                    b'0'..=b'9' | b'+' | b'-' => Atom::Number(token),
+                    _ => Atom::Keyword(token),
+                };
+                stack.last_mut().unwrap().0.push(Sexpr::Atom(atom, start));
+            }
+            _ => return Err(TextError::new(start, "unexpected character")),
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(TextError::new(stack.last().unwrap().1, "unclosed `(`"));
+    }
+    Ok(stack.pop().unwrap().0)
+}
+
+/// Parses a string literal starting at `bytes[start]`, returning its contents and the end offset.
+fn string(bytes: &[u8], start: usize) -> Result<(Vec<u8>, usize), TextError> {
+    let mut s = Vec::new();
+    let mut pos = start + 1;
+    loop {
+        let Some(&b) = bytes.get(pos) else {
+            return Err(TextError::new(start, "unterminated string"));
+        };
+        pos += 1;
+        match b {
+            b'"' => return Ok((s, pos)),
+            b'\\' => {
+                let escape = pos - 1;
+                let invalid = || TextError::new(escape, "invalid escape sequence");
+                let &c = bytes.get(pos).ok_or_else(invalid)?;
+                pos += 1;
+                match c {
+                    b't' => s.push(b'\t'),
+                    b'n' => s.push(b'\n'),
+                    b'r' => s.push(b'\r'),
+                    b'"' | b'\'' | b'\\' => s.push(c),
+                    b'u' => {
+                        if bytes.get(pos) != Some(&b'{') {
+                            return Err(invalid());
+                        }
+                        let end = pos
+                            + bytes[pos..]
+                                .iter()
+                                .position(|&x| x == b'}')
+                                .ok_or_else(invalid)?;
+                        let hex =
+                            std::str::from_utf8(&bytes[pos + 1..end]).map_err(|_| invalid())?;
+                        let c = u32::from_str_radix(&hex.replace('_', ""), 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(invalid)?;
+                        s.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        pos = end + 1;
+                    }
+                    _ => {
+                        let hex = |x: u8| (x as char).to_digit(16);
+                        let hi = hex(c).ok_or_else(invalid)?;
+                        let lo = bytes.get(pos).and_then(|&x| hex(x)).ok_or_else(invalid)?;
+                        s.push((hi * 16 + lo) as u8);
+                        pos += 1;
+                    }
+                }
+            }
+            b'\n' | b'\r' | 0x7F => {
+                return Err(TextError::new(pos - 1, "invalid character in string"))
+            }
+            b if b < 0x20 && b != b'\t' => {
+                return Err(TextError::new(pos - 1, "invalid character in string"))
+            }
+            b => s.push(b),
+        }
+    }
+}
+
+/// Parses an unsigned integer literal, with an optional `0x` prefix and `_` separators.
+fn magnitude(token: &str) -> Option<u64> {
+    let (digits, radix) = match token.strip_prefix("0x") {
+        Some(digits) => (digits, 16),
+        None => (token, 10),
+    };
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+    {
+        return None;
+    }
+    let mut n: u64 = 0;
+    for c in digits.chars().filter(|&c| c != '_') {
+        n = n
+            .checked_mul(radix)?
+            .checked_add(c.to_digit(radix as u32)?.into())?;
+    }
+    Some(n)
+}
+
+/// Splits the sign of a number literal, returning whether it is negative.
+fn sign(token: &str) -> (bool, &str) {
+    match token.as_bytes().first() {
+        Some(b'-') => (true, &token[1..]),
+        Some(b'+') => (false, &token[1..]),
+        _ => (false, token),
+    }
+}
+
+pub(crate) fn parse_u32(token: &str) -> Option<u32> {
+    if token.starts_with(['+', '-']) {
+        return None;
+    }
+    magnitude(token)?.try_into().ok()
+}
+
+pub(crate) fn parse_u64(token: &str) -> Option<u64> {
+    if token.starts_with(['+', '-']) {
+        return None;
+    }
+    magnitude(token)
+}
+
+/// Parses an integer literal in the range of either `u32` or `i32`.
+pub(crate) fn parse_i32(token: &str) -> Option<i32> {
+    let (negative, rest) = sign(token);
+    let n = magnitude(rest)?;
+    if negative {
+        (n <= 1 << 31).then(|| (n as i64).wrapping_neg() as i32)
+    } else if token.starts_with('+') {
+        i32::try_from(n).ok()
+    } else {
+        u32::try_from(n).ok().map(|x| x as i32)
+    }
+}
+
+/// Parses an integer literal in the range of either `u64` or `i64`.
+pub(crate) fn parse_i64(token: &str) -> Option<i64> {
+    let (negative, rest) = sign(token);
+    let n = magnitude(rest)?;
+    if negative {
+        (n <= 1 << 63).then(|| n.wrapping_neg() as i64)
+    } else if token.starts_with('+') {
+        i64::try_from(n).ok()
+    } else {
+        Some(n as i64)
+    }
+}
+
+/// Parses a float literal into the bits of a float with `mantissa_bits` explicit mantissa bits and
+/// `exponent_bits` exponent bits.
+fn parse_float_bits(token: &str, mantissa_bits: u32, exponent_bits: u32) -> Option<u64> {
+    let (negative, rest) = sign(token);
+    let sign_bit = u64::from(negative) << (mantissa_bits + exponent_bits);
+    let exponent_mask = (1u64 << exponent_bits) - 1;
+    let mantissa_mask = (1u64 << mantissa_bits) - 1;
+
+    if rest == "inf" {
+        return Some(sign_bit | exponent_mask << mantissa_bits);
+    }
+    if rest == "nan" {
+        return Some(sign_bit | exponent_mask << mantissa_bits | 1 << (mantissa_bits - 1));
+    }
+    if let Some(payload) = rest.strip_prefix("nan:0x") {
+        let payload = magnitude(&format!("0x{payload}"))?;
+        if payload == 0 || payload > mantissa_mask {
+            return None;
+        }
+        return Some(sign_bit | exponent_mask << mantissa_bits | payload);
+    }
+    if rest.starts_with('_') || rest.ends_with('_') || rest.contains("__") {
+        return None;
+    }
+    let rest = rest.replace('_', "");
+
+    let Some(hex) = rest.strip_prefix("0x") else {
+        // decimal literals are rounded by the standard library
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let bits = if mantissa_bits == 23 {
+            let x: f32 = rest.parse().ok()?;
+            x.is_finite()
+                .then(|| u64::from(x.to_bits() & 0x7FFF_FFFF))?
+        } else {
+            let x: f64 = rest.parse().ok()?;
+            x.is_finite().then(|| x.to_bits() & 0x7FFF_FFFF_FFFF_FFFF)?
+        };
+        return Some(sign_bit | bits);
+    };
+
+    // hexadecimal literals are rounded to nearest, ties to even
+    let (digits, exponent) = match hex.split_once(['p', 'P']) {
+        Some((digits, exponent)) => {
+            let (negative, exponent) = sign(exponent);
+            let exponent = i64::try_from(magnitude(exponent)?).ok()?;
+            (digits, if negative { -exponent } else { exponent })
+        }
+        None => (hex, 0),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() {
+        return None;
+    }
+    let mut mantissa: u128 = 0;
+    let mut exponent = exponent;
+    let mut sticky = false;
+    for (i, c) in int.chars().chain(frac.chars()).enumerate() {
+        let d = c.to_digit(16)?;
+        if i >= int.len() {
+            exponent = exponent.checked_sub(4)?;
+        }
+        if mantissa >> 120 == 0 {
+            mantissa = mantissa << 4 | u128::from(d);
+        } else {
+            // digits beyond the precision of u128 only affect rounding
+            exponent = exponent.checked_add(4)?;
+            sticky |= d != 0;
+        }
+    }
+    if mantissa == 0 {
+        return Some(sign_bit);
+    }
+
+    let bias = (1i64 << (exponent_bits - 1)) - 1;
+    let msb = 127 - i64::from(mantissa.leading_zeros());
+    let top = msb + exponent;
+    // exponent of the least significant mantissa bit of the result
+    let lsb = (top - i64::from(mantissa_bits)).max(1 - bias - i64::from(mantissa_bits));
+    let shift = lsb - exponent;
+    let mut q = if shift <= 0 {
+        mantissa << -shift
+    } else if shift >= 128 {
+        // the mantissa is shorter than 124 bits, so it is less than half of the smallest
+        // subnormal
+        0
+    } else {
+        let q = mantissa >> shift;
+        let rem = mantissa & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        let round_up = rem > half || (rem == half && (sticky || q & 1 == 1));
+        q + u128::from(round_up)
+    };
+    let mut lsb = lsb;
+    if q >> (mantissa_bits + 1) != 0 {
+        q >>= 1;
+        lsb += 1;
+    }
+    let q = q as u64;
+    if q >> mantissa_bits == 0 {
+        // subnormal
+        return Some(sign_bit | q);
+    }
+    let biased = lsb + i64::from(mantissa_bits) + bias;
+    if biased >= exponent_mask as i64 {
+        return None;
+    }
+    Some(sign_bit | (biased as u64) << mantissa_bits | q & mantissa_mask)
+}
+
+pub(crate) fn parse_f32(token: &str) -> Option<f32> {
+    parse_float_bits(token, 23, 8).map(|x| f32::from_bits(x as u32))
+}
+
+pub(crate) fn parse_f64(token: &str) -> Option<f64> {
+    parse_float_bits(token, 52, 11).map(f64::from_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_f32, parse_f64, parse_i32, parse_i64};
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_i32("0xFFFF_FFFF"), Some(-1));
+        assert_eq!(parse_i32("-2147483648"), Some(i32::MIN));
+        assert_eq!(parse_i32("+2147483648"), None);
+        assert_eq!(parse_i32("4294967296"), None);
+        assert_eq!(parse_i64("-0x8000000000000000"), Some(i64::MIN));
+        assert_eq!(parse_i64("18446744073709551615"), Some(-1));
+
+        assert_eq!(parse_f32("0.1"), Some(0.1));
+        assert_eq!(parse_f32("-0x1.8p1"), Some(-3.0));
+        assert_eq!(parse_f32("0x1p-149"), Some(f32::from_bits(1)));
+        assert_eq!(parse_f32("0x1p-150"), Some(0.0));
+        assert_eq!(parse_f32("0x1.000001p0"), Some(1.0));
+        assert_eq!(parse_f32("0x1.000003p0"), Some(f32::from_bits(0x3F80_0002)));
+        assert_eq!(parse_f32("0x1p128"), None);
+        assert_eq!(parse_f32("-inf"), Some(f32::NEG_INFINITY));
+        assert_eq!(
+            parse_f32("nan:0x200000").map(f32::to_bits),
+            Some(0x7FA0_0000)
+        );
+        assert_eq!(parse_f64("1_000.5e1"), Some(10005.0));
+        assert_eq!(parse_f64("0x1.fffffffffffff8p1023"), None);
+        assert_eq!(parse_f64("0x1.fffffffffffff7p1023"), Some(f64::MAX));
+    }
+}
@@ -0,0 +1,3114 @@
+//! WebAssembly module validation.
+//!
+//! <https://webassembly.github.io/spec/core/valid/index.html>
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    instructions::{BlockType, Catch, Expression, Instruction, MemArg},
+    synth::{
+        sections::{
+            SynthData, SynthElemInit, SynthElemKind, SynthElemMode, SynthExportDescription,
+            SynthImportDescription,
+        },
+        SynthModule,
+    },
+    wasm_types::{
+        CompositeType, FieldType, FuncType, GlobalType, HeapType, Limits, MemType, ReferenceType,
+        StorageType, SubType, TableType, ValueType,
+    },
+    Error,
+};
+
+/// Maximum number of pages a 32-bit memory can have.
+const MAX_MEMORY_PAGES: u64 = 65536;
+
+/// Maximum number of pages a 64-bit memory can have.
+const MAX_MEMORY64_PAGES: u64 = 1 << 48;
+
+/// A reason why a module failed validation.
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationError {
+    #[error("type mismatch: expected {expected}, got {actual}")]
+    TypeMismatch {
+        expected: ValueType,
+        actual: ValueType,
+    },
+    #[error("expected a reference type, got {0}")]
+    ExpectedReference(ValueType),
+    #[error("expected a numeric or vector type, got {0}")]
+    ExpectedNumeric(ValueType),
+    #[error("operand stack underflow")]
+    StackUnderflow,
+    #[error("{0} operands left on the stack at the end of a block")]
+    UnconsumedOperands(usize),
+    #[error("else without a matching if")]
+    ElseWithoutIf,
+    #[error("if without else must have the same parameter and result types")]
+    IfWithoutElse,
+    #[error("br_table targets have inconsistent arities")]
+    BrTableArity,
+    #[error("select must have exactly one type annotation, got {0}")]
+    SelectArity(usize),
+    #[error("unknown label {0}")]
+    UnknownLabel(u32),
+    #[error("unknown type {0}")]
+    UnknownType(i64),
+    #[error("type {index} is not a {expected} type")]
+    CompositeType { index: u32, expected: &'static str },
+    #[error("unknown field {field} of type {ty}")]
+    UnknownField { ty: u32, field: u32 },
+    #[error("field {field} of type {ty} is immutable")]
+    ImmutableField { ty: u32, field: u32 },
+    #[error("sign extension must be given exactly for packed fields")]
+    PackedField,
+    #[error("type {0} is not defaultable")]
+    NonDefaultable(u32),
+    #[error("invalid supertype {supertype} of type {ty}")]
+    Supertype { ty: u32, supertype: u32 },
+    #[error("label {0} does not take a reference as its last value")]
+    ReferenceLabel(u32),
+    #[error("unknown function {0}")]
+    UnknownFunction(u32),
+    #[error("unknown local {0}")]
+    UnknownLocal(u32),
+    #[error("local {0} is used before being initialized")]
+    UninitializedLocal(u32),
+    #[error("unknown global {0}")]
+    UnknownGlobal(u32),
+    #[error("unknown table {0}")]
+    UnknownTable(u32),
+    #[error("unknown memory {0}")]
+    UnknownMemory(u32),
+    #[error("unknown element segment {0}")]
+    UnknownElementSegment(u32),
+    #[error("unknown data segment {0}")]
+    UnknownDataSegment(u32),
+    #[error("unknown tag {0}")]
+    UnknownTag(u32),
+    #[error("tag type {0} must have no results")]
+    TagResults(u32),
+    #[error("rethrow label {0} is not a catch block")]
+    RethrowLabel(u32),
+    #[error("catch clause does not match the types of label {0}")]
+    CatchLabelType(u32),
+    #[error("data count section is required")]
+    MissingDataCount,
+    #[error("data count {0} does not match the number of data segments {1}")]
+    DataCountMismatch(u32, usize),
+    #[error("global {0} is immutable")]
+    ImmutableGlobal(u32),
+    #[error("table element type mismatch: expected {expected}, got {actual}")]
+    TableElementType {
+        expected: ReferenceType,
+        actual: ReferenceType,
+    },
+    #[error("alignment 2^{align} is larger than natural alignment 2^{natural}")]
+    Alignment { align: u32, natural: u32 },
+    #[error("atomic alignment 2^{align} is not equal to natural alignment 2^{natural}")]
+    AtomicAlignment { align: u32, natural: u32 },
+    #[error("lane index {lane} is out of range for {lanes} lanes")]
+    LaneIndex { lane: u32, lanes: u32 },
+    #[error("undeclared function reference {0}")]
+    UndeclaredFunctionReference(u32),
+    #[error("non-constant instruction in a constant expression")]
+    NonConstantInstruction,
+    #[error("function section declares {0} functions, but code section has {1} bodies")]
+    FunctionCodeMismatch(usize, usize),
+    #[error("tail call results do not match the results of the function")]
+    TailCallResults,
+    #[error("start function {0} must have type [] -> []")]
+    StartFunctionType(u32),
+    #[error("duplicate export name {0:?}")]
+    DuplicateExportName(String),
+    #[error("invalid limits: min {min} is larger than max {max}")]
+    LimitsRange { min: u64, max: u64 },
+    #[error("size {0} exceeds the maximum of {1}")]
+    MemorySize(u64, u64),
+    #[error("offset {0} is out of range for a 32-bit memory")]
+    MemArgOffset(u64),
+    #[error("shared memory must have a maximum size")]
+    SharedMemoryMaximum,
+    #[error("global {index} initializer is invalid")]
+    GlobalInit {
+        index: u32,
+        #[source]
+        source: Box<ValidationError>,
+    },
+    #[error("element segment {index} is invalid")]
+    ElementSegment {
+        index: u32,
+        #[source]
+        source: Box<ValidationError>,
+    },
+    #[error("data segment {index} is invalid")]
+    DataSegment {
+        index: u32,
+        #[source]
+        source: Box<ValidationError>,
+    },
+}
+
+/// Index spaces and types of a module, referred to while validating function bodies.
+///
+/// <https://webassembly.github.io/spec/core/valid/conventions.html#contexts>
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ModuleContext {
+    pub(crate) types: Vec<SubType>,
+    /// Canonical index of every defined type, equal for types which are equivalent.
+    pub(crate) canonical: Vec<u32>,
+    /// Type indices of every function, including imported ones.
+    pub(crate) funcs: Vec<u32>,
+    pub(crate) tables: Vec<TableType>,
+    pub(crate) memories: Vec<MemType>,
+    pub(crate) globals: Vec<GlobalType>,
+    pub(crate) imported_globals: usize,
+    pub(crate) elems: Vec<ReferenceType>,
+    pub(crate) data_count: Option<u32>,
+    /// Type indices of every tag, including imported ones.
+    pub(crate) tags: Vec<u32>,
+    /// Function indices which may be referenced with `ref.func` inside function bodies.
+    pub(crate) refs: HashSet<u32>,
+}
+
+impl ModuleContext {
+    pub(crate) fn from_synth(module: &SynthModule) -> Self {
+        let mut ctx = Self {
+            types: module
+                .type_section
+                .as_ref()
+                .map(|x| x.types.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        ctx.canonical = canonicalize(
+            &ctx.types,
+            module
+                .type_section
+                .as_ref()
+                .map_or(&[][..], |x| &x.rec_groups),
+        );
+
+        if let Some(imsec) = &module.import_section {
+            for import in &imsec.imports {
+                match import.description {
+                    SynthImportDescription::Type(x) => ctx.funcs.push(x),
+                    SynthImportDescription::Table(x) => ctx.tables.push(x),
+                    SynthImportDescription::Memory(x) => ctx.memories.push(x),
+                    SynthImportDescription::Global(x) => ctx.globals.push(x),
+                    SynthImportDescription::Tag(x) => ctx.tags.push(x.type_index()),
+                }
+            }
+        }
+        ctx.imported_globals = ctx.globals.len();
+
+        if let Some(funcsec) = &module.function_section {
+            ctx.funcs.extend_from_slice(&funcsec.type_indices);
+        }
+        if let Some(tablesec) = &module.table_section {
+            ctx.tables.extend_from_slice(&tablesec.tables);
+        }
+        if let Some(memsec) = &module.memory_section {
+            ctx.memories.extend_from_slice(&memsec.memories);
+        }
+        if let Some(tagsec) = &module.tag_section {
+            ctx.tags.extend(tagsec.tags.iter().map(|x| x.type_index()));
+        }
+        if let Some(glsec) = &module.global_section {
+            ctx.globals.extend(glsec.globals.iter().map(|x| x.ty));
+        }
+        if let Some(elsec) = &module.element_section {
+            ctx.elems
+                .extend(elsec.elements.iter().map(|x| match x.kind {
+                    SynthElemKind::FuncRef => ReferenceType::FuncRef,
+                    SynthElemKind::ReferenceType(rt) => rt,
+                }));
+        }
+        ctx.data_count = module.data_count_section.as_ref().map(|x| x.data_count);
+
+        fn collect_refs(refs: &mut HashSet<u32>, expr: &Expression) {
+            for instr in expr.instructions() {
+                if let Instruction::RefFunc(x) = instr {
+                    refs.insert(*x);
+                }
+            }
+        }
+
+        if let Some(glsec) = &module.global_section {
+            for global in &glsec.globals {
+                collect_refs(&mut ctx.refs, &global.init);
+            }
+        }
+        if let Some(elsec) = &module.element_section {
+            for elem in &elsec.elements {
+                match &elem.init {
+                    SynthElemInit::FuncIndices(x) => ctx.refs.extend(x.iter().copied()),
+                    SynthElemInit::Expressions(x) => {
+                        for expr in x {
+                            collect_refs(&mut ctx.refs, expr);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(exsec) = &module.export_section {
+            for export in &exsec.exports {
+                if let SynthExportDescription::Func(x) = export.desc {
+                    ctx.refs.insert(x);
+                }
+            }
+        }
+
+        ctx
+    }
+
+    fn sub_type(&self, tyidx: u32) -> Result<&SubType, ValidationError> {
+        self.types
+            .get(tyidx as usize)
+            .ok_or(ValidationError::UnknownType(tyidx.into()))
+    }
+
+    /// Returns the function type defined at `tyidx`.
+    fn defined_func_type(&self, tyidx: u32) -> Result<&FuncType, ValidationError> {
+        self.sub_type(tyidx)?
+            .func_type()
+            .ok_or(ValidationError::CompositeType {
+                index: tyidx,
+                expected: "function",
+            })
+    }
+
+    fn struct_type(&self, tyidx: u32) -> Result<&[FieldType], ValidationError> {
+        match self.sub_type(tyidx)?.composite() {
+            CompositeType::Struct(fields) => Ok(fields),
+            _ => Err(ValidationError::CompositeType {
+                index: tyidx,
+                expected: "struct",
+            }),
+        }
+    }
+
+    fn array_type(&self, tyidx: u32) -> Result<FieldType, ValidationError> {
+        match self.sub_type(tyidx)?.composite() {
+            CompositeType::Array(field) => Ok(*field),
+            _ => Err(ValidationError::CompositeType {
+                index: tyidx,
+                expected: "array",
+            }),
+        }
+    }
+
+    fn field(&self, tyidx: u32, field: u32) -> Result<FieldType, ValidationError> {
+        self.struct_type(tyidx)?
+            .get(field as usize)
+            .copied()
+            .ok_or(ValidationError::UnknownField { ty: tyidx, field })
+    }
+
+    fn func_type(&self, funcidx: u32) -> Result<&FuncType, ValidationError> {
+        let tyidx = *self
+            .funcs
+            .get(funcidx as usize)
+            .ok_or(ValidationError::UnknownFunction(funcidx))?;
+        self.defined_func_type(tyidx)
+    }
+
+    /// Checks that a concrete heap type of `t` refers to a defined type.
+    fn value_type(&self, t: ValueType) -> Result<(), ValidationError> {
+        match t.type_index() {
+            Some(x) => self.sub_type(x).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether `actual` is a subtype of `expected`.
+    fn matches(&self, actual: ValueType, expected: ValueType) -> bool {
+        match (actual.reference_type(), expected.reference_type()) {
+            (Some(a), Some(b)) => self.ref_matches(a, b),
+            _ => actual == expected,
+        }
+    }
+
+    fn ref_matches(&self, actual: ReferenceType, expected: ReferenceType) -> bool {
+        (!actual.nullable() || expected.nullable())
+            && self.heap_matches(actual.heap_type(), expected.heap_type())
+    }
+
+    fn heap_matches(&self, actual: HeapType, expected: HeapType) -> bool {
+        use HeapType::{Any, Array, Concrete, Eq, Exn, Extern, Func, NoExn, NoExtern, NoFunc, I31};
+
+        if actual == expected {
+            return true;
+        }
+        match (actual, expected) {
+            (Concrete(x), Concrete(y)) => {
+                let Some(y) = self.canonical.get(y as usize) else {
+                    return false;
+                };
+                let mut x = Some(x);
+                while let Some(t) = x {
+                    if self.canonical.get(t as usize) == Some(y) {
+                        return true;
+                    }
+                    // supertypes have smaller indices, which guarantees termination
+                    x = self
+                        .types
+                        .get(t as usize)
+                        .and_then(|t| t.supertypes().first())
+                        .copied()
+                        .filter(|&s| s < t);
+                }
+                false
+            }
+            (Concrete(x), _) => match self.types.get(x as usize).map(SubType::composite) {
+                Some(CompositeType::Func(_)) => self.heap_matches(Func, expected),
+                Some(CompositeType::Struct(_)) => self.heap_matches(HeapType::Struct, expected),
+                Some(CompositeType::Array(_)) => self.heap_matches(Array, expected),
+                None => false,
+            },
+            (HeapType::None, _) => self.heap_matches(expected, Any),
+            (NoFunc, _) => self.heap_matches(expected, Func),
+            (NoExtern, _) => expected == Extern,
+            (NoExn, _) => expected == Exn,
+            (I31 | HeapType::Struct | Array, Eq | Any) | (Eq, Any) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the top type of the hierarchy `heap` belongs to.
+    fn top_type(&self, heap: HeapType) -> HeapType {
+        if self.heap_matches(heap, HeapType::Func) {
+            HeapType::Func
+        } else if self.heap_matches(heap, HeapType::Extern) {
+            HeapType::Extern
+        } else if self.heap_matches(heap, HeapType::Exn) {
+            HeapType::Exn
+        } else {
+            HeapType::Any
+        }
+    }
+
+    /// Returns whether the field type `actual` is a subtype of `expected`.
+    fn field_matches(&self, actual: FieldType, expected: FieldType) -> bool {
+        let storage = match (actual.storage(), expected.storage()) {
+            (StorageType::Val(a), StorageType::Val(b)) => {
+                self.matches(a, b) && (!expected.mutable() || self.matches(b, a))
+            }
+            (a, b) => a == b,
+        };
+        storage && actual.mutable() == expected.mutable()
+    }
+
+    /// Returns whether the composite type `actual` may declare `expected` as its supertype.
+    fn composite_matches(&self, actual: &CompositeType, expected: &CompositeType) -> bool {
+        match (actual, expected) {
+            (CompositeType::Func(a), CompositeType::Func(b)) => {
+                let (ap, bp) = (&a.param().0, &b.param().0);
+                let (ar, br) = (&a.result().0, &b.result().0);
+                ap.len() == bp.len()
+                    && ar.len() == br.len()
+                    && ap.iter().zip(bp).all(|(a, b)| self.matches(*b, *a))
+                    && ar.iter().zip(br).all(|(a, b)| self.matches(*a, *b))
+            }
+            (CompositeType::Struct(a), CompositeType::Struct(b)) => {
+                a.len() >= b.len() && a.iter().zip(b).all(|(a, b)| self.field_matches(*a, *b))
+            }
+            (CompositeType::Array(a), CompositeType::Array(b)) => self.field_matches(*a, *b),
+            _ => false,
+        }
+    }
+
+    fn table(&self, tableidx: u32) -> Result<&TableType, ValidationError> {
+        self.tables
+            .get(tableidx as usize)
+            .ok_or(ValidationError::UnknownTable(tableidx))
+    }
+
+    fn memory(&self, memidx: u32) -> Result<&MemType, ValidationError> {
+        self.memories
+            .get(memidx as usize)
+            .ok_or(ValidationError::UnknownMemory(memidx))
+    }
+
+    fn global(&self, globalidx: u32) -> Result<&GlobalType, ValidationError> {
+        self.globals
+            .get(globalidx as usize)
+            .ok_or(ValidationError::UnknownGlobal(globalidx))
+    }
+
+    fn elem(&self, elemidx: u32) -> Result<ReferenceType, ValidationError> {
+        self.elems
+            .get(elemidx as usize)
+            .copied()
+            .ok_or(ValidationError::UnknownElementSegment(elemidx))
+    }
+
+    fn data(&self, dataidx: u32) -> Result<(), ValidationError> {
+        match self.data_count {
+            None => Err(ValidationError::MissingDataCount),
+            Some(n) if dataidx >= n => Err(ValidationError::UnknownDataSegment(dataidx)),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn tag_type(&self, tagidx: u32) -> Result<&FuncType, ValidationError> {
+        let tyidx = *self
+            .tags
+            .get(tagidx as usize)
+            .ok_or(ValidationError::UnknownTag(tagidx))?;
+        self.defined_func_type(tyidx)
+    }
+
+    fn block_type(
+        &self,
+        bt: BlockType,
+    ) -> Result<(Vec<ValueType>, Vec<ValueType>), ValidationError> {
+        match bt {
+            BlockType::Empty => Ok((Vec::new(), Vec::new())),
+            BlockType::Value(t) => {
+                self.value_type(t)?;
+                Ok((Vec::new(), vec![t]))
+            }
+            BlockType::TypeIndex(x) => {
+                let x = u32::try_from(x).map_err(|_| ValidationError::UnknownType(x))?;
+                let ty = self.defined_func_type(x)?;
+                Ok((ty.param.0.clone(), ty.result.0.clone()))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+    Try,
+    Catch,
+    CatchAll,
+}
+
+#[derive(Clone, Debug)]
+struct ControlFrame {
+    kind: FrameKind,
+    start_types: Vec<ValueType>,
+    end_types: Vec<ValueType>,
+    height: usize,
+    /// Length of the initialized locals log when this frame was opened.
+    init_height: usize,
+    unreachable: bool,
+    /// Offset of the instruction which opened this frame.
+    offset: usize,
+}
+
+impl ControlFrame {
+    fn label_types(&self) -> &[ValueType] {
+        if self.kind == FrameKind::Loop {
+            &self.start_types
+        } else {
+            &self.end_types
+        }
+    }
+}
+
+/// Operand and control stacks of the validation algorithm.
+///
+/// <https://webassembly.github.io/spec/core/appendix/algorithm.html>
+struct FuncValidator<'a> {
+    ctx: &'a ModuleContext,
+    locals: Vec<ValueType>,
+    /// Whether each local is initialized, as locals of non-defaultable types must be set first.
+    inits: Vec<bool>,
+    /// Locals initialized inside the current blocks, which become uninitialized again at `end`.
+    init_log: Vec<u32>,
+    results: Vec<ValueType>,
+    /// `None` represents an unknown type, which appears only in unreachable code.
+    operands: Vec<Option<ValueType>>,
+    controls: Vec<ControlFrame>,
+}
+
+impl<'a> FuncValidator<'a> {
+    fn new(ctx: &'a ModuleContext, locals: Vec<ValueType>, results: Vec<ValueType>) -> Self {
+        Self::with_params(ctx, locals, 0, results)
+    }
+
+    /// Creates a validator whose first `params` locals are parameters and thus initialized.
+    fn with_params(
+        ctx: &'a ModuleContext,
+        locals: Vec<ValueType>,
+        params: usize,
+        results: Vec<ValueType>,
+    ) -> Self {
+        let inits = locals
+            .iter()
+            .enumerate()
+            .map(|(i, t)| i < params || t.is_defaultable())
+            .collect();
+        let mut this = Self {
+            ctx,
+            locals,
+            inits,
+            init_log: Vec::new(),
+            results: results.clone(),
+            operands: Vec::new(),
+            controls: Vec::new(),
+        };
+        this.push_ctrl(FrameKind::Function, Vec::new(), results, 0);
+        this
+    }
+
+    fn push_operand(&mut self, ty: Option<ValueType>) {
+        self.operands.push(ty);
+    }
+
+    fn push_values(&mut self, types: &[ValueType]) {
+        self.operands.extend(types.iter().copied().map(Some));
+    }
+
+    fn pop_operand(&mut self) -> Result<Option<ValueType>, ValidationError> {
+        let frame = self.controls.last().expect("empty control stack");
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err(ValidationError::StackUnderflow);
+        }
+        Ok(self.operands.pop().expect("empty operand stack"))
+    }
+
+    fn pop_expected(&mut self, expected: ValueType) -> Result<(), ValidationError> {
+        match self.pop_operand()? {
+            Some(actual) if !self.ctx.matches(actual, expected) => {
+                Err(ValidationError::TypeMismatch { expected, actual })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn pop_values(&mut self, types: &[ValueType]) -> Result<(), ValidationError> {
+        for ty in types.iter().rev() {
+            self.pop_expected(*ty)?;
+        }
+        Ok(())
+    }
+
+    fn pop_reference(&mut self) -> Result<Option<ValueType>, ValidationError> {
+        match self.pop_operand()? {
+            Some(t) if t.reference_type().is_some() => Ok(Some(t)),
+            Some(t) => Err(ValidationError::ExpectedReference(t)),
+            None => Ok(None),
+        }
+    }
+
+    fn push_ctrl(
+        &mut self,
+        kind: FrameKind,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+        offset: usize,
+    ) {
+        let height = self.operands.len();
+        self.push_values(&start_types);
+        self.controls.push(ControlFrame {
+            kind,
+            start_types,
+            end_types,
+            height,
+            init_height: self.init_log.len(),
+            unreachable: false,
+            offset,
+        });
+    }
+
+    fn pop_ctrl(&mut self) -> Result<ControlFrame, ValidationError> {
+        let end_types = self
+            .controls
+            .last()
+            .expect("empty control stack")
+            .end_types
+            .clone();
+        self.pop_values(&end_types)?;
+        let frame = self.controls.pop().expect("empty control stack");
+        for local in self.init_log.drain(frame.init_height..) {
+            self.inits[local as usize] = false;
+        }
+        if self.operands.len() != frame.height {
+            return Err(ValidationError::UnconsumedOperands(
+                self.operands.len() - frame.height,
+            ));
+        }
+        Ok(frame)
+    }
+
+    fn label_types(&self, label: u32) -> Result<Vec<ValueType>, ValidationError> {
+        let depth = label as usize;
+        if depth >= self.controls.len() {
+            return Err(ValidationError::UnknownLabel(label));
+        }
+        Ok(self.controls[self.controls.len() - 1 - depth]
+            .label_types()
+            .to_vec())
+    }
+
+    /// Checks that a catch clause of `try_table` passes the types of its label.
+    fn catch_clause(&self, catch: &Catch) -> Result<(), ValidationError> {
+        let mut types = match catch.tag() {
+            Some(tag) => self.ctx.tag_type(tag)?.param.0.clone(),
+            None => Vec::new(),
+        };
+        if catch.is_ref() {
+            types.push(ValueType::ExnRef);
+        }
+        if self.label_types(catch.label())? != types {
+            return Err(ValidationError::CatchLabelType(catch.label()));
+        }
+        Ok(())
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.controls.last_mut().expect("empty control stack");
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn local(&self, localidx: u32) -> Result<ValueType, ValidationError> {
+        self.locals
+            .get(localidx as usize)
+            .copied()
+            .ok_or(ValidationError::UnknownLocal(localidx))
+    }
+
+    /// Marks a local as initialized until the end of the current block.
+    fn init_local(&mut self, localidx: u32) {
+        if !self.inits[localidx as usize] {
+            self.inits[localidx as usize] = true;
+            self.init_log.push(localidx);
+        }
+    }
+
+    /// Checks `memarg` of a memory access, returning the type of its addresses.
+    fn memarg(&self, memarg: &MemArg, natural: u32) -> Result<ValueType, ValidationError> {
+        let memory = self.ctx.memory(memarg.memory)?;
+        if memarg.align > natural {
+            return Err(ValidationError::Alignment {
+                align: memarg.align,
+                natural,
+            });
+        }
+        if !memory.is_64() && memarg.offset > u32::MAX.into() {
+            return Err(ValidationError::MemArgOffset(memarg.offset));
+        }
+        Ok(memory.index_type())
+    }
+
+    /// Validates the memory argument of an atomic instruction, whose alignment must be exactly
+    /// natural.
+    fn atomic_memarg(&self, memarg: &MemArg, natural: u32) -> Result<ValueType, ValidationError> {
+        if memarg.align != natural {
+            return Err(ValidationError::AtomicAlignment {
+                align: memarg.align,
+                natural,
+            });
+        }
+        self.memarg(memarg, natural)
+    }
+
+    /// Returns the type `ty` of an indirect call through `table`, which must hold `funcref`s.
+    fn indirect_type(&self, ty: u32, table: u32) -> Result<FuncType, ValidationError> {
+        let table = self.ctx.table(table)?;
+        if !self
+            .ctx
+            .ref_matches(table.element(), ReferenceType::FuncRef)
+        {
+            return Err(ValidationError::TableElementType {
+                expected: ReferenceType::FuncRef,
+                actual: table.element(),
+            });
+        }
+        self.ctx.defined_func_type(ty).cloned()
+    }
+
+    /// Validates a tail call to a function of type `ty`, whose results become the results of
+    /// the current function.
+    fn tail_call(&mut self, ty: &FuncType) -> Result<(), ValidationError> {
+        if ty.result.0.len() != self.results.len()
+            || !(ty.result.0.iter().zip(&self.results)).all(|(a, b)| self.ctx.matches(*a, *b))
+        {
+            return Err(ValidationError::TailCallResults);
+        }
+        self.pop_values(&ty.param.0)?;
+        self.set_unreachable();
+        Ok(())
+    }
+
+    fn lane(lane: u32, lanes: u32) -> Result<(), ValidationError> {
+        if lane >= lanes {
+            return Err(ValidationError::LaneIndex { lane, lanes });
+        }
+        Ok(())
+    }
+
+    fn operation(
+        &mut self,
+        params: &[ValueType],
+        results: &[ValueType],
+    ) -> Result<(), ValidationError> {
+        self.pop_values(params)?;
+        self.push_values(results);
+        Ok(())
+    }
+
+    /// Validates a sequence of instructions, returning the position of the instruction at fault on
+    /// error.
+    fn validate(&mut self, instrs: &[Instruction]) -> Result<(), (usize, ValidationError)> {
+        // We manually implement a call stack as in `Instruction::write_slice_into`
+        enum Action<'a> {
+            Instruction(&'a Instruction),
+            Else,
+            Catch(u32),
+            CatchAll,
+            Delegate(u32),
+            End,
+        }
+
+        let mut stack = Vec::new();
+        for instr in instrs.iter().rev() {
+            stack.push(Action::Instruction(instr));
+        }
+
+        let mut offset = 0;
+        while let Some(action) = stack.pop() {
+            match action {
+                Action::Instruction(instr) => {
+                    let current = offset;
+                    offset += 1;
+                    match instr {
+                        Instruction::Block(bt, body) | Instruction::Loop(bt, body) => {
+                            let kind = if matches!(instr, Instruction::Block(..)) {
+                                FrameKind::Block
+                            } else {
+                                FrameKind::Loop
+                            };
+                            let (params, results) =
+                                self.ctx.block_type(*bt).map_err(|e| (current, e))?;
+                            self.pop_values(&params).map_err(|e| (current, e))?;
+                            self.push_ctrl(kind, params, results, current);
+                            stack.push(Action::End);
+                            for instr in body.iter().rev() {
+                                stack.push(Action::Instruction(instr));
+                            }
+                        }
+                        Instruction::If(bt, then_body, else_body) => {
+                            let (params, results) =
+                                self.ctx.block_type(*bt).map_err(|e| (current, e))?;
+                            self.pop_expected(ValueType::I32)
+                                .map_err(|e| (current, e))?;
+                            self.pop_values(&params).map_err(|e| (current, e))?;
+                            self.push_ctrl(FrameKind::If, params, results, current);
+                            stack.push(Action::End);
+                            if let Some(else_body) = else_body {
+                                for instr in else_body.iter().rev() {
+                                    stack.push(Action::Instruction(instr));
+                                }
+                                stack.push(Action::Else);
+                            }
+                            for instr in then_body.iter().rev() {
+                                stack.push(Action::Instruction(instr));
+                            }
+                        }
+                        Instruction::Try(bt, body, catches, catch_all) => {
+                            let (params, results) =
+                                self.ctx.block_type(*bt).map_err(|e| (current, e))?;
+                            self.pop_values(&params).map_err(|e| (current, e))?;
+                            self.push_ctrl(FrameKind::Try, params, results, current);
+                            stack.push(Action::End);
+                            if let Some(catch_all) = catch_all {
+                                for instr in catch_all.iter().rev() {
+                                    stack.push(Action::Instruction(instr));
+                                }
+                                stack.push(Action::CatchAll);
+                            }
+                            for (tag, catch) in catches.iter().rev() {
+                                for instr in catch.iter().rev() {
+                                    stack.push(Action::Instruction(instr));
+                                }
+                                stack.push(Action::Catch(*tag));
+                            }
+                            for instr in body.iter().rev() {
+                                stack.push(Action::Instruction(instr));
+                            }
+                        }
+                        Instruction::TryDelegate(bt, body, l) => {
+                            let (params, results) =
+                                self.ctx.block_type(*bt).map_err(|e| (current, e))?;
+                            self.pop_values(&params).map_err(|e| (current, e))?;
+                            self.push_ctrl(FrameKind::Try, params, results, current);
+                            stack.push(Action::Delegate(*l));
+                            for instr in body.iter().rev() {
+                                stack.push(Action::Instruction(instr));
+                            }
+                        }
+                        Instruction::TryTable(bt, catches, body) => {
+                            let (params, results) =
+                                self.ctx.block_type(*bt).map_err(|e| (current, e))?;
+                            for catch in catches {
+                                self.catch_clause(catch).map_err(|e| (current, e))?;
+                            }
+                            self.pop_values(&params).map_err(|e| (current, e))?;
+                            self.push_ctrl(FrameKind::Block, params, results, current);
+                            stack.push(Action::End);
+                            for instr in body.iter().rev() {
+                                stack.push(Action::Instruction(instr));
+                            }
+                        }
+                        instr => self.instruction(instr).map_err(|e| (current, e))?,
+                    }
+                }
+                Action::Else => {
+                    let frame_offset = self.controls.last().expect("empty control stack").offset;
+                    let frame = self.pop_ctrl().map_err(|e| (frame_offset, e))?;
+                    if frame.kind != FrameKind::If {
+                        return Err((frame_offset, ValidationError::ElseWithoutIf));
+                    }
+                    self.push_ctrl(
+                        FrameKind::Else,
+                        frame.start_types,
+                        frame.end_types,
+                        frame.offset,
+                    );
+                }
+                Action::Catch(tag) => {
+                    let frame_offset = self.controls.last().expect("empty control stack").offset;
+                    let frame = self.pop_ctrl().map_err(|e| (frame_offset, e))?;
+                    let params = self
+                        .ctx
+                        .tag_type(tag)
+                        .map_err(|e| (frame_offset, e))?
+                        .param
+                        .0
+                        .clone();
+                    self.push_ctrl(FrameKind::Catch, params, frame.end_types, frame.offset);
+                }
+                Action::CatchAll => {
+                    let frame_offset = self.controls.last().expect("empty control stack").offset;
+                    let frame = self.pop_ctrl().map_err(|e| (frame_offset, e))?;
+                    self.push_ctrl(
+                        FrameKind::CatchAll,
+                        Vec::new(),
+                        frame.end_types,
+                        frame.offset,
+                    );
+                }
+                Action::Delegate(l) => {
+                    let frame_offset = self.controls.last().expect("empty control stack").offset;
+                    let frame = self.pop_ctrl().map_err(|e| (frame_offset, e))?;
+                    // the label is relative to the blocks enclosing the try block
+                    self.label_types(l).map_err(|e| (frame_offset, e))?;
+                    self.push_values(&frame.end_types);
+                }
+                Action::End => {
+                    let frame_offset = self.controls.last().expect("empty control stack").offset;
+                    let frame = self.pop_ctrl().map_err(|e| (frame_offset, e))?;
+                    if frame.kind == FrameKind::If && frame.start_types != frame.end_types {
+                        return Err((frame_offset, ValidationError::IfWithoutElse));
+                    }
+                    self.push_values(&frame.end_types);
+                }
+            }
+        }
+
+        // the implicit `end` of the function body
+        self.pop_ctrl().map_err(|e| (offset, e))?;
+        Ok(())
+    }
+
+    fn instruction(&mut self, instr: &Instruction) -> Result<(), ValidationError> {
+        use ValueType::{ExnRef, F32, F64, I32, I64, V128};
+
+        match instr {
+            Instruction::Block(..)
+            | Instruction::Loop(..)
+            | Instruction::If(..)
+            | Instruction::Try(..)
+            | Instruction::TryDelegate(..)
+            | Instruction::TryTable(..) => {
+                unreachable!("structured instructions are handled by the caller")
+            }
+            Instruction::Unreachable => self.set_unreachable(),
+            Instruction::Nop => (),
+            Instruction::Br(l) => {
+                let types = self.label_types(*l)?;
+                self.pop_values(&types)?;
+                self.set_unreachable();
+            }
+            Instruction::BrIf(l) => {
+                self.pop_expected(I32)?;
+                let types = self.label_types(*l)?;
+                self.pop_values(&types)?;
+                self.push_values(&types);
+            }
+            Instruction::BrTable(ls, ln) => {
+                self.pop_expected(I32)?;
+                let default_types = self.label_types(*ln)?;
+                for l in ls {
+                    let types = self.label_types(*l)?;
+                    if types.len() != default_types.len() {
+                        return Err(ValidationError::BrTableArity);
+                    }
+                    // values are popped and pushed again to check types against every target
+                    let mut popped = Vec::new();
+                    for ty in types.iter().rev() {
+                        let actual = self.pop_operand()?;
+                        if let Some(actual) = actual {
+                            if !self.ctx.matches(actual, *ty) {
+                                return Err(ValidationError::TypeMismatch {
+                                    expected: *ty,
+                                    actual,
+                                });
+                            }
+                        }
+                        popped.push(actual);
+                    }
+                    for ty in popped.into_iter().rev() {
+                        self.push_operand(ty);
+                    }
+                }
+                self.pop_values(&default_types)?;
+                self.set_unreachable();
+            }
+            Instruction::Throw(x) => {
+                let params = self.ctx.tag_type(*x)?.param.0.clone();
+                self.pop_values(&params)?;
+                self.set_unreachable();
+            }
+            Instruction::Rethrow(l) => {
+                self.label_types(*l)?;
+                let frame = &self.controls[self.controls.len() - 1 - *l as usize];
+                if !matches!(frame.kind, FrameKind::Catch | FrameKind::CatchAll) {
+                    return Err(ValidationError::RethrowLabel(*l));
+                }
+                self.set_unreachable();
+            }
+            Instruction::ThrowRef => {
+                self.pop_expected(ExnRef)?;
+                self.set_unreachable();
+            }
+            Instruction::Return => {
+                let results = self.results.clone();
+                self.pop_values(&results)?;
+                self.set_unreachable();
+            }
+            Instruction::Call(f) => {
+                let ty = self.ctx.func_type(*f)?.clone();
+                self.operation(&ty.param.0, &ty.result.0)?;
+            }
+            Instruction::CallIndirect { ty, table } => {
+                let ty = self.indirect_type(*ty, *table)?;
+                self.pop_expected(I32)?;
+                self.operation(&ty.param.0, &ty.result.0)?;
+            }
+            Instruction::ReturnCall(f) => {
+                let ty = self.ctx.func_type(*f)?.clone();
+                self.tail_call(&ty)?;
+            }
+            Instruction::ReturnCallIndirect { ty, table } => {
+                let ty = self.indirect_type(*ty, *table)?;
+                self.pop_expected(I32)?;
+                self.tail_call(&ty)?;
+            }
+            Instruction::CallRef(x) => {
+                let ty = self.ctx.defined_func_type(*x)?.clone();
+                self.pop_expected(ReferenceType::new(true, HeapType::Concrete(*x)).into())?;
+                self.operation(&ty.param.0, &ty.result.0)?;
+            }
+            Instruction::ReturnCallRef(x) => {
+                let ty = self.ctx.defined_func_type(*x)?.clone();
+                self.pop_expected(ReferenceType::new(true, HeapType::Concrete(*x)).into())?;
+                self.tail_call(&ty)?;
+            }
+            Instruction::RefNull(rt) => {
+                self.ctx.value_type((*rt).into())?;
+                self.push_values(&[(*rt).into()]);
+            }
+            Instruction::RefIsNull => {
+                self.pop_reference()?;
+                self.push_values(&[I32]);
+            }
+            Instruction::RefFunc(f) => {
+                self.ctx.func_type(*f)?;
+                if !self.ctx.refs.contains(f) {
+                    return Err(ValidationError::UndeclaredFunctionReference(*f));
+                }
+                let ty = self.ctx.funcs[*f as usize];
+                self.push_values(&[ReferenceType::new(false, HeapType::Concrete(ty)).into()]);
+            }
+            Instruction::RefEq => {
+                let eqref = ReferenceType::new(true, HeapType::Eq).into();
+                self.operation(&[eqref, eqref], &[I32])?;
+            }
+            Instruction::RefAsNonNull => {
+                let t = self.pop_reference()?;
+                self.push_operand(t.map(|t| non_null(t).into()));
+            }
+            Instruction::BrOnNull(l) => {
+                let t = self.pop_reference()?;
+                let types = self.label_types(*l)?;
+                self.pop_values(&types)?;
+                self.push_values(&types);
+                self.push_operand(t.map(|t| non_null(t).into()));
+            }
+            Instruction::BrOnNonNull(l) => {
+                let types = self.label_types(*l)?;
+                let Some((rt, rest)) = types
+                    .split_last()
+                    .and_then(|(t, rest)| Some((t.reference_type()?, rest)))
+                else {
+                    return Err(ValidationError::ReferenceLabel(*l));
+                };
+                self.pop_expected(rt.with_nullable(true).into())?;
+                self.pop_values(rest)?;
+                self.push_values(rest);
+            }
+            Instruction::StructNew(x) => {
+                let fields: Vec<_> = self.ctx.struct_type(*x)?.iter().map(unpacked).collect();
+                self.operation(&fields, &[concrete(*x, false)])?;
+            }
+            Instruction::StructNewDefault(x) => {
+                let fields = self.ctx.struct_type(*x)?;
+                if !fields.iter().all(|ft| unpacked(ft).is_defaultable()) {
+                    return Err(ValidationError::NonDefaultable(*x));
+                }
+                self.push_values(&[concrete(*x, false)]);
+            }
+            Instruction::StructGet(x, y)
+            | Instruction::StructGetS(x, y)
+            | Instruction::StructGetU(x, y) => {
+                let field = self.ctx.field(*x, *y)?;
+                if field.storage().is_packed() == matches!(instr, Instruction::StructGet(..)) {
+                    return Err(ValidationError::PackedField);
+                }
+                self.operation(&[concrete(*x, true)], &[unpacked(&field)])?;
+            }
+            Instruction::StructSet(x, y) => {
+                let field = self.ctx.field(*x, *y)?;
+                if !field.mutable() {
+                    return Err(ValidationError::ImmutableField { ty: *x, field: *y });
+                }
+                self.operation(&[concrete(*x, true), unpacked(&field)], &[])?;
+            }
+            Instruction::ArrayNew(x) => {
+                let t = unpacked(&self.ctx.array_type(*x)?);
+                self.operation(&[t, I32], &[concrete(*x, false)])?;
+            }
+            Instruction::ArrayNewDefault(x) => {
+                if !unpacked(&self.ctx.array_type(*x)?).is_defaultable() {
+                    return Err(ValidationError::NonDefaultable(*x));
+                }
+                self.operation(&[I32], &[concrete(*x, false)])?;
+            }
+            Instruction::ArrayNewFixed(x, n) => {
+                let t = unpacked(&self.ctx.array_type(*x)?);
+                for _ in 0..*n {
+                    self.pop_expected(t)?;
+                }
+                self.push_values(&[concrete(*x, false)]);
+            }
+            Instruction::ArrayNewData(x, d) => {
+                self.array_data(*x, *d, false)?;
+                self.operation(&[I32, I32], &[concrete(*x, false)])?;
+            }
+            Instruction::ArrayNewElem(x, e) => {
+                self.array_elem(*x, *e, false)?;
+                self.operation(&[I32, I32], &[concrete(*x, false)])?;
+            }
+            Instruction::ArrayGet(x) | Instruction::ArrayGetS(x) | Instruction::ArrayGetU(x) => {
+                let field = self.ctx.array_type(*x)?;
+                if field.storage().is_packed() == matches!(instr, Instruction::ArrayGet(..)) {
+                    return Err(ValidationError::PackedField);
+                }
+                self.operation(&[concrete(*x, true), I32], &[unpacked(&field)])?;
+            }
+            Instruction::ArraySet(x) => {
+                let t = self.mutable_array(*x)?;
+                self.operation(&[concrete(*x, true), I32, t], &[])?;
+            }
+            Instruction::ArrayLen => {
+                let arrayref = ReferenceType::new(true, HeapType::Array).into();
+                self.operation(&[arrayref], &[I32])?;
+            }
+            Instruction::ArrayFill(x) => {
+                let t = self.mutable_array(*x)?;
+                self.operation(&[concrete(*x, true), I32, t, I32], &[])?;
+            }
+            Instruction::ArrayCopy(x, y) => {
+                self.mutable_array(*x)?;
+                let dst = self.ctx.array_type(*x)?;
+                let src = self.ctx.array_type(*y)?;
+                let storage = match (src.storage(), dst.storage()) {
+                    (StorageType::Val(a), StorageType::Val(b)) => self.ctx.matches(a, b),
+                    (a, b) => a == b,
+                };
+                if !storage {
+                    return Err(ValidationError::TypeMismatch {
+                        expected: unpacked(&dst),
+                        actual: unpacked(&src),
+                    });
+                }
+                let (dst, src) = (concrete(*x, true), concrete(*y, true));
+                self.operation(&[dst, I32, src, I32, I32], &[])?;
+            }
+            Instruction::ArrayInitData(x, d) => {
+                self.array_data(*x, *d, true)?;
+                self.operation(&[concrete(*x, true), I32, I32, I32], &[])?;
+            }
+            Instruction::ArrayInitElem(x, e) => {
+                self.array_elem(*x, *e, true)?;
+                self.operation(&[concrete(*x, true), I32, I32, I32], &[])?;
+            }
+            Instruction::RefTest(rt) | Instruction::RefCast(rt) => {
+                self.ctx.value_type((*rt).into())?;
+                let top = ReferenceType::new(true, self.ctx.top_type(rt.heap_type()));
+                self.pop_expected(top.into())?;
+                if matches!(instr, Instruction::RefTest(..)) {
+                    self.push_values(&[I32]);
+                } else {
+                    self.push_values(&[(*rt).into()]);
+                }
+            }
+            Instruction::BrOnCast(l, rt1, rt2) | Instruction::BrOnCastFail(l, rt1, rt2) => {
+                self.ctx.value_type((*rt1).into())?;
+                self.ctx.value_type((*rt2).into())?;
+                if !self.ctx.ref_matches(*rt2, *rt1) {
+                    return Err(ValidationError::TypeMismatch {
+                        expected: (*rt1).into(),
+                        actual: (*rt2).into(),
+                    });
+                }
+                // the type of values which fail the cast
+                let diff = rt1.with_nullable(rt1.nullable() && !rt2.nullable());
+                let (taken, fallthrough) = if matches!(instr, Instruction::BrOnCast(..)) {
+                    (*rt2, diff)
+                } else {
+                    (diff, *rt2)
+                };
+                let types = self.label_types(*l)?;
+                let Some((&last, rest)) = types.split_last() else {
+                    return Err(ValidationError::ReferenceLabel(*l));
+                };
+                if !self.ctx.matches(taken.into(), last) {
+                    return Err(ValidationError::TypeMismatch {
+                        expected: last,
+                        actual: taken.into(),
+                    });
+                }
+                self.pop_expected((*rt1).into())?;
+                self.pop_values(rest)?;
+                self.push_values(rest);
+                self.push_values(&[fallthrough.into()]);
+            }
+            Instruction::AnyConvertExtern | Instruction::ExternConvertAny => {
+                let (from, to) = if matches!(instr, Instruction::AnyConvertExtern) {
+                    (HeapType::Extern, HeapType::Any)
+                } else {
+                    (HeapType::Any, HeapType::Extern)
+                };
+                let t = self.pop_operand()?;
+                if let Some(t) = t {
+                    let expected = ReferenceType::new(true, from).into();
+                    if !self.ctx.matches(t, expected) {
+                        return Err(ValidationError::TypeMismatch {
+                            expected,
+                            actual: t,
+                        });
+                    }
+                }
+                let nullable = t
+                    .and_then(|t| t.reference_type())
+                    .is_none_or(|rt| rt.nullable());
+                self.push_values(&[ReferenceType::new(nullable, to).into()]);
+            }
+            Instruction::RefI31 => {
+                self.operation(&[I32], &[ReferenceType::new(false, HeapType::I31).into()])?;
+            }
+            Instruction::I31GetS | Instruction::I31GetU => {
+                self.operation(&[ReferenceType::new(true, HeapType::I31).into()], &[I32])?;
+            }
+            Instruction::Drop => {
+                self.pop_operand()?;
+            }
+            Instruction::SelectNumeric => {
+                self.pop_expected(I32)?;
+                let t1 = self.pop_operand()?;
+                let t2 = self.pop_operand()?;
+                for t in [t1, t2].into_iter().flatten() {
+                    if t.reference_type().is_some() {
+                        return Err(ValidationError::ExpectedNumeric(t));
+                    }
+                }
+                match (t1, t2) {
+                    (Some(t1), Some(t2)) if t1 != t2 => {
+                        return Err(ValidationError::TypeMismatch {
+                            expected: t1,
+                            actual: t2,
+                        })
+                    }
+                    _ => self.push_operand(t1.or(t2)),
+                }
+            }
+            Instruction::Select(ts) => {
+                let [t] = ts[..] else {
+                    return Err(ValidationError::SelectArity(ts.len()));
+                };
+                self.ctx.value_type(t)?;
+                self.operation(&[t, t, I32], &[t])?;
+            }
+            Instruction::LocalGet(x) => {
+                let t = self.local(*x)?;
+                if !self.inits[*x as usize] {
+                    return Err(ValidationError::UninitializedLocal(*x));
+                }
+                self.push_values(&[t]);
+            }
+            Instruction::LocalSet(x) => {
+                let t = self.local(*x)?;
+                self.pop_expected(t)?;
+                self.init_local(*x);
+            }
+            Instruction::LocalTee(x) => {
+                let t = self.local(*x)?;
+                self.operation(&[t], &[t])?;
+                self.init_local(*x);
+            }
+            Instruction::GlobalGet(x) => {
+                let t = self.ctx.global(*x)?.ty();
+                self.push_values(&[t]);
+            }
+            Instruction::GlobalSet(x) => {
+                let global = self.ctx.global(*x)?;
+                if !global.mutable() {
+                    return Err(ValidationError::ImmutableGlobal(*x));
+                }
+                self.pop_expected(global.ty())?;
+            }
+            Instruction::TableGet(x) => {
+                let t = self.ctx.table(*x)?.element().into();
+                self.operation(&[I32], &[t])?;
+            }
+            Instruction::TableSet(x) => {
+                let t = self.ctx.table(*x)?.element().into();
+                self.operation(&[I32, t], &[])?;
+            }
+            Instruction::TableInit(e, x) => {
+                let expected = self.ctx.table(*x)?.element();
+                let actual = self.ctx.elem(*e)?;
+                if !self.ctx.ref_matches(actual, expected) {
+                    return Err(ValidationError::TableElementType { expected, actual });
+                }
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+            Instruction::ElemDrop(e) => {
+                self.ctx.elem(*e)?;
+            }
+            Instruction::TableCopy(x, y) => {
+                let expected = self.ctx.table(*x)?.element();
+                let actual = self.ctx.table(*y)?.element();
+                if !self.ctx.ref_matches(actual, expected) {
+                    return Err(ValidationError::TableElementType { expected, actual });
+                }
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+            Instruction::TableGrow(x) => {
+                let t = self.ctx.table(*x)?.element().into();
+                self.operation(&[t, I32], &[I32])?;
+            }
+            Instruction::TableSize(x) => {
+                self.ctx.table(*x)?;
+                self.push_values(&[I32]);
+            }
+            Instruction::TableFill(x) => {
+                let t = self.ctx.table(*x)?.element().into();
+                self.operation(&[I32, t, I32], &[])?;
+            }
+
+            Instruction::I32Load(ma) => self.load(ma, 2, I32)?,
+            Instruction::I64Load(ma) => self.load(ma, 3, I64)?,
+            Instruction::F32Load(ma) => self.load(ma, 2, F32)?,
+            Instruction::F64Load(ma) => self.load(ma, 3, F64)?,
+            Instruction::I32Load8S(ma) | Instruction::I32Load8U(ma) => self.load(ma, 0, I32)?,
+            Instruction::I32Load16S(ma) | Instruction::I32Load16U(ma) => self.load(ma, 1, I32)?,
+            Instruction::I64Load8S(ma) | Instruction::I64Load8U(ma) => self.load(ma, 0, I64)?,
+            Instruction::I64Load16S(ma) | Instruction::I64Load16U(ma) => self.load(ma, 1, I64)?,
+            Instruction::I64Load32S(ma) | Instruction::I64Load32U(ma) => self.load(ma, 2, I64)?,
+            Instruction::I32Store(ma) => self.store(ma, 2, I32)?,
+            Instruction::I64Store(ma) => self.store(ma, 3, I64)?,
+            Instruction::F32Store(ma) => self.store(ma, 2, F32)?,
+            Instruction::F64Store(ma) => self.store(ma, 3, F64)?,
+            Instruction::I32Store8(ma) => self.store(ma, 0, I32)?,
+            Instruction::I32Store16(ma) => self.store(ma, 1, I32)?,
+            Instruction::I64Store8(ma) => self.store(ma, 0, I64)?,
+            Instruction::I64Store16(ma) => self.store(ma, 1, I64)?,
+            Instruction::I64Store32(ma) => self.store(ma, 2, I64)?,
+            Instruction::MemorySize(m) => {
+                let at = self.ctx.memory(*m)?.index_type();
+                self.push_values(&[at]);
+            }
+            Instruction::MemoryGrow(m) => {
+                let at = self.ctx.memory(*m)?.index_type();
+                self.operation(&[at], &[at])?;
+            }
+            Instruction::MemoryInit(d, m) => {
+                self.ctx.data(*d)?;
+                let at = self.ctx.memory(*m)?.index_type();
+                self.operation(&[at, I32, I32], &[])?;
+            }
+            Instruction::DataDrop(d) => self.ctx.data(*d)?,
+            Instruction::MemoryCopy(m1, m2) => {
+                let at1 = self.ctx.memory(*m1)?.index_type();
+                let at2 = self.ctx.memory(*m2)?.index_type();
+                // the length is addressable in both memories
+                let len = if at1 == I64 && at2 == I64 { I64 } else { I32 };
+                self.operation(&[at1, at2, len], &[])?;
+            }
+            Instruction::MemoryFill(m) => {
+                let at = self.ctx.memory(*m)?.index_type();
+                self.operation(&[at, I32, at], &[])?;
+            }
+
+            Instruction::MemoryAtomicNotify(ma) => self.atomic(ma, 2, &[I32], I32)?,
+            Instruction::MemoryAtomicWait32(ma) => self.atomic(ma, 2, &[I32, I64], I32)?,
+            Instruction::MemoryAtomicWait64(ma) => self.atomic(ma, 3, &[I64, I64], I32)?,
+            Instruction::AtomicFence => {}
+            Instruction::I32AtomicLoad8U(ma) => self.atomic(ma, 0, &[], I32)?,
+            Instruction::I64AtomicLoad8U(ma) => self.atomic(ma, 0, &[], I64)?,
+            Instruction::I32AtomicLoad16U(ma) => self.atomic(ma, 1, &[], I32)?,
+            Instruction::I64AtomicLoad16U(ma) => self.atomic(ma, 1, &[], I64)?,
+            Instruction::I32AtomicLoad(ma) => self.atomic(ma, 2, &[], I32)?,
+            Instruction::I64AtomicLoad32U(ma) => self.atomic(ma, 2, &[], I64)?,
+            Instruction::I64AtomicLoad(ma) => self.atomic(ma, 3, &[], I64)?,
+            Instruction::I32AtomicStore8(ma) => self.atomic_store(ma, 0, I32)?,
+            Instruction::I64AtomicStore8(ma) => self.atomic_store(ma, 0, I64)?,
+            Instruction::I32AtomicStore16(ma) => self.atomic_store(ma, 1, I32)?,
+            Instruction::I64AtomicStore16(ma) => self.atomic_store(ma, 1, I64)?,
+            Instruction::I32AtomicStore(ma) => self.atomic_store(ma, 2, I32)?,
+            Instruction::I64AtomicStore32(ma) => self.atomic_store(ma, 2, I64)?,
+            Instruction::I64AtomicStore(ma) => self.atomic_store(ma, 3, I64)?,
+            Instruction::I32AtomicRmw8AddU(ma)
+            | Instruction::I32AtomicRmw8SubU(ma)
+            | Instruction::I32AtomicRmw8AndU(ma)
+            | Instruction::I32AtomicRmw8OrU(ma)
+            | Instruction::I32AtomicRmw8XorU(ma)
+            | Instruction::I32AtomicRmw8XchgU(ma) => self.atomic(ma, 0, &[I32], I32)?,
+            Instruction::I64AtomicRmw8AddU(ma)
+            | Instruction::I64AtomicRmw8SubU(ma)
+            | Instruction::I64AtomicRmw8AndU(ma)
+            | Instruction::I64AtomicRmw8OrU(ma)
+            | Instruction::I64AtomicRmw8XorU(ma)
+            | Instruction::I64AtomicRmw8XchgU(ma) => self.atomic(ma, 0, &[I64], I64)?,
+            Instruction::I32AtomicRmw16AddU(ma)
+            | Instruction::I32AtomicRmw16SubU(ma)
+            | Instruction::I32AtomicRmw16AndU(ma)
+            | Instruction::I32AtomicRmw16OrU(ma)
+            | Instruction::I32AtomicRmw16XorU(ma)
+            | Instruction::I32AtomicRmw16XchgU(ma) => self.atomic(ma, 1, &[I32], I32)?,
+            Instruction::I64AtomicRmw16AddU(ma)
+            | Instruction::I64AtomicRmw16SubU(ma)
+            | Instruction::I64AtomicRmw16AndU(ma)
+            | Instruction::I64AtomicRmw16OrU(ma)
+            | Instruction::I64AtomicRmw16XorU(ma)
+            | Instruction::I64AtomicRmw16XchgU(ma) => self.atomic(ma, 1, &[I64], I64)?,
+            Instruction::I32AtomicRmwAdd(ma)
+            | Instruction::I32AtomicRmwSub(ma)
+            | Instruction::I32AtomicRmwAnd(ma)
+            | Instruction::I32AtomicRmwOr(ma)
+            | Instruction::I32AtomicRmwXor(ma)
+            | Instruction::I32AtomicRmwXchg(ma) => self.atomic(ma, 2, &[I32], I32)?,
+            Instruction::I64AtomicRmw32AddU(ma)
+            | Instruction::I64AtomicRmw32SubU(ma)
+            | Instruction::I64AtomicRmw32AndU(ma)
+            | Instruction::I64AtomicRmw32OrU(ma)
+            | Instruction::I64AtomicRmw32XorU(ma)
+            | Instruction::I64AtomicRmw32XchgU(ma) => self.atomic(ma, 2, &[I64], I64)?,
+            Instruction::I64AtomicRmwAdd(ma)
+            | Instruction::I64AtomicRmwSub(ma)
+            | Instruction::I64AtomicRmwAnd(ma)
+            | Instruction::I64AtomicRmwOr(ma)
+            | Instruction::I64AtomicRmwXor(ma)
+            | Instruction::I64AtomicRmwXchg(ma) => self.atomic(ma, 3, &[I64], I64)?,
+            Instruction::I32AtomicRmw8CmpxchgU(ma) => self.atomic(ma, 0, &[I32, I32], I32)?,
+            Instruction::I64AtomicRmw8CmpxchgU(ma) => self.atomic(ma, 0, &[I64, I64], I64)?,
+            Instruction::I32AtomicRmw16CmpxchgU(ma) => self.atomic(ma, 1, &[I32, I32], I32)?,
+            Instruction::I64AtomicRmw16CmpxchgU(ma) => self.atomic(ma, 1, &[I64, I64], I64)?,
+            Instruction::I32AtomicRmwCmpxchg(ma) => self.atomic(ma, 2, &[I32, I32], I32)?,
+            Instruction::I64AtomicRmw32CmpxchgU(ma) => self.atomic(ma, 2, &[I64, I64], I64)?,
+            Instruction::I64AtomicRmwCmpxchg(ma) => self.atomic(ma, 3, &[I64, I64], I64)?,
+
+            Instruction::V128Load(ma) => self.load(ma, 4, V128)?,
+            Instruction::V128Load8x8S(ma)
+            | Instruction::V128Load8x8U(ma)
+            | Instruction::V128Load16x4S(ma)
+            | Instruction::V128Load16x4U(ma)
+            | Instruction::V128Load32x2S(ma)
+            | Instruction::V128Load32x2U(ma)
+            | Instruction::V128Load64Splat(ma)
+            | Instruction::V128Load64Zero(ma) => self.load(ma, 3, V128)?,
+            Instruction::V128Load8Splat(ma) => self.load(ma, 0, V128)?,
+            Instruction::V128Load16Splat(ma) => self.load(ma, 1, V128)?,
+            Instruction::V128Load32Splat(ma) | Instruction::V128Load32Zero(ma) => {
+                self.load(ma, 2, V128)?
+            }
+            Instruction::V128Store(ma) => self.store(ma, 4, V128)?,
+            Instruction::V128Load8Lane(ma, l) => self.load_lane(ma, *l, 0, false)?,
+            Instruction::V128Load16Lane(ma, l) => self.load_lane(ma, *l, 1, false)?,
+            Instruction::V128Load32Lane(ma, l) => self.load_lane(ma, *l, 2, false)?,
+            Instruction::V128Load64Lane(ma, l) => self.load_lane(ma, *l, 3, false)?,
+            Instruction::V128Store8Lane(ma, l) => self.load_lane(ma, *l, 0, true)?,
+            Instruction::V128Store16Lane(ma, l) => self.load_lane(ma, *l, 1, true)?,
+            Instruction::V128Store32Lane(ma, l) => self.load_lane(ma, *l, 2, true)?,
+            Instruction::V128Store64Lane(ma, l) => self.load_lane(ma, *l, 3, true)?,
+            Instruction::I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    Self::lane(*lane, 32)?;
+                }
+                self.operation(&[V128, V128], &[V128])?;
+            }
+            Instruction::I8x16ExtractLaneS(l) | Instruction::I8x16ExtractLaneU(l) => {
+                Self::lane(*l, 16)?;
+                self.operation(&[V128], &[I32])?;
+            }
+            Instruction::I16x8ExtractLaneS(l) | Instruction::I16x8ExtractLaneU(l) => {
+                Self::lane(*l, 8)?;
+                self.operation(&[V128], &[I32])?;
+            }
+            Instruction::I32x4ExtractLane(l) => {
+                Self::lane(*l, 4)?;
+                self.operation(&[V128], &[I32])?;
+            }
+            Instruction::I64x2ExtractLane(l) => {
+                Self::lane(*l, 2)?;
+                self.operation(&[V128], &[I64])?;
+            }
+            Instruction::F32x4ExtractLane(l) => {
+                Self::lane(*l, 4)?;
+                self.operation(&[V128], &[F32])?;
+            }
+            Instruction::F64x2ExtractLane(l) => {
+                Self::lane(*l, 2)?;
+                self.operation(&[V128], &[F64])?;
+            }
+            Instruction::I8x16ReplaceLane(l) => {
+                Self::lane(*l, 16)?;
+                self.operation(&[V128, I32], &[V128])?;
+            }
+            Instruction::I16x8ReplaceLane(l) => {
+                Self::lane(*l, 8)?;
+                self.operation(&[V128, I32], &[V128])?;
+            }
+            Instruction::I32x4ReplaceLane(l) => {
+                Self::lane(*l, 4)?;
+                self.operation(&[V128, I32], &[V128])?;
+            }
+            Instruction::I64x2ReplaceLane(l) => {
+                Self::lane(*l, 2)?;
+                self.operation(&[V128, I64], &[V128])?;
+            }
+            Instruction::F32x4ReplaceLane(l) => {
+                Self::lane(*l, 4)?;
+                self.operation(&[V128, F32], &[V128])?;
+            }
+            Instruction::F64x2ReplaceLane(l) => {
+                Self::lane(*l, 2)?;
+                self.operation(&[V128, F64], &[V128])?;
+            }
+
+            instr => {
+                let (params, results) = numeric_signature(instr);
+                self.operation(params, results)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, memarg: &MemArg, natural: u32, t: ValueType) -> Result<(), ValidationError> {
+        let at = self.memarg(memarg, natural)?;
+        self.operation(&[at], &[t])
+    }
+
+    fn store(
+        &mut self,
+        memarg: &MemArg,
+        natural: u32,
+        t: ValueType,
+    ) -> Result<(), ValidationError> {
+        let at = self.memarg(memarg, natural)?;
+        self.operation(&[at, t], &[])
+    }
+
+    /// Validates an atomic memory instruction taking `operands` after its address.
+    fn atomic(
+        &mut self,
+        memarg: &MemArg,
+        natural: u32,
+        operands: &[ValueType],
+        result: ValueType,
+    ) -> Result<(), ValidationError> {
+        let at = self.atomic_memarg(memarg, natural)?;
+        let params: Vec<_> = std::iter::once(at)
+            .chain(operands.iter().copied())
+            .collect();
+        self.operation(&params, &[result])
+    }
+
+    fn atomic_store(
+        &mut self,
+        memarg: &MemArg,
+        natural: u32,
+        t: ValueType,
+    ) -> Result<(), ValidationError> {
+        let at = self.atomic_memarg(memarg, natural)?;
+        self.operation(&[at, t], &[])
+    }
+
+    fn load_lane(
+        &mut self,
+        memarg: &MemArg,
+        lane: u32,
+        natural: u32,
+        store: bool,
+    ) -> Result<(), ValidationError> {
+        let at = self.memarg(memarg, natural)?;
+        Self::lane(lane, 16 >> natural)?;
+        if store {
+            self.operation(&[at, ValueType::V128], &[])
+        } else {
+            self.operation(&[at, ValueType::V128], &[ValueType::V128])
+        }
+    }
+
+    /// Returns the unpacked element type of the array type `x`, which must be mutable.
+    fn mutable_array(&self, x: u32) -> Result<ValueType, ValidationError> {
+        let field = self.ctx.array_type(x)?;
+        if !field.mutable() {
+            return Err(ValidationError::ImmutableField { ty: x, field: 0 });
+        }
+        Ok(unpacked(&field))
+    }
+
+    /// Checks that elements of the array type `x` can be read from data segment `d`.
+    fn array_data(&self, x: u32, d: u32, init: bool) -> Result<(), ValidationError> {
+        let t = if init {
+            self.mutable_array(x)?
+        } else {
+            unpacked(&self.ctx.array_type(x)?)
+        };
+        if t.reference_type().is_some() {
+            return Err(ValidationError::ExpectedNumeric(t));
+        }
+        self.ctx.data(d)
+    }
+
+    /// Checks that elements of the array type `x` can be read from element segment `e`.
+    fn array_elem(&self, x: u32, e: u32, init: bool) -> Result<(), ValidationError> {
+        let t = if init {
+            self.mutable_array(x)?
+        } else {
+            unpacked(&self.ctx.array_type(x)?)
+        };
+        let expected = t
+            .reference_type()
+            .ok_or(ValidationError::ExpectedReference(t))?;
+        let actual = self.ctx.elem(e)?;
+        if !self.ctx.ref_matches(actual, expected) {
+            return Err(ValidationError::TableElementType { expected, actual });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the canonical index of every type, as of iso-recursive type equivalence of the GC
+/// proposal.
+///
+/// Rec groups are compared structurally, with indices of types inside the group taken relative to
+/// the group and indices of preceding types replaced by their canonical indices. Types of
+/// equivalent groups get the indices of the first of them.
+fn canonicalize(types: &[SubType], rec_groups: &[Range<u32>]) -> Vec<u32> {
+    let mut canonical: Vec<u32> = Vec::with_capacity(types.len());
+    // rewritten types of each group, along with whether each index was relative to the group
+    let mut groups: HashMap<(Vec<SubType>, Vec<bool>), u32> = HashMap::new();
+    let mut explicit = rec_groups.iter().peekable();
+    while canonical.len() < types.len() {
+        let start = canonical.len();
+        let end = match explicit.next_if(|x| x.start as usize == start) {
+            Some(group) => (group.end as usize).clamp(start + 1, types.len()),
+            None => start + 1,
+        };
+
+        let mut relative = Vec::new();
+        let group = types[start..end]
+            .iter()
+            .map(|ty| {
+                let mut ty = ty.clone();
+                ty.visit_type_indices_mut(&mut |x| {
+                    let inner = (start..end).contains(&(*x as usize));
+                    relative.push(inner);
+                    if inner {
+                        *x -= start as u32;
+                    } else if let Some(&c) = canonical.get(*x as usize) {
+                        *x = c;
+                    }
+                });
+                ty
+            })
+            .collect();
+
+        let first = *groups.entry((group, relative)).or_insert(start as u32);
+        canonical.extend(first..first + (end - start) as u32);
+    }
+    canonical
+}
+
+/// Returns the type of values read from or written to a field.
+fn unpacked(field: &FieldType) -> ValueType {
+    field.storage().unpacked()
+}
+
+/// Returns a reference to the concrete type `x`.
+fn concrete(x: u32, nullable: bool) -> ValueType {
+    ReferenceType::new(nullable, HeapType::Concrete(x)).into()
+}
+
+/// Returns the non-nullable version of a reference type.
+fn non_null(t: ValueType) -> ReferenceType {
+    t.reference_type()
+        .expect("not a reference type")
+        .with_nullable(false)
+}
+
+/// Returns the operand and result types of an instruction which has no immediates affecting its
+/// type.
+///
+/// Every other instruction is listed explicitly, so that a new instruction cannot be added without
+/// deciding how it is validated.
+///
+/// # Panics
+///
+/// Panics if the instruction is not a plain numeric or vector instruction.
+fn numeric_signature(instr: &Instruction) -> (&'static [ValueType], &'static [ValueType]) {
+    use ValueType::{F32, F64, I32, I64, V128};
+
+    match instr {
+        Instruction::I32Const(_) => (&[], &[I32]),
+        Instruction::I64Const(_) => (&[], &[I64]),
+        Instruction::F32Const(_) => (&[], &[F32]),
+        Instruction::F64Const(_) => (&[], &[F64]),
+        Instruction::V128Const(_) => (&[], &[V128]),
+
+        Instruction::I32Eqz
+        | Instruction::I32Clz
+        | Instruction::I32Ctz
+        | Instruction::I32Popcnt
+        | Instruction::I32Extend8S
+        | Instruction::I32Extend16S => (&[I32], &[I32]),
+        Instruction::I32Eq
+        | Instruction::I32Ne
+        | Instruction::I32LtS
+        | Instruction::I32LtU
+        | Instruction::I32GtS
+        | Instruction::I32GtU
+        | Instruction::I32LeS
+        | Instruction::I32LeU
+        | Instruction::I32GeS
+        | Instruction::I32GeU
+        | Instruction::I32Add
+        | Instruction::I32Sub
+        | Instruction::I32Mul
+        | Instruction::I32DivS
+        | Instruction::I32DivU
+        | Instruction::I32RemS
+        | Instruction::I32RemU
+        | Instruction::I32And
+        | Instruction::I32Or
+        | Instruction::I32Xor
+        | Instruction::I32Shl
+        | Instruction::I32ShrS
+        | Instruction::I32ShrU
+        | Instruction::I32Rotl
+        | Instruction::I32Rotr => (&[I32, I32], &[I32]),
+
+        Instruction::I64Eqz => (&[I64], &[I32]),
+        Instruction::I64Eq
+        | Instruction::I64Ne
+        | Instruction::I64LtS
+        | Instruction::I64LtU
+        | Instruction::I64GtS
+        | Instruction::I64GtU
+        | Instruction::I64LeS
+        | Instruction::I64LeU
+        | Instruction::I64GeS
+        | Instruction::I64GeU => (&[I64, I64], &[I32]),
+        Instruction::I64Clz
+        | Instruction::I64Ctz
+        | Instruction::I64Popcnt
+        | Instruction::I64Extend8S
+        | Instruction::I64Extend16S
+        | Instruction::I64Extend32S => (&[I64], &[I64]),
+        Instruction::I64Add
+        | Instruction::I64Sub
+        | Instruction::I64Mul
+        | Instruction::I64DivS
+        | Instruction::I64DivU
+        | Instruction::I64RemS
+        | Instruction::I64RemU
+        | Instruction::I64And
+        | Instruction::I64Or
+        | Instruction::I64Xor
+        | Instruction::I64Shl
+        | Instruction::I64ShrS
+        | Instruction::I64ShrU
+        | Instruction::I64Rotl
+        | Instruction::I64Rotr => (&[I64, I64], &[I64]),
+
+        Instruction::F32Eq
+        | Instruction::F32Ne
+        | Instruction::F32Lt
+        | Instruction::F32Gt
+        | Instruction::F32Le
+        | Instruction::F32Ge => (&[F32, F32], &[I32]),
+        Instruction::F64Eq
+        | Instruction::F64Ne
+        | Instruction::F64Lt
+        | Instruction::F64Gt
+        | Instruction::F64Le
+        | Instruction::F64Ge => (&[F64, F64], &[I32]),
+        Instruction::F32Abs
+        | Instruction::F32Neg
+        | Instruction::F32Ceil
+        | Instruction::F32Floor
+        | Instruction::F32Trunc
+        | Instruction::F32Nearest
+        | Instruction::F32Sqrt => (&[F32], &[F32]),
+        Instruction::F32Add
+        | Instruction::F32Sub
+        | Instruction::F32Mul
+        | Instruction::F32Div
+        | Instruction::F32Min
+        | Instruction::F32Max
+        | Instruction::F32Copysign => (&[F32, F32], &[F32]),
+        Instruction::F64Abs
+        | Instruction::F64Neg
+        | Instruction::F64Ceil
+        | Instruction::F64Floor
+        | Instruction::F64Trunc
+        | Instruction::F64Nearest
+        | Instruction::F64Sqrt => (&[F64], &[F64]),
+        Instruction::F64Add
+        | Instruction::F64Sub
+        | Instruction::F64Mul
+        | Instruction::F64Div
+        | Instruction::F64Min
+        | Instruction::F64Max
+        | Instruction::F64Copysign => (&[F64, F64], &[F64]),
+
+        Instruction::I32WrapI64 => (&[I64], &[I32]),
+        Instruction::I32TruncF32S
+        | Instruction::I32TruncF32U
+        | Instruction::I32TruncSatF32S
+        | Instruction::I32TruncSatF32U
+        | Instruction::I32ReinterpretF32 => (&[F32], &[I32]),
+        Instruction::I32TruncF64S
+        | Instruction::I32TruncF64U
+        | Instruction::I32TruncSatF64S
+        | Instruction::I32TruncSatF64U => (&[F64], &[I32]),
+        Instruction::I64ExtendI32S | Instruction::I64ExtendI32U => (&[I32], &[I64]),
+        Instruction::I64TruncF32S
+        | Instruction::I64TruncF32U
+        | Instruction::I64TruncSatF32S
+        | Instruction::I64TruncSatF32U => (&[F32], &[I64]),
+        Instruction::I64TruncF64S
+        | Instruction::I64TruncF64U
+        | Instruction::I64TruncSatF64S
+        | Instruction::I64TruncSatF64U
+        | Instruction::I64ReinterpretF64 => (&[F64], &[I64]),
+        Instruction::F32ConvertI32S
+        | Instruction::F32ConvertI32U
+        | Instruction::F32ReinterpretI32 => (&[I32], &[F32]),
+        Instruction::F32ConvertI64S | Instruction::F32ConvertI64U => (&[I64], &[F32]),
+        Instruction::F32DemoteF64 => (&[F64], &[F32]),
+        Instruction::F64ConvertI32S | Instruction::F64ConvertI32U => (&[I32], &[F64]),
+        Instruction::F64ConvertI64S
+        | Instruction::F64ConvertI64U
+        | Instruction::F64ReinterpretI64 => (&[I64], &[F64]),
+        Instruction::F64PromoteF32 => (&[F32], &[F64]),
+
+        Instruction::I8x16Splat | Instruction::I16x8Splat | Instruction::I32x4Splat => {
+            (&[I32], &[V128])
+        }
+        Instruction::I64x2Splat => (&[I64], &[V128]),
+        Instruction::F32x4Splat => (&[F32], &[V128]),
+        Instruction::F64x2Splat => (&[F64], &[V128]),
+
+        Instruction::V128AnyTrue
+        | Instruction::I8x16AllTrue
+        | Instruction::I8x16Bitmask
+        | Instruction::I16x8AllTrue
+        | Instruction::I16x8Bitmask
+        | Instruction::I32x4AllTrue
+        | Instruction::I32x4Bitmask
+        | Instruction::I64x2AllTrue
+        | Instruction::I64x2Bitmask => (&[V128], &[I32]),
+
+        Instruction::V128Bitselect => (&[V128, V128, V128], &[V128]),
+
+        Instruction::I8x16Shl
+        | Instruction::I8x16ShrS
+        | Instruction::I8x16ShrU
+        | Instruction::I16x8Shl
+        | Instruction::I16x8ShrS
+        | Instruction::I16x8ShrU
+        | Instruction::I32x4Shl
+        | Instruction::I32x4ShrS
+        | Instruction::I32x4ShrU
+        | Instruction::I64x2Shl
+        | Instruction::I64x2ShrS
+        | Instruction::I64x2ShrU => (&[V128, I32], &[V128]),
+
+        Instruction::V128Not
+        | Instruction::I8x16Abs
+        | Instruction::I8x16Neg
+        | Instruction::I8x16Popcnt
+        | Instruction::I16x8ExtAddPairwiseI8x16S
+        | Instruction::I16x8ExtAddPairwiseI8x16U
+        | Instruction::I16x8Abs
+        | Instruction::I16x8Neg
+        | Instruction::I16x8ExtendLowI8X16S
+        | Instruction::I16x8ExtendHighI8X16S
+        | Instruction::I16x8ExtendLowI8X16U
+        | Instruction::I16x8ExtendHighI8X16U
+        | Instruction::I32x4ExtAddPairwiseI16x8S
+        | Instruction::I32x4ExtAddPairwiseI16x8U
+        | Instruction::I32x4Abs
+        | Instruction::I32x4Neg
+        | Instruction::I32x4ExtendLowI16X8S
+        | Instruction::I32x4ExtendHighI16X8S
+        | Instruction::I32x4ExtendLowI16X8U
+        | Instruction::I32x4ExtendHighI16X8U
+        | Instruction::I64x2Abs
+        | Instruction::I64x2Neg
+        | Instruction::I64x2ExtendLowI16X8S
+        | Instruction::I64x2ExtendHighI16X8S
+        | Instruction::I64x2ExtendLowI16X8U
+        | Instruction::I64x2ExtendHighI16X8U
+        | Instruction::F32x4Ceil
+        | Instruction::F32x4Floor
+        | Instruction::F32x4Trunc
+        | Instruction::F32x4Nearest
+        | Instruction::F32x4Abs
+        | Instruction::F32x4Neg
+        | Instruction::F32x4Sqrt
+        | Instruction::F64x2Ceil
+        | Instruction::F64x2Floor
+        | Instruction::F64x2Trunc
+        | Instruction::F64x2Nearest
+        | Instruction::F64x2Abs
+        | Instruction::F64x2Neg
+        | Instruction::F64x2Sqrt
+        | Instruction::I32x4TruncSatF32x4S
+        | Instruction::I32x4TruncSatF32x4U
+        | Instruction::F32x4ConvertI32x4S
+        | Instruction::F32x4ConvertI32x4U
+        | Instruction::I32x4TruncSatF64x2SZero
+        | Instruction::I32x4TruncSatF64x2UZero
+        | Instruction::F64x2ConvertLowI32x4S
+        | Instruction::F64x2ConvertLowI32x4U
+        | Instruction::F32x4DemoteF64x2Zero
+        | Instruction::F64x2PromoteLowF32x4 => (&[V128], &[V128]),
+
+        Instruction::I8X16Swizzle
+        | Instruction::I8x16Eq
+        | Instruction::I8x16Ne
+        | Instruction::I8X16LtS
+        | Instruction::I8X16LtU
+        | Instruction::I8X16GtS
+        | Instruction::I8X16GtU
+        | Instruction::I8X16LeS
+        | Instruction::I8X16LeU
+        | Instruction::I8X16GeS
+        | Instruction::I8X16GeU
+        | Instruction::I16x8Eq
+        | Instruction::I16x8Ne
+        | Instruction::I16x8LtS
+        | Instruction::I16x8LtU
+        | Instruction::I16x8GtS
+        | Instruction::I16x8GtU
+        | Instruction::I16x8LeS
+        | Instruction::I16x8LeU
+        | Instruction::I16x8GeS
+        | Instruction::I16x8GeU
+        | Instruction::I32x4Eq
+        | Instruction::I32x4Ne
+        | Instruction::I32x4LtS
+        | Instruction::I32x4LtU
+        | Instruction::I32x4GtS
+        | Instruction::I32x4GtU
+        | Instruction::I32x4LeS
+        | Instruction::I32x4LeU
+        | Instruction::I32x4GeS
+        | Instruction::I32x4GeU
+        | Instruction::I64x2Eq
+        | Instruction::I64x2Ne
+        | Instruction::I64x2LtS
+        | Instruction::I64x2GtS
+        | Instruction::I64x2LeS
+        | Instruction::I64x2GeS
+        | Instruction::F32x4Eq
+        | Instruction::F32x4Ne
+        | Instruction::F32x4Lt
+        | Instruction::F32x4Gt
+        | Instruction::F32x4Le
+        | Instruction::F32x4Ge
+        | Instruction::F64x2Eq
+        | Instruction::F64x2Ne
+        | Instruction::F64x2Lt
+        | Instruction::F64x2Gt
+        | Instruction::F64x2Le
+        | Instruction::F64x2Ge
+        | Instruction::V128And
+        | Instruction::V128AndNot
+        | Instruction::V128Or
+        | Instruction::V128Xor
+        | Instruction::I8x16NarrowI16x8S
+        | Instruction::I8x16NarrowI16x8U
+        | Instruction::I8x16Add
+        | Instruction::I8x16AddSatS
+        | Instruction::I8x16AddSatU
+        | Instruction::I8x16Sub
+        | Instruction::I8x16SubSatS
+        | Instruction::I8x16SubSatU
+        | Instruction::I8x16MinS
+        | Instruction::I8x16MinU
+        | Instruction::I8x16MaxS
+        | Instruction::I8x16MaxU
+        | Instruction::I8x16AvgrU
+        | Instruction::I16x8Q15MulrSatS
+        | Instruction::I16x8NarrowI32x4S
+        | Instruction::I16x8NarrowI32x4U
+        | Instruction::I16x8Add
+        | Instruction::I16x8AddSatS
+        | Instruction::I16x8AddSatU
+        | Instruction::I16x8Sub
+        | Instruction::I16x8SubSatS
+        | Instruction::I16x8SubSatU
+        | Instruction::I16X8Mul
+        | Instruction::I16x8MinS
+        | Instruction::I16x8MinU
+        | Instruction::I16x8MaxS
+        | Instruction::I16x8MaxU
+        | Instruction::I16x8AvgrU
+        | Instruction::I16x8ExtmulLowI8x16S
+        | Instruction::I16x8ExtmulHighI8x16S
+        | Instruction::I16x8ExtmulLowI8x16U
+        | Instruction::I16x8ExtmulHighI8x16U
+        | Instruction::I32x4Add
+        | Instruction::I32x4Sub
+        | Instruction::I32x4Mul
+        | Instruction::I32x4MinS
+        | Instruction::I32x4MinU
+        | Instruction::I32x4MaxS
+        | Instruction::I32x4MaxU
+        | Instruction::I32x4DotI16x8S
+        | Instruction::I32x4ExtmulLowI16x8S
+        | Instruction::I32x4ExtmulHighI16x8S
+        | Instruction::I32x4ExtmulLowI16x8U
+        | Instruction::I32x4ExtmulHighI16x8U
+        | Instruction::I64x2Add
+        | Instruction::I64x2Sub
+        | Instruction::I64x2Mul
+        | Instruction::I64x2ExtmulLowI32x4S
+        | Instruction::I64x2ExtmulHighI32x4S
+        | Instruction::I64x2ExtmulLowI32x4U
+        | Instruction::I64x2ExtmulHighI32x4U
+        | Instruction::F32x4Add
+        | Instruction::F32x4Sub
+        | Instruction::F32x4Mul
+        | Instruction::F32x4Div
+        | Instruction::F32x4Min
+        | Instruction::F32x4Max
+        | Instruction::F32x4Pmin
+        | Instruction::F32x4Pmax
+        | Instruction::F64x2Add
+        | Instruction::F64x2Sub
+        | Instruction::F64x2Mul
+        | Instruction::F64x2Div
+        | Instruction::F64x2Min
+        | Instruction::F64x2Max
+        | Instruction::F64x2Pmin
+        | Instruction::F64x2Pmax => (&[V128, V128], &[V128]),
+
+        Instruction::I32x4RelaxedTruncF32x4S
+        | Instruction::I32x4RelaxedTruncF32x4U
+        | Instruction::I32x4RelaxedTruncF64x2SZero
+        | Instruction::I32x4RelaxedTruncF64x2UZero => (&[V128], &[V128]),
+        Instruction::I8x16RelaxedSwizzle
+        | Instruction::F32x4RelaxedMin
+        | Instruction::F32x4RelaxedMax
+        | Instruction::F64x2RelaxedMin
+        | Instruction::F64x2RelaxedMax
+        | Instruction::I16x8RelaxedQ15mulrS
+        | Instruction::I16x8RelaxedDotI8x16I7x16S => (&[V128, V128], &[V128]),
+        Instruction::F32x4RelaxedMadd
+        | Instruction::F32x4RelaxedNmadd
+        | Instruction::F64x2RelaxedMadd
+        | Instruction::F64x2RelaxedNmadd
+        | Instruction::I8x16RelaxedLaneselect
+        | Instruction::I16x8RelaxedLaneselect
+        | Instruction::I32x4RelaxedLaneselect
+        | Instruction::I64x2RelaxedLaneselect
+        | Instruction::I32x4RelaxedDotI8x16I7x16AddS => (&[V128, V128, V128], &[V128]),
+
+        // control instructions
+        Instruction::Unreachable
+        | Instruction::Nop
+        | Instruction::Block(..)
+        | Instruction::Loop(..)
+        | Instruction::If(..)
+        | Instruction::Try(..)
+        | Instruction::TryDelegate(..)
+        | Instruction::Throw(..)
+        | Instruction::Rethrow(..)
+        | Instruction::ThrowRef
+        | Instruction::TryTable(..)
+        | Instruction::Br(..)
+        | Instruction::BrIf(..)
+        | Instruction::BrTable(..)
+        | Instruction::Return
+        | Instruction::Call(..)
+        | Instruction::CallIndirect { .. }
+        | Instruction::ReturnCall(..)
+        | Instruction::ReturnCallIndirect { .. }
+        | Instruction::CallRef(..)
+        | Instruction::ReturnCallRef(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // reference and aggregate instructions
+        Instruction::RefNull(..)
+        | Instruction::RefIsNull
+        | Instruction::RefFunc(..)
+        | Instruction::RefEq
+        | Instruction::RefAsNonNull
+        | Instruction::BrOnNull(..)
+        | Instruction::BrOnNonNull(..)
+        | Instruction::StructNew(..)
+        | Instruction::StructNewDefault(..)
+        | Instruction::StructGet(..)
+        | Instruction::StructGetS(..)
+        | Instruction::StructGetU(..)
+        | Instruction::StructSet(..)
+        | Instruction::ArrayNew(..)
+        | Instruction::ArrayNewDefault(..)
+        | Instruction::ArrayNewFixed(..)
+        | Instruction::ArrayNewData(..)
+        | Instruction::ArrayNewElem(..)
+        | Instruction::ArrayGet(..)
+        | Instruction::ArrayGetS(..)
+        | Instruction::ArrayGetU(..)
+        | Instruction::ArraySet(..)
+        | Instruction::ArrayLen
+        | Instruction::ArrayFill(..)
+        | Instruction::ArrayCopy(..)
+        | Instruction::ArrayInitData(..)
+        | Instruction::ArrayInitElem(..)
+        | Instruction::RefTest(..)
+        | Instruction::RefCast(..)
+        | Instruction::BrOnCast(..)
+        | Instruction::BrOnCastFail(..)
+        | Instruction::AnyConvertExtern
+        | Instruction::ExternConvertAny
+        | Instruction::RefI31
+        | Instruction::I31GetS
+        | Instruction::I31GetU => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // parametric and variable instructions
+        Instruction::Drop
+        | Instruction::SelectNumeric
+        | Instruction::Select(..)
+        | Instruction::LocalGet(..)
+        | Instruction::LocalSet(..)
+        | Instruction::LocalTee(..)
+        | Instruction::GlobalGet(..)
+        | Instruction::GlobalSet(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // table instructions
+        Instruction::TableGet(..)
+        | Instruction::TableSet(..)
+        | Instruction::TableInit(..)
+        | Instruction::ElemDrop(..)
+        | Instruction::TableCopy(..)
+        | Instruction::TableGrow(..)
+        | Instruction::TableSize(..)
+        | Instruction::TableFill(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // memory instructions
+        Instruction::I32Load(..)
+        | Instruction::I64Load(..)
+        | Instruction::F32Load(..)
+        | Instruction::F64Load(..)
+        | Instruction::I32Load8S(..)
+        | Instruction::I32Load8U(..)
+        | Instruction::I32Load16S(..)
+        | Instruction::I32Load16U(..)
+        | Instruction::I64Load8S(..)
+        | Instruction::I64Load8U(..)
+        | Instruction::I64Load16S(..)
+        | Instruction::I64Load16U(..)
+        | Instruction::I64Load32S(..)
+        | Instruction::I64Load32U(..)
+        | Instruction::I32Store(..)
+        | Instruction::I64Store(..)
+        | Instruction::F32Store(..)
+        | Instruction::F64Store(..)
+        | Instruction::I32Store8(..)
+        | Instruction::I32Store16(..)
+        | Instruction::I64Store8(..)
+        | Instruction::I64Store16(..)
+        | Instruction::I64Store32(..)
+        | Instruction::MemorySize(..)
+        | Instruction::MemoryGrow(..)
+        | Instruction::MemoryInit(..)
+        | Instruction::DataDrop(..)
+        | Instruction::MemoryCopy(..)
+        | Instruction::MemoryFill(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // vector memory and lane instructions
+        Instruction::V128Load(..)
+        | Instruction::V128Load8x8S(..)
+        | Instruction::V128Load8x8U(..)
+        | Instruction::V128Load16x4S(..)
+        | Instruction::V128Load16x4U(..)
+        | Instruction::V128Load32x2S(..)
+        | Instruction::V128Load32x2U(..)
+        | Instruction::V128Load8Splat(..)
+        | Instruction::V128Load16Splat(..)
+        | Instruction::V128Load32Splat(..)
+        | Instruction::V128Load64Splat(..)
+        | Instruction::V128Load32Zero(..)
+        | Instruction::V128Load64Zero(..)
+        | Instruction::V128Store(..)
+        | Instruction::V128Load8Lane(..)
+        | Instruction::V128Load16Lane(..)
+        | Instruction::V128Load32Lane(..)
+        | Instruction::V128Load64Lane(..)
+        | Instruction::V128Store8Lane(..)
+        | Instruction::V128Store16Lane(..)
+        | Instruction::V128Store32Lane(..)
+        | Instruction::V128Store64Lane(..)
+        | Instruction::I8x16Shuffle(..)
+        | Instruction::I8x16ExtractLaneS(..)
+        | Instruction::I8x16ExtractLaneU(..)
+        | Instruction::I8x16ReplaceLane(..)
+        | Instruction::I16x8ExtractLaneS(..)
+        | Instruction::I16x8ExtractLaneU(..)
+        | Instruction::I16x8ReplaceLane(..)
+        | Instruction::I32x4ExtractLane(..)
+        | Instruction::I32x4ReplaceLane(..)
+        | Instruction::I64x2ExtractLane(..)
+        | Instruction::I64x2ReplaceLane(..)
+        | Instruction::F32x4ExtractLane(..)
+        | Instruction::F32x4ReplaceLane(..)
+        | Instruction::F64x2ExtractLane(..)
+        | Instruction::F64x2ReplaceLane(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+
+        // atomic instructions
+        Instruction::MemoryAtomicNotify(..)
+        | Instruction::MemoryAtomicWait32(..)
+        | Instruction::MemoryAtomicWait64(..)
+        | Instruction::AtomicFence
+        | Instruction::I32AtomicLoad(..)
+        | Instruction::I64AtomicLoad(..)
+        | Instruction::I32AtomicLoad8U(..)
+        | Instruction::I32AtomicLoad16U(..)
+        | Instruction::I64AtomicLoad8U(..)
+        | Instruction::I64AtomicLoad16U(..)
+        | Instruction::I64AtomicLoad32U(..)
+        | Instruction::I32AtomicStore(..)
+        | Instruction::I64AtomicStore(..)
+        | Instruction::I32AtomicStore8(..)
+        | Instruction::I32AtomicStore16(..)
+        | Instruction::I64AtomicStore8(..)
+        | Instruction::I64AtomicStore16(..)
+        | Instruction::I64AtomicStore32(..)
+        | Instruction::I32AtomicRmwAdd(..)
+        | Instruction::I64AtomicRmwAdd(..)
+        | Instruction::I32AtomicRmw8AddU(..)
+        | Instruction::I32AtomicRmw16AddU(..)
+        | Instruction::I64AtomicRmw8AddU(..)
+        | Instruction::I64AtomicRmw16AddU(..)
+        | Instruction::I64AtomicRmw32AddU(..)
+        | Instruction::I32AtomicRmwSub(..)
+        | Instruction::I64AtomicRmwSub(..)
+        | Instruction::I32AtomicRmw8SubU(..)
+        | Instruction::I32AtomicRmw16SubU(..)
+        | Instruction::I64AtomicRmw8SubU(..)
+        | Instruction::I64AtomicRmw16SubU(..)
+        | Instruction::I64AtomicRmw32SubU(..)
+        | Instruction::I32AtomicRmwAnd(..)
+        | Instruction::I64AtomicRmwAnd(..)
+        | Instruction::I32AtomicRmw8AndU(..)
+        | Instruction::I32AtomicRmw16AndU(..)
+        | Instruction::I64AtomicRmw8AndU(..)
+        | Instruction::I64AtomicRmw16AndU(..)
+        | Instruction::I64AtomicRmw32AndU(..)
+        | Instruction::I32AtomicRmwOr(..)
+        | Instruction::I64AtomicRmwOr(..)
+        | Instruction::I32AtomicRmw8OrU(..)
+        | Instruction::I32AtomicRmw16OrU(..)
+        | Instruction::I64AtomicRmw8OrU(..)
+        | Instruction::I64AtomicRmw16OrU(..)
+        | Instruction::I64AtomicRmw32OrU(..)
+        | Instruction::I32AtomicRmwXor(..)
+        | Instruction::I64AtomicRmwXor(..)
+        | Instruction::I32AtomicRmw8XorU(..)
+        | Instruction::I32AtomicRmw16XorU(..)
+        | Instruction::I64AtomicRmw8XorU(..)
+        | Instruction::I64AtomicRmw16XorU(..)
+        | Instruction::I64AtomicRmw32XorU(..)
+        | Instruction::I32AtomicRmwXchg(..)
+        | Instruction::I64AtomicRmwXchg(..)
+        | Instruction::I32AtomicRmw8XchgU(..)
+        | Instruction::I32AtomicRmw16XchgU(..)
+        | Instruction::I64AtomicRmw8XchgU(..)
+        | Instruction::I64AtomicRmw16XchgU(..)
+        | Instruction::I64AtomicRmw32XchgU(..)
+        | Instruction::I32AtomicRmwCmpxchg(..)
+        | Instruction::I64AtomicRmwCmpxchg(..)
+        | Instruction::I32AtomicRmw8CmpxchgU(..)
+        | Instruction::I32AtomicRmw16CmpxchgU(..)
+        | Instruction::I64AtomicRmw8CmpxchgU(..)
+        | Instruction::I64AtomicRmw16CmpxchgU(..)
+        | Instruction::I64AtomicRmw32CmpxchgU(..) => {
+            unreachable!("{instr:?} is not a plain numeric instruction")
+        }
+    }
+}
+
+/// Validates a constant expression producing a value of type `expected`.
+///
+/// Only immutable globals among the first `globals` ones may be referred by `global.get`, i.e.
+/// imported globals and globals defined before the global being initialized, or every global for
+/// segment offsets and elements. Integer `add`, `sub` and `mul` of the extended constant
+/// expressions proposal are allowed.
+fn validate_const_expr(
+    ctx: &ModuleContext,
+    expr: &Expression,
+    expected: ValueType,
+    globals: usize,
+) -> Result<(), ValidationError> {
+    for instr in expr.instructions() {
+        match instr {
+            Instruction::I32Const(_)
+            | Instruction::I64Const(_)
+            | Instruction::F32Const(_)
+            | Instruction::F64Const(_)
+            | Instruction::V128Const(_)
+            | Instruction::RefNull(_)
+            | Instruction::RefFunc(_)
+            | Instruction::StructNew(_)
+            | Instruction::StructNewDefault(_)
+            | Instruction::ArrayNew(_)
+            | Instruction::ArrayNewDefault(_)
+            | Instruction::ArrayNewFixed(..)
+            | Instruction::RefI31
+            | Instruction::AnyConvertExtern
+            | Instruction::ExternConvertAny
+            | Instruction::I32Add
+            | Instruction::I32Sub
+            | Instruction::I32Mul
+            | Instruction::I64Add
+            | Instruction::I64Sub
+            | Instruction::I64Mul => (),
+            Instruction::GlobalGet(x) => {
+                if *x as usize >= globals {
+                    return Err(ValidationError::UnknownGlobal(*x));
+                }
+                if ctx.global(*x)?.mutable() {
+                    return Err(ValidationError::NonConstantInstruction);
+                }
+            }
+            _ => return Err(ValidationError::NonConstantInstruction),
+        }
+    }
+
+    FuncValidator::new(ctx, Vec::new(), vec![expected])
+        .validate(expr.instructions())
+        .map_err(|(_, e)| e)
+}
+
+fn validate_limits(limits: &Limits, bound: u64) -> Result<(), ValidationError> {
+    let (min, max) = (limits.min(), limits.max());
+    for x in std::iter::once(min).chain(max) {
+        if x > bound {
+            return Err(ValidationError::MemorySize(x, bound));
+        }
+    }
+    match max {
+        Some(max) if min > max => Err(ValidationError::LimitsRange { min, max }),
+        _ => Ok(()),
+    }
+}
+
+impl SynthModule {
+    /// Validates the module against the WebAssembly specification.
+    ///
+    /// Every function body is type-checked against the types of the module. On failure of a
+    /// function body, [`Error::InvalidFunction`] holds the function index and the position of the
+    /// faulty instruction in a pre-order walk of the function body. Its offset in the module binary
+    /// is only known when validating a parsed [`Module`](crate::parse::Module).
+    pub fn validate(&self) -> Result<(), Error> {
+        let ctx = ModuleContext::from_synth(self);
+        let imported_funcs = ctx.funcs.len()
+            - self
+                .function_section
+                .as_ref()
+                .map(|x| x.type_indices.len())
+                .unwrap_or(0);
+
+        for (i, ty) in ctx.types.iter().enumerate() {
+            let i = i as u32;
+            let mut types = Vec::new();
+            ty.visit_type_indices(&mut |x| types.push(x));
+            for x in types {
+                ctx.sub_type(x).map_err(Error::InvalidModule)?;
+            }
+            if ty.supertypes().len() > 1 {
+                return Err(Error::InvalidModule(ValidationError::Supertype {
+                    ty: i,
+                    supertype: ty.supertypes()[1],
+                }));
+            }
+            for &supertype in ty.supertypes() {
+                let valid = supertype < i && {
+                    let sup = &ctx.types[supertype as usize];
+                    !sup.is_final() && ctx.composite_matches(ty.composite(), sup.composite())
+                };
+                if !valid {
+                    return Err(Error::InvalidModule(ValidationError::Supertype {
+                        ty: i,
+                        supertype,
+                    }));
+                }
+            }
+        }
+        for &tyidx in &ctx.funcs {
+            ctx.defined_func_type(tyidx).map_err(Error::InvalidModule)?;
+        }
+        for t in ctx
+            .tables
+            .iter()
+            .map(|x| x.element().into())
+            .chain(ctx.globals.iter().map(GlobalType::ty))
+            .chain(ctx.elems.iter().map(|&x| x.into()))
+        {
+            ctx.value_type(t).map_err(Error::InvalidModule)?;
+        }
+        for table in &ctx.tables {
+            validate_limits(table.limits(), u32::MAX.into()).map_err(Error::InvalidModule)?;
+        }
+        for memory in &ctx.memories {
+            let bound = if memory.is_64() {
+                MAX_MEMORY64_PAGES
+            } else {
+                MAX_MEMORY_PAGES
+            };
+            validate_limits(memory.size(), bound).map_err(Error::InvalidModule)?;
+            if memory.is_shared() && memory.size().max().is_none() {
+                return Err(Error::InvalidModule(ValidationError::SharedMemoryMaximum));
+            }
+        }
+        for &tyidx in &ctx.tags {
+            let ty = ctx.defined_func_type(tyidx).map_err(Error::InvalidModule)?;
+            if !ty.result.0.is_empty() {
+                return Err(Error::InvalidModule(ValidationError::TagResults(tyidx)));
+            }
+        }
+        if let Some(glsec) = &self.global_section {
+            for (i, global) in glsec.globals.iter().enumerate() {
+                let visible = ctx.imported_globals + i;
+                validate_const_expr(&ctx, &global.init, global.ty.ty(), visible).map_err(|e| {
+                    Error::InvalidModule(ValidationError::GlobalInit {
+                        index: (ctx.imported_globals + i) as u32,
+                        source: Box::new(e),
+                    })
+                })?;
+            }
+        }
+
+        if let Some(elsec) = &self.element_section {
+            for (i, elem) in elsec.elements.iter().enumerate() {
+                let ty = ctx.elems[i];
+                let check = || -> Result<(), ValidationError> {
+                    match &elem.init {
+                        SynthElemInit::FuncIndices(x) => {
+                            for f in x {
+                                ctx.func_type(*f)?;
+                            }
+                        }
+                        SynthElemInit::Expressions(x) => {
+                            for expr in x {
+                                validate_const_expr(&ctx, expr, ty.into(), ctx.globals.len())?;
+                            }
+                        }
+                    }
+                    if let SynthElemMode::Active { table, offset } = &elem.mode {
+                        let actual = ctx.table(*table)?.element();
+                        if !ctx.ref_matches(ty, actual) {
+                            return Err(ValidationError::TableElementType {
+                                expected: ty,
+                                actual,
+                            });
+                        }
+                        validate_const_expr(&ctx, offset, ValueType::I32, ctx.globals.len())?;
+                    }
+                    Ok(())
+                };
+                check().map_err(|e| {
+                    Error::InvalidModule(ValidationError::ElementSegment {
+                        index: i as u32,
+                        source: Box::new(e),
+                    })
+                })?;
+            }
+        }
+
+        let all_data = self
+            .data_section
+            .as_ref()
+            .map(|x| x.all_data.as_slice())
+            .unwrap_or_default();
+        if let Some(data_count) = ctx.data_count {
+            if data_count as usize != all_data.len() {
+                return Err(Error::InvalidModule(ValidationError::DataCountMismatch(
+                    data_count,
+                    all_data.len(),
+                )));
+            }
+        }
+        for (i, data) in all_data.iter().enumerate() {
+            if let SynthData::Active {
+                memory_index,
+                offset,
+                ..
+            } = data
+            {
+                ctx.memory(*memory_index)
+                    .and_then(|mt| {
+                        validate_const_expr(&ctx, offset, mt.index_type(), ctx.globals.len())
+                    })
+                    .map_err(|e| {
+                        Error::InvalidModule(ValidationError::DataSegment {
+                            index: i as u32,
+                            source: Box::new(e),
+                        })
+                    })?;
+            }
+        }
+
+        if let Some(stsec) = &self.start_section {
+            let ty = ctx.func_type(stsec.start).map_err(Error::InvalidModule)?;
+            if !ty.param.0.is_empty() || !ty.result.0.is_empty() {
+                return Err(Error::InvalidModule(ValidationError::StartFunctionType(
+                    stsec.start,
+                )));
+            }
+        }
+
+        if let Some(exsec) = &self.export_section {
+            let mut names = HashSet::new();
+            for export in &exsec.exports {
+                if !names.insert(export.name.as_str()) {
+                    return Err(Error::InvalidModule(ValidationError::DuplicateExportName(
+                        export.name.clone(),
+                    )));
+                }
+                match export.desc {
+                    SynthExportDescription::Func(x) => ctx.func_type(x).map(|_| ()),
+                    SynthExportDescription::Table(x) => ctx.table(x).map(|_| ()),
+                    SynthExportDescription::Mem(x) => ctx.memory(x).map(|_| ()),
+                    SynthExportDescription::Global(x) => ctx.global(x).map(|_| ()),
+                    SynthExportDescription::Tag(x) => ctx.tag_type(x).map(|_| ()),
+                }
+                .map_err(Error::InvalidModule)?;
+            }
+        }
+
+        let codes = self
+            .code_section
+            .as_ref()
+            .map(|x| x.codes.as_slice())
+            .unwrap_or_default();
+        let defined_funcs = ctx.funcs.len() - imported_funcs;
+        if defined_funcs != codes.len() {
+            return Err(Error::InvalidModule(ValidationError::FunctionCodeMismatch(
+                defined_funcs,
+                codes.len(),
+            )));
+        }
+
+        for (i, code) in codes.iter().enumerate() {
+            let func = (imported_funcs + i) as u32;
+            let ty = ctx.func_type(func).map_err(Error::InvalidModule)?;
+            let mut locals = ty.param.0.clone();
+            locals.extend_from_slice(&code.locals);
+            for &t in &code.locals {
+                ctx.value_type(t).map_err(|source| Error::InvalidFunction {
+                    func,
+                    instr_index: None,
+                    offset: None,
+                    source,
+                })?;
+            }
+            FuncValidator::with_params(&ctx, locals, ty.param.0.len(), ty.result.0.clone())
+                .validate(code.func_expr.instructions())
+                .map_err(|(idx, source)| Error::InvalidFunction {
+                    func,
+                    instr_index: Some(idx),
+                    offset: None,
+                    source,
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse::Module, validate::ValidationError, Error};
+
+    fn validate_wat(wat_s: &str) -> Result<(), Error> {
+        let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+        Module::from_binary(&wasm)
+            .expect("cannot parse wasm")
+            .validate()
+    }
+
+    #[test]
+    fn test_valid() {
+        validate_wat(
+            r#"(module
+                (func $f (param i32) (result i32)
+                    local.get 0
+                    if (result i32)
+                        i32.const 1
+                    else
+                        block (result i32)
+                            i32.const 2
+                            br 0
+                        end
+                    end
+                )
+                (func (result i32)
+                    i32.const 0
+                    call $f
+                    unreachable
+                    i32.add
+                )
+            )"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let err = validate_wat(
+            r#"(module
+                (func (result i32)
+                    i32.const 1
+                    block
+                        nop
+                    end
+                    i64.const 2
+                    i32.add
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(4),
+                offset: Some(0x20),
+                source: ValidationError::TypeMismatch { .. },
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unknown_label() {
+        let err = validate_wat(r#"(module (func block br 2 end))"#).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(_),
+                source: ValidationError::UnknownLabel(2),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unknown_function() {
+        let err = validate_wat(
+            r#"(module
+                (import "env" "f" (func))
+                (func call 2)
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 1,
+                instr_index: Some(0),
+                offset: Some(_),
+                source: ValidationError::UnknownFunction(2),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unconsumed_operands() {
+        let err = validate_wat(r#"(module (func i32.const 1))"#).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(25),
+                source: ValidationError::UnconsumedOperands(1),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_memory64() {
+        validate_wat(
+            r#"(module
+                (memory i64 1)
+                (data (i64.const 0) "a")
+                (func (result i64)
+                    i64.const 0
+                    i64.load offset=4294967296
+                    memory.size
+                    i64.add
+                )
+            )"#,
+        )
+        .unwrap();
+
+        let err =
+            validate_wat(r#"(module (memory i64 1) (func (result i32) i32.const 0 i32.load))"#)
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(_),
+                source: ValidationError::TypeMismatch { .. },
+            }
+        ));
+
+        let err = validate_wat(
+            r#"(module (memory 1) (func (result i32) i32.const 0 i32.load offset=4294967296))"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(_),
+                source: ValidationError::MemArgOffset(4294967296),
+            }
+        ));
+
+        let err = validate_wat(r#"(module (memory i64 281474976710657))"#).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidModule(ValidationError::MemorySize(281474976710657, _))
+        ));
+    }
+
+    #[test]
+    fn test_multi_memory() {
+        let err = validate_wat(
+            r#"(module
+                (memory 1)
+                (memory i64 1)
+                (func
+                    i32.const 0
+                    i64.const 0
+                    i32.const 1
+                    memory.copy 0 1
+                    i32.const 0
+                    i32.const 0
+                    i32.store 2
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(6),
+                offset: Some(_),
+                source: ValidationError::UnknownMemory(2),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_threads() {
+        validate_wat(
+            r#"(module
+                (memory 1 1 shared)
+                (func (param i32) (result i64)
+                    local.get 0
+                    i32.const 1
+                    i64.const -1
+                    memory.atomic.wait32
+                    drop
+                    atomic.fence
+                    local.get 0
+                    i64.const 0
+                    i64.const 1
+                    i64.atomic.rmw32.cmpxchg_u
+                )
+            )"#,
+        )
+        .unwrap();
+
+        let err = validate_wat(
+            r#"(module
+                (memory 1)
+                (func
+                    i32.const 0
+                    i32.const 1
+                    i32.atomic.store16 align=1
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(2),
+                offset: Some(_),
+                source: ValidationError::AtomicAlignment {
+                    align: 0,
+                    natural: 1
+                },
+            }
+        ));
+
+        let err = validate_wat("(module (memory 1 shared))").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidModule(ValidationError::SharedMemoryMaximum)
+        ));
+    }
+
+    #[test]
+    fn test_exceptions() {
+        validate_wat(
+            r#"(module
+                (tag $e (param i32))
+                (func (result i32)
+                    try (result i32)
+                        i32.const 1
+                        throw $e
+                    catch $e
+                    catch_all
+                        rethrow 0
+                    end
+                )
+            )"#,
+        )
+        .unwrap();
+
+        let err = validate_wat(
+            r#"(module
+                (func
+                    block
+                        rethrow 0
+                    end
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(_),
+                source: ValidationError::RethrowLabel(0),
+            }
+        ));
+
+        let err = validate_wat(
+            r#"(module
+                (tag $e (param i64))
+                (func
+                    block $l (result i32)
+                        try_table (catch $e $l)
+                        end
+                        i32.const 0
+                    end
+                    drop
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 0,
+                instr_index: Some(1),
+                offset: Some(_),
+                source: ValidationError::CatchLabelType(0),
+            }
+        ));
+
+        let err = validate_wat("(module (tag (result i32)))").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidModule(ValidationError::TagResults(0))
+        ));
+    }
+
+    #[test]
+    fn test_tail_call() {
+        let err = validate_wat(
+            r#"(module
+                (func $f (result i64)
+                    i64.const 0
+                )
+                (func (result i32)
+                    return_call $f
+                )
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidFunction {
+                func: 1,
+                instr_index: Some(0),
+                offset: Some(_),
+                source: ValidationError::TailCallResults,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_gc() {
+        let types = r#"
+            (type $point (sub (struct (field $x i32) (field $y (mut i8)))))
+            (type $point3 (sub $point (struct (field i32) (field (mut i8)) (field f64))))
+        "#;
+        validate_wat(&format!(
+            r#"(module {types}
+                (func $norm (param (ref null $point)) (result i32)
+                    (struct.get $point $x (local.get 0)))
+                (func (result i32) (local $p (ref $point3))
+                    (local.set $p (struct.new_default $point3))
+                    (struct.set $point $y (local.get $p) (i32.const 3))
+                    (call $norm (local.get $p))
+                )
+            )"#
+        ))
+        .unwrap();
+
+        let cases = [
+            (
+                "(func (param (ref $point)) (result (ref $point3)) local.get 0)",
+                "TypeMismatch",
+            ),
+            (
+                "(func (param (ref $point)) (struct.set $point $x (local.get 0) (i32.const 1)))",
+                "ImmutableField",
+            ),
+            (
+                "(func (param (ref $point)) (result i32) (struct.get $point $y (local.get 0)))",
+                "PackedField",
+            ),
+            (
+                "(func (local (ref $point)) (drop (local.get 0)))",
+                "UninitializedLocal",
+            ),
+            (
+                "(func (param (ref $point)) (drop (struct.get $point 2 (local.get 0))))",
+                "UnknownField",
+            ),
+            (
+                "(func (result (ref $point)) (ref.null none))",
+                "TypeMismatch",
+            ),
+        ];
+        for (func, expected) in cases {
+            let err = validate_wat(&format!("(module {types} {func})")).unwrap_err();
+            let Error::InvalidFunction { source, .. } = err else {
+                panic!("expected an invalid function, got {err:?}");
+            };
+            assert!(format!("{source:?}").starts_with(expected), "{source:?}");
+        }
+
+        let err = validate_wat(
+            r#"(module
+                (type $a (struct (field i32)))
+                (type $b (sub $a (struct (field i32))))
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidModule(ValidationError::Supertype {
+                ty: 1,
+                supertype: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_type_equivalence() {
+        validate_wat(
+            r#"(module
+                (type $a (struct (field i32)))
+                (type $b (struct (field i32)))
+                (func (param (ref $a)) (result (ref $b)) local.get 0)
+            )"#,
+        )
+        .unwrap();
+        validate_wat(
+            r#"(module
+                (type $a (func (param i32) (result i32)))
+                (type $b (func (param i32) (result i32)))
+                (func $f (type $a) local.get 0)
+                (elem declare func $f)
+                (func (result i32)
+                    (call_ref $b (i32.const 1) (ref.func $f)))
+            )"#,
+        )
+        .unwrap();
+        validate_wat(
+            r#"(module
+                (rec (type $l1 (struct (field (ref null $l1)))) (type $x1 (array i8)))
+                (rec (type $l2 (struct (field (ref null $l2)))) (type $x2 (array i8)))
+                (type $s (sub (struct (field (ref null $l1)))))
+                (type $t (sub $s (struct (field (ref null $l2)) (field i32))))
+                (func (param (ref $l1)) (result (ref $l2)) local.get 0)
+                (func (param (ref $t)) (result (ref $s)) local.get 0)
+            )"#,
+        )
+        .unwrap();
+
+        let cases = [
+            // same structure in a different rec group
+            r#"(rec (type $a (struct (field i32))) (type (array i8)))
+               (type $b (struct (field i32)))
+               (func (param (ref $a)) (result (ref $b)) local.get 0)"#,
+            // different finality
+            r#"(type $a (sub (struct (field i32))))
+               (type $b (struct (field i32)))
+               (func (param (ref $a)) (result (ref $b)) local.get 0)"#,
+            // recursive references to different groups
+            r#"(type $a (struct (field (ref null $a))))
+               (type $b (struct (field (ref null $a))))
+               (func (param (ref $b)) (result (ref $a)) local.get 0)"#,
+        ];
+        for case in cases {
+            let err = validate_wat(&format!("(module {case})")).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    Error::InvalidFunction {
+                        source: ValidationError::TypeMismatch { .. },
+                        ..
+                    }
+                ),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_extended_const() {
+        validate_wat(
+            r#"(module
+                (import "env" "base" (global $base i32))
+                (memory 1)
+                (global i64 (i64.sub (i64.const 8) (i64.mul (i64.const 2) (i64.const 3))))
+                (data (offset (i32.add (global.get $base) (i32.const 16))) "a")
+            )"#,
+        )
+        .unwrap();
+
+        let err = validate_wat(
+            r#"(module
+                (global i32 (i32.div_u (i32.const 8) (i32.const 2)))
+            )"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidModule(ValidationError::GlobalInit { index: 0, source })
+                if matches!(*source, ValidationError::NonConstantInstruction)
+        ));
+
+        validate_wat(
+            r#"(module
+                (memory 1)
+                (global $size i32 (i32.const 16))
+                (global i32 (i32.mul (global.get $size) (i32.const 2)))
+                (data (offset (global.get $size)) "a")
+            )"#,
+        )
+        .unwrap();
+        let cases = [
+            (
+                "(global $g (mut i32) (i32.const 0)) (global i32 (global.get $g))",
+                "NonConstantInstruction",
+            ),
+            (
+                "(global i32 (global.get $g)) (global $g i32 (i32.const 0))",
+                "UnknownGlobal(1)",
+            ),
+        ];
+        for (globals, expected) in cases {
+            let err = validate_wat(&format!("(module {globals})")).unwrap_err();
+            let Error::InvalidModule(ValidationError::GlobalInit { source, .. }) = err else {
+                panic!("expected an invalid global initializer, got {err:?}");
+            };
+            assert_eq!(format!("{source:?}"), expected);
+        }
+    }
+}
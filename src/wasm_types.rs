@@ -9,38 +9,229 @@ use std::{
 
 use crate::{Bytes, Error, WriteExt};
 
+/// A heap type of the typed function references and GC proposals, which a reference points to.
+///
+/// <https://webassembly.github.io/gc/core/binary/types.html#heap-types>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeapType {
+    Func,
+    Extern,
+    Exn,
+    Any,
+    Eq,
+    I31,
+    Struct,
+    Array,
+    /// The bottom type of `any`, `eq`, `i31`, `struct`, `array` and defined struct and array
+    /// types.
+    None,
+    /// The bottom type of `func` and defined function types.
+    NoFunc,
+    /// The bottom type of `extern`.
+    NoExtern,
+    /// The bottom type of `exn`.
+    NoExn,
+    /// A type defined in the type section, by its index.
+    Concrete(u32),
+}
+
+impl HeapType {
+    /// Returns the abstract heap type encoded as `byte`, if any.
+    fn from_abstract_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x70 => Some(Self::Func),
+            0x6F => Some(Self::Extern),
+            0x69 => Some(Self::Exn),
+            0x6E => Some(Self::Any),
+            0x6D => Some(Self::Eq),
+            0x6C => Some(Self::I31),
+            0x6B => Some(Self::Struct),
+            0x6A => Some(Self::Array),
+            0x71 => Some(Self::None),
+            0x73 => Some(Self::NoFunc),
+            0x72 => Some(Self::NoExtern),
+            0x74 => Some(Self::NoExn),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let Some(&first) = bytes.first() else {
+            return Err(Error::UnexpectedEof(1, 0));
+        };
+        if let Some(x) = Self::from_abstract_byte(first) {
+            return Ok((x, &bytes[1..]));
+        }
+
+        // NOTE: The specification defines the type index as s33
+        let (idx, rest) = bytes.advance_s64()?;
+        match u32::try_from(idx) {
+            Ok(idx) => Ok((Self::Concrete(idx), rest)),
+            Err(_) => Err(Error::ReferenceTypeId(first)),
+        }
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        let byte = match *self {
+            HeapType::Func => 0x70,
+            HeapType::Extern => 0x6F,
+            HeapType::Exn => 0x69,
+            HeapType::Any => 0x6E,
+            HeapType::Eq => 0x6D,
+            HeapType::I31 => 0x6C,
+            HeapType::Struct => 0x6B,
+            HeapType::Array => 0x6A,
+            HeapType::None => 0x71,
+            HeapType::NoFunc => 0x73,
+            HeapType::NoExtern => 0x72,
+            HeapType::NoExn => 0x74,
+            HeapType::Concrete(x) => return wr.write_s64(x.into()),
+        };
+        wr.write_all(&[byte])
+    }
+
+    /// Returns the index of the defined type, if the heap type is concrete.
+    pub fn type_index(&self) -> Option<u32> {
+        match *self {
+            HeapType::Concrete(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn type_index_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            HeapType::Concrete(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl Display for HeapType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeapType::Func => write!(f, "func"),
+            HeapType::Extern => write!(f, "extern"),
+            HeapType::Exn => write!(f, "exn"),
+            HeapType::Any => write!(f, "any"),
+            HeapType::Eq => write!(f, "eq"),
+            HeapType::I31 => write!(f, "i31"),
+            HeapType::Struct => write!(f, "struct"),
+            HeapType::Array => write!(f, "array"),
+            HeapType::None => write!(f, "none"),
+            HeapType::NoFunc => write!(f, "nofunc"),
+            HeapType::NoExtern => write!(f, "noextern"),
+            HeapType::NoExn => write!(f, "noexn"),
+            HeapType::Concrete(x) => write!(f, "{x}"),
+        }
+    }
+}
+
 /// A WebAssembly reference type.
 ///
+/// References other than the nullable `func`, `extern` and `exn` references are of the typed
+/// function references and GC proposals. Use [`ReferenceType::new`] to construct them, which maps
+/// the nullable references above to their dedicated variants.
+///
 /// <https://webassembly.github.io/spec/core/binary/types.html#reference-types>
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ReferenceType {
     FuncRef,
     ExternRef,
+    /// A reference to a caught exception, of the exception handling proposal.
+    ExnRef,
+    Ref {
+        nullable: bool,
+        heap: HeapType,
+    },
 }
 
 impl ReferenceType {
-    pub(crate) fn from_byte(byte: u8) -> Result<Self, Error> {
-        match byte {
-            0x70 => Ok(Self::FuncRef),
-            0x6F => Ok(Self::ExternRef),
-            x => Err(Error::ReferenceTypeId(x)),
+    pub fn new(nullable: bool, heap: HeapType) -> Self {
+        match (nullable, heap) {
+            (true, HeapType::Func) => Self::FuncRef,
+            (true, HeapType::Extern) => Self::ExternRef,
+            (true, HeapType::Exn) => Self::ExnRef,
+            (nullable, heap) => Self::Ref { nullable, heap },
+        }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (&[first], rest) = bytes.advance()?;
+        match first {
+            0x63 | 0x64 => {
+                let (heap, rest) = HeapType::from_bytes(rest)?;
+                Ok((Self::new(first == 0x63, heap), rest))
+            }
+            x => match HeapType::from_abstract_byte(x) {
+                Some(heap) => Ok((Self::new(true, heap), rest)),
+                None => Err(Error::ReferenceTypeId(x)),
+            },
         }
     }
 
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        match self.heap_type() {
+            // nullable abstract references have shorthands of a single byte
+            heap if self.nullable() && heap.type_index().is_none() => heap.write_into(wr),
+            heap => {
+                wr.write_all(&[if self.nullable() { 0x63 } else { 0x64 }])?;
+                heap.write_into(wr)
+            }
+        }
+    }
+
+    pub fn nullable(&self) -> bool {
+        match *self {
+            ReferenceType::FuncRef | ReferenceType::ExternRef | ReferenceType::ExnRef => true,
+            ReferenceType::Ref { nullable, .. } => nullable,
+        }
+    }
+
+    pub fn heap_type(&self) -> HeapType {
+        match *self {
+            ReferenceType::FuncRef => HeapType::Func,
+            ReferenceType::ExternRef => HeapType::Extern,
+            ReferenceType::ExnRef => HeapType::Exn,
+            ReferenceType::Ref { heap, .. } => heap,
+        }
+    }
+
+    /// Returns the reference type with the given nullability.
+    pub fn with_nullable(&self, nullable: bool) -> Self {
+        Self::new(nullable, self.heap_type())
+    }
+
+    pub(crate) fn type_index(&self) -> Option<u32> {
+        self.heap_type().type_index()
+    }
+
+    pub(crate) fn type_index_mut(&mut self) -> Option<&mut u32> {
         match self {
-            ReferenceType::FuncRef => wr.write_all(&[0x70]),
-            ReferenceType::ExternRef => wr.write_all(&[0x6F]),
+            ReferenceType::Ref { heap, .. } => heap.type_index_mut(),
+            _ => None,
         }
     }
 }
 
 impl Display for ReferenceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ReferenceType::FuncRef => write!(f, "funcref"),
-            ReferenceType::ExternRef => write!(f, "externref"),
-        }
+        let shorthand = match (self.nullable(), self.heap_type()) {
+            (true, HeapType::Func) => "funcref",
+            (true, HeapType::Extern) => "externref",
+            (true, HeapType::Exn) => "exnref",
+            (true, HeapType::Any) => "anyref",
+            (true, HeapType::Eq) => "eqref",
+            (true, HeapType::I31) => "i31ref",
+            (true, HeapType::Struct) => "structref",
+            (true, HeapType::Array) => "arrayref",
+            (true, HeapType::None) => "nullref",
+            (true, HeapType::NoFunc) => "nullfuncref",
+            (true, HeapType::NoExtern) => "nullexternref",
+            (true, HeapType::NoExn) => "nullexnref",
+            (true, heap) => return write!(f, "(ref null {heap})"),
+            (false, heap) => return write!(f, "(ref {heap})"),
+        };
+        write!(f, "{shorthand}")
     }
 }
 
@@ -49,14 +240,18 @@ impl From<ReferenceType> for ValueType {
         match value {
             ReferenceType::FuncRef => ValueType::FuncRef,
             ReferenceType::ExternRef => ValueType::ExternRef,
+            ReferenceType::ExnRef => ValueType::ExnRef,
+            ReferenceType::Ref { nullable, heap } => ValueType::Ref { nullable, heap },
         }
     }
 }
 
 /// A WebAssembly value type.
 ///
+/// Reference types mirror the variants of [`ReferenceType`].
+///
 /// <https://webassembly.github.io/spec/core/binary/types.html#value-types>
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ValueType {
     // NOTE: this is not a Rust i32, but WebAssembly 32-bit wide uninterpreted 'integer'.
     I32,
@@ -67,20 +262,31 @@ pub enum ValueType {
     V128,
     FuncRef,
     ExternRef,
+    ExnRef,
+    Ref { nullable: bool, heap: HeapType },
 }
 
 impl ValueType {
-    pub(crate) fn from_byte(byte: u8) -> Result<Self, Error> {
-        match byte {
-            0x7F => Ok(Self::I32),
-            0x7E => Ok(Self::I64),
-            0x7D => Ok(Self::F32),
-            0x7C => Ok(Self::F64),
-            0x7B => Ok(Self::V128),
-            0x70 => Ok(Self::FuncRef),
-            0x6F => Ok(Self::ExternRef),
-            x => Err(Error::ValueTypeId(x)),
-        }
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let Some(&first) = bytes.first() else {
+            return Err(Error::UnexpectedEof(1, 0));
+        };
+        let numeric = match first {
+            0x7F => Self::I32,
+            0x7E => Self::I64,
+            0x7D => Self::F32,
+            0x7C => Self::F64,
+            0x7B => Self::V128,
+            0x63 | 0x64 => {
+                let (rt, rest) = ReferenceType::from_bytes(bytes)?;
+                return Ok((rt.into(), rest));
+            }
+            x => match HeapType::from_abstract_byte(x) {
+                Some(heap) => return Ok((ReferenceType::new(true, heap).into(), &bytes[1..])),
+                None => return Err(Error::ValueTypeId(x)),
+            },
+        };
+        Ok((numeric, &bytes[1..]))
     }
 
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
@@ -90,8 +296,38 @@ impl ValueType {
             ValueType::F32 => wr.write_all(&[0x7D]),
             ValueType::F64 => wr.write_all(&[0x7C]),
             ValueType::V128 => wr.write_all(&[0x7B]),
-            ValueType::FuncRef => wr.write_all(&[0x70]),
-            ValueType::ExternRef => wr.write_all(&[0x6F]),
+            _ => self
+                .reference_type()
+                .expect("non-numeric value type")
+                .write_into(wr),
+        }
+    }
+
+    /// Returns the reference type, if the value type is a reference.
+    pub fn reference_type(&self) -> Option<ReferenceType> {
+        match *self {
+            ValueType::FuncRef => Some(ReferenceType::FuncRef),
+            ValueType::ExternRef => Some(ReferenceType::ExternRef),
+            ValueType::ExnRef => Some(ReferenceType::ExnRef),
+            ValueType::Ref { nullable, heap } => Some(ReferenceType::Ref { nullable, heap }),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a value of the type has a default value, i.e. is not a non-nullable
+    /// reference.
+    pub fn is_defaultable(&self) -> bool {
+        self.reference_type().is_none_or(|rt| rt.nullable())
+    }
+
+    pub(crate) fn type_index(&self) -> Option<u32> {
+        self.reference_type()?.type_index()
+    }
+
+    pub(crate) fn type_index_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            ValueType::Ref { heap, .. } => heap.type_index_mut(),
+            _ => None,
         }
     }
 }
@@ -104,8 +340,11 @@ impl Display for ValueType {
             ValueType::F32 => write!(f, "f32"),
             ValueType::F64 => write!(f, "f64"),
             ValueType::V128 => write!(f, "v128"),
-            ValueType::FuncRef => write!(f, "funcref"),
-            ValueType::ExternRef => write!(f, "externref"),
+            _ => write!(
+                f,
+                "{}",
+                self.reference_type().expect("non-numeric value type")
+            ),
         }
     }
 }
@@ -113,16 +352,13 @@ impl Display for ValueType {
 /// A WebAssembly result type.
 ///
 /// <https://webassembly.github.io/spec/core/binary/types.html#result-types>
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ResultType(pub Vec<ValueType>);
 
 impl ResultType {
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
         let mut v = Vec::new();
-        let mut it = bytes.advance_vector(|bytes| {
-            let (&[b], bytes) = bytes.advance()?;
-            Ok((ValueType::from_byte(b)?, bytes))
-        })?;
+        let mut it = bytes.advance_vector(ValueType::from_bytes)?;
         for t in &mut it {
             v.push(t?)
         }
@@ -151,13 +387,17 @@ impl Display for ResultType {
 /// A WebAssembly function type.
 ///
 /// <https://webassembly.github.io/spec/core/binary/types.html#function-types>
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FuncType {
     pub(crate) param: ResultType,
     pub(crate) result: ResultType,
 }
 
 impl FuncType {
+    pub fn new(param: ResultType, result: ResultType) -> Self {
+        Self { param, result }
+    }
+
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
         let (&[id], bytes) = bytes.advance()?;
         if id != 0x60 {
@@ -197,63 +437,517 @@ impl Display for FuncType {
     }
 }
 
+/// Storage type of a struct field or an array element, of the GC proposal.
+///
+/// <https://webassembly.github.io/gc/core/binary/types.html#storage-types>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StorageType {
+    /// A packed 8-bit integer, which is read as an `i32`.
+    I8,
+    /// A packed 16-bit integer, which is read as an `i32`.
+    I16,
+    Val(ValueType),
+}
+
+impl StorageType {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        match bytes.first() {
+            Some(0x78) => Ok((Self::I8, &bytes[1..])),
+            Some(0x77) => Ok((Self::I16, &bytes[1..])),
+            _ => ValueType::from_bytes(bytes).map(|(x, bytes)| (Self::Val(x), bytes)),
+        }
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        match self {
+            StorageType::I8 => wr.write_all(&[0x78]),
+            StorageType::I16 => wr.write_all(&[0x77]),
+            StorageType::Val(x) => x.write_into(wr),
+        }
+    }
+
+    pub fn is_packed(&self) -> bool {
+        !matches!(self, StorageType::Val(_))
+    }
+
+    /// Returns the type of values on the operand stack, which is `i32` for packed types.
+    pub fn unpacked(&self) -> ValueType {
+        match self {
+            StorageType::I8 | StorageType::I16 => ValueType::I32,
+            StorageType::Val(x) => *x,
+        }
+    }
+}
+
+impl Display for StorageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageType::I8 => write!(f, "i8"),
+            StorageType::I16 => write!(f, "i16"),
+            StorageType::Val(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+/// Type of a struct field or array elements, of the GC proposal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldType {
+    storage: StorageType,
+    mutable: bool,
+}
+
+impl FieldType {
+    pub fn new(storage: StorageType, mutable: bool) -> Self {
+        Self { storage, mutable }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (storage, bytes) = StorageType::from_bytes(bytes)?;
+        let (&[mutable], bytes) = bytes.advance()?;
+        let mutable = match mutable {
+            0x00 => false,
+            0x01 => true,
+            x => return Err(Error::FieldTypeMutability(x)),
+        };
+
+        Ok((Self { storage, mutable }, bytes))
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        self.storage.write_into(wr)?;
+        wr.write_all(&[u8::from(self.mutable)])
+    }
+
+    pub fn storage(&self) -> StorageType {
+        self.storage
+    }
+
+    pub fn mutable(&self) -> bool {
+        self.mutable
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.mutable {
+            write!(f, "(mut {})", self.storage)
+        } else {
+            write!(f, "{}", self.storage)
+        }
+    }
+}
+
+/// A type defined in the type section.
+///
+/// <https://webassembly.github.io/gc/core/binary/types.html#composite-types>
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompositeType {
+    Func(FuncType),
+    /// A struct type of the GC proposal, by its fields.
+    Struct(Vec<FieldType>),
+    /// An array type of the GC proposal, by its element type.
+    Array(FieldType),
+}
+
+impl CompositeType {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        match bytes.first() {
+            Some(0x60) => FuncType::from_bytes(bytes).map(|(x, bytes)| (Self::Func(x), bytes)),
+            Some(0x5F) => {
+                let mut it = bytes[1..].advance_vector(FieldType::from_bytes)?;
+                let fields = (&mut it).collect::<Result<Vec<_>, Error>>()?;
+                Ok((Self::Struct(fields), it.finalize()))
+            }
+            Some(0x5E) => {
+                FieldType::from_bytes(&bytes[1..]).map(|(x, bytes)| (Self::Array(x), bytes))
+            }
+            Some(&x) => Err(Error::CompositeTypeId(x)),
+            None => Err(Error::UnexpectedEof(1, 0)),
+        }
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        match self {
+            CompositeType::Func(x) => x.write_into(wr),
+            CompositeType::Struct(fields) => {
+                wr.write_all(&[0x5F])?;
+                wr.write_vector(fields, FieldType::write_into)
+            }
+            CompositeType::Array(x) => {
+                wr.write_all(&[0x5E])?;
+                x.write_into(wr)
+            }
+        }
+    }
+}
+
+impl Display for CompositeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompositeType::Func(x) => write!(f, "{x}"),
+            CompositeType::Struct(fields) => {
+                let inner = fields
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "struct {{{inner}}}")
+            }
+            CompositeType::Array(x) => write!(f, "array [{x}]"),
+        }
+    }
+}
+
+/// A type definition of the type section with its supertypes, as of the GC proposal.
+///
+/// Types without the GC proposal are final and have no supertypes.
+///
+/// <https://webassembly.github.io/gc/core/binary/types.html#recursive-types>
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SubType {
+    pub(crate) is_final: bool,
+    pub(crate) supertypes: Vec<u32>,
+    pub(crate) composite: CompositeType,
+}
+
+impl SubType {
+    pub fn new(is_final: bool, supertypes: Vec<u32>, composite: CompositeType) -> Self {
+        Self {
+            is_final,
+            supertypes,
+            composite,
+        }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let is_final = match bytes.first() {
+            Some(0x50) => false,
+            Some(0x4F) => true,
+            _ => {
+                let (composite, bytes) = CompositeType::from_bytes(bytes)?;
+                return Ok((composite.into(), bytes));
+            }
+        };
+        let mut it = bytes[1..].advance_vector(|bytes| bytes.advance_u32())?;
+        let supertypes = (&mut it).collect::<Result<Vec<_>, Error>>()?;
+        let (composite, bytes) = CompositeType::from_bytes(it.finalize())?;
+        Ok((
+            Self {
+                is_final,
+                supertypes,
+                composite,
+            },
+            bytes,
+        ))
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        if !(self.is_final && self.supertypes.is_empty()) {
+            wr.write_all(&[if self.is_final { 0x4F } else { 0x50 }])?;
+            wr.write_vector(&self.supertypes, |x, wr| wr.write_u32(*x))?;
+        }
+        self.composite.write_into(wr)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    pub fn supertypes(&self) -> &[u32] {
+        &self.supertypes
+    }
+
+    pub fn supertypes_mut(&mut self) -> &mut Vec<u32> {
+        &mut self.supertypes
+    }
+
+    pub fn composite(&self) -> &CompositeType {
+        &self.composite
+    }
+
+    pub fn composite_mut(&mut self) -> &mut CompositeType {
+        &mut self.composite
+    }
+
+    /// Returns the function type, if the type is a function type.
+    pub fn func_type(&self) -> Option<&FuncType> {
+        match &self.composite {
+            CompositeType::Func(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns the value types the type consists of, i.e. function parameters and results, or
+    /// unpacked fields and elements.
+    fn value_types(&self) -> Vec<ValueType> {
+        match &self.composite {
+            CompositeType::Func(x) => x.param.0.iter().chain(&x.result.0).copied().collect(),
+            CompositeType::Struct(fields) => fields.iter().map(|x| x.storage.unpacked()).collect(),
+            CompositeType::Array(field) => vec![field.storage.unpacked()],
+        }
+    }
+
+    /// Calls `func` with every type index the type refers to, including its supertypes.
+    pub(crate) fn visit_type_indices(&self, func: &mut impl FnMut(u32)) {
+        self.supertypes.iter().copied().for_each(&mut *func);
+        self.value_types()
+            .iter()
+            .filter_map(ValueType::type_index)
+            .for_each(func);
+    }
+
+    pub(crate) fn visit_type_indices_mut(&mut self, func: &mut impl FnMut(&mut u32)) {
+        self.supertypes.iter_mut().for_each(&mut *func);
+        let value_types: Vec<&mut ValueType> = match &mut self.composite {
+            CompositeType::Func(x) => x.param.0.iter_mut().chain(&mut x.result.0).collect(),
+            CompositeType::Struct(fields) => fields
+                .iter_mut()
+                .filter_map(|x| match &mut x.storage {
+                    StorageType::Val(vt) => Some(vt),
+                    _ => None,
+                })
+                .collect(),
+            CompositeType::Array(field) => match &mut field.storage {
+                StorageType::Val(vt) => vec![vt],
+                _ => Vec::new(),
+            },
+        };
+        value_types
+            .into_iter()
+            .filter_map(ValueType::type_index_mut)
+            .for_each(func);
+    }
+}
+
+impl Display for SubType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_final {
+            write!(f, "sub ")?;
+        }
+        if !self.supertypes.is_empty() {
+            write!(f, "{:?} ", self.supertypes)?;
+        }
+        write!(f, "{}", self.composite)
+    }
+}
+
+impl From<FuncType> for SubType {
+    fn from(value: FuncType) -> Self {
+        CompositeType::Func(value).into()
+    }
+}
+
+impl From<CompositeType> for SubType {
+    fn from(value: CompositeType) -> Self {
+        Self {
+            is_final: true,
+            supertypes: Vec::new(),
+            composite: value,
+        }
+    }
+}
+
+/// A recursion group of the GC proposal, whose types may refer to each other.
+///
+/// A type definition outside of any `rec` group is a group of its own, with `explicit` unset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecGroup {
+    pub(crate) types: Vec<SubType>,
+    pub(crate) explicit: bool,
+}
+
+impl RecGroup {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        if bytes.first() != Some(&0x4E) {
+            let (ty, bytes) = SubType::from_bytes(bytes)?;
+            return Ok((
+                Self {
+                    types: vec![ty],
+                    explicit: false,
+                },
+                bytes,
+            ));
+        }
+        let mut it = bytes[1..].advance_vector(SubType::from_bytes)?;
+        let types = (&mut it).collect::<Result<Vec<_>, Error>>()?;
+        Ok((
+            Self {
+                types,
+                explicit: true,
+            },
+            it.finalize(),
+        ))
+    }
+
+    pub fn types(&self) -> &[SubType] {
+        &self.types
+    }
+
+    /// Returns whether the group is written with `rec`, rather than a lone type definition.
+    pub fn explicit(&self) -> bool {
+        self.explicit
+    }
+}
+
+/// Limits of a table or a memory.
+///
+/// Limits of tables and 32-bit memories fit in `u32`, while those of 64-bit memories of the
+/// memory64 proposal may not.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Limits {
-    Unbounded { min: u32 },
-    Bounded { min: u32, max: u32 },
+    Unbounded { min: u64 },
+    Bounded { min: u64, max: u64 },
 }
 
 impl Limits {
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (&[id], bytes) = bytes.advance()?;
-        match id {
-            0x00 => {
-                let (min, bytes) = bytes.advance_u32()?;
-                Ok((Self::Unbounded { min }, bytes))
-            }
-            0x01 => {
-                let (min, bytes) = bytes.advance_u32()?;
-                let (max, bytes) = bytes.advance_u32()?;
-                Ok((Self::Bounded { min, max }, bytes))
-            }
+        let (&[flags], bytes) = bytes.advance()?;
+        match flags {
+            0x00 | 0x01 => Self::read_values(flags, false, bytes),
             x => Err(Error::LimitsTag(x)),
         }
     }
 
+    /// Reads the minimum, and the maximum if the lowest bit of `flags` is set. Values are encoded
+    /// as `u64` if `is_64` is set, or `u32` otherwise.
+    fn read_values(flags: u8, is_64: bool, bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        fn read(bytes: &[u8], is_64: bool) -> Result<(u64, &[u8]), Error> {
+            if is_64 {
+                bytes.advance_u64()
+            } else {
+                bytes.advance_u32().map(|(x, bytes)| (x.into(), bytes))
+            }
+        }
+
+        let (min, bytes) = read(bytes, is_64)?;
+        if flags & 0x01 == 0 {
+            return Ok((Self::Unbounded { min }, bytes));
+        }
+        let (max, bytes) = read(bytes, is_64)?;
+        Ok((Self::Bounded { min, max }, bytes))
+    }
+
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        self.write_with_flags(0x00, wr)
+    }
+
+    /// Writes the limits, setting the lowest bit of `flags` if bounded.
+    fn write_with_flags(&self, flags: u8, wr: &mut impl Write) -> Result<(), io::Error> {
         match *self {
             Limits::Unbounded { min } => {
-                wr.write_all(&[0x00])?;
-                wr.write_u32(min)?;
+                wr.write_all(&[flags])?;
+                wr.write_u64(min)?;
                 Ok(())
             }
             Limits::Bounded { min, max } => {
-                wr.write_all(&[0x01])?;
-                wr.write_u32(min)?;
-                wr.write_u32(max)?;
+                wr.write_all(&[flags | 0x01])?;
+                wr.write_u64(min)?;
+                wr.write_u64(max)?;
                 Ok(())
             }
         }
     }
+
+    pub fn min(&self) -> u64 {
+        match *self {
+            Limits::Unbounded { min } | Limits::Bounded { min, .. } => min,
+        }
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        match *self {
+            Limits::Unbounded { .. } => None,
+            Limits::Bounded { max, .. } => Some(max),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MemType {
     size: Limits,
+    is_64: bool,
+    shared: bool,
 }
 
 impl MemType {
+    pub fn new(size: Limits) -> Self {
+        Self {
+            size,
+            is_64: false,
+            shared: false,
+        }
+    }
+
+    /// Creates a type of a 64-bit memory of the memory64 proposal, which is addressed with `i64`.
+    pub fn new64(size: Limits) -> Self {
+        Self {
+            size,
+            is_64: true,
+            shared: false,
+        }
+    }
+
+    /// Marks the memory as shared between threads, as of the threads proposal.
+    ///
+    /// A shared memory must have a maximum size.
+    pub fn shared(self) -> Self {
+        Self {
+            shared: true,
+            ..self
+        }
+    }
+
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        Limits::from_bytes(bytes).map(|(l, bytes)| (Self { size: l }, bytes))
+        let (&[flags], bytes) = bytes.advance()?;
+        if flags & !0x07 != 0 {
+            return Err(Error::LimitsTag(flags));
+        }
+        let is_64 = flags & 0x04 != 0;
+        let shared = flags & 0x02 != 0;
+        let (size, bytes) = Limits::read_values(flags, is_64, bytes)?;
+        Ok((
+            Self {
+                size,
+                is_64,
+                shared,
+            },
+            bytes,
+        ))
     }
 
     pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
-        self.size.write_into(wr)
+        let mut flags = 0x00;
+        if self.shared {
+            flags |= 0x02;
+        }
+        if self.is_64 {
+            flags |= 0x04;
+        }
+        self.size.write_with_flags(flags, wr)
     }
 
     pub fn size(&self) -> &Limits {
         &self.size
     }
+
+    /// Returns whether the memory is a 64-bit memory.
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    /// Returns whether the memory is shared between threads.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Returns the type of addresses of the memory, which is `i64` for 64-bit memories.
+    pub fn index_type(&self) -> ValueType {
+        if self.is_64 {
+            ValueType::I64
+        } else {
+            ValueType::I32
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -263,9 +957,12 @@ pub struct TableType {
 }
 
 impl TableType {
+    pub fn new(element: ReferenceType, limits: Limits) -> Self {
+        Self { element, limits }
+    }
+
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (&[element], bytes) = bytes.advance()?;
-        let element = ReferenceType::from_byte(element)?;
+        let (element, bytes) = ReferenceType::from_bytes(bytes)?;
         let (limits, bytes) = Limits::from_bytes(bytes)?;
         Ok((Self { element, limits }, bytes))
     }
@@ -280,6 +977,10 @@ impl TableType {
         self.element
     }
 
+    pub(crate) fn type_index_mut(&mut self) -> Option<&mut u32> {
+        self.element.type_index_mut()
+    }
+
     pub fn limits(&self) -> &Limits {
         &self.limits
     }
@@ -292,9 +993,12 @@ pub struct GlobalType {
 }
 
 impl GlobalType {
+    pub fn new(ty: ValueType, mutable: bool) -> Self {
+        Self { ty, mutable }
+    }
+
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
-        let (&[ty], bytes) = bytes.advance()?;
-        let ty = ValueType::from_byte(ty)?;
+        let (ty, bytes) = ValueType::from_bytes(bytes)?;
         let (&[mutable], bytes) = bytes.advance()?;
         let mutable = match mutable {
             0x00 => false,
@@ -310,4 +1014,53 @@ impl GlobalType {
         wr.write_all(&[u8::from(self.mutable)])?;
         Ok(())
     }
+
+    pub fn ty(&self) -> ValueType {
+        self.ty
+    }
+
+    pub(crate) fn type_index_mut(&mut self) -> Option<&mut u32> {
+        self.ty.type_index_mut()
+    }
+
+    pub fn mutable(&self) -> bool {
+        self.mutable
+    }
+}
+
+/// Type of an exception tag of the exception handling proposal.
+///
+/// The parameters of the function type are the values carried by exceptions of the tag, and its
+/// results must be empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TagType {
+    type_index: u32,
+}
+
+impl TagType {
+    pub fn new(type_index: u32) -> Self {
+        Self { type_index }
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (&[attribute], bytes) = bytes.advance()?;
+        if attribute != 0x00 {
+            return Err(Error::TagAttribute(attribute));
+        }
+        let (type_index, bytes) = bytes.advance_u32()?;
+        Ok((Self { type_index }, bytes))
+    }
+
+    pub(crate) fn write_into(&self, wr: &mut impl Write) -> Result<(), io::Error> {
+        wr.write_all(&[0x00])?;
+        wr.write_u32(self.type_index)
+    }
+
+    pub fn type_index(&self) -> u32 {
+        self.type_index
+    }
+
+    pub fn type_index_mut(&mut self) -> &mut u32 {
+        &mut self.type_index
+    }
 }
@@ -57,9 +57,16 @@ fn generate_tests_inner() -> anyhow::Result<TokenStream> {
                             init_logger();
                             let contents = std::fs::read(#path_lit).expect("cannot read wat file");
                             let contents = String::from_utf8(contents).expect("cannot read wat file as an UTF-8 string");
-                            let module = parse_wat(&contents);
+                            let owned = parse_wat(&contents);
+                            let module = owned.module();
                             test_sections(&module);
                             test_synth(&module);
+                            let wasm = wat::parse_str(&contents).expect("cannot parse wat");
+                            test_preserving(&wasm);
+                            test_offsets(&wasm);
+                            test_stream(&wasm);
+                            test_print(&module);
+                            test_from_wat(&module, Some(&contents));
                             test_instrument(&module);
                         }
                     }
@@ -74,6 +81,11 @@ fn generate_tests_inner() -> anyhow::Result<TokenStream> {
                             let module = parse_wasm(&contents);
                             test_sections(&module);
                             test_synth(&module);
+                            test_preserving(&contents);
+                            test_offsets(&contents);
+                            test_stream(&contents);
+                            test_print(&module);
+                            test_from_wat(&module, None);
                             test_instrument(&module);
                         }
                     }
@@ -0,0 +1,12 @@
+(module $custom_sections
+  (@custom "first" (before first) "\00\01")
+  (type $t (func (param i32) (result i32)))
+  (@custom "after_type" (after type) "type")
+  (memory 1)
+  (@custom "before_code" (before code) "")
+  (func $id (type $t) (param $x i32) (result i32)
+    local.get $x
+  )
+  (data "passive")
+  (@custom "last" "end")
+)
@@ -0,0 +1,50 @@
+(module
+ (type $i32 (func (param i32)))
+ (type $none (func))
+ (import "env" "error" (tag $error (type $i32)))
+ (import "env" "callback" (func $callback (param i32)))
+ (tag $abort (export "abort") (type $none))
+ (export "error" (tag $error))
+ (func $guarded (export "guarded") (param $x i32) (result i32)
+  try $outer (result i32)
+   local.get $x
+   call $callback
+   local.get $x
+   i32.eqz
+   if
+    local.get $x
+    throw $error
+   end
+   local.get $x
+  catch $error
+   i32.const 1
+   i32.add
+  catch $abort
+   i32.const -1
+  catch_all
+   i32.const 0
+   rethrow $outer
+  end
+ )
+ (func $delegated (export "delegated") (param $x i32)
+  block $exit
+   try
+    try $inner
+     local.get $x
+     call $callback
+    delegate 0
+   catch_all
+    br $exit
+   end
+  end
+ )
+ (func $folded (param $x i32) (result i32)
+  try (result i32)
+   (call $callback (local.get $x))
+   (throw $abort)
+  catch $error
+  catch_all
+   (i32.const 2)
+  end
+ )
+)
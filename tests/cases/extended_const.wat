@@ -0,0 +1,14 @@
+(module
+ (import "env" "memory_base" (global $memory_base i32))
+ (import "env" "table_base" (global $table_base i32))
+ (memory 1)
+ (table 8 funcref)
+ (global $stack_pointer (mut i32) (i32.add (global.get $memory_base) (i32.const 4096)))
+ (global $heap_end i64 (i64.mul (i64.const 64) (i64.const 1024)))
+ (elem (offset (i32.add (global.get $table_base) (i32.const 1))) $get_sp)
+ (data (offset (i32.sub (i32.add (global.get $memory_base) (i32.const 32)) (i32.const 16))) "hello")
+ (export "get_sp" (func $get_sp))
+ (func $get_sp (result i32)
+  global.get $stack_pointer
+ )
+)
@@ -0,0 +1,96 @@
+(module
+ (rec
+  (type $node (sub (struct (field (mut i32)) (field (ref null $node)))))
+  (type $tagged (sub final $node (struct (field (mut i32)) (field (ref null $node)) (field i8))))
+ )
+ (type $point (struct (field $x i32) (field $y (mut i32))))
+ (type $bytes (array (mut i8)))
+ (type $unary (func (param i32) (result i32)))
+ (type $unary2 (func (param i32) (result i32)))
+ (global $head (mut (ref null $node)) (ref.null none))
+ (elem declare func $double)
+ (export "push" (func $push))
+ (export "sum" (func $sum))
+ (export "swap" (func $swap))
+ (func $push (param $value i32)
+  local.get $value
+  global.get $head
+  struct.new $node
+  global.set $head
+ )
+ (func $sum (result i32) (local $node (ref null $node)) (local $acc i32)
+  global.get $head
+  local.set $node
+  block $done
+   loop $next
+    local.get $node
+    br_on_null $done
+    struct.get $node 0
+    local.get $acc
+    i32.add
+    local.set $acc
+    local.get $node
+    ref.as_non_null
+    struct.get $node 1
+    local.set $node
+    br $next
+   end
+  end
+  local.get $acc
+ )
+ (func $tag (param $x anyref) (result i32)
+  block $not_tagged (result anyref)
+   local.get $x
+   br_on_cast_fail $not_tagged anyref (ref $tagged)
+   struct.get_s $tagged 2
+   return
+  end
+  ref.test i31ref
+  if (result i32)
+   local.get $x
+   ref.cast i31ref
+   i31.get_u
+  else
+   i32.const -1
+  end
+ )
+ (func $swap (param $p (ref $point)) (result (ref $point))
+  local.get $p
+  struct.get $point $y
+  local.get $p
+  struct.get $point $x
+  struct.new $point
+ )
+ (func $fill (param $len i32) (result (ref $bytes))
+  (local $arr (ref $bytes))
+  i32.const 7
+  local.get $len
+  array.new $bytes
+  local.tee $arr
+  i32.const 0
+  i32.const 42
+  array.set $bytes
+  local.get $arr
+  local.get $arr
+  array.len
+  i32.const 1
+  i32.sub
+  array.get_u $bytes
+  drop
+  local.get $arr
+ )
+ (func $double (type $unary)
+  local.get 0
+  i32.const 2
+  i32.mul
+ )
+ (func $apply (param i32) (result i32)
+  local.get 0
+  ref.func $double
+  call_ref $unary2
+ )
+ (func $wrap (param externref) (result anyref)
+  local.get 0
+  any.convert_extern
+ )
+)
@@ -0,0 +1,39 @@
+(module
+ (memory $mem i64 1 65536)
+ (data (i64.const 16) "memory64")
+ (export "memory" (memory $mem))
+ (export "load" (func $load))
+ (export "grow" (func $grow))
+ (export "fill" (func $fill))
+ (func $load (param $addr i64) (result i64)
+  local.get $addr
+  i64.load offset=4294967296
+  local.get $addr
+  i32.load8_u offset=8 align=1
+  i64.extend_i32_u
+  i64.add
+  local.get $addr
+  v128.load offset=16
+  i64x2.extract_lane 1
+  i64.add
+ )
+ (func $grow (param $pages i64) (result i64)
+  local.get $pages
+  memory.grow
+  drop
+  memory.size
+ )
+ (func $fill (param $dst i64) (param $src i64) (param $len i64)
+  local.get $dst
+  i32.const 0
+  local.get $len
+  memory.fill
+  local.get $dst
+  local.get $src
+  local.get $len
+  memory.copy
+  local.get $dst
+  i64.const 42
+  i64.store32 offset=4
+ )
+)
@@ -0,0 +1,52 @@
+(module
+ (import "env" "heap" (memory $heap 1))
+ (memory $scratch 1 2)
+ (memory $wide i64 1)
+ (data $init (memory $scratch) (i32.const 8) "scratch")
+ (data $wide_init (memory $wide) (i64.const 0) "wide")
+ (data $passive "passive")
+ (export "scratch" (memory $scratch))
+ (export "copy" (func $copy))
+ (export "sizes" (func $sizes))
+ (func $copy (param $dst i32) (param $src i32) (param $len i32) (result i64)
+  local.get $dst
+  local.get $src
+  local.get $len
+  memory.copy $heap $scratch
+  local.get $dst
+  i32.const 0
+  i32.const 7
+  memory.init $scratch $passive
+  local.get $dst
+  i32.const 0
+  local.get $len
+  memory.fill $scratch
+  local.get $dst
+  local.get $src
+  i32.load $scratch offset=4
+  i32.store16 $heap align=1
+  local.get $dst
+  local.get $dst
+  v128.load64_splat $scratch
+  v128.store $heap offset=16
+  local.get $dst
+  local.get $dst
+  v128.load $heap
+  v128.store32_lane $scratch 3
+  data.drop $passive
+  i64.const 0
+  i64.load8_u $wide
+ )
+ (func $sizes (result i32)
+  memory.size $scratch
+  i32.const 1
+  memory.grow $heap
+  i32.add
+  memory.size
+  i32.add
+  i64.const 1
+  memory.grow $wide
+  i32.wrap_i64
+  i32.add
+ )
+)
@@ -0,0 +1,29 @@
+(module
+ (memory 1)
+ (export "dot" (func $dot))
+ (export "madd" (func $madd))
+ (func $dot (param $a i32) (param $b i32) (param $acc v128) (result v128)
+  local.get $a
+  v128.load
+  local.get $b
+  v128.load
+  local.get $acc
+  i32x4.relaxed_dot_i8x16_i7x16_add_s
+ )
+ (func $madd (param v128 v128 v128) (result v128)
+  local.get 0
+  local.get 1
+  local.get 2
+  f32x4.relaxed_madd
+  local.get 1
+  f32x4.relaxed_min
+  local.get 0
+  local.get 1
+  local.get 2
+  i32x4.relaxed_laneselect
+  i8x16.relaxed_swizzle
+  i32x4.relaxed_trunc_f64x2_u_zero
+  local.get 2
+  i16x8.relaxed_q15mulr_s
+ )
+)
@@ -0,0 +1,40 @@
+(module
+ (type $binop (func (param i32 i32) (result i32)))
+ (table $ops 2 funcref)
+ (elem (i32.const 0) $add $mul)
+ (export "fold" (func $fold))
+ (func $add (type $binop)
+  local.get 0
+  local.get 1
+  i32.add
+ )
+ (func $mul (type $binop)
+  local.get 0
+  local.get 1
+  i32.mul
+ )
+ (func $fold (param $n i32) (param $acc i32) (result i32)
+  local.get $n
+  i32.eqz
+  if
+   local.get $acc
+   return
+  end
+  local.get $n
+  i32.const 1
+  i32.sub
+  local.get $acc
+  local.get $n
+  local.get $n
+  i32.const 1
+  i32.and
+  call_indirect $ops (type $binop)
+  return_call $fold
+ )
+ (func $apply (param i32 i32 i32) (result i32)
+  local.get 0
+  local.get 1
+  local.get 2
+  return_call_indirect $ops (type $binop)
+ )
+)
@@ -0,0 +1,273 @@
+(module
+ (import "env" "memory" (memory $shared 1 16 shared))
+ (memory $wide i64 1 1 shared)
+ (export "notify" (func $notify))
+ (export "rmw" (func $rmw))
+ (func $notify (param $addr i32) (result i32)
+  local.get $addr
+  i32.const 0
+  i64.const -1
+  memory.atomic.wait32 offset=4
+  drop
+  i64.const 8
+  i64.const 0
+  i64.const 100
+  memory.atomic.wait64 $wide
+  drop
+  atomic.fence
+  local.get $addr
+  i32.const 1
+  memory.atomic.notify offset=4
+ )
+ (func $rmw (param $addr i32)
+  local.get $addr
+  i32.atomic.load
+  drop
+  local.get $addr
+  i64.atomic.load
+  drop
+  local.get $addr
+  i32.atomic.load8_u
+  drop
+  local.get $addr
+  i32.atomic.load16_u
+  drop
+  local.get $addr
+  i64.atomic.load8_u
+  drop
+  local.get $addr
+  i64.atomic.load16_u
+  drop
+  local.get $addr
+  i64.atomic.load32_u
+  drop
+  local.get $addr
+  i32.const 23
+  i32.atomic.store
+  local.get $addr
+  i64.const 24
+  i64.atomic.store
+  local.get $addr
+  i32.const 25
+  i32.atomic.store8
+  local.get $addr
+  i32.const 26
+  i32.atomic.store16
+  local.get $addr
+  i64.const 27
+  i64.atomic.store8
+  local.get $addr
+  i64.const 28
+  i64.atomic.store16
+  local.get $addr
+  i64.const 29
+  i64.atomic.store32
+  local.get $addr
+  i32.const 30
+  i32.atomic.rmw.add
+  drop
+  local.get $addr
+  i64.const 31
+  i64.atomic.rmw.add
+  drop
+  local.get $addr
+  i32.const 32
+  i32.atomic.rmw8.add_u
+  drop
+  local.get $addr
+  i32.const 33
+  i32.atomic.rmw16.add_u
+  drop
+  local.get $addr
+  i64.const 34
+  i64.atomic.rmw8.add_u
+  drop
+  local.get $addr
+  i64.const 35
+  i64.atomic.rmw16.add_u
+  drop
+  local.get $addr
+  i64.const 36
+  i64.atomic.rmw32.add_u
+  drop
+  local.get $addr
+  i32.const 37
+  i32.atomic.rmw.sub
+  drop
+  local.get $addr
+  i64.const 38
+  i64.atomic.rmw.sub
+  drop
+  local.get $addr
+  i32.const 39
+  i32.atomic.rmw8.sub_u
+  drop
+  local.get $addr
+  i32.const 40
+  i32.atomic.rmw16.sub_u
+  drop
+  local.get $addr
+  i64.const 41
+  i64.atomic.rmw8.sub_u
+  drop
+  local.get $addr
+  i64.const 42
+  i64.atomic.rmw16.sub_u
+  drop
+  local.get $addr
+  i64.const 43
+  i64.atomic.rmw32.sub_u
+  drop
+  local.get $addr
+  i32.const 44
+  i32.atomic.rmw.and
+  drop
+  local.get $addr
+  i64.const 45
+  i64.atomic.rmw.and
+  drop
+  local.get $addr
+  i32.const 46
+  i32.atomic.rmw8.and_u
+  drop
+  local.get $addr
+  i32.const 47
+  i32.atomic.rmw16.and_u
+  drop
+  local.get $addr
+  i64.const 48
+  i64.atomic.rmw8.and_u
+  drop
+  local.get $addr
+  i64.const 49
+  i64.atomic.rmw16.and_u
+  drop
+  local.get $addr
+  i64.const 50
+  i64.atomic.rmw32.and_u
+  drop
+  local.get $addr
+  i32.const 51
+  i32.atomic.rmw.or
+  drop
+  local.get $addr
+  i64.const 52
+  i64.atomic.rmw.or
+  drop
+  local.get $addr
+  i32.const 53
+  i32.atomic.rmw8.or_u
+  drop
+  local.get $addr
+  i32.const 54
+  i32.atomic.rmw16.or_u
+  drop
+  local.get $addr
+  i64.const 55
+  i64.atomic.rmw8.or_u
+  drop
+  local.get $addr
+  i64.const 56
+  i64.atomic.rmw16.or_u
+  drop
+  local.get $addr
+  i64.const 57
+  i64.atomic.rmw32.or_u
+  drop
+  local.get $addr
+  i32.const 58
+  i32.atomic.rmw.xor
+  drop
+  local.get $addr
+  i64.const 59
+  i64.atomic.rmw.xor
+  drop
+  local.get $addr
+  i32.const 60
+  i32.atomic.rmw8.xor_u
+  drop
+  local.get $addr
+  i32.const 61
+  i32.atomic.rmw16.xor_u
+  drop
+  local.get $addr
+  i64.const 62
+  i64.atomic.rmw8.xor_u
+  drop
+  local.get $addr
+  i64.const 63
+  i64.atomic.rmw16.xor_u
+  drop
+  local.get $addr
+  i64.const 64
+  i64.atomic.rmw32.xor_u
+  drop
+  local.get $addr
+  i32.const 65
+  i32.atomic.rmw.xchg
+  drop
+  local.get $addr
+  i64.const 66
+  i64.atomic.rmw.xchg
+  drop
+  local.get $addr
+  i32.const 67
+  i32.atomic.rmw8.xchg_u
+  drop
+  local.get $addr
+  i32.const 68
+  i32.atomic.rmw16.xchg_u
+  drop
+  local.get $addr
+  i64.const 69
+  i64.atomic.rmw8.xchg_u
+  drop
+  local.get $addr
+  i64.const 70
+  i64.atomic.rmw16.xchg_u
+  drop
+  local.get $addr
+  i64.const 71
+  i64.atomic.rmw32.xchg_u
+  drop
+  local.get $addr
+  i32.const 0
+  i32.const 72
+  i32.atomic.rmw.cmpxchg
+  drop
+  local.get $addr
+  i64.const 0
+  i64.const 73
+  i64.atomic.rmw.cmpxchg
+  drop
+  local.get $addr
+  i32.const 0
+  i32.const 74
+  i32.atomic.rmw8.cmpxchg_u
+  drop
+  local.get $addr
+  i32.const 0
+  i32.const 75
+  i32.atomic.rmw16.cmpxchg_u
+  drop
+  local.get $addr
+  i64.const 0
+  i64.const 76
+  i64.atomic.rmw8.cmpxchg_u
+  drop
+  local.get $addr
+  i64.const 0
+  i64.const 77
+  i64.atomic.rmw16.cmpxchg_u
+  drop
+  local.get $addr
+  i64.const 0
+  i64.const 78
+  i64.atomic.rmw32.cmpxchg_u
+  drop
+  i64.const 0
+  i64.const 1
+  i64.atomic.rmw.add $wide offset=8
+  drop
+ )
+)
@@ -1,21 +1,43 @@
 use std::sync::Once;
 
-use wasynth::{instrument::install_all, parse::Module};
+use wasynth::{
+    instrument::{install_all, uninstall, InstrumentConfig},
+    parse::{Module, OwnedModule, Section, StreamEvent, StreamParser},
+    synth::SynthModule,
+};
 
 fn init_logger() {
     static ONCE: Once = Once::new();
     ONCE.call_once(env_logger::init);
 }
 
-fn parse_wat(wat_s: &str) -> Module {
-    let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+/// Validates a module with wasmparser, enabling the proposals wasynth supports.
+fn wasmparser_validate(wasm: &[u8]) -> wasmparser::Result<()> {
+    use wasmparser::WasmFeatures;
 
-    // NOTE: wasm binary contents are leaked here
-    parse_wasm(wasm.leak())
+    let features = WasmFeatures::default()
+        | WasmFeatures::MEMORY64
+        | WasmFeatures::MULTI_MEMORY
+        | WasmFeatures::THREADS
+        | WasmFeatures::EXCEPTIONS
+        | WasmFeatures::LEGACY_EXCEPTIONS
+        | WasmFeatures::TAIL_CALL
+        | WasmFeatures::EXTENDED_CONST
+        | WasmFeatures::RELAXED_SIMD
+        | WasmFeatures::FUNCTION_REFERENCES
+        | WasmFeatures::GC;
+    wasmparser::Validator::new_with_features(features)
+        .validate_all(wasm)
+        .map(|_| ())
 }
 
-fn parse_wasm(wasm: &[u8]) -> Module {
-    wasmparser::validate(wasm).expect("pre-parse validation fail");
+fn parse_wat(wat_s: &str) -> OwnedModule {
+    let wasm = wat::parse_str(wat_s).expect("cannot parse wat");
+    parse_wasm(&wasm).into_owned()
+}
+
+fn parse_wasm(wasm: &[u8]) -> Module<'_> {
+    wasmparser_validate(wasm).expect("pre-parse validation fail");
     wasynth::parse::Module::from_binary(wasm).expect("cannot parse wasm")
 }
 
@@ -23,6 +45,19 @@ fn test_sections(module: &Module) {
     module.validate().expect("validation failed");
 }
 
+/// Returns IDs of sections, with names of custom sections.
+fn section_order(module: &Module) -> Vec<(u8, Option<String>)> {
+    module
+        .sections()
+        .iter()
+        .map(|section| match section {
+            Section::Custom(x) => (0, Some(x.name().to_string())),
+            Section::Name(_) => (0, Some(String::from("name"))),
+            x => (x.id(), None),
+        })
+        .collect()
+}
+
 fn test_synth(module: &Module) {
     log::trace!("test_synth");
     let mut buf = Vec::new();
@@ -37,29 +72,227 @@ fn test_synth(module: &Module) {
     let module2 = Module::from_binary(&buf).expect("self-validation fail");
     module2.validate().expect("self-validation fail");
     log::trace!("self-validation end");
-    log::trace!(
-        "wat: {}",
-        wasmprinter::print_bytes(&buf).expect("cannot parse synthesized wasm module")
+    log::trace!("wat: {module2}");
+    wasmparser_validate(&buf).expect("wasmparser validation fail");
+    assert_eq!(
+        section_order(module),
+        section_order(&module2),
+        "section order is not preserved"
     );
-    wasmparser::validate(&buf).expect("wasmparser validation fail");
+}
+
+fn test_preserving(wasm: &[u8]) {
+    log::trace!("test_preserving");
+    let mut buf = Vec::new();
+    Module::from_binary(wasm)
+        .expect("cannot parse wasm")
+        .into_synth_preserving()
+        .expect("into_synth_preserving fail")
+        .write_into(&mut buf)
+        .expect("write_into fail");
+    assert!(buf == wasm, "unmodified module is not written byte-exact");
+}
+
+/// Compares offsets of sections and instructions with wasmparser.
+fn test_offsets(wasm: &[u8]) {
+    log::trace!("test_offsets");
+    let mut section_ends = Vec::new();
+    let mut instruction_offsets = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        let payload = payload.expect("wasmparser parse fail");
+        if let Some((_, range)) = payload.as_section() {
+            section_ends.push(range.end);
+        }
+        if let wasmparser::Payload::CodeSectionEntry(body) = payload {
+            let offsets = body
+                .get_operators_reader()
+                .expect("wasmparser parse fail")
+                .into_iter_with_offsets()
+                .map(|x| x.expect("wasmparser parse fail"))
+                // `else`, `end` and clauses of `try` are not instructions of wasynth
+                .filter(|(op, _)| {
+                    !matches!(
+                        op,
+                        wasmparser::Operator::Else
+                            | wasmparser::Operator::End
+                            | wasmparser::Operator::Catch { .. }
+                            | wasmparser::Operator::CatchAll
+                            | wasmparser::Operator::Delegate { .. }
+                    )
+                })
+                .map(|(_, offset)| offset)
+                .collect::<Vec<_>>();
+            instruction_offsets.push(offsets);
+        }
+    }
+
+    let module = Module::from_binary(wasm).expect("cannot parse wasm");
+    // wasmparser reports ranges of section contents, without section IDs and sizes
+    let ends = module
+        .section_ranges()
+        .iter()
+        .map(|x| x.end)
+        .collect::<Vec<_>>();
+    assert_eq!(ends, section_ends, "section ranges mismatch");
+
+    let Some(Section::Code(codesec)) = module.sections().iter().find(|x| x.id() == 10) else {
+        return;
+    };
+    for (code, expected) in codesec
+        .codes_with_instruction_offsets()
+        .expect("cannot read codes")
+        .zip(instruction_offsets)
+    {
+        let code = code.expect("cannot read code");
+        assert_eq!(
+            code.instruction_offsets().unwrap(),
+            expected,
+            "instruction offsets mismatch"
+        );
+    }
+}
+
+fn test_stream(wasm: &[u8]) {
+    log::trace!("test_stream");
+    let module = Module::from_binary(wasm).expect("cannot parse wasm");
+    let codes = match module.sections().iter().find(|x| x.id() == 10) {
+        Some(Section::Code(codesec)) => codesec
+            .codes_with_instruction_offsets()
+            .expect("cannot read codes")
+            .map(|x| format!("{:?}", x.expect("cannot read code")))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut parser = StreamParser::new(wasm);
+    let mut ranges = Vec::new();
+    let mut streamed_codes = Vec::new();
+    while let Some(event) = parser.next_event().expect("cannot read event") {
+        match event {
+            StreamEvent::Section(section, range) => {
+                let expected = module.sections()[ranges.len()];
+                assert_eq!(section.id(), expected.id(), "section id mismatch");
+                ranges.push(range);
+            }
+            StreamEvent::CodeSectionStart { count, range } => {
+                assert_eq!(count as usize, codes.len(), "function count mismatch");
+                ranges.push(range);
+            }
+            // odd function bodies are skipped
+            StreamEvent::FunctionBody(body) if body.index() % 2 == 1 => (),
+            StreamEvent::FunctionBody(body) => {
+                let code = body
+                    .read_with_instruction_offsets()
+                    .expect("cannot read code");
+                streamed_codes.push(format!("{code:?}"));
+            }
+        }
+    }
+    assert_eq!(ranges, module.section_ranges(), "section ranges mismatch");
+    let codes = codes.into_iter().step_by(2).collect::<Vec<_>>();
+    assert_eq!(streamed_codes, codes, "function bodies mismatch");
+}
+
+fn test_print(module: &Module) {
+    log::trace!("test_print");
+    let printed = module.to_string();
+    let wasm = wat::parse_str(&printed).expect("cannot parse printed wat");
+    wasmparser_validate(&wasm).expect("wasmparser validation fail");
+    let reprinted = Module::from_binary(&wasm)
+        .expect("cannot parse wasm from printed wat")
+        .to_string();
+    assert_same_wat(&printed, &reprinted, "printed wat does not round-trip");
+}
+
+/// Compares printed wat texts line by line.
+fn assert_same_wat(expected: &str, actual: &str, message: &str) {
+    if let Some((line, (expected, actual))) = expected
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (x, y))| x != y)
+    {
+        panic!("{message} at line {line}:\n- {expected}\n+ {actual}");
+    }
+    assert_eq!(expected.len(), actual.len(), "{message}");
+}
+
+fn test_from_wat(module: &Module, wat_s: Option<&str>) {
+    log::trace!("test_from_wat");
+    let printed = module.to_string();
+    let parsed = SynthModule::from_wat(&printed).expect("cannot parse printed wat");
+    let mut buf = Vec::new();
+    parsed.write_into(&mut buf).expect("write_into fail");
+    wasmparser_validate(&buf).expect("wasmparser validation fail");
+    assert_same_wat(&printed, &parsed.to_string(), "parsed wat differs");
+
+    if let Some(wat_s) = wat_s {
+        let parsed = SynthModule::from_wat(wat_s).expect("cannot parse wat");
+        assert_same_wat(
+            &printed,
+            &parsed.to_string(),
+            "parsed wat differs from wat crate",
+        );
+        let mut buf = Vec::new();
+        parsed.write_into(&mut buf).expect("write_into fail");
+        let module2 = Module::from_binary(&buf).expect("cannot parse wasm");
+        assert_eq!(
+            section_order(module),
+            section_order(&module2),
+            "section order differs from wat crate"
+        );
+    }
 }
 
 fn test_instrument(module: &Module) {
     log::trace!("test_instrument");
+    let mut synth_module = module.clone().into_synth().expect("into_synth fail");
+    install_all(&mut synth_module).expect("install_all");
+    check_instrumented(module, synth_module);
+
+    log::trace!("test_instrument with arguments and results");
+    let mut synth_module = module.clone().into_synth().expect("into_synth fail");
+    InstrumentConfig::new()
+        .hook_module("tracer")
+        .pass_arguments(true)
+        .pass_results(true)
+        .install_all(&mut synth_module)
+        .expect("install_all");
+    check_instrumented(module, synth_module);
+
+    log::trace!("test_instrument for even function indices");
+    let mut synth_module = module.clone().into_synth().expect("into_synth fail");
+    InstrumentConfig::new()
+        .filter(|f| f.index() % 2 == 0)
+        .install_all(&mut synth_module)
+        .expect("install_all");
+    check_instrumented(module, synth_module);
+}
+
+fn check_instrumented(original: &Module, module: wasynth::synth::SynthModule) {
     let mut buf = Vec::new();
-    let mut module = module.clone().into_synth().expect("into_synth fail");
-    install_all(&mut module).expect("install_all");
     module.write_into(&mut buf).expect("write_into fail");
 
     log::trace!("self-validation");
     let module2 = Module::from_binary(&buf).expect("self-validation fail");
     module2.validate().expect("self-validation fail");
     log::trace!("self-validation end");
-    log::trace!(
-        "wat: {}",
-        wasmprinter::print_bytes(&buf).expect("cannot parse instrumented wasm module")
-    );
-    wasmparser::validate(&buf).expect("wasmparser validation fail");
+    log::trace!("wat: {module2}");
+    wasmparser_validate(&buf).expect("wasmparser validation fail");
+
+    log::trace!("uninstall");
+    let mut module = module2.into_synth().expect("into_synth fail");
+    uninstall(&mut module).expect("uninstall fail");
+    let mut restored = Vec::new();
+    module.write_into(&mut restored).expect("write_into fail");
+    let mut expected = Vec::new();
+    original
+        .clone()
+        .into_synth()
+        .expect("into_synth fail")
+        .write_into(&mut expected)
+        .expect("write_into fail");
+    assert!(restored == expected, "uninstall did not restore the module");
 }
 
 mod autogenerated_from_files {